@@ -0,0 +1,142 @@
+//! Derive macro for the `Filter` trait of `tower-fallthrough-filter`.
+//!
+//! Use it through the `derive` feature of `tower-fallthrough-filter`,
+//! which re-exports it as `tower_fallthrough_filter::Filter`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Fields, LitStr, Path,
+    Result, Type,
+};
+
+/// Derives `Filter<T>` for an enum, delegating every variant to a function.
+///
+/// Every variant has to be annotated with `#[filter(fn = "path::to::function")]`,
+/// the function has the signature `fn(&T) -> bool`. The item type `T` is
+/// generic unless it is given using `#[filter(item = "Type")]` on the enum.
+///
+/// # Example
+/// ```rust,ignore
+/// use tower_fallthrough_filter::Filter;
+///
+/// fn is_api(path: &String) -> bool {
+///     path.starts_with("/api")
+/// }
+///
+/// fn is_asset(path: &String) -> bool {
+///     path.starts_with("/assets")
+/// }
+///
+/// #[derive(Clone, Filter)]
+/// #[filter(item = "String")]
+/// enum Route {
+///     #[filter(fn = "is_api")]
+///     Api,
+///     #[filter(fn = "is_asset")]
+///     Asset,
+/// }
+///
+/// assert!(Route::Api.matches(&"/api/users".to_string()));
+/// ```
+#[proc_macro_derive(Filter, attributes(filter))]
+pub fn derive_filter(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "`Filter` can only be derived for enums",
+        ));
+    };
+
+    let item = item_type(&input.attrs)?;
+
+    let arms = data
+        .variants
+        .iter()
+        .map(|variant| {
+            let ident = &variant.ident;
+            let function = variant_function(&variant.attrs)?.ok_or_else(|| {
+                Error::new_spanned(
+                    variant,
+                    "missing `#[filter(fn = \"...\")]` attribute on variant",
+                )
+            })?;
+            let pattern = match &variant.fields {
+                Fields::Unit => quote! {},
+                Fields::Unnamed(_) => quote! { (..) },
+                Fields::Named(_) => quote! { { .. } },
+            };
+
+            Ok(quote! { Self::#ident #pattern => #function(item), })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let name = &input.ident;
+    let mut generics = input.generics.clone();
+    let item = match item {
+        Some(item) => item,
+        None => {
+            generics.params.push(parse_quote! { __T });
+            parse_quote! { __T }
+        }
+    };
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::tower_fallthrough_filter::Filter<#item> for #name #ty_generics
+        #where_clause
+        {
+            fn matches(&self, item: &#item) -> bool {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    })
+}
+
+/// Parses the optional `#[filter(item = "Type")]` of the enum.
+fn item_type(attrs: &[Attribute]) -> Result<Option<Type>> {
+    let mut item = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("filter")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("item") {
+                item = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `item = \"...\"`"))
+            }
+        })?;
+    }
+
+    Ok(item)
+}
+
+/// Parses the `#[filter(fn = "path")]` of a variant.
+fn variant_function(attrs: &[Attribute]) -> Result<Option<Path>> {
+    let mut function = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("filter")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("fn") {
+                function = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `fn = \"...\"`"))
+            }
+        })?;
+    }
+
+    Ok(function)
+}
@@ -0,0 +1,172 @@
+//! Measures the overhead `AsyncFilterService` and the underlying
+//! `SelectServiceAndCallFut` add on top of the services they
+//! dispatch to.
+//!
+//! Both branches are no-op services and the filter completes
+//! immediately, so the numbers are the cost of the future machinery
+//! itself. Run with `cargo bench --bench async_filter --features async`,
+//! the reports are written to `target/criterion`.
+//!
+//! As long as the filter completes immediately, its future is polled
+//! only once, within the first poll of the response future.
+//!
+//! The `decided_filter` group uses services allocating when cloned.
+//! Awaiting the filter clones the service that wasn't called for every
+//! request, as the called one is reused, while a filter deciding in
+//! `AsyncFilter::matches_now` calls the selected service without
+//! cloning either. Compare the two cases on the same machine, as the
+//! cost of the allocation varies widely.
+
+use std::{
+    convert::Infallible,
+    task::{Context, Poll},
+};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use futures::{
+    future::{ready, Ready},
+    FutureExt,
+};
+use tower::{Layer, Service};
+use tower_fallthrough_filter::{futures::SelectServiceAndCallFut, AsyncFilter, AsyncFilterLayer};
+
+#[derive(Clone)]
+struct TestService;
+
+impl Service<u32> for TestService {
+    type Response = u32;
+    type Error = Infallible;
+    type Future = Ready<Result<u32, Infallible>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: u32) -> Self::Future {
+        ready(Ok(req))
+    }
+}
+
+#[derive(Clone)]
+struct TestFilter(bool);
+
+impl AsyncFilter<u32> for TestFilter {
+    type Future = Ready<bool>;
+
+    fn matches(&self, _: &u32) -> Self::Future {
+        ready(self.0)
+    }
+}
+
+/// Like `TestFilter`, but deciding without the future.
+#[derive(Clone)]
+struct DecidedFilter(bool);
+
+impl AsyncFilter<u32> for DecidedFilter {
+    type Future = Ready<bool>;
+
+    fn matches(&self, _: &u32) -> Self::Future {
+        ready(self.0)
+    }
+
+    fn matches_now(&self, _: &u32) -> Option<bool> {
+        Some(self.0)
+    }
+}
+
+/// A service with a per-clone buffer, so cloning it allocates.
+#[derive(Clone)]
+struct BufferedService(Vec<u8>);
+
+impl BufferedService {
+    fn new() -> Self {
+        Self(vec![0; 1024])
+    }
+}
+
+impl Service<u32> for BufferedService {
+    type Response = u32;
+    type Error = Infallible;
+    type Future = Ready<Result<u32, Infallible>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: u32) -> Self::Future {
+        ready(Ok(req + self.0.len() as u32))
+    }
+}
+
+fn call<S: Service<u32, Response = u32, Error = Infallible>>(service: &mut S, req: u32) -> u32 {
+    // NOTE: All futures are immediately ready, so there is no need
+    //       to pay for the setup of a full blown runtime.
+    service.call(req).now_or_never().unwrap().unwrap()
+}
+
+fn async_filter_service(c: &mut Criterion) {
+    let mut group = c.benchmark_group("async_filter_service");
+
+    group.bench_function("always_true", |b| {
+        let mut service = AsyncFilterLayer::new(TestFilter(true), TestService).layer(TestService);
+        b.iter(|| call(&mut service, black_box(1)))
+    });
+
+    group.bench_function("always_false", |b| {
+        let mut service = AsyncFilterLayer::new(TestFilter(false), TestService).layer(TestService);
+        b.iter(|| call(&mut service, black_box(1)))
+    });
+
+    group.finish();
+}
+
+fn select_service_and_call_fut(c: &mut Criterion) {
+    let mut group = c.benchmark_group("select_service_and_call_fut");
+
+    group.bench_function("direct_call", |b| {
+        b.iter(|| {
+            TestService
+                .call(black_box(1))
+                .now_or_never()
+                .unwrap()
+                .unwrap()
+        })
+    });
+
+    group.bench_function("select_and_call", |b| {
+        b.iter(|| {
+            SelectServiceAndCallFut::new(ready(true), black_box(1), TestService, TestService)
+                .now_or_never()
+                .unwrap()
+                .unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+fn decided_filter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decided_filter");
+
+    group.bench_function("awaited", |b| {
+        let mut service = AsyncFilterLayer::new(TestFilter(true), BufferedService::new())
+            .layer(BufferedService::new());
+        b.iter(|| call(&mut service, black_box(1)))
+    });
+
+    group.bench_function("decided_now", |b| {
+        let mut service = AsyncFilterLayer::new(DecidedFilter(true), BufferedService::new())
+            .layer(BufferedService::new());
+        b.iter(|| call(&mut service, black_box(1)))
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    async_filter_service,
+    select_service_and_call_fut,
+    decided_filter
+);
+criterion_main!(benches);
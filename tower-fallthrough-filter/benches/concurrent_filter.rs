@@ -0,0 +1,70 @@
+//! Compares evaluating several slow filters one after the other,
+//! using an `AsyncFilterChain`, to evaluating them concurrently,
+//! using a `ConcurrentFilterLayer`.
+//!
+//! There are 5 filters each taking 1ms, none of them matching, so all
+//! of them are evaluated before falling through. Run with
+//! `cargo bench --bench concurrent_filter --features async,chain`,
+//! the reports are written to `target/criterion`.
+//!
+//! The sequential chain takes the sum of the filters, while the
+//! concurrent layer takes as long as the slowest one. As the timer of
+//! tokio rounds a 1ms sleep up to around 2ms, that is around 10.5ms
+//! compared to 2.1ms on an x86_64 machine.
+
+use std::{convert::Infallible, time::Duration};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures::future::BoxFuture;
+use tower::{service_fn, Layer, Service, ServiceExt};
+use tower_fallthrough_filter::{AsyncFilter, AsyncFilterChain, ConcurrentFilterLayer};
+
+const FILTERS: usize = 5;
+
+#[derive(Clone)]
+struct SlowFilter;
+
+impl AsyncFilter<u32> for SlowFilter {
+    type Future = BoxFuture<'static, bool>;
+
+    fn matches(&self, _: &u32) -> Self::Future {
+        Box::pin(async {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            false
+        })
+    }
+}
+
+fn respond(
+    n: u32,
+) -> impl Service<u32, Response = u32, Error = Infallible, Future = impl Send> + Clone + Send {
+    service_fn(move |_: u32| async move { Ok(n) })
+}
+
+fn slow_filters(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("slow_filters");
+
+    group.bench_function("sequential", |b| {
+        let chain = (0..FILTERS).fold(AsyncFilterChain::new(), |chain, _| {
+            chain.when(SlowFilter, respond(1))
+        });
+        let service = chain.layer(respond(0));
+
+        b.to_async(&runtime).iter(|| service.clone().oneshot(0))
+    });
+
+    group.bench_function("concurrent", |b| {
+        let layer = (0..FILTERS).fold(ConcurrentFilterLayer::new(), |layer, _| {
+            layer.when(SlowFilter, respond(1))
+        });
+        let service = layer.layer(respond(0));
+
+        b.to_async(&runtime).iter(|| service.clone().oneshot(0))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, slow_filters);
+criterion_main!(benches);
@@ -0,0 +1,55 @@
+use axum::{
+    extract::{MatchedPath, Request},
+    routing::get,
+    Router,
+};
+use tokio::net::TcpListener;
+use tower_fallthrough_filter::{filters::MatchCountFilter, Filter, FilterLayer};
+
+#[derive(Clone)]
+struct IsUnknownRoute;
+
+impl Filter<Request> for IsUnknownRoute {
+    fn matches(&self, req: &Request) -> bool {
+        req.extensions().get::<MatchedPath>().is_none()
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    // The counters are shared with every clone of the filter,
+    // so the `/metrics` handler can read them while serving.
+    let filter = MatchCountFilter::new(IsUnknownRoute);
+    let counts = filter.clone();
+
+    let not_found = Router::new().fallback(|| async { "Nothing to see here!" });
+    let layer = FilterLayer::new(filter, not_found);
+
+    let app = Router::<()>::new()
+        .route("/hello", get(|| async { "Hello, World!" }))
+        .route(
+            "/metrics",
+            get(move || async move {
+                format!(
+                    "filter_matched_total {}\nfilter_fallthrough_total {}\n",
+                    counts.matched_count(),
+                    counts.fallthrough_count()
+                )
+            }),
+        )
+        .layer(layer);
+
+    let listener = TcpListener::bind("127.0.0.1:1337")
+        .await
+        .expect("Failed to create TCP Listener!");
+
+    println!("Listening on http://127.0.0.1:1337/");
+    println!();
+    println!("Try to open: http://127.0.0.1:1337/hello");
+    println!("Try to open: http://127.0.0.1:1337/unknown");
+    println!("Try to open: http://127.0.0.1:1337/metrics");
+
+    axum::serve(listener, app)
+        .await
+        .expect("Failed to start axum server!")
+}
@@ -0,0 +1,80 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use axum::{
+    extract::Request,
+    response::{Html, IntoResponse},
+    routing::get,
+    Router,
+};
+use tokio::{net::TcpListener, sync::RwLock};
+use tower_fallthrough_filter::filters::StatefulAsyncFilterLayer;
+
+// Imagine that this is a connection pool to a database
+// storing which pages were published by the users.
+#[derive(Clone, Default)]
+struct Database {
+    pages: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl Database {
+    async fn find_page(&self, path: &str) -> Option<String> {
+        // Pretend that the query takes a while.
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        self.pages.read().await.get(path).cloned()
+    }
+}
+
+#[derive(Clone)]
+struct AppState {
+    db: Database,
+}
+
+// The filter borrows the state and the request, just like a handler
+// would extract them. The request is a copy without the body.
+async fn is_published_page(state: &AppState, req: &Request<()>) -> bool {
+    state.db.find_page(req.uri().path()).await.is_some()
+}
+
+async fn render_page(req: Request) -> impl IntoResponse {
+    // The page was looked up by the filter already, a real renderer
+    // would query it again or receive it through an extension.
+    Html(format!("<h1>Published page at {}</h1>", req.uri().path()))
+}
+
+#[tokio::main]
+async fn main() {
+    let db = Database::default();
+    db.pages
+        .write()
+        .await
+        .insert("/about".to_string(), "About us".to_string());
+
+    let state = AppState { db };
+    let renderer = Router::new().fallback(get(render_page));
+
+    // Published pages are rendered, every other request
+    // is handled by the routes or the fallback of the app.
+    let app = Router::<()>::new()
+        .route("/api/hello", get(|| async { "Hello, World!" }))
+        .fallback(get(|| async { "Page not found" }))
+        .layer(StatefulAsyncFilterLayer::new(
+            state,
+            is_published_page,
+            renderer,
+        ));
+
+    let listener = TcpListener::bind("127.0.0.1:1337")
+        .await
+        .expect("Failed to create TCP Listener!");
+
+    println!("Listening on http://127.0.0.1:1337/");
+    println!();
+    println!("Try to open: http://127.0.0.1:1337/about");
+    println!("Try to open: http://127.0.0.1:1337/unknown");
+    println!("Try to open: http://127.0.0.1:1337/api/hello");
+
+    axum::serve(listener, app)
+        .await
+        .expect("Failed to start axum server!")
+}
@@ -0,0 +1,139 @@
+use std::{
+    fmt,
+    marker::PhantomData,
+    task::{Context, Poll},
+};
+
+use futures::{future::MapOk, TryFutureExt};
+use tower::Service;
+
+use crate::{Filter, FilterService};
+
+/// A service running a function on every successful response,
+/// created by [`FilterService::with_after_call`].
+///
+/// The function runs on the responses of both the filtered and
+/// the inner service, e.g. to add common headers.
+pub struct AfterCallFilterService<A, S, T> {
+    after: A,
+    service: S,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F, S, I, T> FilterService<F, S, I, T>
+where
+    F: Filter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    /// Runs `after` on every successful response, regardless of which
+    /// service handled the request, e.g. to wrap it in an envelope.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tower::{service_fn, ServiceExt};
+    /// use tower_fallthrough_filter::{Filter, FilterService};
+    ///
+    /// #[derive(Clone)]
+    /// struct IsApi;
+    ///
+    /// impl Filter<String> for IsApi {
+    ///     fn matches(&self, path: &String) -> bool {
+    ///         path.starts_with("/api")
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let respond = |name: &'static str| {
+    ///     service_fn(move |_: String| async move { Ok::<_, ()>(name.to_string()) })
+    /// };
+    ///
+    /// let service = FilterService::new(IsApi, respond("api"), respond("page"))
+    ///     .with_after_call(|body: String| format!("<main>{body}</main>"));
+    ///
+    /// let res = service.oneshot("/api/users".to_string()).await;
+    /// assert_eq!(res.as_deref(), Ok("<main>api</main>"));
+    /// # }
+    /// ```
+    pub fn with_after_call<A>(self, after: A) -> AfterCallFilterService<A, Self, T>
+    where
+        A: Fn(S::Response) -> S::Response + Clone,
+    {
+        AfterCallFilterService {
+            after,
+            service: self,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<A, S, T> AfterCallFilterService<A, S, T> {
+    /// The wrapped service.
+    pub fn get_ref(&self) -> &S {
+        &self.service
+    }
+
+    /// Consumes `self`, returning the wrapped service.
+    pub fn into_inner(self) -> S {
+        self.service
+    }
+}
+
+impl<A: Clone, S: Clone, T> Clone for AfterCallFilterService<A, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            after: self.after.clone(),
+            service: self.service.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<A, S: fmt::Debug, T> fmt::Debug for AfterCallFilterService<A, S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AfterCallFilterService")
+            .field("after", &std::any::type_name::<A>())
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+impl<A, S, T> Service<T> for AfterCallFilterService<A, S, T>
+where
+    A: Fn(S::Response) -> S::Response + Clone,
+    S: Service<T>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = MapOk<S::Future, A>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        self.service.call(req).map_ok(self.after.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_util::*;
+
+    #[tokio::test]
+    async fn should_transform_both_responses() {
+        for (matches, expected) in [(true, 10), (false, 20)] {
+            let service = FilterService::new(TestFilter(matches), TestService(1), TestService(2))
+                .with_after_call(|n: u32| n * 10);
+
+            assert_eq!(service.oneshot(()).await, Ok(expected));
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use ::futures::ready;
+use http::{HeaderName, HeaderValue, Request, Response};
+use tower::{Layer, Service};
+
+use crate::{Filter, FilterLayer};
+
+#[cfg(feature = "async")]
+use crate::{AsyncFilter, AsyncFilterLayer};
+
+/// The name of the response header added by an [`AnnotatedLayer`].
+pub const X_EXPERIMENT: HeaderName = HeaderName::from_static("x-experiment");
+
+/// Which branch of an experiment handled a request.
+///
+/// Inserted into the request extensions by an [`AnnotatedLayer`]
+/// before the chosen service is called.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExperimentAssignment {
+    /// The name of the experiment.
+    pub name: Arc<str>,
+    /// Whether the filter matched, if not the request fell through.
+    pub matched: bool,
+}
+
+/// A service tagging every request with an [`ExperimentAssignment`]
+/// and every response with an `X-Experiment` header.
+#[derive(Debug, Clone)]
+pub struct AnnotateService<S> {
+    service: S,
+    assignment: ExperimentAssignment,
+    header: HeaderValue,
+}
+
+impl<S> AnnotateService<S> {
+    fn new(service: S, name: Arc<str>, matched: bool) -> Self {
+        let outcome = if matched { "matched" } else { "fallthrough" };
+        let header = HeaderValue::try_from(format!("{name}={outcome}"))
+            .expect("Experiment name is not a valid header value");
+
+        Self {
+            service,
+            assignment: ExperimentAssignment { name, matched },
+            header,
+        }
+    }
+}
+
+impl<S, B, RB> Service<Request<B>> for AnnotateService<S>
+where
+    S: Service<Request<B>, Response = Response<RB>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = AnnotateFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<B>) -> Self::Future {
+        req.extensions_mut().insert(self.assignment.clone());
+
+        AnnotateFuture {
+            future: self.service.call(req),
+            header: Some(self.header.clone()),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct AnnotateFuture<Fut> {
+    #[pin]
+    future: Fut,
+    header: Option<HeaderValue>,
+}
+
+impl<Fut, RB, E> Future for AnnotateFuture<Fut>
+where
+    Fut: Future<Output = Result<Response<RB>, E>>,
+{
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = ready!(this.future.poll(cx))?;
+
+        if let Some(header) = this.header.take() {
+            // NOTE: Appending keeps headers added by other experiments.
+            res.headers_mut().append(X_EXPERIMENT, header);
+        }
+
+        Poll::Ready(Ok(res))
+    }
+}
+
+/// A layer wrapping a filter layer, so every request is tagged with
+/// the [`ExperimentAssignment`] of the branch that handles it and
+/// every response gets an `X-Experiment: <name>=matched|fallthrough`
+/// header appended.
+///
+/// Created by [`FilterLayer::annotate`] or `AsyncFilterLayer::annotate`.
+#[derive(Debug, Clone)]
+pub struct AnnotatedLayer<L> {
+    layer: L,
+    name: Arc<str>,
+}
+
+impl<L, I> Layer<I> for AnnotatedLayer<L>
+where
+    L: Layer<AnnotateService<I>>,
+{
+    type Service = L::Service;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        self.layer.layer(AnnotateService::new(
+            inner_service,
+            self.name.clone(),
+            false,
+        ))
+    }
+}
+
+/// The layer returned by [`FilterLayer::annotate`].
+pub type AnnotatedFilterLayer<F, S, B> =
+    AnnotatedLayer<FilterLayer<F, AnnotateService<S>, Request<B>>>;
+
+/// The layer returned by `AsyncFilterLayer::annotate`.
+#[cfg(feature = "async")]
+pub type AnnotatedAsyncFilterLayer<F, S, B> =
+    AnnotatedLayer<AsyncFilterLayer<F, AnnotateService<S>, Request<B>>>;
+
+impl<F, S, B, RB> FilterLayer<F, S, Request<B>>
+where
+    F: Filter<Request<B>>,
+    S: Service<Request<B>, Response = Response<RB>>,
+{
+    /// Annotates every request and response with the branch that
+    /// handled it, see [`AnnotatedLayer`].
+    ///
+    /// # Panics
+    /// Panics if `name` can't be used in a header value.
+    ///
+    /// # Example
+    /// ```rust
+    /// use axum::{extract::Request, routing::get, Extension, Router};
+    /// use tower_fallthrough_filter::{ExperimentAssignment, Filter, FilterLayer};
+    ///
+    /// #[derive(Clone)]
+    /// struct IsBeta;
+    ///
+    /// impl Filter<Request> for IsBeta {
+    ///     fn matches(&self, req: &Request) -> bool {
+    ///         req.headers().contains_key("x-beta")
+    ///     }
+    /// }
+    ///
+    /// let renderer = Router::new().fallback(get(
+    ///     |Extension(assignment): Extension<ExperimentAssignment>| async move {
+    ///         format!("rendered for {}", assignment.name)
+    ///     },
+    /// ));
+    ///
+    /// let app: Router = Router::new()
+    ///     .route("/", get(|| async { "old" }))
+    ///     .layer(FilterLayer::new(IsBeta, renderer).annotate("new-renderer"));
+    /// ```
+    pub fn annotate(self, name: impl Into<Arc<str>>) -> AnnotatedFilterLayer<F, S, B> {
+        let name = name.into();
+        let service = AnnotateService::new(self.service, name.clone(), true);
+
+        AnnotatedLayer {
+            layer: FilterLayer::new(self.filter, service),
+            name,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F, S, B, RB> AsyncFilterLayer<F, S, Request<B>>
+where
+    F: AsyncFilter<Request<B>>,
+    S: Service<Request<B>, Response = Response<RB>>,
+    B: Send + 'static,
+{
+    /// Annotates every request and response with the branch that
+    /// handled it, see [`AnnotatedLayer`].
+    ///
+    /// # Panics
+    /// Panics if `name` can't be used in a header value.
+    pub fn annotate(self, name: impl Into<Arc<str>>) -> AnnotatedAsyncFilterLayer<F, S, B> {
+        let name = name.into();
+        let (filter, service) = self.into_parts();
+        let service = AnnotateService::new(service, name.clone(), true);
+
+        AnnotatedLayer {
+            layer: AsyncFilterLayer::new(filter, service),
+            name,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{extract::Request, routing::get, Extension, Router};
+    use axum_test::TestServer;
+
+    use super::*;
+    use crate::test_util::*;
+
+    async fn handler(Extension(assignment): Extension<ExperimentAssignment>) -> String {
+        format!("{} {}", assignment.name, assignment.matched)
+    }
+
+    fn server<L>(layer: L) -> TestServer
+    where
+        L: Layer<axum::routing::Route> + Clone + Send + 'static,
+        L::Service: Service<Request, Response = axum::response::Response, Error = std::convert::Infallible>
+            + Clone
+            + Send
+            + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+    {
+        let app = Router::new()
+            .route(
+                "/",
+                get(handler).layer(axum::middleware::map_response(
+                    |mut res: axum::response::Response| async move {
+                        res.headers_mut()
+                            .insert(X_EXPERIMENT, HeaderValue::from_static("other=matched"));
+                        res
+                    },
+                )),
+            )
+            .layer(layer);
+
+        TestServer::new(app).unwrap()
+    }
+
+    fn renderer() -> Router {
+        Router::new().fallback(get(handler))
+    }
+
+    #[tokio::test]
+    async fn should_annotate_matched_requests() {
+        let server = server(FilterLayer::new(TestFilter(true), renderer()).annotate("beta"));
+
+        let res = server.get("/").await;
+        res.assert_text("beta true");
+        assert_eq!(res.header(X_EXPERIMENT), "beta=matched");
+    }
+
+    #[tokio::test]
+    async fn should_annotate_fallthrough_without_clobbering() {
+        let server = server(FilterLayer::new(TestFilter(false), renderer()).annotate("beta"));
+
+        let res = server.get("/").await;
+        res.assert_text("beta false");
+
+        let headers: Vec<_> = res
+            .headers()
+            .get_all(X_EXPERIMENT)
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect();
+        assert_eq!(headers, ["other=matched", "beta=fallthrough"]);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn should_annotate_async_filters() {
+        let server = server(AsyncFilterLayer::new(TestFilter(true), renderer()).annotate("beta"));
+
+        let res = server.get("/").await;
+        res.assert_text("beta true");
+        assert_eq!(res.header(X_EXPERIMENT), "beta=matched");
+    }
+}
@@ -0,0 +1,252 @@
+use std::{
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::{future::BoxFuture, ready};
+use tower::{util::BoxCloneService, Layer, Service};
+
+use crate::{futures::AsyncFilterChainFut, ready::take_ready, AsyncFilter};
+
+pub(crate) type BoxAsyncFilter<T> = Arc<dyn Fn(&T) -> BoxFuture<'static, bool> + Send + Sync>;
+
+/// The async counterpart of [`FilterChain`](crate::FilterChain),
+/// dispatching to the service of the first matching [`AsyncFilter`]
+/// out of an ordered list.
+///
+/// The filters are evaluated one after the other, each only once the
+/// previous one didn't match. As soon as a filter matches the remaining
+/// ones aren't evaluated at all, so e.g. a slow lookup placed last is
+/// skipped for requests an earlier filter already claimed.
+///
+/// # Readiness
+/// The created service is only ready once all services of the chain
+/// and the inner service are ready, as any of them might be picked
+/// for the next request.
+///
+/// # Example
+/// ```rust
+/// use futures::future::{ready, Ready};
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{AsyncFilter, AsyncFilterChain};
+///
+/// #[derive(Clone)]
+/// struct Below(u32);
+///
+/// impl AsyncFilter<u32> for Below {
+///     type Future = Ready<bool>;
+///
+///     fn matches(&self, item: &u32) -> Self::Future {
+///         ready(*item < self.0)
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name: &'static str| service_fn(move |_: u32| async move { Ok::<_, ()>(name) });
+///
+/// let chain = AsyncFilterChain::new()
+///     .when(Below(10), respond("small"))
+///     .when(Below(100), respond("medium"));
+///
+/// let service = chain.layer(respond("large"));
+/// assert_eq!(service.clone().oneshot(5).await, Ok("small"));
+/// assert_eq!(service.clone().oneshot(50).await, Ok("medium"));
+/// assert_eq!(service.oneshot(500).await, Ok("large"));
+/// # }
+/// ```
+pub struct AsyncFilterChain<T, R, E> {
+    filters: Vec<BoxAsyncFilter<T>>,
+    services: Vec<BoxCloneService<T, R, E>>,
+}
+
+impl<T, R, E> AsyncFilterChain<T, R, E> {
+    /// Creates a new, empty AsyncFilterChain.
+    pub fn new() -> Self {
+        Self {
+            filters: Vec::new(),
+            services: Vec::new(),
+        }
+    }
+
+    /// Adds an entry calling `service` if `filter` matches and
+    /// none of the previously added filters did.
+    pub fn when<F, S>(mut self, filter: F, service: S) -> Self
+    where
+        F: AsyncFilter<T> + Send + Sync + 'static,
+        F::Future: 'static,
+        S: Service<T, Response = R, Error = E> + Clone + Send + 'static,
+        S::Future: Send + 'static,
+        T: 'static,
+    {
+        let filter = Arc::new(move |item: &T| -> BoxFuture<'static, bool> {
+            Box::pin(filter.matches(item))
+        });
+
+        self.filters.push(filter);
+        self.services.push(BoxCloneService::new(service));
+        self
+    }
+}
+
+impl<T, R, E> Default for AsyncFilterChain<T, R, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, R, E> Clone for AsyncFilterChain<T, R, E> {
+    fn clone(&self) -> Self {
+        Self {
+            filters: self.filters.clone(),
+            services: self.services.clone(),
+        }
+    }
+}
+
+impl<T, R, E> fmt::Debug for AsyncFilterChain<T, R, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncFilterChain")
+            .field("entries", &self.filters.len())
+            .finish()
+    }
+}
+
+impl<I, T, R, E> Layer<I> for AsyncFilterChain<T, R, E>
+where
+    I: Service<T, Response = R, Error = E>,
+{
+    type Service = AsyncFilterChainService<I, T, R, E>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        AsyncFilterChainService {
+            filters: self.filters.clone().into(),
+            services: self.services.clone(),
+            inner: inner_service,
+        }
+    }
+}
+
+pub struct AsyncFilterChainService<I, T, R, E> {
+    filters: Arc<[BoxAsyncFilter<T>]>,
+    services: Vec<BoxCloneService<T, R, E>>,
+    inner: I,
+}
+
+impl<I: Clone, T, R, E> Clone for AsyncFilterChainService<I, T, R, E> {
+    fn clone(&self) -> Self {
+        Self {
+            filters: self.filters.clone(),
+            services: self.services.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<I: fmt::Debug, T, R, E> fmt::Debug for AsyncFilterChainService<I, T, R, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncFilterChainService")
+            .field("entries", &self.filters.len())
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<I, T, R, E> Service<T> for AsyncFilterChainService<I, T, R, E>
+where
+    I: Service<T, Response = R, Error = E> + Clone,
+{
+    type Response = R;
+    type Error = E;
+    type Future = AsyncFilterChainFut<I, T, R, E>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        for service in &mut self.services {
+            ready!(service.poll_ready(cx))?;
+        }
+        ready!(self.inner.poll_ready(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        let services = take_ready(&mut self.services);
+        let inner = take_ready(&mut self.inner);
+
+        AsyncFilterChainFut::new(self.filters.clone(), req, services, inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, future::Future, pin::Pin};
+
+    use futures::future::{ready, Ready};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_util::*;
+
+    #[derive(Clone)]
+    struct Below(u32);
+
+    impl AsyncFilter<u32> for Below {
+        type Future = Ready<bool>;
+
+        fn matches(&self, item: &u32) -> Self::Future {
+            ready(*item < self.0)
+        }
+    }
+
+    #[derive(Clone)]
+    struct PanicsWhenPolled;
+
+    struct PanickingFuture;
+
+    impl Future for PanickingFuture {
+        type Output = bool;
+
+        fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<bool> {
+            panic!("The filter was evaluated after an earlier one matched")
+        }
+    }
+
+    impl AsyncFilter<u32> for PanicsWhenPolled {
+        type Future = PanickingFuture;
+
+        fn matches(&self, _: &u32) -> Self::Future {
+            PanickingFuture
+        }
+    }
+
+    fn chain() -> AsyncFilterChainService<TestService<&'static str>, u32, &'static str, Infallible>
+    {
+        AsyncFilterChain::new()
+            .when(Below(10), TestService("first"))
+            .when(Below(100), TestService("second"))
+            .when(Below(5), TestService("third"))
+            .layer(TestService("inner"))
+    }
+
+    #[tokio::test]
+    async fn should_pick_first_match() {
+        assert_eq!(chain().oneshot(1).await, Ok("first"));
+        assert_eq!(chain().oneshot(50).await, Ok("second"));
+    }
+
+    #[tokio::test]
+    async fn should_fall_through() {
+        assert_eq!(chain().oneshot(500).await, Ok("inner"));
+    }
+
+    #[tokio::test]
+    async fn should_short_circuit() {
+        let service = AsyncFilterChain::new()
+            .when(Below(10), TestService("first"))
+            .when(PanicsWhenPolled, TestService("second"))
+            .layer(TestService("inner"));
+
+        assert_eq!(service.oneshot(1).await, Ok("first"));
+    }
+}
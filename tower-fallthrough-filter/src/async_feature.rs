@@ -1,11 +1,17 @@
+use std::any::Any;
 use std::future::Future;
 use std::marker::PhantomData;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
-use futures::ready;
+use futures::{future::Either, ready};
 use tower::{Layer, Service};
 
-use crate::futures::SelectServiceAndCallFut;
+use crate::{
+    catch_panic::CatchPanics,
+    futures::{Cancellation, OnCancel, Recycled, SelectServiceAndCallFut},
+    ready::take_ready_with,
+};
 
 /// A filter that allows a service to be executed based on a condition
 ///
@@ -31,43 +37,49 @@ use crate::futures::SelectServiceAndCallFut;
 /// assert_eq!(filter.matches(&()).await, true);
 /// # }
 /// ```
-pub trait AsyncFilter<T>: Clone + Send + Sync {
+pub trait AsyncFilter<T>: Clone {
     type Future: Future<Output = bool> + Send;
 
     fn matches(&self, item: &T) -> Self::Future;
+
+    /// Whether the filter matches, if it is able to decide without
+    /// awaiting anything, e.g. as the request was cached.
+    ///
+    /// If this returns a decision, [`AsyncFilterService`] calls the
+    /// selected service right away, without creating the future of the
+    /// filter or cloning the services. The decision has to be the one
+    /// the future of [`AsyncFilter::matches`] would resolve to.
+    ///
+    /// Returns `None` by default, so the future is always awaited.
+    fn matches_now(&self, item: &T) -> Option<bool> {
+        let _ = item;
+        None
+    }
 }
 
-pub struct AsyncFilterLayer<F, S, T, R, E>
-where
-    F: AsyncFilter<T>,
-    S: Service<T, Response = R, Error = E>,
-{
+pub struct AsyncFilterLayer<F, S, T> {
     filter: F,
     service: S,
+    on_cancel: Option<OnCancel>,
+    catch_panics: CatchPanics,
 
-    _marker: PhantomData<(T, R, E)>,
+    _marker: PhantomData<fn(T)>,
 }
 
-// NOTE: This is required to make the `FilterLayer` clonable
-//       as the `PhantomData` might be not clonable.
-impl<F, S, R, E, T> Clone for AsyncFilterLayer<F, S, T, R, E>
-where
-    F: AsyncFilter<T> + Clone,
-    S: Service<T, Response = R, Error = E> + Clone,
-{
+impl<F: Clone, S: Clone, T> Clone for AsyncFilterLayer<F, S, T> {
     fn clone(&self) -> Self {
         Self {
             filter: self.filter.clone(),
             service: self.service.clone(),
+            on_cancel: self.on_cancel.clone(),
+            catch_panics: self.catch_panics.clone(),
 
             _marker: PhantomData,
         }
     }
 }
 
-impl<F: AsyncFilter<T>, S: Service<T>, T: Send + 'static>
-    AsyncFilterLayer<F, S, T, S::Response, S::Error>
-{
+impl<F: AsyncFilter<T>, S: Service<T>, T: Send + 'static> AsyncFilterLayer<F, S, T> {
     /// Creates a new FilterLayer given a `Service` and a `Filter`.
     ///
     /// NOTE: The Service and the Filter have to operate on the same
@@ -76,20 +88,142 @@ impl<F: AsyncFilter<T>, S: Service<T>, T: Send + 'static>
         Self {
             filter,
             service,
+            on_cancel: None,
+            catch_panics: CatchPanics::default(),
 
             _marker: PhantomData,
         }
     }
+
+    /// Creates the service falling through to `inner`, like
+    /// `Layer::layer` but consuming the layer instead of cloning
+    /// the filter and the service.
+    pub fn with_fallthrough<I>(self, inner: I) -> AsyncFilterService<F, S, I, T>
+    where
+        I: Service<T, Response = S::Response, Error = S::Error>,
+    {
+        AsyncFilterService {
+            on_cancel: self.on_cancel,
+            catch_panics: self.catch_panics,
+            ..AsyncFilterService::new(self.filter, self.service, inner)
+        }
+    }
+
+    /// Sets a callback invoked when the future of a request is dropped
+    /// before producing a response, e.g. because the client disconnected.
+    ///
+    /// The callback receives whether the filter had decided and which
+    /// service was called, see [`Cancellation`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// use tower_fallthrough_filter::{AsyncFilterFn, AsyncFilterLayer, Cancellation};
+    ///
+    /// static ABANDONED: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// let filter = AsyncFilterFn::new(|n: &u32| {
+    ///     let matches = *n < 10;
+    ///     async move { matches }
+    /// });
+    /// let layer = AsyncFilterLayer::new(filter, tower::service_fn(|_: u32| async { Ok::<_, ()>("small") }))
+    ///     .on_cancel(|cancellation: Cancellation| {
+    ///         if !cancellation.is_decided() {
+    ///             ABANDONED.fetch_add(1, Ordering::Relaxed);
+    ///         }
+    ///     });
+    /// ```
+    pub fn on_cancel(mut self, f: impl Fn(Cancellation) + Send + Sync + 'static) -> Self {
+        self.on_cancel = Some(OnCancel(Arc::new(f)));
+        self
+    }
+
+    /// Whether a panicking filter should be treated as not matching,
+    /// see [`FilterLayer::catch_panics`](crate::FilterLayer::catch_panics).
+    ///
+    /// This covers both creating the future of the filter and
+    /// polling it.
+    pub fn catch_panics(mut self, enabled: bool) -> Self {
+        self.catch_panics.enable(enabled);
+        self
+    }
+
+    /// Sets a hook called with the payload of a panicking filter,
+    /// see [`FilterLayer::on_panic`](crate::FilterLayer::on_panic).
+    pub fn on_panic(mut self, hook: impl Fn(&(dyn Any + Send)) + Send + Sync + 'static) -> Self {
+        self.catch_panics.set_hook(hook);
+        self
+    }
 }
 
-impl<F, S, I, T, R, E> Layer<I> for AsyncFilterLayer<F, S, T, R, E>
+impl<F, S, T> AsyncFilterLayer<F, S, T> {
+    /// The filter deciding whether the service is executed.
+    pub fn filter(&self) -> &F {
+        &self.filter
+    }
+
+    /// A mutable reference to the filter.
+    pub fn filter_mut(&mut self) -> &mut F {
+        &mut self.filter
+    }
+
+    /// The service executed if the filter matches.
+    pub fn matched_service_ref(&self) -> &S {
+        &self.service
+    }
+
+    /// A mutable reference to the service executed if the filter matches.
+    pub fn matched_service_mut(&mut self) -> &mut S {
+        &mut self.service
+    }
+
+    /// Returns the filter and the service.
+    pub fn into_parts(self) -> (F, S) {
+        (self.filter, self.service)
+    }
+
+    /// Replaces the filter with the result of `f`,
+    /// e.g. to wrap it in another filter.
+    pub fn map_filter<F2: AsyncFilter<T>>(
+        self,
+        f: impl FnOnce(F) -> F2,
+    ) -> AsyncFilterLayer<F2, S, T> {
+        AsyncFilterLayer {
+            filter: f(self.filter),
+            service: self.service,
+            on_cancel: self.on_cancel,
+            catch_panics: self.catch_panics,
+
+            _marker: PhantomData,
+        }
+    }
+
+    /// Replaces the service executed if the filter matches with
+    /// the result of `f`, see [`FilterLayer::map_service`](crate::FilterLayer::map_service).
+    pub fn map_service<S2: Service<T>>(
+        self,
+        f: impl FnOnce(S) -> S2,
+    ) -> AsyncFilterLayer<F, S2, T> {
+        AsyncFilterLayer {
+            filter: self.filter,
+            service: f(self.service),
+            on_cancel: self.on_cancel,
+            catch_panics: self.catch_panics,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> Layer<I> for AsyncFilterLayer<F, S, T>
 where
-    F: AsyncFilter<T> + Clone,
-    S: Service<T, Response = R, Error = E> + Clone,
-    I: Service<T, Response = R, Error = E> + Clone,
+    F: AsyncFilter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
     T: Send + 'static,
 {
-    type Service = AsyncFilterService<F, S, I, T, R, E>;
+    type Service = AsyncFilterService<F, S, I, T>;
 
     fn layer(&self, inner_service: I) -> Self::Service {
         let filter = self.filter.clone();
@@ -99,6 +233,9 @@ where
             filter,
             service: filtered_service,
             inner: inner_service,
+            on_cancel: self.on_cancel.clone(),
+            catch_panics: self.catch_panics.clone(),
+            recycled: Default::default(),
 
             _marker: PhantomData,
         }
@@ -106,52 +243,185 @@ where
 }
 
 #[derive(Debug)]
-pub struct AsyncFilterService<F, S, I, T, R, E>
-where
-    F: AsyncFilter<T>,
-    S: Service<T, Response = R, Error = E>,
-    I: Service<T, Response = R, Error = E>,
-{
+pub struct AsyncFilterService<F, S, I, T> {
     filter: F,
     service: S,
     inner: I,
+    on_cancel: Option<OnCancel>,
+    catch_panics: CatchPanics,
 
-    _marker: PhantomData<(T, R, E)>,
+    // NOTE: The services called by earlier requests, replacing the
+    //       called ones instead of clones where available.
+    recycled: (Recycled<S>, Recycled<I>),
+
+    _marker: PhantomData<fn(T)>,
 }
 
-// NOTE: This is required to make the `FilterService` clonable
-//       as the `PhantomData` might be not clonable.
-impl<F, S, I, T, R, E> Clone for AsyncFilterService<F, S, I, T, R, E>
+/// An [`AsyncFilterService`] handling `http::Request`s with a body of
+/// type `B`, see [`HttpFilterService`](crate::HttpFilterService).
+#[cfg(feature = "http")]
+pub type HttpAsyncFilterService<F, S, I, B> = AsyncFilterService<F, S, I, http::Request<B>>;
+
+impl<F, S, I, T> AsyncFilterService<F, S, I, T>
 where
     F: AsyncFilter<T>,
-    S: Service<T, Response = R, Error = E> + Clone,
-    I: Service<T, Response = R, Error = E> + Clone,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
 {
+    /// Creates a new AsyncFilterService given an `AsyncFilter`, the
+    /// `Service` that is executed if it matches and the `Service` to
+    /// fall through to.
+    pub fn new(filter: F, service: S, inner: I) -> Self {
+        Self {
+            filter,
+            service,
+            inner,
+            on_cancel: None,
+            catch_panics: CatchPanics::default(),
+            recycled: Default::default(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> AsyncFilterService<F, S, I, T> {
+    /// The filter deciding which service is executed.
+    pub fn filter(&self) -> &F {
+        &self.filter
+    }
+
+    /// A mutable reference to the filter.
+    pub fn filter_mut(&mut self) -> &mut F {
+        &mut self.filter
+    }
+
+    /// The service executed if the filter matches.
+    pub fn matched_service_ref(&self) -> &S {
+        &self.service
+    }
+
+    /// A mutable reference to the service executed if the filter matches.
+    ///
+    /// NOTE: `poll_ready` has to be called again before the next request,
+    /// as the service might be changed, e.g. replaced by one that isn't ready.
+    pub fn matched_service_mut(&mut self) -> &mut S {
+        &mut self.service
+    }
+
+    /// The inner service requests fall through to.
+    pub fn fallthrough_ref(&self) -> &I {
+        &self.inner
+    }
+
+    /// A mutable reference to the inner service requests fall through to,
+    /// see [`AsyncFilterService::matched_service_mut`].
+    pub fn fallthrough_mut(&mut self) -> &mut I {
+        &mut self.inner
+    }
+
+    /// Returns the filter, the filtered and the inner service.
+    pub fn into_parts(self) -> (F, S, I) {
+        (self.filter, self.service, self.inner)
+    }
+
+    /// Replaces the filter with the result of `f`,
+    /// e.g. to wrap it in another filter.
+    pub fn map_filter<F2: AsyncFilter<T>>(
+        self,
+        f: impl FnOnce(F) -> F2,
+    ) -> AsyncFilterService<F2, S, I, T> {
+        AsyncFilterService {
+            filter: f(self.filter),
+            service: self.service,
+            inner: self.inner,
+            on_cancel: self.on_cancel,
+            catch_panics: self.catch_panics,
+            recycled: self.recycled,
+
+            _marker: PhantomData,
+        }
+    }
+
+    /// Replaces the service executed if the filter matches with
+    /// the result of `f`, see [`FilterService::map_service`](crate::FilterService::map_service).
+    pub fn map_service<S2: Service<T>>(
+        self,
+        f: impl FnOnce(S) -> S2,
+    ) -> AsyncFilterService<F, S2, I, T> {
+        AsyncFilterService {
+            filter: self.filter,
+            service: f(self.service),
+            inner: self.inner,
+            on_cancel: self.on_cancel,
+            catch_panics: self.catch_panics,
+            recycled: (Recycled::default(), self.recycled.1),
+
+            _marker: PhantomData,
+        }
+    }
+
+    /// Replaces the inner service requests fall through to with
+    /// the result of `f`, see [`AsyncFilterService::map_service`].
+    pub fn map_inner<I2: Service<T>>(
+        self,
+        f: impl FnOnce(I) -> I2,
+    ) -> AsyncFilterService<F, S, I2, T> {
+        AsyncFilterService {
+            filter: self.filter,
+            service: self.service,
+            inner: f(self.inner),
+            on_cancel: self.on_cancel,
+            catch_panics: self.catch_panics,
+            recycled: (self.recycled.0, Recycled::default()),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: Clone, S: Clone, I: Clone, T> Clone for AsyncFilterService<F, S, I, T> {
     fn clone(&self) -> Self {
         Self {
             filter: self.filter.clone(),
             service: self.service.clone(),
             inner: self.inner.clone(),
+            on_cancel: self.on_cancel.clone(),
+            catch_panics: self.catch_panics.clone(),
+            recycled: self.recycled.clone(),
 
             _marker: PhantomData,
         }
     }
 }
 
-impl<F, S, I, T, R, E> Service<T> for AsyncFilterService<F, S, I, T, R, E>
+type AsyncFilterFut<F, S, I, T> = SelectServiceAndCallFut<
+    <F as AsyncFilter<T>>::Future,
+    S,
+    I,
+    T,
+    <S as Service<T>>::Response,
+    <S as Service<T>>::Error,
+>;
+
+impl<F, S, I, T> Service<T> for AsyncFilterService<F, S, I, T>
 where
     F: AsyncFilter<T>,
     F::Future: Send + 'static,
-    S: Service<T, Response = R, Error = E> + Clone + Send + 'static,
+    S: Service<T> + Clone + Send + 'static,
     S::Future: Send + 'static,
-    I: Service<T, Response = R, Error = E> + Clone + Send + 'static,
+    I: Service<T, Response = S::Response, Error = S::Error> + Clone + Send + 'static,
     I::Future: Send + 'static,
     T: Send + 'static,
 {
     type Response = S::Response;
     type Error = S::Error;
-    type Future = SelectServiceAndCallFut<F::Future, S, I, T, R, E>;
+    type Future = AsyncFilterFut<F, S, I, T>;
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "async_filter_service_poll_ready", level = "trace", skip_all)
+    )]
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         ready!(self.service.poll_ready(cx))?;
         ready!(self.inner.poll_ready(cx))?;
@@ -160,16 +430,63 @@ where
     }
 
     fn call(&mut self, req: T) -> Self::Future {
-        let matches = self.filter.matches(&req);
-        // https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
-        // As the inner service is cloned, the clone might not be ready to accept requests.
-        // So, we need to clone the inner service, and use the original one to make the call, as it is ready.
-        let clone = self.service.clone();
-        let service = std::mem::replace(&mut self.service, clone);
-        let clone = self.inner.clone();
-        let inner = std::mem::replace(&mut self.inner, clone);
+        // NOTE: The span stays entered while creating the future,
+        //       which enters it again whenever it is polled.
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "filter_service",
+            filter = %std::any::type_name::<F>()
+        )
+        .entered();
+
+        let decided = match self.catch_panics.catch(|| self.filter.matches_now(&req)) {
+            Some(decided) => decided,
+            // NOTE: The filter panicked, so the request falls through.
+            None => Some(false),
+        };
+        if let Some(select) = decided {
+            return self.call_selected(select, req);
+        }
+
+        let Some(matches) = self.catch_panics.catch(|| self.filter.matches(&req)) else {
+            // NOTE: The filter panicked, so the request falls through.
+            return self.call_selected(false, req);
+        };
+        // NOTE: The service called by an earlier request is reused
+        //       instead of a clone where available.
+        let (recycled_service, recycled_inner) = &self.recycled;
+        let service = take_ready_with(&mut self.service, |service| {
+            recycled_service.take().unwrap_or_else(|| service.clone())
+        });
+        let inner = take_ready_with(&mut self.inner, |inner| {
+            recycled_inner.take().unwrap_or_else(|| inner.clone())
+        });
 
         SelectServiceAndCallFut::new(matches, req, service, inner)
+            .on_cancel(self.on_cancel.clone())
+            .catch_panics(self.catch_panics.clone())
+            .recycle(recycled_service.clone(), recycled_inner.clone())
+    }
+}
+
+impl<F, S, I, T> AsyncFilterService<F, S, I, T>
+where
+    F: AsyncFilter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    /// Calls the selected service once the filter decided without
+    /// awaiting. Both services were polled ready, so neither is cloned.
+    fn call_selected(&mut self, select: bool, req: T) -> AsyncFilterFut<F, S, I, T> {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(matched = %select);
+
+        let future = if select {
+            Either::Left(self.service.call(req))
+        } else {
+            Either::Right(self.inner.call(req))
+        };
+        SelectServiceAndCallFut::calling(future).on_cancel(self.on_cancel.clone())
     }
 }
 
@@ -203,4 +520,245 @@ mod tests {
 
         assert_eq!(middleware.call(()).await, Ok("b"));
     }
+
+    #[tokio::test]
+    async fn should_skip_clones_when_decided_now() {
+        use tower::ServiceExt;
+
+        for (matches, expected) in [(true, "a"), (false, "b")] {
+            let (service_a, clones_a) = CloneCountingService::new("a");
+            let (service_b, clones_b) = CloneCountingService::new("b");
+            let mut service = AsyncFilterLayer::new(DecidedFilter(matches), service_a)
+                .with_fallthrough(service_b);
+
+            for _ in 0..3 {
+                let res = service.ready().await.unwrap().call(()).await;
+                assert_eq!(res, Ok(expected));
+            }
+
+            assert_eq!(clones_a.load(std::sync::atomic::Ordering::SeqCst), 0);
+            assert_eq!(clones_b.load(std::sync::atomic::Ordering::SeqCst), 0);
+        }
+    }
+
+    #[derive(Clone)]
+    struct SleepingFilter;
+
+    impl AsyncFilter<bool> for SleepingFilter {
+        type Future = futures::future::BoxFuture<'static, bool>;
+
+        fn matches(&self, matches: &bool) -> Self::Future {
+            let matches = *matches;
+            Box::pin(async move {
+                tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+                matches
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn should_clone_only_the_uncalled_service() {
+        use tower::ServiceExt;
+
+        let (service_a, clones_a) = CloneCountingService::new("a");
+        let (service_b, clones_b) = CloneCountingService::new("b");
+        let mut service =
+            AsyncFilterLayer::new(SleepingFilter, service_a).with_fallthrough(service_b);
+
+        for _ in 0..4 {
+            assert_eq!(service.ready().await.unwrap().call(true).await, Ok("a"));
+        }
+
+        // NOTE: The called service is reused after the first request.
+        assert_eq!(clones_a.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(clones_b.load(std::sync::atomic::Ordering::SeqCst), 4);
+
+        assert_eq!(service.ready().await.unwrap().call(false).await, Ok("b"));
+        assert_eq!(service.ready().await.unwrap().call(false).await, Ok("b"));
+
+        // NOTE: One clone per request, of the service that isn't reused.
+        assert_eq!(clones_a.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert_eq!(clones_b.load(std::sync::atomic::Ordering::SeqCst), 5);
+    }
+
+    /// A filter whose future stays pending, keeping the waker
+    /// it was last polled with.
+    #[derive(Clone, Default)]
+    struct WakerFilter(Arc<std::sync::Mutex<Option<std::task::Waker>>>);
+
+    impl<T> AsyncFilter<T> for WakerFilter {
+        type Future =
+            futures::future::PollFn<Box<dyn FnMut(&mut Context<'_>) -> Poll<bool> + Send>>;
+
+        fn matches(&self, _: &T) -> Self::Future {
+            let waker = self.0.clone();
+            futures::future::poll_fn(Box::new(move |cx| {
+                *waker.lock().unwrap() = Some(cx.waker().clone());
+                Poll::Pending
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn should_poll_filter_with_the_task_context() {
+        let filter = WakerFilter::default();
+        let mut service =
+            AsyncFilterLayer::new(filter.clone(), TestService("a")).layer(TestService("b"));
+
+        let future = service.call(());
+        assert!(filter.0.lock().unwrap().is_none());
+
+        tokio::pin!(future);
+        assert!(futures::poll!(future.as_mut()).is_pending());
+        let waker = filter.0.lock().unwrap().take().unwrap();
+        assert!(!waker.will_wake(futures::task::noop_waker_ref()));
+    }
+
+    #[tokio::test]
+    async fn should_recycle_across_concurrent_requests() {
+        use tower::ServiceExt;
+
+        let (service_a, clones_a) = CloneCountingService::new("a");
+        let (service_b, _) = CloneCountingService::new("b");
+        let mut service =
+            AsyncFilterLayer::new(SleepingFilter, service_a).with_fallthrough(service_b);
+
+        let first = service.ready().await.unwrap().call(true);
+        let second = service.ready().await.unwrap().call(false);
+        let third = service.ready().await.unwrap().call(true);
+
+        assert_eq!(
+            futures::join!(first, second, third),
+            (Ok("a"), Ok("b"), Ok("a"))
+        );
+        assert_eq!(clones_a.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        // NOTE: Only one of the called `a` services was kept for reuse.
+        assert_eq!(service.ready().await.unwrap().call(true).await, Ok("a"));
+        assert_eq!(service.ready().await.unwrap().call(true).await, Ok("a"));
+        assert_eq!(clones_a.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn should_fall_through_when_filter_panics() {
+        for on_poll in [false, true] {
+            let panics = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+            let hook_panics = panics.clone();
+            let layer = AsyncFilterLayer::new(PanicFilter { on_poll }, TestService("a"))
+                .catch_panics(true)
+                .on_panic(move |_| {
+                    hook_panics.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                });
+
+            let mut service = layer.layer(TestService("b"));
+            assert_eq!(service.call(()).await, Ok("b"));
+            assert_eq!(panics.load(std::sync::atomic::Ordering::SeqCst), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_not_catch_panics_by_default() {
+        use futures::FutureExt;
+
+        let mut service = AsyncFilterLayer::new(PanicFilter { on_poll: true }, TestService("a"))
+            .layer(TestService("b"));
+
+        let called = std::panic::AssertUnwindSafe(service.call(()))
+            .catch_unwind()
+            .await;
+        assert!(called.is_err());
+    }
+
+    #[derive(Clone, Default)]
+    struct CachedFilter {
+        // NOTE: Neither `Send` nor `Sync`, fine on a current thread runtime.
+        seen: std::rc::Rc<std::cell::RefCell<Vec<u32>>>,
+    }
+
+    impl AsyncFilter<u32> for CachedFilter {
+        type Future = std::future::Ready<bool>;
+
+        fn matches(&self, item: &u32) -> Self::Future {
+            let mut seen = self.seen.borrow_mut();
+            let repeated = seen.contains(item);
+            seen.push(*item);
+
+            std::future::ready(repeated)
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn should_allow_unsync_filters() {
+        let mut service = AsyncFilterLayer::new(CachedFilter::default(), TestService("cached"))
+            .layer(TestService("fresh"));
+
+        assert_eq!(service.call(1).await, Ok("fresh"));
+        assert_eq!(service.call(2).await, Ok("fresh"));
+        assert_eq!(service.call(1).await, Ok("cached"));
+    }
+
+    #[tokio::test]
+    async fn should_construct_without_layer() {
+        use tower::ServiceExt;
+
+        for matches in [true, false] {
+            let layered = AsyncFilterLayer::new(TestFilter(matches), TestService("a"))
+                .layer(TestService("b"))
+                .oneshot(())
+                .await;
+            let consumed = AsyncFilterLayer::new(TestFilter(matches), TestService("a"))
+                .with_fallthrough(TestService("b"))
+                .oneshot(())
+                .await;
+            let constructed =
+                AsyncFilterService::new(TestFilter(matches), TestService("a"), TestService("b"))
+                    .oneshot(())
+                    .await;
+
+            assert_eq!(layered, consumed);
+            assert_eq!(layered, constructed);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_mutate_parts() {
+        let mut middleware = AsyncFilterLayer::new(TestFilter(false), TestService("a"))
+            .map_filter(|TestFilter(matches)| TestFilter(!matches))
+            .layer(TestService("b"));
+
+        assert_eq!(middleware.call(()).await, Ok("a"));
+
+        middleware.matched_service_mut().0 = "c";
+        assert_eq!(middleware.call(()).await, Ok("c"));
+
+        middleware.filter_mut().0 = false;
+        middleware.fallthrough_mut().0 = "d";
+        assert_eq!(middleware.call(()).await, Ok("d"));
+
+        let (filter, service, inner) = middleware.into_parts();
+        assert!(!filter.0);
+        assert_eq!((service.0, inner.0), ("c", "d"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn should_call_the_services_polled_ready() {
+        use tower::{limit::ConcurrencyLimitLayer, ServiceExt};
+
+        // NOTE: The permit of a `ConcurrencyLimit` is acquired by the
+        //       instance polled ready, calling a fresh clone panics.
+        let limit = ConcurrencyLimitLayer::new(1);
+
+        for matches in [true, false] {
+            let service = AsyncFilterLayer::new(TestFilter(matches), limit.layer(TestService("a")))
+                .layer(limit.layer(TestService("b")));
+
+            let tasks: Vec<_> = (0..100)
+                .map(|_| tokio::spawn(service.clone().oneshot(())))
+                .collect();
+            for task in tasks {
+                let expected = if matches { "a" } else { "b" };
+                assert_eq!(task.await.unwrap(), Ok(expected));
+            }
+        }
+    }
 }
@@ -0,0 +1,162 @@
+use std::{
+    fmt,
+    marker::PhantomData,
+    task::{Context, Poll},
+};
+
+use tower::Service;
+
+use crate::{Filter, FilterService};
+
+/// A service running a function on every request before passing it on,
+/// created by [`FilterService::with_before_call`].
+///
+/// The function runs before the filter, so both the filter and the
+/// selected service see the changed request.
+pub struct BeforeCallFilterService<B, S, T> {
+    before: B,
+    service: S,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F, S, I, T> FilterService<F, S, I, T>
+where
+    F: Filter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    /// Runs `before` on every request before it is passed to the filter
+    /// and the selected service, e.g. to normalize the request
+    /// regardless of which service handles it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tower::{service_fn, ServiceExt};
+    /// use tower_fallthrough_filter::{Filter, FilterService};
+    ///
+    /// #[derive(Clone)]
+    /// struct IsApi;
+    ///
+    /// impl Filter<String> for IsApi {
+    ///     fn matches(&self, path: &String) -> bool {
+    ///         path.starts_with("/api")
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let respond = |name| service_fn(move |_: String| async move { Ok::<_, ()>(name) });
+    ///
+    /// let service = FilterService::new(IsApi, respond("api"), respond("page"))
+    ///     .with_before_call(|path: String| path.to_lowercase());
+    ///
+    /// assert_eq!(service.oneshot("/API/users".to_string()).await, Ok("api"));
+    /// # }
+    /// ```
+    pub fn with_before_call<B>(self, before: B) -> BeforeCallFilterService<B, Self, T>
+    where
+        B: Fn(T) -> T + Clone,
+    {
+        BeforeCallFilterService {
+            before,
+            service: self,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<B, S, T> BeforeCallFilterService<B, S, T> {
+    /// The wrapped service.
+    pub fn get_ref(&self) -> &S {
+        &self.service
+    }
+
+    /// Consumes `self`, returning the wrapped service.
+    pub fn into_inner(self) -> S {
+        self.service
+    }
+}
+
+impl<B: Clone, S: Clone, T> Clone for BeforeCallFilterService<B, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            before: self.before.clone(),
+            service: self.service.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<B, S: fmt::Debug, T> fmt::Debug for BeforeCallFilterService<B, S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BeforeCallFilterService")
+            .field("before", &std::any::type_name::<B>())
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+impl<B, S, T> Service<T> for BeforeCallFilterService<B, S, T>
+where
+    B: Fn(T) -> T,
+    S: Service<T>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        self.service.call((self.before)(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use tower::{service_fn, ServiceExt};
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct IsApi;
+
+    impl Filter<String> for IsApi {
+        fn matches(&self, path: &String) -> bool {
+            path.starts_with("/api/")
+        }
+    }
+
+    fn normalize(path: String) -> String {
+        let path = path.to_lowercase();
+        match path.strip_suffix('/') {
+            Some(stripped) if !stripped.is_empty() => stripped.to_string(),
+            _ => path,
+        }
+    }
+
+    #[tokio::test]
+    async fn should_pass_normalized_request() {
+        let echo = |name: &'static str| {
+            service_fn(
+                move |path: String| async move { Ok::<_, Infallible>(format!("{name} {path}")) },
+            )
+        };
+
+        let service =
+            FilterService::new(IsApi, echo("api"), echo("page")).with_before_call(normalize);
+
+        let res = service.clone().oneshot("/API/Users/".to_string()).await;
+        assert_eq!(res.unwrap(), "api /api/users");
+
+        let res = service.oneshot("/About/".to_string()).await;
+        assert_eq!(res.unwrap(), "page /about");
+    }
+}
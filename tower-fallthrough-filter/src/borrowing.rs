@@ -0,0 +1,225 @@
+use std::{
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    task::{Context, Poll},
+};
+
+use futures::{future::BoxFuture, ready};
+use tower::{Layer, Service};
+
+use crate::ready::take_ready;
+
+/// An async filter whose future can borrow the filter and the request,
+/// instead of cloning the parts it needs before awaiting.
+///
+/// # Example
+/// ```rust
+/// use futures::future::BoxFuture;
+/// use tower_fallthrough_filter::BorrowingAsyncFilter;
+///
+/// #[derive(Clone)]
+/// struct HasPrefix(String);
+///
+/// impl BorrowingAsyncFilter<String> for HasPrefix {
+///     type Future<'a> = BoxFuture<'a, bool>;
+///
+///     fn matches<'a>(&'a self, path: &'a String) -> Self::Future<'a> {
+///         Box::pin(async move {
+///             tokio::task::yield_now().await;
+///             // NOTE: Neither the prefix nor the path has to be cloned.
+///             path.starts_with(&self.0)
+///         })
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let filter = HasPrefix("/api".to_string());
+/// assert!(filter.matches(&"/api/users".to_string()).await);
+/// # }
+/// ```
+pub trait BorrowingAsyncFilter<T>: Clone {
+    type Future<'a>: Future<Output = bool> + Send + 'a
+    where
+        Self: 'a,
+        T: 'a;
+
+    fn matches<'a>(&'a self, item: &'a T) -> Self::Future<'a>;
+}
+
+/// The counterpart of [`AsyncFilterLayer`](crate::AsyncFilterLayer)
+/// for a [`BorrowingAsyncFilter`].
+///
+/// As the filter future borrows the request, it is evaluated within a
+/// boxed future owning the request, the filter and both services, which
+/// afterwards calls the selected service with the request. The request
+/// has to be `Sync`, so the borrowing future can be `Send`.
+#[derive(Debug)]
+pub struct BorrowingAsyncFilterLayer<F, S, T> {
+    filter: F,
+    service: S,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, T> Clone for BorrowingAsyncFilterLayer<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: BorrowingAsyncFilter<T>, S: Service<T>, T> BorrowingAsyncFilterLayer<F, S, T> {
+    /// Creates a new BorrowingAsyncFilterLayer given a
+    /// `BorrowingAsyncFilter` and a `Service`.
+    pub fn new(filter: F, service: S) -> Self {
+        Self {
+            filter,
+            service,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> Layer<I> for BorrowingAsyncFilterLayer<F, S, T>
+where
+    F: BorrowingAsyncFilter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = BorrowingAsyncFilterService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        BorrowingAsyncFilterService {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: inner_service,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+pub struct BorrowingAsyncFilterService<F, S, I, T> {
+    filter: F,
+    service: S,
+    inner: I,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, I: Clone, T> Clone for BorrowingAsyncFilterService<F, S, I, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: self.inner.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> fmt::Debug for BorrowingAsyncFilterService<F, S, I, T>
+where
+    F: fmt::Debug,
+    S: fmt::Debug,
+    I: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BorrowingAsyncFilterService")
+            .field("filter", &self.filter)
+            .field("service", &self.service)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<F, S, I, T> Service<T> for BorrowingAsyncFilterService<F, S, I, T>
+where
+    F: BorrowingAsyncFilter<T> + Send + Sync + 'static,
+    S: Service<T> + Clone + Send + 'static,
+    S::Future: Send,
+    I: Service<T, Response = S::Response, Error = S::Error> + Clone + Send + 'static,
+    I::Future: Send,
+    T: Send + Sync + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.service.poll_ready(cx))?;
+        ready!(self.inner.poll_ready(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        let filter = self.filter.clone();
+        let mut service = take_ready(&mut self.service);
+        let mut inner = take_ready(&mut self.inner);
+
+        // NOTE: The filter future borrows the request, so the request is
+        //       only moved into the selected service once it completed.
+        Box::pin(async move {
+            if filter.matches(&req).await {
+                service.call(req).await
+            } else {
+                inner.call(req).await
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_util::*;
+
+    struct Request {
+        headers: HashMap<String, String>,
+    }
+
+    #[derive(Clone)]
+    struct HeaderIs(&'static str, &'static str);
+
+    impl BorrowingAsyncFilter<Request> for HeaderIs {
+        type Future<'a> = BoxFuture<'a, bool>;
+
+        fn matches<'a>(&'a self, req: &'a Request) -> Self::Future<'a> {
+            Box::pin(async move {
+                // NOTE: The header is borrowed across the `.await`.
+                let value = req.headers.get(self.0);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+
+                value.is_some_and(|value| value == self.1)
+            })
+        }
+    }
+
+    fn request(beta: &str) -> Request {
+        Request {
+            headers: HashMap::from([("x-beta".to_string(), beta.to_string())]),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_filter_while_borrowing_request() {
+        let service = BorrowingAsyncFilterLayer::new(HeaderIs("x-beta", "on"), TestService("beta"))
+            .layer(TestService("stable"));
+
+        assert_eq!(service.clone().oneshot(request("on")).await, Ok("beta"));
+        assert_eq!(service.oneshot(request("off")).await, Ok("stable"));
+    }
+}
@@ -0,0 +1,599 @@
+use std::{
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use tower::{
+    layer::layer_fn,
+    util::{BoxCloneService, BoxLayer, BoxService},
+    Layer, Service,
+};
+
+use crate::{BoxFilter, Filter, FilterLayer, FilterService};
+
+#[cfg(feature = "async")]
+use crate::{AsyncFilter, AsyncFilterLayer, AsyncFilterService};
+
+/// A type erased layer creating a `BoxCloneService`, like
+/// `tower::util::BoxLayer` but for clonable services.
+///
+/// Created by [`FilterLayer::boxed`] or [`BoxCloneLayer::new`] for any
+/// other layer, so they can be stored alongside each other, e.g. in a
+/// `Vec` of layers built from configuration.
+pub struct BoxCloneLayer<In, T, U, E> {
+    boxed: Arc<dyn Layer<In, Service = BoxCloneService<T, U, E>> + Send + Sync>,
+}
+
+impl<In, T, U, E> BoxCloneLayer<In, T, U, E> {
+    /// Creates a new BoxCloneLayer given the `Layer` to erase.
+    pub fn new<L>(inner_layer: L) -> Self
+    where
+        L: Layer<In> + Send + Sync + 'static,
+        L::Service: Service<T, Response = U, Error = E> + Clone + Send + 'static,
+        <L::Service as Service<T>>::Future: Send + 'static,
+    {
+        let layer = layer_fn(move |inner: In| BoxCloneService::new(inner_layer.layer(inner)));
+
+        Self {
+            boxed: Arc::new(layer),
+        }
+    }
+}
+
+impl<In, T, U, E> Layer<In> for BoxCloneLayer<In, T, U, E> {
+    type Service = BoxCloneService<T, U, E>;
+
+    fn layer(&self, inner: In) -> Self::Service {
+        self.boxed.layer(inner)
+    }
+}
+
+impl<In, T, U, E> Clone for BoxCloneLayer<In, T, U, E> {
+    fn clone(&self) -> Self {
+        Self {
+            boxed: self.boxed.clone(),
+        }
+    }
+}
+
+impl<In, T, U, E> fmt::Debug for BoxCloneLayer<In, T, U, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxCloneLayer").finish_non_exhaustive()
+    }
+}
+
+impl<F, S, T> FilterLayer<F, S, T>
+where
+    F: Filter<T> + Send + Sync + 'static,
+    S: Service<T> + Clone + Send + Sync + 'static,
+    S::Future: Send + 'static,
+    T: Send + 'static,
+{
+    /// Erases the type of the layer and the services it creates, so it
+    /// can be stored alongside other layers, e.g. in a `Vec` of layers
+    /// built from configuration.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tower::{
+    ///     service_fn,
+    ///     util::{BoxCloneService, MapResponseLayer},
+    ///     Layer, ServiceExt,
+    /// };
+    /// use tower_fallthrough_filter::{BoxCloneLayer, Filter, FilterLayer};
+    ///
+    /// #[derive(Clone)]
+    /// struct IsEven;
+    ///
+    /// impl Filter<u32> for IsEven {
+    ///     fn matches(&self, item: &u32) -> bool {
+    ///         item.is_multiple_of(2)
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let echo = service_fn(|n: u32| async move { Ok::<_, ()>(n) });
+    ///
+    /// let layers: Vec<BoxCloneLayer<BoxCloneService<u32, u32, ()>, u32, u32, ()>> = vec![
+    ///     FilterLayer::new(IsEven, echo.map_response(|n| n / 2)).boxed(),
+    ///     BoxCloneLayer::new(MapResponseLayer::new(|n: u32| n + 1)),
+    /// ];
+    ///
+    /// let service = layers
+    ///     .iter()
+    ///     .fold(BoxCloneService::new(echo), |service, layer| layer.layer(service));
+    ///
+    /// assert_eq!(service.oneshot(4).await, Ok(3));
+    /// # }
+    /// ```
+    pub fn boxed<I>(self) -> BoxCloneLayer<I, T, S::Response, S::Error>
+    where
+        I: Service<T, Response = S::Response, Error = S::Error> + Clone + Send + 'static,
+        I::Future: Send + 'static,
+    {
+        BoxCloneLayer::new(self)
+    }
+
+    /// Erases the type of the layer into a `tower::util::BoxLayer`,
+    /// creating `tower::util::BoxService`s.
+    ///
+    /// Unlike [`FilterLayer::boxed`] the inner service doesn't have to
+    /// be clonable, but neither are the created services.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tower::{service_fn, util::BoxLayer, Layer, ServiceExt};
+    /// use tower_fallthrough_filter::{FilterFn, FilterLayer};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let respond = |name| service_fn(move |_: u32| async move { Ok::<_, ()>(name) });
+    ///
+    /// let layer: BoxLayer<_, u32, &str, ()> =
+    ///     FilterLayer::new(FilterFn::new(|n: &u32| *n < 10), respond("small")).into_layer();
+    ///
+    /// assert_eq!(layer.layer(respond("large")).oneshot(5).await, Ok("small"));
+    /// # }
+    /// ```
+    pub fn into_layer<I>(self) -> BoxLayer<I, T, S::Response, S::Error>
+    where
+        I: Service<T, Response = S::Response, Error = S::Error> + Send + 'static,
+        I::Future: Send + 'static,
+    {
+        BoxLayer::new(self)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F, S, T> AsyncFilterLayer<F, S, T>
+where
+    F: AsyncFilter<T> + Send + Sync + 'static,
+    F::Future: Send + 'static,
+    S: Service<T> + Clone + Send + Sync + 'static,
+    S::Future: Send + 'static,
+    T: Send + 'static,
+{
+    /// Erases the type of the layer and the services it creates,
+    /// see [`FilterLayer::boxed`].
+    pub fn boxed<I>(self) -> BoxCloneLayer<I, T, S::Response, S::Error>
+    where
+        I: Service<T, Response = S::Response, Error = S::Error> + Clone + Send + 'static,
+        I::Future: Send + 'static,
+    {
+        BoxCloneLayer::new(self)
+    }
+
+    /// Erases the type of the layer into a `tower::util::BoxLayer`,
+    /// see [`FilterLayer::into_layer`].
+    pub fn into_layer<I>(self) -> BoxLayer<I, T, S::Response, S::Error>
+    where
+        I: Service<T, Response = S::Response, Error = S::Error> + Clone + Send + 'static,
+        I::Future: Send + 'static,
+    {
+        BoxLayer::new(self)
+    }
+}
+
+impl<F, S, I, T> FilterService<F, S, I, T>
+where
+    F: Filter<T> + Send + 'static,
+    S: Service<T> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    I: Service<T, Response = S::Response, Error = S::Error> + Clone + Send + 'static,
+    I::Future: Send + 'static,
+    T: 'static,
+{
+    /// Erases the type of the service, so it can be stored without
+    /// spelling out the types of the filter and the services.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::convert::Infallible;
+    ///
+    /// use tower::{service_fn, util::BoxCloneService, ServiceExt};
+    /// use tower_fallthrough_filter::{Filter, FilterService};
+    ///
+    /// #[derive(Clone)]
+    /// struct IsApi;
+    ///
+    /// impl Filter<String> for IsApi {
+    ///     fn matches(&self, path: &String) -> bool {
+    ///         path.starts_with("/api")
+    ///     }
+    /// }
+    ///
+    /// #[derive(Clone)]
+    /// struct AppState {
+    ///     router: BoxCloneService<String, &'static str, Infallible>,
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let respond = |name| service_fn(move |_: String| async move { Ok::<_, Infallible>(name) });
+    ///
+    /// let state = AppState {
+    ///     router: FilterService::new(IsApi, respond("api"), respond("page")).into_box_clone(),
+    /// };
+    ///
+    /// let path = "/api/users".to_string();
+    /// assert_eq!(state.router.clone().oneshot(path).await, Ok("api"));
+    /// # }
+    /// ```
+    pub fn into_box_clone(self) -> BoxCloneService<T, S::Response, S::Error> {
+        BoxCloneService::new(self)
+    }
+}
+
+impl<F, S, I, T> FilterService<F, S, I, T>
+where
+    F: Filter<T> + Send + 'static,
+    S: Service<T> + Send + 'static,
+    S::Future: Send + 'static,
+    I: Service<T, Response = S::Response, Error = S::Error> + Send + 'static,
+    I::Future: Send + 'static,
+    T: 'static,
+{
+    /// Erases the type of the service into a `tower::util::BoxService`,
+    /// e.g. to pass it to code expecting one.
+    ///
+    /// Unlike [`FilterService::into_box_clone`] the services don't
+    /// have to be clonable, but neither is the boxed service.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tower::{service_fn, util::BoxService, ServiceExt};
+    /// use tower_fallthrough_filter::{FilterFn, FilterService};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let respond = |name| service_fn(move |_: u32| async move { Ok::<_, ()>(name) });
+    ///
+    /// let service: BoxService<u32, &str, ()> =
+    ///     FilterService::new(FilterFn::new(|n: &u32| *n < 10), respond("small"), respond("large"))
+    ///         .into_tower_service();
+    ///
+    /// assert_eq!(service.oneshot(50).await, Ok("large"));
+    /// # }
+    /// ```
+    pub fn into_tower_service(self) -> BoxService<T, S::Response, S::Error> {
+        BoxService::new(self)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F, S, I, T> AsyncFilterService<F, S, I, T>
+where
+    F: AsyncFilter<T> + Send + 'static,
+    F::Future: 'static,
+    S: Service<T> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    I: Service<T, Response = S::Response, Error = S::Error> + Clone + Send + 'static,
+    I::Future: Send + 'static,
+    T: Send + 'static,
+{
+    /// Erases the type of the service,
+    /// see [`FilterService::into_box_clone`].
+    pub fn into_box_clone(self) -> BoxCloneService<T, S::Response, S::Error> {
+        BoxCloneService::new(self)
+    }
+
+    /// Erases the type of the service into a `tower::util::BoxService`,
+    /// see [`FilterService::into_tower_service`].
+    pub fn into_tower_service(self) -> BoxService<T, S::Response, S::Error> {
+        BoxService::new(self)
+    }
+}
+
+/// A [`FilterLayer`] with the filter and the service type-erased,
+/// created by [`FilterLayer::erase`].
+///
+/// The layer and the [`DynFilterService`]s it creates have the same
+/// type regardless of the filter and the services inside, so nesting
+/// them doesn't grow the type of the stack, which keeps compile times
+/// and error messages in check.
+///
+/// The price is an allocation per request for the future of the called
+/// service and dynamic dispatch for the filter and the services.
+pub struct DynFilterLayer<T, R, E> {
+    layer: FilterLayer<BoxFilter<T>, BoxCloneService<T, R, E>, T>,
+}
+
+impl<T, R, E> Clone for DynFilterLayer<T, R, E> {
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<T, R, E> fmt::Debug for DynFilterLayer<T, R, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynFilterLayer").finish_non_exhaustive()
+    }
+}
+
+impl<T, R, E, I> Layer<I> for DynFilterLayer<T, R, E>
+where
+    I: Service<T, Response = R, Error = E> + Clone + Send + 'static,
+    I::Future: Send + 'static,
+{
+    type Service = DynFilterService<T, R, E>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        DynFilterService {
+            service: self.layer.layer(BoxCloneService::new(inner_service)),
+        }
+    }
+}
+
+type ErasedFilterService<T, R, E> =
+    FilterService<BoxFilter<T>, BoxCloneService<T, R, E>, BoxCloneService<T, R, E>, T>;
+
+/// The service created by a [`DynFilterLayer`].
+pub struct DynFilterService<T, R, E> {
+    service: ErasedFilterService<T, R, E>,
+}
+
+impl<T, R, E> Clone for DynFilterService<T, R, E> {
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+        }
+    }
+}
+
+impl<T, R, E> fmt::Debug for DynFilterService<T, R, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynFilterService").finish_non_exhaustive()
+    }
+}
+
+impl<T, R, E> Service<T> for DynFilterService<T, R, E> {
+    type Response = R;
+    type Error = E;
+    type Future = BoxFuture<'static, Result<R, E>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        // NOTE: Both services return a `BoxFuture`, so the future
+        //       isn't boxed a second time.
+        self.service.call(req).into_inner()
+    }
+}
+
+impl<F, S, T> FilterLayer<F, S, T>
+where
+    F: Filter<T> + Send + Sync + 'static,
+    S: Service<T> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    /// Erases the type of the filter and the service, see [`DynFilterLayer`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tower::{service_fn, Layer, ServiceExt};
+    /// use tower_fallthrough_filter::{DynFilterService, FilterFn, FilterLayer};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let respond = |name| service_fn(move |_: u32| async move { Ok::<_, ()>(name) });
+    ///
+    /// let medium = FilterLayer::new(FilterFn::new(|n: &u32| *n < 100), respond("medium"))
+    ///     .erase()
+    ///     .layer(respond("large"));
+    ///
+    /// // NOTE: The type stays the same however many layers are nested.
+    /// let service: DynFilterService<u32, &str, ()> =
+    ///     FilterLayer::new(FilterFn::new(|n: &u32| *n < 10), respond("small"))
+    ///         .erase()
+    ///         .layer(medium);
+    ///
+    /// assert_eq!(service.clone().oneshot(50).await, Ok("medium"));
+    /// assert_eq!(service.oneshot(500).await, Ok("large"));
+    /// # }
+    /// ```
+    pub fn erase(self) -> DynFilterLayer<T, S::Response, S::Error> {
+        DynFilterLayer {
+            layer: self
+                .map_filter(BoxFilter::new)
+                .map_service(BoxCloneService::new),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, convert::Infallible, rc::Rc};
+
+    use tower::{util::MapResponseLayer, ServiceExt};
+
+    use super::*;
+    use crate::test_util::*;
+
+    type Layers<T> = Vec<BoxCloneLayer<BoxCloneService<T, u32, Infallible>, T, u32, Infallible>>;
+
+    #[tokio::test]
+    async fn should_fold_boxed_layers() {
+        for (matches, expected) in [(true, 11), (false, 21)] {
+            let layers: Layers<()> = vec![
+                FilterLayer::new(TestFilter(matches), TestService(1)).boxed(),
+                BoxCloneLayer::new(MapResponseLayer::new(|n: u32| n * 10)),
+                BoxCloneLayer::new(MapResponseLayer::new(|n: u32| n + 1)),
+            ];
+
+            let service = layers
+                .iter()
+                .fold(BoxCloneService::new(TestService(2)), |service, layer| {
+                    layer.layer(service)
+                });
+
+            assert_eq!(service.oneshot(()).await, Ok(expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn should_box_layers_for_unsync_requests() {
+        let layers: Layers<Cell<u32>> =
+            vec![FilterLayer::new(TestFilter(true), TestService(1)).boxed()];
+
+        let service = layers[0].layer(BoxCloneService::new(TestService(2)));
+        assert_eq!(service.oneshot(Cell::new(0)).await, Ok(1));
+    }
+
+    fn assert_box_clone<S, T>(_: &S)
+    where
+        S: Service<T> + Clone + Send + 'static,
+        S::Future: Send + 'static,
+    {
+    }
+
+    #[tokio::test]
+    async fn should_box_services() {
+        let service = FilterService::new(TestFilter(true), TestService(1), TestService(2));
+        assert_box_clone::<_, ()>(&service);
+
+        let boxed: BoxCloneService<(), u32, Infallible> = service.into_box_clone();
+        assert_eq!(boxed.clone().oneshot(()).await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn should_box_services_for_unsend_requests() {
+        let service = FilterService::new(TestFilter(false), TestService(1), TestService(2));
+        assert_box_clone::<_, Rc<u32>>(&service);
+
+        assert_eq!(service.into_box_clone().oneshot(Rc::new(0)).await, Ok(2));
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test]
+    async fn should_box_http_services() {
+        let service: crate::HttpFilterService<_, _, _, ()> =
+            FilterService::new(TestFilter(true), TestService(1), TestService(2));
+
+        let req = http::Request::new(());
+        assert_eq!(service.into_box_clone().oneshot(req).await, Ok(1));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn should_box_async_services() {
+        let service = AsyncFilterService::new(TestFilter(true), TestService(1), TestService(2));
+        assert_box_clone::<_, ()>(&service);
+
+        assert_eq!(service.into_box_clone().oneshot(()).await, Ok(1));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn should_box_async_services_with_unsync_filters() {
+        #[derive(Clone)]
+        struct UnsyncFilter(Cell<bool>);
+
+        impl<T> AsyncFilter<T> for UnsyncFilter {
+            type Future = futures::future::Ready<bool>;
+
+            fn matches(&self, _: &T) -> Self::Future {
+                futures::future::ready(self.0.get())
+            }
+        }
+
+        let filter = UnsyncFilter(Cell::new(true));
+        let service = AsyncFilterService::new(filter.clone(), TestService(1), TestService(2));
+        assert_eq!(service.into_box_clone().oneshot(()).await, Ok(1));
+
+        filter.0.set(false);
+        let service = AsyncFilterService::new(filter, TestService(1), TestService(2));
+        assert_eq!(service.into_tower_service().oneshot(()).await, Ok(2));
+    }
+
+    #[tokio::test]
+    async fn should_box_into_tower_services() {
+        let (service_a, ready) = PendingService::new(1);
+        let service = FilterService::new(TestFilter(true), service_a, TestService(2));
+
+        ready.store(true, std::sync::atomic::Ordering::SeqCst);
+        let boxed: BoxService<(), u32, Infallible> = service.into_tower_service();
+        assert_eq!(boxed.oneshot(()).await, Ok(1));
+
+        #[cfg(feature = "async")]
+        {
+            let service =
+                AsyncFilterService::new(TestFilter(false), TestService(1), TestService(2));
+            assert_eq!(service.into_tower_service().oneshot(()).await, Ok(2));
+        }
+    }
+
+    #[tokio::test]
+    async fn should_box_into_tower_layers() {
+        let layer: BoxLayer<NotClone, (), usize, Infallible> =
+            FilterLayer::new(TestFilter(false), TestService(0)).into_layer();
+        assert_eq!(layer.layer(NotClone::default()).oneshot(()).await, Ok(1));
+
+        #[cfg(feature = "async")]
+        {
+            let layer = AsyncFilterLayer::new(TestFilter(true), TestService(1)).into_layer();
+            assert_eq!(layer.layer(TestService(2)).oneshot(()).await, Ok(1));
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn should_box_async_layers() {
+        let layers: Layers<()> = vec![
+            AsyncFilterLayer::new(TestFilter(false), TestService(1)).boxed(),
+            BoxCloneLayer::new(MapResponseLayer::new(|n: u32| n * 10)),
+        ];
+
+        let service = layers
+            .iter()
+            .fold(BoxCloneService::new(TestService(2)), |service, layer| {
+                layer.layer(service)
+            });
+
+        assert_eq!(service.oneshot(()).await, Ok(20));
+    }
+
+    #[tokio::test]
+    async fn should_route_through_nested_erased_layers() {
+        let below = |limit: u32, name: &'static str| {
+            FilterLayer::new(
+                crate::FilterFn::new(move |n: &u32| *n < limit),
+                TestService(name),
+            )
+            .erase()
+        };
+
+        // NOTE: Each erased layer creates the same type of service.
+        let service: DynFilterService<u32, &'static str, Infallible> =
+            below(10, "tiny").layer(below(100, "small").layer(
+                below(1_000, "medium").layer(below(10_000, "large").layer(TestService("huge"))),
+            ));
+
+        for (n, expected) in [
+            (5, "tiny"),
+            (50, "small"),
+            (500, "medium"),
+            (5_000, "large"),
+            (50_000, "huge"),
+        ] {
+            assert_eq!(service.clone().oneshot(n).await, Ok(expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn should_keep_settings_when_erased() {
+        let (service_a, _) = PendingService::new("a");
+        let service = FilterLayer::new(TestFilter(true), service_a)
+            .failover_on_pending(true)
+            .erase()
+            .layer(TestService("b"));
+
+        assert_eq!(service.oneshot(()).await, Ok("b"));
+    }
+}
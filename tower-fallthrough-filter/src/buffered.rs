@@ -0,0 +1,303 @@
+use std::marker::PhantomData;
+
+use tower::{buffer::Buffer, BoxError, Layer, Service};
+
+use crate::{Filter, FilterLayer, FilterService};
+
+#[cfg(feature = "async")]
+use crate::{AsyncFilter, AsyncFilterLayer};
+
+/// A Tower layer that wraps the [`FilterService`] it creates in a
+/// [`tower::buffer::Buffer`], so the resulting service can be cloned
+/// cheaply and shared between threads and tasks.
+///
+/// All clones send their requests through a channel to a single worker
+/// task owning the `FilterService`, so the filter and services
+/// themselves don't have to be `Sync`. Errors of the services are
+/// boxed into a [`tower::BoxError`].
+///
+/// Created by [`FilterLayer::buffered`].
+///
+/// # Panics
+/// Calling [`Layer::layer`] spawns the worker and thus panics
+/// when it's not called within a Tokio runtime.
+#[derive(Debug)]
+pub struct BufferedFilterLayer<F, S, T> {
+    filter: F,
+    service: S,
+    capacity: usize,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, T> Clone for BufferedFilterLayer<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            capacity: self.capacity,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, T> FilterLayer<F, S, T>
+where
+    F: Filter<T>,
+    S: Service<T>,
+{
+    /// Wraps the created services in a [`tower::buffer::Buffer`]
+    /// holding up to `capacity` requests, see [`BufferedFilterLayer`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tower::{service_fn, Layer, Service, ServiceExt};
+    /// use tower_fallthrough_filter::{Filter, FilterLayer};
+    ///
+    /// #[derive(Clone)]
+    /// struct IsEven;
+    ///
+    /// impl Filter<u32> for IsEven {
+    ///     fn matches(&self, item: &u32) -> bool {
+    ///         item % 2 == 0
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let even = service_fn(|_: u32| async { Ok::<_, std::io::Error>("even".to_string()) });
+    /// let odd = service_fn(|_: u32| async { Ok::<_, std::io::Error>("odd".to_string()) });
+    ///
+    /// let service = FilterLayer::new(IsEven, even).buffered(32).layer(odd);
+    ///
+    /// let handles = (0..4).map(|i| {
+    ///     let service = service.clone();
+    ///     tokio::spawn(async move { service.oneshot(i).await.unwrap() })
+    /// });
+    ///
+    /// for (i, handle) in handles.enumerate() {
+    ///     let expected = if i % 2 == 0 { "even" } else { "odd" };
+    ///     assert_eq!(handle.await.unwrap(), expected);
+    /// }
+    /// # }
+    /// ```
+    pub fn buffered(self, capacity: usize) -> BufferedFilterLayer<F, S, T> {
+        BufferedFilterLayer {
+            filter: self.filter,
+            service: self.service,
+            capacity,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, T> FilterLayer<F, Buffer<S, T>, T>
+where
+    F: Filter<T>,
+    S: Service<T> + Send + 'static,
+    S::Error: Into<BoxError> + Send + Sync,
+    S::Future: Send,
+    T: Send + 'static,
+{
+    /// Creates a new FilterLayer given a `Filter` and a `Service` that
+    /// isn't clonable, e.g. as it owns a connection, by wrapping it in a
+    /// [`tower::buffer::Buffer`] holding up to `capacity` requests.
+    ///
+    /// Unlike [`FilterLayer::buffered`] only the filtered service is
+    /// buffered, so matching requests take an extra hop through a channel
+    /// to the worker task owning the service, while requests falling
+    /// through are called directly. Errors of the filtered service are
+    /// boxed into a [`tower::BoxError`], so the inner service has to use
+    /// it as well, e.g. using `ServiceExt::map_err`.
+    ///
+    /// # Panics
+    /// Spawns the worker and thus panics when it's not called within a
+    /// Tokio runtime.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::{
+    ///     convert::Infallible,
+    ///     future::{ready, Ready},
+    ///     task::{Context, Poll},
+    /// };
+    ///
+    /// use tower::{service_fn, BoxError, Layer, Service, ServiceExt};
+    /// use tower_fallthrough_filter::{FilterFn, FilterLayer};
+    ///
+    /// // NOTE: Not clonable, e.g. as it holds a pool guard.
+    /// struct Database;
+    ///
+    /// impl Service<u32> for Database {
+    ///     type Response = &'static str;
+    ///     type Error = Infallible;
+    ///     type Future = Ready<Result<Self::Response, Self::Error>>;
+    ///
+    ///     fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    ///         Poll::Ready(Ok(()))
+    ///     }
+    ///
+    ///     fn call(&mut self, _: u32) -> Self::Future {
+    ///         ready(Ok("database"))
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let cache = service_fn(|_: u32| async { Ok::<_, BoxError>("cache") });
+    ///
+    /// let is_miss = FilterFn::new(|key: &u32| *key > 100);
+    /// let service = FilterLayer::new_buffered(is_miss, Database, 32).layer(cache);
+    ///
+    /// assert_eq!(service.clone().oneshot(7).await.unwrap(), "cache");
+    /// assert_eq!(service.oneshot(700).await.unwrap(), "database");
+    /// # }
+    /// ```
+    pub fn new_buffered(filter: F, service: S, capacity: usize) -> Self {
+        Self::new(filter, Buffer::new(service, capacity))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F, S, T> AsyncFilterLayer<F, Buffer<S, T>, T>
+where
+    F: AsyncFilter<T>,
+    S: Service<T> + Send + 'static,
+    S::Error: Into<BoxError> + Send + Sync,
+    S::Future: Send,
+    T: Send + 'static,
+{
+    /// Creates a new AsyncFilterLayer given an `AsyncFilter` and a
+    /// `Service` that isn't clonable, see [`FilterLayer::new_buffered`].
+    ///
+    /// # Panics
+    /// Spawns the worker and thus panics when it's not called within a
+    /// Tokio runtime.
+    pub fn new_buffered(filter: F, service: S, capacity: usize) -> Self {
+        Self::new(filter, Buffer::new(service, capacity))
+    }
+}
+
+impl<F, S, I, T> Layer<I> for BufferedFilterLayer<F, S, T>
+where
+    F: Filter<T> + Send + 'static,
+    S: Service<T> + Clone + Send + 'static,
+    S::Response: Send + 'static,
+    S::Error: Into<BoxError> + Send + Sync + 'static,
+    S::Future: Send + 'static,
+    I: Service<T, Response = S::Response, Error = S::Error> + Clone + Send + 'static,
+    I::Future: Send + 'static,
+    T: Send + 'static,
+{
+    type Service = Buffer<FilterService<F, S, I, T>, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        let service = FilterLayer::new(self.filter.clone(), self.service.clone());
+
+        Buffer::new(service.layer(inner_service), self.capacity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_util::*;
+
+    fn assert_send_sync_clone<T: Send + Sync + Clone>(_: &T) {}
+
+    type Odd = tower::util::MapErr<TestService<usize>, fn(std::convert::Infallible) -> BoxError>;
+
+    /// Responds with `0`, the buffered `NotClone` counts from `1`.
+    fn odd() -> Odd {
+        tower::util::MapErr::new(TestService(0), BoxError::from)
+    }
+
+    #[derive(Clone)]
+    struct IsEven;
+
+    impl Filter<u32> for IsEven {
+        fn matches(&self, item: &u32) -> bool {
+            item.is_multiple_of(2)
+        }
+    }
+
+    #[test]
+    fn should_be_send_sync_clone() {
+        type Req = axum::http::Request<axum::body::Body>;
+
+        let service = tower::service_fn(|_: Req| async { Ok::<_, BoxError>("a") });
+        let layer = FilterLayer::new(TestFilter(true), service).buffered(8);
+
+        assert_send_sync_clone(&layer);
+    }
+
+    #[test]
+    fn should_serve_from_multiple_threads() {
+        let runtime = Arc::new(
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(2)
+                .build()
+                .unwrap(),
+        );
+
+        let service = {
+            let _guard = runtime.enter();
+            FilterLayer::new(IsEven, TestService("even"))
+                .buffered(4)
+                .layer(TestService("odd"))
+        };
+        assert_send_sync_clone(&service);
+
+        let threads: Vec<_> = (0..10u32)
+            .map(|i| {
+                let runtime = runtime.clone();
+                let service = service.clone();
+
+                thread::spawn(move || runtime.block_on(service.oneshot(i)).unwrap())
+            })
+            .collect();
+
+        for (i, thread) in threads.into_iter().enumerate() {
+            let expected = if i % 2 == 0 { "even" } else { "odd" };
+            assert_eq!(thread.join().unwrap(), expected);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_buffer_non_clone_service() {
+        let layer = FilterLayer::new_buffered(IsEven, NotClone::default(), 4);
+
+        let tasks: Vec<_> = (0..10u32)
+            .map(|i| {
+                // NOTE: Every request goes through its own `FilterService`,
+                //       sharing the buffered service.
+                let service = layer.layer(odd());
+                tokio::spawn(service.oneshot(i))
+            })
+            .collect();
+
+        let mut responses = Vec::new();
+        for task in tasks {
+            responses.push(task.await.unwrap().unwrap());
+        }
+
+        responses.sort();
+        assert_eq!(responses, [0, 0, 0, 0, 0, 1, 2, 3, 4, 5]);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn should_buffer_non_clone_service_async() {
+        let layer = AsyncFilterLayer::new_buffered(TestFilter(true), NotClone::default(), 4);
+
+        let first = layer.layer(odd()).oneshot(1).await.unwrap();
+        let second = layer.layer(odd()).oneshot(3).await.unwrap();
+        assert_eq!((first, second), (1, 2));
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    any::Any,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+};
+
+use crate::Filter;
+
+type PanicHook = Arc<dyn Fn(&(dyn Any + Send)) + Send + Sync>;
+
+/// Whether the panics of a filter are caught, see
+/// [`FilterLayer::catch_panics`](crate::FilterLayer::catch_panics).
+#[derive(Clone, Default)]
+pub(crate) struct CatchPanics {
+    enabled: bool,
+    hook: Option<PanicHook>,
+}
+
+impl CatchPanics {
+    pub(crate) fn enable(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub(crate) fn set_hook(&mut self, hook: impl Fn(&(dyn Any + Send)) + Send + Sync + 'static) {
+        self.hook = Some(Arc::new(hook));
+    }
+
+    /// Runs `f`, returning `None` if it panicked while catching panics.
+    pub(crate) fn catch<R>(&self, f: impl FnOnce() -> R) -> Option<R> {
+        if !self.enabled {
+            return Some(f());
+        }
+
+        match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(output) => Some(output),
+            Err(payload) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!("filter panicked, falling through");
+
+                if let Some(hook) = &self.hook {
+                    hook(&*payload);
+                }
+                None
+            }
+        }
+    }
+
+    /// Whether the filter matches, a panicking filter doesn't.
+    pub(crate) fn matches<F: Filter<T>, T: ?Sized>(&self, filter: &F, item: &T) -> bool {
+        self.catch(|| filter.matches(item)).unwrap_or(false)
+    }
+}
+
+impl fmt::Debug for CatchPanics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CatchPanics")
+            .field("enabled", &self.enabled)
+            .field("hook", &self.hook.is_some())
+            .finish()
+    }
+}
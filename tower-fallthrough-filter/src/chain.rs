@@ -0,0 +1,639 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, PoisonError, RwLock,
+    },
+    task::{Context, Poll},
+};
+
+use ::futures::{future::Either, ready};
+use tower::{util::BoxCloneService, Layer, Service};
+
+use crate::Filter;
+
+type BoxFilter<T> = Arc<dyn Fn(&T) -> bool + Send + Sync>;
+type DecisionHook<T> = Arc<dyn Fn(&mut T, ChainDecision<'_>) + Send + Sync>;
+
+/// Which entry of a [`FilterChain`] handled a request,
+/// passed to the hook set by [`FilterChain::on_decision`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainDecision<'a> {
+    /// The entry at `index` matched.
+    Matched {
+        /// The position of the entry within the chain.
+        index: usize,
+        /// The name of the entry, if it has one.
+        name: Option<&'a str>,
+    },
+    /// None of the entries matched, so the request fell through.
+    FellThrough,
+}
+
+struct Entry<T, R, E> {
+    name: Option<Arc<str>>,
+    priority: i32,
+    filter: BoxFilter<T>,
+    service: BoxCloneService<T, R, E>,
+}
+
+impl<T, R, E> Clone for Entry<T, R, E> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            priority: self.priority,
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+        }
+    }
+}
+
+struct ChainState<T, R, E> {
+    entries: RwLock<Vec<Entry<T, R, E>>>,
+    // NOTE: Bumped on every change, so the services know when
+    //       their copies of the entries are outdated.
+    generation: AtomicU64,
+}
+
+impl<T, R, E> ChainState<T, R, E> {
+    fn new(entries: Vec<Entry<T, R, E>>) -> Self {
+        Self {
+            entries: RwLock::new(entries),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    fn entries(&self) -> Vec<Entry<T, R, E>> {
+        self.entries
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn update<O>(&self, f: impl FnOnce(&mut Vec<Entry<T, R, E>>) -> O) -> O {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        let output = f(&mut entries);
+        self.generation.fetch_add(1, Ordering::Release);
+
+        output
+    }
+}
+
+/// A Tower layer dispatching to the service of the first matching
+/// filter out of an ordered list, falling through to the inner
+/// service if none of them match.
+///
+/// Compared to stacking multiple [`FilterLayer`](crate::FilterLayer)s
+/// the type stays the same no matter how many entries are added,
+/// as the filters and services are type-erased.
+///
+/// Entries can be named and given a priority to manage them from
+/// configuration, see [`FilterChain::sorted`] and [`FilterChainHandle`].
+///
+/// # Readiness
+/// The created service is only ready once all services of the chain
+/// and the inner service are ready, as any of them might be picked
+/// for the next request.
+///
+/// # Example
+/// ```rust
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{Filter, FilterChain};
+///
+/// #[derive(Clone)]
+/// struct Below(u32);
+///
+/// impl Filter<u32> for Below {
+///     fn matches(&self, item: &u32) -> bool {
+///         *item < self.0
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name: &'static str| service_fn(move |_: u32| async move { Ok::<_, ()>(name) });
+///
+/// let chain = FilterChain::new()
+///     .when(Below(10), respond("small"))
+///     .when(Below(100), respond("medium"));
+///
+/// let service = chain.layer(respond("large"));
+/// assert_eq!(service.clone().oneshot(5).await, Ok("small"));
+/// assert_eq!(service.clone().oneshot(50).await, Ok("medium"));
+/// assert_eq!(service.oneshot(500).await, Ok("large"));
+/// # }
+/// ```
+pub struct FilterChain<T, R, E> {
+    state: Arc<ChainState<T, R, E>>,
+    hook: Option<DecisionHook<T>>,
+}
+
+impl<T, R, E> FilterChain<T, R, E> {
+    /// Creates a new, empty FilterChain.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(ChainState::new(Vec::new())),
+            hook: None,
+        }
+    }
+
+    /// Adds an entry calling `service` if `filter` matches and
+    /// none of the previously added filters did.
+    ///
+    /// Like the other builders it only changes this chain, not its
+    /// clones or the services already created from it.
+    pub fn when<F, S>(mut self, filter: F, service: S) -> Self
+    where
+        F: Filter<T> + Send + Sync + 'static,
+        S: Service<T, Response = R, Error = E> + Clone + Send + 'static,
+        S::Future: Send + 'static,
+        T: 'static,
+    {
+        let entry = Entry {
+            name: None,
+            priority: 0,
+            filter: box_filter(filter),
+            service: BoxCloneService::new(service),
+        };
+
+        self.configure(|entries| entries.push(entry));
+        self
+    }
+
+    /// Names the most recently added entry, so it can be changed
+    /// using a [`FilterChainHandle`] and is reported by the hook set
+    /// with [`FilterChain::on_decision`].
+    ///
+    /// # Panics
+    /// Panics if no entry was added yet.
+    pub fn named(mut self, name: impl Into<Arc<str>>) -> Self {
+        let name = name.into();
+        self.update_last(|entry| entry.name = Some(name));
+        self
+    }
+
+    /// Sets the priority of the most recently added entry, `0` by
+    /// default. Only takes effect once [`FilterChain::sorted`] is called.
+    ///
+    /// # Panics
+    /// Panics if no entry was added yet.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.update_last(|entry| entry.priority = priority);
+        self
+    }
+
+    /// Orders the entries by their priority, highest first.
+    ///
+    /// Entries with the same priority keep the order they were added in.
+    pub fn sorted(mut self) -> Self {
+        self.configure(|entries| {
+            entries.sort_by_key(|entry| std::cmp::Reverse(entry.priority));
+        });
+        self
+    }
+
+    /// Sets a hook called with the decision for every request before
+    /// it is dispatched, e.g. to record which entry handled it or to
+    /// insert the name of the entry into the request extensions.
+    pub fn on_decision<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(&mut T, ChainDecision<'_>) + Send + Sync + 'static,
+    {
+        self.hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Returns a handle to change the named entries at runtime.
+    ///
+    /// The entries are shared by the chain, its clones and all
+    /// services created from it, until one of them is changed using
+    /// a builder, e.g. [`FilterChain::when`], which copies them.
+    pub fn handle(&self) -> FilterChainHandle<T, R, E> {
+        FilterChainHandle(self.state.clone())
+    }
+
+    // NOTE: Copies the entries if they are shared, so building doesn't
+    //       change the clones of the chain or the created services.
+    fn configure(&mut self, f: impl FnOnce(&mut Vec<Entry<T, R, E>>)) {
+        if Arc::get_mut(&mut self.state).is_none() {
+            self.state = Arc::new(ChainState::new(self.state.entries()));
+        }
+
+        self.state.update(f);
+    }
+
+    fn update_last(&mut self, f: impl FnOnce(&mut Entry<T, R, E>)) {
+        self.configure(|entries| {
+            let entry = entries
+                .last_mut()
+                .expect("FilterChain has no entry to configure");
+            f(entry);
+        });
+    }
+}
+
+fn box_filter<F, T>(filter: F) -> BoxFilter<T>
+where
+    F: Filter<T> + Send + Sync + 'static,
+{
+    Arc::new(move |item: &T| filter.matches(item))
+}
+
+impl<T, R, E> Default for FilterChain<T, R, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, R, E> Clone for FilterChain<T, R, E> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            hook: self.hook.clone(),
+        }
+    }
+}
+
+impl<T, R, E> fmt::Debug for FilterChain<T, R, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilterChain")
+            .field("entries", &self.handle().names())
+            .finish()
+    }
+}
+
+/// A handle to change the named entries of a [`FilterChain`] while
+/// serving, e.g. from an admin API.
+///
+/// The services pick up the changes the next time they are polled
+/// for readiness, requests already dispatched are unaffected.
+///
+/// # Example
+/// ```rust
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{Filter, FilterChain};
+///
+/// #[derive(Clone)]
+/// struct Prefix(&'static str);
+///
+/// impl Filter<&'static str> for Prefix {
+///     fn matches(&self, path: &&'static str) -> bool {
+///         path.starts_with(self.0)
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name| service_fn(move |_: &'static str| async move { Ok::<_, ()>(name) });
+///
+/// let chain = FilterChain::new()
+///     .when(Prefix("/api"), respond("api v1"))
+///     .named("api")
+///     .when(Prefix("/"), respond("static"))
+///     .named("static");
+///
+/// let handle = chain.handle();
+/// let service = chain.layer(respond("not found"));
+///
+/// handle.replace("api", Prefix("/api"), respond("api v2"));
+/// assert_eq!(service.clone().oneshot("/api/users").await, Ok("api v2"));
+///
+/// handle.remove("static");
+/// assert_eq!(service.oneshot("/index.html").await, Ok("not found"));
+/// # }
+/// ```
+pub struct FilterChainHandle<T, R, E>(Arc<ChainState<T, R, E>>);
+
+impl<T, R, E> FilterChainHandle<T, R, E> {
+    /// The names of the entries in the order they are evaluated in,
+    /// `None` for unnamed entries.
+    pub fn names(&self) -> Vec<Option<Arc<str>>> {
+        self.0
+            .entries
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|entry| entry.name.clone())
+            .collect()
+    }
+
+    /// Removes the entries named `name`, returning whether there were any.
+    pub fn remove(&self, name: &str) -> bool {
+        self.0.update(|entries| {
+            let len = entries.len();
+            entries.retain(|entry| entry.name.as_deref() != Some(name));
+
+            entries.len() != len
+        })
+    }
+
+    /// Replaces the filter and the service of the entries named `name`,
+    /// keeping their position and priority. Returns whether there were any.
+    pub fn replace<F, S>(&self, name: &str, filter: F, service: S) -> bool
+    where
+        F: Filter<T> + Send + Sync + 'static,
+        S: Service<T, Response = R, Error = E> + Clone + Send + 'static,
+        S::Future: Send + 'static,
+        T: 'static,
+    {
+        let filter = box_filter(filter);
+        let service = BoxCloneService::new(service);
+
+        self.0.update(|entries| {
+            let mut replaced = false;
+            for entry in entries.iter_mut() {
+                if entry.name.as_deref() == Some(name) {
+                    entry.filter = filter.clone();
+                    entry.service = service.clone();
+                    replaced = true;
+                }
+            }
+
+            replaced
+        })
+    }
+}
+
+impl<T, R, E> Clone for FilterChainHandle<T, R, E> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T, R, E> fmt::Debug for FilterChainHandle<T, R, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FilterChainHandle")
+    }
+}
+
+impl<I, T, R, E> Layer<I> for FilterChain<T, R, E>
+where
+    I: Service<T, Response = R, Error = E>,
+{
+    type Service = FilterChainService<I, T, R, E>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        let generation = self.state.generation.load(Ordering::Acquire);
+
+        FilterChainService {
+            state: self.state.clone(),
+            hook: self.hook.clone(),
+            generation,
+            entries: self.state.entries(),
+            inner: inner_service,
+        }
+    }
+}
+
+/// The service created by a [`FilterChain`], calling the service of the
+/// first matching entry or the inner service.
+///
+/// Changes made through a [`FilterChainHandle`] are picked up the next
+/// time it is polled for readiness.
+pub struct FilterChainService<I, T, R, E> {
+    state: Arc<ChainState<T, R, E>>,
+    hook: Option<DecisionHook<T>>,
+
+    // NOTE: The entries as of `generation`, these are the services
+    //       that get polled, so a change can't take away the readiness
+    //       of a service between `poll_ready` and `call`.
+    generation: u64,
+    entries: Vec<Entry<T, R, E>>,
+    inner: I,
+}
+
+impl<I: Clone, T, R, E> Clone for FilterChainService<I, T, R, E> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            hook: self.hook.clone(),
+            generation: self.generation,
+            entries: self.entries.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<I: fmt::Debug, T, R, E> fmt::Debug for FilterChainService<I, T, R, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilterChainService")
+            .field("entries", &self.entries.len())
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<I, T, R, E> Service<T> for FilterChainService<I, T, R, E>
+where
+    I: Service<T, Response = R, Error = E>,
+{
+    type Response = R;
+    type Error = E;
+    type Future = Either<<BoxCloneService<T, R, E> as Service<T>>::Future, I::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let generation = self.state.generation.load(Ordering::Acquire);
+        if generation != self.generation {
+            self.entries = self.state.entries();
+            self.generation = generation;
+        }
+
+        for entry in &mut self.entries {
+            ready!(entry.service.poll_ready(cx))?;
+        }
+        ready!(self.inner.poll_ready(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: T) -> Self::Future {
+        let index = self.entries.iter().position(|entry| (entry.filter)(&req));
+
+        if let Some(hook) = &self.hook {
+            let decision = match index {
+                Some(index) => ChainDecision::Matched {
+                    index,
+                    name: self.entries[index].name.as_deref(),
+                },
+                None => ChainDecision::FellThrough,
+            };
+
+            hook(&mut req, decision);
+        }
+
+        match index {
+            Some(index) => Either::Left(self.entries[index].service.call(req)),
+            None => Either::Right(self.inner.call(req)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use futures::future::{ready, Ready};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_util::*;
+
+    #[derive(Clone)]
+    struct Below(u32);
+
+    impl Filter<u32> for Below {
+        fn matches(&self, item: &u32) -> bool {
+            *item < self.0
+        }
+    }
+
+    #[derive(Clone)]
+    struct NeverReady;
+
+    impl Service<u32> for NeverReady {
+        type Response = &'static str;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Pending
+        }
+
+        fn call(&mut self, _: u32) -> Self::Future {
+            ready(Ok("never"))
+        }
+    }
+
+    fn chain() -> FilterChainService<TestService<&'static str>, u32, &'static str, Infallible> {
+        FilterChain::new()
+            .when(Below(10), TestService("first"))
+            .when(Below(100), TestService("second"))
+            .when(Below(5), TestService("third"))
+            .layer(TestService("inner"))
+    }
+
+    #[tokio::test]
+    async fn should_pick_first_match() {
+        assert_eq!(chain().oneshot(1).await, Ok("first"));
+        assert_eq!(chain().oneshot(50).await, Ok("second"));
+    }
+
+    #[tokio::test]
+    async fn should_fall_through() {
+        assert_eq!(chain().oneshot(500).await, Ok("inner"));
+    }
+
+    #[tokio::test]
+    async fn should_be_ready_once_all_are_ready() {
+        let mut service = FilterChain::new()
+            .when(Below(10), TestService("first"))
+            .when(TestFilter(false), NeverReady)
+            .layer(TestService("inner"));
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert!(service.poll_ready(&mut cx).is_pending());
+    }
+
+    #[tokio::test]
+    async fn should_reorder_by_priority() {
+        let service = FilterChain::new()
+            .when(Below(100), TestService("low"))
+            .priority(1)
+            .when(Below(10), TestService("high"))
+            .priority(5)
+            .when(Below(1000), TestService("default"))
+            .sorted()
+            .layer(TestService("inner"));
+
+        assert_eq!(service.clone().oneshot(1).await, Ok("high"));
+        assert_eq!(service.clone().oneshot(50).await, Ok("low"));
+        assert_eq!(service.oneshot(500).await, Ok("default"));
+    }
+
+    #[tokio::test]
+    async fn should_remove_entries_mid_traffic() {
+        let chain = FilterChain::new()
+            .when(Below(10), TestService("first"))
+            .named("first")
+            .when(Below(100), TestService("second"))
+            .named("second");
+        let handle = chain.handle();
+        let mut service = chain.layer(TestService("inner"));
+
+        // NOTE: A service that is already ready keeps its entries
+        //       until it is polled again.
+        service.ready().await.unwrap();
+        assert!(handle.remove("first"));
+        assert_eq!(service.call(1).await, Ok("first"));
+
+        assert_eq!(service.ready().await.unwrap().call(1).await, Ok("second"));
+        assert_eq!(handle.names(), [Some("second".into())]);
+        assert!(!handle.remove("first"));
+    }
+
+    #[tokio::test]
+    async fn should_replace_entries() {
+        let chain = FilterChain::new()
+            .when(Below(10), TestService("first"))
+            .named("first")
+            .when(Below(100), TestService("second"));
+        let handle = chain.handle();
+        let service = chain.layer(TestService("inner"));
+
+        assert!(handle.replace("first", Below(200), TestService("replaced")));
+        assert_eq!(service.clone().oneshot(150).await, Ok("replaced"));
+        assert!(!handle.replace("unknown", Below(200), TestService("unknown")));
+    }
+
+    #[tokio::test]
+    async fn should_report_matched_entry() {
+        let decisions = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = decisions.clone();
+
+        let service = FilterChain::new()
+            .when(Below(10), TestService("first"))
+            .named("first")
+            .when(Below(100), TestService("second"))
+            .on_decision(move |_: &mut u32, decision| {
+                let decision = match decision {
+                    ChainDecision::Matched { index, name } => {
+                        Some((index, name.map(str::to_owned)))
+                    }
+                    ChainDecision::FellThrough => None,
+                };
+                recorded.lock().unwrap().push(decision);
+            })
+            .layer(TestService("inner"));
+
+        service.clone().oneshot(1).await.unwrap();
+        service.clone().oneshot(50).await.unwrap();
+        service.oneshot(500).await.unwrap();
+
+        assert_eq!(
+            *decisions.lock().unwrap(),
+            [Some((0, Some("first".to_owned()))), Some((1, None)), None]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_not_change_clones_when_building() {
+        let base = FilterChain::new().when(Below(10), TestService("first"));
+        let service = base.clone().layer(TestService("inner"));
+
+        let extended = base
+            .clone()
+            .when(Below(100), TestService("second"))
+            .named("second");
+        let sorted = base.clone().priority(1).sorted();
+
+        assert_eq!(base.handle().names(), [None]);
+        assert_eq!(sorted.handle().names(), [None]);
+        assert_eq!(extended.handle().names(), [None, Some("second".into())]);
+        assert_eq!(service.oneshot(50).await, Ok("inner"));
+        assert_eq!(
+            extended.layer(TestService("inner")).oneshot(50).await,
+            Ok("second")
+        );
+    }
+}
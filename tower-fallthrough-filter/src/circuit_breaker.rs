@@ -0,0 +1,640 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    marker::PhantomData,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+use tower::{Layer, Service};
+
+use crate::{futures::CircuitBreakerFut, Filter, FilterService};
+
+/// The state of the circuit of a [`CircuitBreakerFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CircuitState {
+    /// The filtered service is healthy, so matching requests are
+    /// routed to it.
+    Closed,
+    /// The filtered service failed too often, so all requests fall
+    /// through until the cooldown elapsed.
+    Open,
+    /// The cooldown elapsed, so a single matching request is routed to
+    /// the filtered service as a probe, closing the circuit again if it
+    /// succeeds.
+    HalfOpen,
+}
+
+/// A Tower layer like [`FilterLayer`](crate::FilterLayer), but routing
+/// requests away from the filtered service while it is failing too often.
+///
+/// Once the share of failed calls within the last `window` calls of the
+/// filtered service exceeds the `failure_threshold`, the circuit opens
+/// and all requests fall through for the `cooldown`. Afterwards a single
+/// matching request is routed to the filtered service as a probe, which
+/// closes the circuit if it succeeds or opens it again if it fails.
+///
+/// The circuit is shared by all services created by the layer and its
+/// clones, e.g. by all connections of a server.
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+///
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{CircuitBreakerFilterLayer, CircuitState};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let failing = service_fn(|_: u32| async { Err("unavailable") });
+/// let stable = service_fn(|_: u32| async { Ok("stable") });
+///
+/// let layer = CircuitBreakerFilterLayer::new(true, failing)
+///     .failure_threshold(0.5)
+///     .window(2)
+///     .cooldown(Duration::from_secs(30));
+/// let service = layer.layer(stable);
+///
+/// assert_eq!(service.clone().oneshot(1).await, Err("unavailable"));
+/// assert_eq!(service.clone().oneshot(2).await, Err("unavailable"));
+///
+/// assert_eq!(layer.state(), CircuitState::Open);
+/// assert_eq!(service.oneshot(3).await, Ok("stable"));
+/// # }
+/// ```
+pub struct CircuitBreakerFilterLayer<F, S, T> {
+    filter: F,
+    service: S,
+    circuit: Circuit,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F, S, T> CircuitBreakerFilterLayer<F, S, T>
+where
+    F: Filter<T>,
+    S: Service<T>,
+{
+    /// Creates a new CircuitBreakerFilterLayer given a `Filter` and a
+    /// `Service`, with a closed circuit.
+    ///
+    /// By default the circuit opens once more than half of the last 10
+    /// calls failed, for a cooldown of 30 seconds.
+    pub fn new(filter: F, service: S) -> Self {
+        Self {
+            filter,
+            service,
+            circuit: Circuit::default(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, T> CircuitBreakerFilterLayer<F, S, T> {
+    /// Sets the share of failed calls, between `0.0` and `1.0`, that
+    /// has to be exceeded to open the circuit.
+    ///
+    /// Like the other settings it only applies to the services created
+    /// by this layer afterwards, not to those of its clones.
+    pub fn failure_threshold(mut self, failure_threshold: f64) -> Self {
+        self.circuit.config.failure_threshold = failure_threshold;
+        self
+    }
+
+    /// Sets the number of recent calls the failure rate is computed of.
+    ///
+    /// The circuit doesn't open before that many calls completed.
+    ///
+    /// # Panics
+    /// Panics if `window` is zero.
+    pub fn window(mut self, window: usize) -> Self {
+        assert!(window > 0, "the window has to contain at least one call");
+        self.circuit.config.window = window;
+        self
+    }
+
+    /// Sets how long the circuit stays open before a probe is routed
+    /// to the filtered service.
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.circuit.config.cooldown = cooldown;
+        self
+    }
+
+    /// The current state of the circuit shared by the created services.
+    pub fn state(&self) -> CircuitState {
+        self.circuit.state()
+    }
+}
+
+impl<F: Clone, S: Clone, T> Clone for CircuitBreakerFilterLayer<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            circuit: self.circuit.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: fmt::Debug, S: fmt::Debug, T> fmt::Debug for CircuitBreakerFilterLayer<F, S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBreakerFilterLayer")
+            .field("filter", &self.filter)
+            .field("service", &self.service)
+            .field("state", &self.state())
+            .finish()
+    }
+}
+
+impl<F, S, I, T> Layer<I> for CircuitBreakerFilterLayer<F, S, T>
+where
+    F: Filter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = CircuitBreakerFilterService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        FilterService::new(
+            CircuitBreakerFilter {
+                filter: self.filter.clone(),
+                circuit: self.circuit.clone(),
+            },
+            CircuitBreakerService {
+                service: self.service.clone(),
+                circuit: self.circuit.clone(),
+            },
+            inner_service,
+        )
+    }
+}
+
+/// The service created by a [`CircuitBreakerFilterLayer`].
+pub type CircuitBreakerFilterService<F, S, I, T> =
+    FilterService<CircuitBreakerFilter<F>, CircuitBreakerService<S>, I, T>;
+
+/// A filter matching like the wrapped filter while the circuit allows
+/// routing to the filtered service, see [`CircuitBreakerFilterLayer`].
+#[derive(Clone)]
+pub struct CircuitBreakerFilter<F> {
+    filter: F,
+    circuit: Circuit,
+}
+
+impl<F> CircuitBreakerFilter<F> {
+    /// The current state of the circuit.
+    pub fn state(&self) -> CircuitState {
+        self.circuit.state()
+    }
+}
+
+impl<F: fmt::Debug> fmt::Debug for CircuitBreakerFilter<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBreakerFilter")
+            .field("filter", &self.filter)
+            .field("state", &self.state())
+            .finish()
+    }
+}
+
+impl<F: Filter<T>, T> Filter<T> for CircuitBreakerFilter<F> {
+    fn matches(&self, item: &T) -> bool {
+        // NOTE: The circuit is only asked for matching requests,
+        //       so other requests can't take the probe.
+        self.filter.matches(item) && self.circuit.lock().allow()
+    }
+}
+
+/// The filtered service of a [`CircuitBreakerFilterService`], recording
+/// the outcome of every call into the circuit.
+#[derive(Clone)]
+pub struct CircuitBreakerService<S> {
+    service: S,
+    circuit: Circuit,
+}
+
+impl<S: fmt::Debug> fmt::Debug for CircuitBreakerService<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBreakerService")
+            .field("service", &self.service)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: Service<T>, T> Service<T> for CircuitBreakerService<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = CircuitBreakerFut<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        // NOTE: The filter allowed this call right before, so if it took
+        //       the probe slot, this call is the probe.
+        let probe = self.circuit.lock().start_probe();
+        CircuitBreakerFut::new(self.service.call(req), self.circuit.clone(), probe)
+    }
+}
+
+/// The circuit shared by the filters and services of a layer, along
+/// with the settings of the layer that created them.
+#[derive(Clone, Default)]
+pub(crate) struct Circuit {
+    config: Config,
+    breaker: Arc<Mutex<Breaker>>,
+}
+
+impl Circuit {
+    fn lock(&self) -> MutexGuard<'_, Breaker> {
+        // NOTE: The breaker is always left in a consistent state.
+        self.breaker.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn state(&self) -> CircuitState {
+        match self.lock().state {
+            State::Closed => CircuitState::Closed,
+            State::Open { until } if Instant::now() < until => CircuitState::Open,
+            State::Open { .. } | State::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Records the outcome of a call of the filtered service, which is
+    /// the result of the probe if `probe` is set.
+    pub(crate) fn record(&self, success: bool, probe: bool) {
+        self.lock().record(success, probe, &self.config);
+    }
+
+    /// Records that a call of the filtered service was dropped before
+    /// it completed, so its outcome is unknown.
+    ///
+    /// If it was the probe, the next matching request probes again.
+    pub(crate) fn abandon(&self, probe: bool) {
+        let mut breaker = self.lock();
+        if let (
+            State::HalfOpen {
+                probe: slot @ Probe::Running,
+            },
+            true,
+        ) = (&mut breaker.state, probe)
+        {
+            *slot = Probe::Free;
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Config {
+    failure_threshold: f64,
+    window: usize,
+    cooldown: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 0.5,
+            window: 10,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+struct Breaker {
+    state: State,
+    // NOTE: The outcomes of the most recent calls, `true` if it failed.
+    outcomes: VecDeque<bool>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Closed,
+    Open { until: Instant },
+    HalfOpen { probe: Probe },
+}
+
+/// The probe slot of a half-open circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Probe {
+    /// No request was routed to the filtered service yet.
+    Free,
+    /// The filter routed a request to the filtered service, which is
+    /// about to be called.
+    Allowed,
+    /// The filtered service was called, only the future of that call
+    /// may close or reopen the circuit.
+    Running,
+}
+
+impl Default for Breaker {
+    fn default() -> Self {
+        Self {
+            state: State::Closed,
+            outcomes: VecDeque::new(),
+        }
+    }
+}
+
+impl Breaker {
+    /// Whether a matching request may be routed to the filtered service.
+    fn allow(&mut self) -> bool {
+        match self.state {
+            State::Closed => true,
+            State::Open { until } if Instant::now() < until => false,
+            // NOTE: The first request after the cooldown is the probe.
+            State::Open { .. } | State::HalfOpen { probe: Probe::Free } => {
+                self.state = State::HalfOpen {
+                    probe: Probe::Allowed,
+                };
+                true
+            }
+            State::HalfOpen { .. } => false,
+        }
+    }
+
+    /// Whether the call of the filtered service being started is the
+    /// probe, taking the slot the filter allowed.
+    fn start_probe(&mut self) -> bool {
+        match &mut self.state {
+            State::HalfOpen {
+                probe: slot @ Probe::Allowed,
+            } => {
+                *slot = Probe::Running;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn record(&mut self, success: bool, probe: bool, config: &Config) {
+        match self.state {
+            State::Closed => {
+                // NOTE: Services of differently configured clones share
+                //       the outcomes, so the window might have shrunk.
+                while self.outcomes.len() >= config.window {
+                    self.outcomes.pop_front();
+                }
+                self.outcomes.push_back(!success);
+
+                let failures = self.outcomes.iter().filter(|failed| **failed).count();
+                let failure_rate = failures as f64 / config.window as f64;
+                if self.outcomes.len() == config.window && failure_rate > config.failure_threshold {
+                    self.open(config.cooldown);
+                }
+            }
+            State::HalfOpen { .. } if probe && success => {
+                self.state = State::Closed;
+                self.outcomes.clear();
+            }
+            State::HalfOpen { .. } if probe => self.open(config.cooldown),
+            // NOTE: A call routed to the service before the circuit opened.
+            State::HalfOpen { .. } | State::Open { .. } => {}
+        }
+    }
+
+    fn open(&mut self, cooldown: Duration) {
+        self.state = State::Open {
+            until: Instant::now() + cooldown,
+        };
+        self.outcomes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use futures::future::{ready, Either};
+    use tower::{service_fn, ServiceExt};
+
+    use super::*;
+
+    const COOLDOWN: Duration = Duration::from_secs(30);
+
+    /// Routes the requests that are `true` to the filtered service.
+    type Matching = Arc<dyn Fn(&bool) -> bool + Send + Sync>;
+
+    type Backend = tower::util::BoxCloneService<bool, &'static str, &'static str>;
+
+    type TestLayer = CircuitBreakerFilterLayer<Matching, Backend, bool>;
+
+    /// A service failing while the flag is set.
+    fn backend(failing: &Arc<AtomicBool>) -> Backend {
+        let failing = failing.clone();
+        Backend::new(service_fn(move |_: bool| {
+            match failing.load(Ordering::SeqCst) {
+                true => ready(Err("failed")),
+                false => ready(Ok("a")),
+            }
+        }))
+    }
+
+    fn fallback() -> Backend {
+        Backend::new(service_fn(|_: bool| ready(Ok("b"))))
+    }
+
+    fn layer(failing: &Arc<AtomicBool>) -> TestLayer {
+        let matching: Matching = Arc::new(|matches: &bool| *matches);
+        CircuitBreakerFilterLayer::new(matching, backend(failing))
+            .failure_threshold(0.5)
+            .window(4)
+            .cooldown(COOLDOWN)
+    }
+
+    /// Opens the circuit by failing the whole window.
+    async fn open(layer: &TestLayer, failing: &AtomicBool) {
+        failing.store(true, Ordering::SeqCst);
+        let service = layer.layer(fallback());
+        for _ in 0..4 {
+            assert_eq!(service.clone().oneshot(true).await, Err("failed"));
+        }
+        assert_eq!(layer.state(), CircuitState::Open);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_stay_closed_below_threshold() {
+        let failing = Arc::new(AtomicBool::new(false));
+        let layer = layer(&failing);
+        let service = layer.layer(fallback());
+
+        for failed in [true, false, true, false, true, false] {
+            failing.store(failed, Ordering::SeqCst);
+            let _ = service.clone().oneshot(true).await;
+        }
+
+        assert_eq!(layer.state(), CircuitState::Closed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_not_open_before_window_is_full() {
+        let failing = Arc::new(AtomicBool::new(true));
+        let layer = layer(&failing);
+        let service = layer.layer(fallback());
+
+        for _ in 0..3 {
+            assert_eq!(service.clone().oneshot(true).await, Err("failed"));
+        }
+
+        assert_eq!(layer.state(), CircuitState::Closed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_open_above_threshold() {
+        let failing = Arc::new(AtomicBool::new(false));
+        let layer = layer(&failing);
+        open(&layer, &failing).await;
+
+        failing.store(false, Ordering::SeqCst);
+        let service = layer.layer(fallback());
+        assert_eq!(service.clone().oneshot(true).await, Ok("b"));
+
+        tokio::time::advance(COOLDOWN / 2).await;
+        assert_eq!(layer.state(), CircuitState::Open);
+        assert_eq!(service.oneshot(true).await, Ok("b"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_half_open_after_cooldown() {
+        let failing = Arc::new(AtomicBool::new(false));
+        let layer = layer(&failing);
+        open(&layer, &failing).await;
+
+        tokio::time::advance(COOLDOWN).await;
+        assert_eq!(layer.state(), CircuitState::HalfOpen);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_close_after_successful_probe() {
+        let failing = Arc::new(AtomicBool::new(false));
+        let layer = layer(&failing);
+        open(&layer, &failing).await;
+
+        tokio::time::advance(COOLDOWN).await;
+        failing.store(false, Ordering::SeqCst);
+        let service = layer.layer(fallback());
+
+        assert_eq!(service.clone().oneshot(true).await, Ok("a"));
+        assert_eq!(layer.state(), CircuitState::Closed);
+        assert_eq!(service.oneshot(true).await, Ok("a"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_open_again_after_failed_probe() {
+        let failing = Arc::new(AtomicBool::new(false));
+        let layer = layer(&failing);
+        open(&layer, &failing).await;
+
+        tokio::time::advance(COOLDOWN).await;
+        let service = layer.layer(fallback());
+
+        assert_eq!(service.clone().oneshot(true).await, Err("failed"));
+        assert_eq!(layer.state(), CircuitState::Open);
+        assert_eq!(service.oneshot(true).await, Ok("b"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_route_a_single_probe_while_half_open() {
+        let failing = Arc::new(AtomicBool::new(false));
+        let layer = layer(&failing);
+        open(&layer, &failing).await;
+
+        tokio::time::advance(COOLDOWN).await;
+        let mut service = layer.layer(fallback());
+
+        // NOTE: The probe is still in flight.
+        let probe = service.ready().await.unwrap().call(true);
+        assert!(matches!(probe, Either::Left(_)));
+        assert_eq!(service.clone().oneshot(true).await, Ok("b"));
+
+        // NOTE: An abandoned probe lets the next request probe again.
+        drop(probe);
+        assert_eq!(service.oneshot(true).await, Err("failed"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_only_close_or_reopen_with_the_probe() {
+        let failing = Arc::new(AtomicBool::new(false));
+        let layer = layer(&failing);
+        let mut service = layer.layer(fallback());
+
+        // NOTE: Routed to the service before the circuit opened.
+        let late = service.ready().await.unwrap().call(true);
+        open(&layer, &failing).await;
+
+        tokio::time::advance(COOLDOWN).await;
+        let probe = service.ready().await.unwrap().call(true);
+
+        assert_eq!(late.await, Ok("a"));
+        assert_eq!(layer.state(), CircuitState::HalfOpen);
+        assert_eq!(probe.await, Err("failed"));
+        assert_eq!(layer.state(), CircuitState::Open);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_keep_the_probe_when_other_calls_are_dropped() {
+        let failing = Arc::new(AtomicBool::new(false));
+        let layer = layer(&failing);
+        let mut service = layer.layer(fallback());
+
+        let late = service.ready().await.unwrap().call(true);
+        open(&layer, &failing).await;
+
+        tokio::time::advance(COOLDOWN).await;
+        failing.store(false, Ordering::SeqCst);
+        let probe = service.ready().await.unwrap().call(true);
+
+        drop(late);
+        assert_eq!(service.clone().oneshot(true).await, Ok("b"));
+        assert_eq!(probe.await, Ok("a"));
+        assert_eq!(layer.state(), CircuitState::Closed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_not_probe_with_requests_not_matching() {
+        let failing = Arc::new(AtomicBool::new(false));
+        let layer = layer(&failing);
+        open(&layer, &failing).await;
+
+        tokio::time::advance(COOLDOWN).await;
+        failing.store(false, Ordering::SeqCst);
+        let service = layer.layer(fallback());
+
+        assert_eq!(service.clone().oneshot(false).await, Ok("b"));
+        assert_eq!(layer.state(), CircuitState::HalfOpen);
+        assert_eq!(service.oneshot(true).await, Ok("a"));
+        assert_eq!(layer.state(), CircuitState::Closed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_not_configure_clones() {
+        let failing = Arc::new(AtomicBool::new(false));
+        let layer = layer(&failing);
+        let _ = layer.clone().window(100).failure_threshold(1.0);
+
+        // NOTE: Still opens after a window of 4 failed calls.
+        open(&layer, &failing).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_trim_outcomes_to_a_smaller_window() {
+        let failing = Arc::new(AtomicBool::new(false));
+        let layer = layer(&failing);
+        for _ in 0..4 {
+            assert_eq!(layer.layer(fallback()).oneshot(true).await, Ok("a"));
+        }
+
+        failing.store(true, Ordering::SeqCst);
+        let smaller = layer.clone().window(2).layer(fallback());
+        for _ in 0..2 {
+            assert_eq!(smaller.clone().oneshot(true).await, Err("failed"));
+        }
+
+        assert_eq!(layer.state(), CircuitState::Open);
+    }
+}
@@ -0,0 +1,78 @@
+//! The layers and services as named before `0.1`, taking the response
+//! and error types of the service as additional parameters.
+//!
+//! To migrate, drop the last two parameters, e.g. a
+//! `FilterLayer<F, S, T, R, E>` is now a `FilterLayer<F, S, T>`, as
+//! they are recovered from `S::Response` and `S::Error`.
+#![allow(deprecated)]
+
+use std::marker::PhantomData;
+
+/// Resolves to the first type, so the aliases can refer to the
+/// parameters they ignore.
+#[doc(hidden)]
+pub trait First {
+    type Output;
+}
+
+impl<A, B> First for (A, B) {
+    type Output = A;
+}
+
+#[deprecated(since = "0.1.0", note = "use `FilterLayer<F, S, T>` instead")]
+pub type FilterLayer<F, S, T, R, E> =
+    <(crate::FilterLayer<F, S, T>, PhantomData<(R, E)>) as First>::Output;
+
+#[deprecated(since = "0.1.0", note = "use `FilterService<F, S, I, T>` instead")]
+pub type FilterService<F, S, I, T, R, E> =
+    <(crate::FilterService<F, S, I, T>, PhantomData<(R, E)>) as First>::Output;
+
+#[cfg(feature = "async")]
+#[deprecated(since = "0.1.0", note = "use `AsyncFilterLayer<F, S, T>` instead")]
+pub type AsyncFilterLayer<F, S, T, R, E> =
+    <(crate::AsyncFilterLayer<F, S, T>, PhantomData<(R, E)>) as First>::Output;
+
+#[cfg(feature = "async")]
+#[deprecated(since = "0.1.0", note = "use `AsyncFilterService<F, S, I, T>` instead")]
+pub type AsyncFilterService<F, S, I, T, R, E> =
+    <(crate::AsyncFilterService<F, S, I, T>, PhantomData<(R, E)>) as First>::Output;
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use tower::{Layer, ServiceExt};
+
+    use super::*;
+    use crate::test_util::*;
+
+    type Response = &'static str;
+
+    // NOTE: Only compiles if the old names resolve to the new types.
+    struct OldNames {
+        layer: FilterLayer<TestFilter, TestService<Response>, (), Response, Infallible>,
+        service: FilterService<
+            TestFilter,
+            TestService<Response>,
+            TestService<Response>,
+            (),
+            Response,
+            Infallible,
+        >,
+    }
+
+    #[tokio::test]
+    async fn should_resolve_to_new_types() {
+        let old = OldNames {
+            layer: crate::FilterLayer::new(TestFilter(true), TestService("a")),
+            service: crate::FilterService::new(
+                TestFilter(false),
+                TestService("a"),
+                TestService("b"),
+            ),
+        };
+
+        assert_eq!(old.layer.layer(TestService("b")).oneshot(()).await, Ok("a"));
+        assert_eq!(old.service.oneshot(()).await, Ok("b"));
+    }
+}
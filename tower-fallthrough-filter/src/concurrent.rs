@@ -0,0 +1,270 @@
+use std::{
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::{future::BoxFuture, ready};
+use tower::{util::BoxCloneService, Layer, Service};
+
+use crate::{
+    async_chain::BoxAsyncFilter, futures::ConcurrentFilterFut, ready::take_ready, AsyncFilter,
+};
+
+/// Like [`AsyncFilterChain`](crate::AsyncFilterChain), dispatching to
+/// the service of the first matching [`AsyncFilter`] out of an ordered
+/// list, but evaluating all filters concurrently.
+///
+/// So a request takes as long as the slowest filter, instead of all of
+/// them added up, at the cost of always evaluating every filter. Once
+/// all filters completed, the first matching one in the order they were
+/// added wins, no matter which one completed first.
+///
+/// # Readiness
+/// The created service is only ready once all services of the layer
+/// and the inner service are ready, as any of them might be picked
+/// for the next request.
+///
+/// # Example
+/// ```rust
+/// use futures::future::BoxFuture;
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{AsyncFilter, ConcurrentFilterLayer};
+///
+/// /// Pretends to look up whether the user is in the given group.
+/// #[derive(Clone)]
+/// struct InGroup(&'static str);
+///
+/// impl AsyncFilter<&'static str> for InGroup {
+///     type Future = BoxFuture<'static, bool>;
+///
+///     fn matches(&self, user: &&'static str) -> Self::Future {
+///         let matches = user.starts_with(self.0);
+///         Box::pin(async move {
+///             tokio::task::yield_now().await;
+///             matches
+///         })
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name| service_fn(move |_: &'static str| async move { Ok::<_, ()>(name) });
+///
+/// let layer = ConcurrentFilterLayer::new()
+///     .when(InGroup("admin"), respond("admin"))
+///     .when(InGroup("ad"), respond("ads"));
+///
+/// let service = layer.layer(respond("user"));
+/// assert_eq!(service.clone().oneshot("admin-bob").await, Ok("admin"));
+/// assert_eq!(service.clone().oneshot("ads-alice").await, Ok("ads"));
+/// assert_eq!(service.oneshot("carol").await, Ok("user"));
+/// # }
+/// ```
+pub struct ConcurrentFilterLayer<T, R, E> {
+    filters: Vec<BoxAsyncFilter<T>>,
+    services: Vec<BoxCloneService<T, R, E>>,
+}
+
+impl<T, R, E> ConcurrentFilterLayer<T, R, E> {
+    /// Creates a new, empty ConcurrentFilterLayer.
+    pub fn new() -> Self {
+        Self {
+            filters: Vec::new(),
+            services: Vec::new(),
+        }
+    }
+
+    /// Adds an entry calling `service` if `filter` matches and
+    /// none of the previously added filters did.
+    pub fn when<F, S>(mut self, filter: F, service: S) -> Self
+    where
+        F: AsyncFilter<T> + Send + Sync + 'static,
+        F::Future: 'static,
+        S: Service<T, Response = R, Error = E> + Clone + Send + 'static,
+        S::Future: Send + 'static,
+        T: 'static,
+    {
+        let filter = Arc::new(move |item: &T| -> BoxFuture<'static, bool> {
+            Box::pin(filter.matches(item))
+        });
+
+        self.filters.push(filter);
+        self.services.push(BoxCloneService::new(service));
+        self
+    }
+}
+
+impl<T, R, E> Default for ConcurrentFilterLayer<T, R, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, R, E> Clone for ConcurrentFilterLayer<T, R, E> {
+    fn clone(&self) -> Self {
+        Self {
+            filters: self.filters.clone(),
+            services: self.services.clone(),
+        }
+    }
+}
+
+impl<T, R, E> fmt::Debug for ConcurrentFilterLayer<T, R, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConcurrentFilterLayer")
+            .field("entries", &self.filters.len())
+            .finish()
+    }
+}
+
+impl<I, T, R, E> Layer<I> for ConcurrentFilterLayer<T, R, E>
+where
+    I: Service<T, Response = R, Error = E>,
+{
+    type Service = ConcurrentFilterService<I, T, R, E>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        ConcurrentFilterService {
+            filters: self.filters.clone().into(),
+            services: self.services.clone(),
+            inner: inner_service,
+        }
+    }
+}
+
+pub struct ConcurrentFilterService<I, T, R, E> {
+    filters: Arc<[BoxAsyncFilter<T>]>,
+    services: Vec<BoxCloneService<T, R, E>>,
+    inner: I,
+}
+
+impl<I: Clone, T, R, E> Clone for ConcurrentFilterService<I, T, R, E> {
+    fn clone(&self) -> Self {
+        Self {
+            filters: self.filters.clone(),
+            services: self.services.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<I: fmt::Debug, T, R, E> fmt::Debug for ConcurrentFilterService<I, T, R, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConcurrentFilterService")
+            .field("entries", &self.filters.len())
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<I, T, R, E> Service<T> for ConcurrentFilterService<I, T, R, E>
+where
+    I: Service<T, Response = R, Error = E> + Clone,
+{
+    type Response = R;
+    type Error = E;
+    type Future = ConcurrentFilterFut<I, T, R, E>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        for service in &mut self.services {
+            ready!(service.poll_ready(cx))?;
+        }
+        ready!(self.inner.poll_ready(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        let conditions = self.filters.iter().map(|filter| filter(&req)).collect();
+
+        let services = take_ready(&mut self.services);
+        let inner = take_ready(&mut self.inner);
+
+        ConcurrentFilterFut::new(conditions, req, services, inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, time::Duration};
+
+    use futures::future::{ready, Ready};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_util::*;
+
+    #[derive(Clone)]
+    struct Below(u32);
+
+    impl AsyncFilter<u32> for Below {
+        type Future = Ready<bool>;
+
+        fn matches(&self, item: &u32) -> Self::Future {
+            ready(*item < self.0)
+        }
+    }
+
+    /// Matches after the given delay.
+    #[derive(Clone)]
+    struct After(u64, bool);
+
+    impl AsyncFilter<u32> for After {
+        type Future = BoxFuture<'static, bool>;
+
+        fn matches(&self, _: &u32) -> Self::Future {
+            let (delay, matches) = (self.0, self.1);
+
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                matches
+            })
+        }
+    }
+
+    fn layer() -> ConcurrentFilterService<TestService<&'static str>, u32, &'static str, Infallible>
+    {
+        ConcurrentFilterLayer::new()
+            .when(Below(10), TestService("first"))
+            .when(Below(100), TestService("second"))
+            .when(Below(5), TestService("third"))
+            .layer(TestService("inner"))
+    }
+
+    #[tokio::test]
+    async fn should_pick_first_match() {
+        assert_eq!(layer().oneshot(1).await, Ok("first"));
+        assert_eq!(layer().oneshot(50).await, Ok("second"));
+    }
+
+    #[tokio::test]
+    async fn should_fall_through() {
+        assert_eq!(layer().oneshot(500).await, Ok("inner"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_keep_priority_over_completion_order() {
+        let service = ConcurrentFilterLayer::new()
+            .when(After(20, true), TestService("slow"))
+            .when(After(10, true), TestService("fast"))
+            .layer(TestService("inner"));
+
+        assert_eq!(service.oneshot(0).await, Ok("slow"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_evaluate_concurrently() {
+        let mut layer = ConcurrentFilterLayer::new();
+        for _ in 0..5 {
+            layer = layer.when(After(10, false), TestService("filtered"));
+        }
+
+        let start = tokio::time::Instant::now();
+        let result = layer.layer(TestService("inner")).oneshot(0).await;
+
+        assert_eq!(result, Ok("inner"));
+        // NOTE: Evaluated one after the other, it would take 50ms.
+        assert!(start.elapsed() < Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,167 @@
+use tower::{Layer, Service};
+
+use crate::{Filter, FilterLayer, FilterService};
+
+/// Whether a [`ConditionalFilterLayer`] is enabled, used to only
+/// store the [`FilterLayer`] if it is.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy)]
+pub struct Enabled<const ENABLED: bool>;
+
+/// Maps a layer to what a [`ConditionalFilterLayer`] stores,
+/// the layer itself if enabled and nothing otherwise.
+#[doc(hidden)]
+pub trait Toggle<L> {
+    type Output;
+
+    fn toggle(layer: L) -> Self::Output;
+}
+
+impl<L> Toggle<L> for Enabled<true> {
+    type Output = L;
+
+    fn toggle(layer: L) -> Self::Output {
+        layer
+    }
+}
+
+impl<L> Toggle<L> for Enabled<false> {
+    type Output = ();
+
+    fn toggle(_: L) -> Self::Output {}
+}
+
+type Stored<const ENABLED: bool, F, S, T> =
+    <Enabled<ENABLED> as Toggle<FilterLayer<F, S, T>>>::Output;
+
+/// A [`FilterLayer`] that can be disabled at compile time, e.g. to
+/// compile out diagnostics in release builds.
+///
+/// If `ENABLED` is `true` it behaves identically to [`FilterLayer`].
+/// Otherwise it is a zero-sized passthrough like
+/// `tower::layer::util::Identity`, returning the inner service as is,
+/// so neither the filter nor the filtered service are kept around.
+///
+/// # Example
+/// ```rust
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{ConditionalFilterLayer, Filter};
+///
+/// #[derive(Clone)]
+/// struct IsDebug;
+///
+/// impl Filter<&'static str> for IsDebug {
+///     fn matches(&self, path: &&'static str) -> bool {
+///         path.starts_with("/debug")
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name| service_fn(move |_: &'static str| async move { Ok::<_, ()>(name) });
+///
+/// let debug = ConditionalFilterLayer::<{ cfg!(debug_assertions) }, _, _, _>::new(
+///     IsDebug,
+///     respond("debug"),
+/// );
+///
+/// let expected = if cfg!(debug_assertions) { "debug" } else { "app" };
+/// assert_eq!(debug.layer(respond("app")).oneshot("/debug/vars").await, Ok(expected));
+/// # }
+/// ```
+pub struct ConditionalFilterLayer<const ENABLED: bool, F, S, T>
+where
+    Enabled<ENABLED>: Toggle<FilterLayer<F, S, T>>,
+{
+    layer: Stored<ENABLED, F, S, T>,
+}
+
+// NOTE: This is required to make the `ConditionalFilterLayer` clonable
+//       as the stored layer is only known to be clonable if enabled.
+impl<const ENABLED: bool, F, S, T> Clone for ConditionalFilterLayer<ENABLED, F, S, T>
+where
+    Enabled<ENABLED>: Toggle<FilterLayer<F, S, T>>,
+    Stored<ENABLED, F, S, T>: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<const ENABLED: bool, F, S, T> ConditionalFilterLayer<ENABLED, F, S, T>
+where
+    F: Filter<T>,
+    S: Service<T>,
+    Enabled<ENABLED>: Toggle<FilterLayer<F, S, T>>,
+{
+    /// Creates a new ConditionalFilterLayer given a `Service` and a
+    /// `Filter`, which are dropped right away if disabled.
+    pub fn new(filter: F, service: S) -> Self {
+        Self {
+            layer: Enabled::<ENABLED>::toggle(FilterLayer::new(filter, service)),
+        }
+    }
+}
+
+impl<F, S, I, T> Layer<I> for ConditionalFilterLayer<true, F, S, T>
+where
+    F: Filter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = FilterService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        self.layer.layer(inner_service)
+    }
+}
+
+impl<F, S, I, T> Layer<I> for ConditionalFilterLayer<false, F, S, T> {
+    type Service = I;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        inner_service
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of;
+
+    use tower::layer::util::Identity;
+
+    use super::*;
+    use crate::test_util::*;
+
+    type TestConditionalFilterLayer<const ENABLED: bool> =
+        ConditionalFilterLayer<ENABLED, TestFilter, TestService<&'static str>, ()>;
+
+    #[test]
+    fn should_be_sized_like_identity_if_disabled() {
+        assert_eq!(
+            size_of::<TestConditionalFilterLayer<false>>(),
+            size_of::<Identity>()
+        );
+        assert_eq!(
+            size_of::<TestConditionalFilterLayer<true>>(),
+            size_of::<FilterLayer<TestFilter, TestService<&'static str>, ()>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn should_filter_if_enabled() {
+        let layer = TestConditionalFilterLayer::<true>::new(TestFilter(true), TestService("a"));
+
+        assert_eq!(layer.layer(TestService("b")).call(()).await, Ok("a"));
+    }
+
+    #[tokio::test]
+    async fn should_pass_through_if_disabled() {
+        let layer = TestConditionalFilterLayer::<false>::new(TestFilter(true), TestService("a"));
+        let mut service: TestService<&'static str> = layer.layer(TestService("b"));
+
+        assert_eq!(service.call(()).await, Ok("b"));
+    }
+}
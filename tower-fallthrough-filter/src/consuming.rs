@@ -0,0 +1,240 @@
+use std::{
+    future::Future,
+    marker::PhantomData,
+    task::{Context, Poll},
+};
+
+use futures::ready;
+use tower::{Layer, Service};
+
+use crate::{futures::ConsumingSelectFut, ready::take_ready};
+
+/// An async filter that takes ownership of the request and hands it
+/// back together with its decision, e.g. to insert an extension or to
+/// read the body.
+///
+/// As the request is part of the output, the filter can't lose it.
+///
+/// # Example
+/// ```rust
+/// use futures::future::{ready, Ready};
+/// use tower_fallthrough_filter::ConsumingFilter;
+///
+/// struct Request {
+///     path: String,
+///     user: Option<String>,
+/// }
+///
+/// #[derive(Clone)]
+/// struct IsUserPage;
+///
+/// impl ConsumingFilter<Request> for IsUserPage {
+///     type Future = Ready<(bool, Request)>;
+///
+///     fn matches(&self, mut req: Request) -> Self::Future {
+///         req.user = req.path.strip_prefix("/users/").map(str::to_string);
+///         ready((req.user.is_some(), req))
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let req = Request { path: "/users/alice".to_string(), user: None };
+///
+/// let (matches, req) = IsUserPage.matches(req).await;
+/// assert!(matches);
+/// assert_eq!(req.user.as_deref(), Some("alice"));
+/// # }
+/// ```
+pub trait ConsumingFilter<T>: Clone {
+    type Future: Future<Output = (bool, T)> + Send;
+
+    fn matches(&self, item: T) -> Self::Future;
+}
+
+/// The counterpart of [`AsyncFilterLayer`](crate::AsyncFilterLayer)
+/// for a [`ConsumingFilter`].
+///
+/// The request returned by the filter is passed to the selected
+/// service, so changes made by the filter are visible to either one.
+#[derive(Debug)]
+pub struct ConsumingFilterLayer<F, S, T> {
+    filter: F,
+    service: S,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, T> Clone for ConsumingFilterLayer<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: ConsumingFilter<T>, S: Service<T>, T> ConsumingFilterLayer<F, S, T> {
+    /// Creates a new ConsumingFilterLayer given a `ConsumingFilter`
+    /// and a `Service`.
+    pub fn new(filter: F, service: S) -> Self {
+        Self {
+            filter,
+            service,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> Layer<I> for ConsumingFilterLayer<F, S, T>
+where
+    F: ConsumingFilter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = ConsumingFilterService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        ConsumingFilterService::new(self.filter.clone(), self.service.clone(), inner_service)
+    }
+}
+
+#[derive(Debug)]
+pub struct ConsumingFilterService<F, S, I, T> {
+    filter: F,
+    service: S,
+    inner: I,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F, S, I, T> ConsumingFilterService<F, S, I, T>
+where
+    F: ConsumingFilter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    /// Creates a new ConsumingFilterService given a `ConsumingFilter`,
+    /// the `Service` that is executed if it matches and the `Service`
+    /// to fall through to.
+    pub fn new(filter: F, service: S, inner: I) -> Self {
+        Self {
+            filter,
+            service,
+            inner,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: Clone, S: Clone, I: Clone, T> Clone for ConsumingFilterService<F, S, I, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: self.inner.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> Service<T> for ConsumingFilterService<F, S, I, T>
+where
+    F: ConsumingFilter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ConsumingSelectFut<F::Future, S, I, T>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.service.poll_ready(cx))?;
+        ready!(self.inner.poll_ready(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        let matches = self.filter.matches(req);
+        let service = take_ready(&mut self.service);
+        let inner = take_ready(&mut self.inner);
+
+        ConsumingSelectFut::new(matches, service, inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, convert::Infallible};
+
+    use futures::future::BoxFuture;
+    use tower::{service_fn, ServiceExt};
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Session(&'static str);
+
+    #[derive(Default)]
+    struct Request {
+        cookie: Option<&'static str>,
+        extensions: HashMap<&'static str, Session>,
+    }
+
+    /// Looks up the session of the cookie and inserts it as an extension.
+    #[derive(Clone)]
+    struct HasSession;
+
+    impl ConsumingFilter<Request> for HasSession {
+        type Future = BoxFuture<'static, (bool, Request)>;
+
+        fn matches(&self, mut req: Request) -> Self::Future {
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+
+                let session = match req.cookie {
+                    Some("alice") => Session("alice"),
+                    _ => Session("anonymous"),
+                };
+                let matches = session != Session("anonymous");
+                req.extensions.insert("session", session);
+
+                (matches, req)
+            })
+        }
+    }
+
+    fn echo(
+        name: &'static str,
+    ) -> impl Service<Request, Response = String, Error = Infallible> + Clone {
+        service_fn(move |req: Request| async move {
+            let session = req.extensions.get("session").map(|session| session.0);
+            Ok(format!("{name} {session:?}"))
+        })
+    }
+
+    #[tokio::test]
+    async fn should_pass_mutated_request_to_service() {
+        let service = ConsumingFilterLayer::new(HasSession, echo("app")).layer(echo("login"));
+
+        let req = Request {
+            cookie: Some("alice"),
+            ..Default::default()
+        };
+        assert_eq!(service.oneshot(req).await.unwrap(), "app Some(\"alice\")");
+    }
+
+    #[tokio::test]
+    async fn should_pass_mutated_request_to_inner_service() {
+        let service = ConsumingFilterLayer::new(HasSession, echo("app")).layer(echo("login"));
+
+        let res = service.oneshot(Request::default()).await;
+        assert_eq!(res.unwrap(), "login Some(\"anonymous\")");
+    }
+}
@@ -0,0 +1,350 @@
+use std::{
+    fmt,
+    task::{Context, Poll},
+};
+
+use futures::future::{ready, Either, Ready};
+use tower::{Layer, Service};
+
+use crate::{catch_panic::CatchPanics, Filter, FilterLayer};
+
+impl<F: Filter<T>, S: Service<T>, T> FilterLayer<F, S, T> {
+    /// Defers the errors returned by `poll_ready` until a request is
+    /// routed to the failed service, see [`DeferredErrorFilterLayer`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::task::{Context, Poll};
+    ///
+    /// use futures::future::{ready, Ready};
+    /// use tower::{service_fn, Layer, Service, ServiceExt};
+    /// use tower_fallthrough_filter::{FilterFn, FilterLayer};
+    ///
+    /// #[derive(Clone)]
+    /// struct Unavailable;
+    ///
+    /// impl Service<u32> for Unavailable {
+    ///     type Response = &'static str;
+    ///     type Error = &'static str;
+    ///     type Future = Ready<Result<Self::Response, Self::Error>>;
+    ///
+    ///     fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    ///         Poll::Ready(Err("unavailable"))
+    ///     }
+    ///
+    ///     fn call(&mut self, _: u32) -> Self::Future {
+    ///         unreachable!("never ready")
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let cache = service_fn(|_: u32| async { Ok::<_, &'static str>("cached") });
+    ///
+    /// let service = FilterLayer::new(FilterFn::new(|n: &u32| *n < 10), cache)
+    ///     .defer_ready_errors()
+    ///     .layer(Unavailable);
+    ///
+    /// assert_eq!(service.clone().oneshot(5).await, Ok("cached"));
+    /// assert_eq!(service.oneshot(50).await, Err("unavailable"));
+    /// # }
+    /// ```
+    pub fn defer_ready_errors(self) -> DeferredErrorFilterLayer<F, S, T> {
+        DeferredErrorFilterLayer { layer: self }
+    }
+}
+
+/// A Tower layer like [`FilterLayer`], but not failing as a whole if
+/// either service fails to get ready, created by
+/// [`FilterLayer::defer_ready_errors`].
+///
+/// The error of a service is stored instead and returned to the next
+/// request routed to it, while the other service keeps serving.
+/// The failed service is polled again on every `poll_ready`, so it is
+/// used again once it recovers.
+///
+/// NOTE: Services are usually considered broken once `poll_ready`
+/// returned an error, so this is only useful with services that are
+/// able to recover, e.g. ones reconnecting to a backend.
+///
+/// The settings of the [`FilterLayer`], e.g.
+/// [`FilterLayer::failover_on_pending`], are kept.
+#[derive(Debug)]
+pub struct DeferredErrorFilterLayer<F, S, T> {
+    layer: FilterLayer<F, S, T>,
+}
+
+impl<F: Clone, S: Clone, T> Clone for DeferredErrorFilterLayer<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<F, S, I, T> Layer<I> for DeferredErrorFilterLayer<F, S, T>
+where
+    F: Filter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = DeferredErrorFilterService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        DeferredErrorFilterService {
+            filter: self.layer.filter.clone(),
+            service: self.layer.service.clone(),
+            inner: inner_service,
+            failover_on_pending: self.layer.failover_on_pending,
+            catch_panics: self.layer.catch_panics.clone(),
+            service_ready: false,
+            service_error: None,
+            inner_error: None,
+        }
+    }
+}
+
+/// The service created by a [`DeferredErrorFilterLayer`].
+pub struct DeferredErrorFilterService<F, S: Service<T>, I, T> {
+    filter: F,
+    service: S,
+    inner: I,
+    failover_on_pending: bool,
+    catch_panics: CatchPanics,
+    // NOTE: Whether the filtered service reported to be ready, only
+    //       tracked when failing over, see `FilterService`.
+    service_ready: bool,
+    // NOTE: The errors of the last `poll_ready`, returned to the next
+    //       request routed to the failed service.
+    service_error: Option<S::Error>,
+    inner_error: Option<S::Error>,
+}
+
+// NOTE: This is required to make the `DeferredErrorFilterService`
+//       clonable without requiring the error to be clonable.
+impl<F, S, I, T> Clone for DeferredErrorFilterService<F, S, I, T>
+where
+    F: Clone,
+    S: Service<T> + Clone,
+    I: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: self.inner.clone(),
+            failover_on_pending: self.failover_on_pending,
+            catch_panics: self.catch_panics.clone(),
+            // NOTE: The readiness and the errors belong to the original services.
+            service_ready: false,
+            service_error: None,
+            inner_error: None,
+        }
+    }
+}
+
+impl<F, S, I, T> fmt::Debug for DeferredErrorFilterService<F, S, I, T>
+where
+    F: fmt::Debug,
+    S: Service<T> + fmt::Debug,
+    I: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeferredErrorFilterService")
+            .field("filter", &self.filter)
+            .field("service", &self.service)
+            .field("inner", &self.inner)
+            .field("failover_on_pending", &self.failover_on_pending)
+            .field("catch_panics", &self.catch_panics)
+            .field("service_failed", &self.service_error.is_some())
+            .field("inner_failed", &self.inner_error.is_some())
+            .finish()
+    }
+}
+
+impl<F, S, I, T> Service<T> for DeferredErrorFilterService<F, S, I, T>
+where
+    F: Filter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<Either<S::Future, I::Future>, Ready<Result<S::Response, S::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let service = poll_branch(&mut self.service, &mut self.service_error, cx);
+        let inner = poll_branch(&mut self.inner, &mut self.inner_error, cx);
+
+        // NOTE: When failing over, the requests fall through as long
+        //       as the filtered service isn't ready.
+        self.service_ready = service.is_ready();
+        if (service.is_pending() && !self.failover_on_pending) || inner.is_pending() {
+            return Poll::Pending;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        if !self.catch_panics.matches(&self.filter, &req)
+            || (self.failover_on_pending && !self.service_ready)
+        {
+            return match self.inner_error.take() {
+                Some(err) => Either::Right(ready(Err(err))),
+                None => Either::Left(Either::Right(self.inner.call(req))),
+            };
+        }
+
+        self.service_ready = false;
+        match self.service_error.take() {
+            Some(err) => Either::Right(ready(Err(err))),
+            None => Either::Left(Either::Left(self.service.call(req))),
+        }
+    }
+}
+
+/// Polls the readiness of a single service, storing its error.
+///
+/// A service that failed counts as ready, as the requests routed
+/// to it get the error until it recovers.
+fn poll_branch<S, T, E>(service: &mut S, error: &mut Option<E>, cx: &mut Context<'_>) -> Poll<()>
+where
+    S: Service<T, Error = E>,
+{
+    match service.poll_ready(cx) {
+        Poll::Ready(Ok(())) => {
+            *error = None;
+            Poll::Ready(())
+        }
+        Poll::Ready(Err(err)) => {
+            *error = Some(err);
+            Poll::Ready(())
+        }
+        Poll::Pending if error.is_some() => Poll::Ready(()),
+        Poll::Pending => Poll::Pending,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use tower::{service_fn, ServiceExt};
+
+    use super::*;
+    use crate::test_util::*;
+
+    /// A service failing to get ready while it isn't healthy.
+    #[derive(Clone)]
+    struct ErrService(Arc<AtomicBool>);
+
+    impl Service<()> for ErrService {
+        type Response = &'static str;
+        type Error = &'static str;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.0.load(Ordering::SeqCst) {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Ready(Err("unhealthy"))
+            }
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            assert!(self.0.load(Ordering::SeqCst), "called while unhealthy");
+            ready(Ok("b"))
+        }
+    }
+
+    fn respond(
+        name: &'static str,
+    ) -> impl Service<(), Response = &'static str, Error = &'static str> + Clone {
+        service_fn(move |_: ()| async move { Ok(name) })
+    }
+
+    #[tokio::test]
+    async fn should_serve_matching_requests_while_fallthrough_fails() {
+        let healthy = Arc::new(AtomicBool::new(false));
+        let service = FilterLayer::new(TestFilter(true), respond("a"))
+            .defer_ready_errors()
+            .layer(ErrService(healthy));
+
+        assert_eq!(service.clone().oneshot(()).await, Ok("a"));
+        assert_eq!(service.oneshot(()).await, Ok("a"));
+    }
+
+    #[tokio::test]
+    async fn should_return_stored_error_to_fallthrough_requests() {
+        let healthy = Arc::new(AtomicBool::new(false));
+        let mut service = FilterLayer::new(TestFilter(false), respond("a"))
+            .defer_ready_errors()
+            .layer(ErrService(healthy));
+
+        assert_eq!(
+            service.ready().await.unwrap().call(()).await,
+            Err("unhealthy")
+        );
+        assert_eq!(
+            service.ready().await.unwrap().call(()).await,
+            Err("unhealthy")
+        );
+    }
+
+    #[tokio::test]
+    async fn should_recover_once_healed() {
+        let healthy = Arc::new(AtomicBool::new(false));
+        let mut service = FilterLayer::new(TestFilter(false), respond("a"))
+            .defer_ready_errors()
+            .layer(ErrService(healthy.clone()));
+
+        assert_eq!(
+            service.ready().await.unwrap().call(()).await,
+            Err("unhealthy")
+        );
+
+        healthy.store(true, Ordering::SeqCst);
+        assert_eq!(service.ready().await.unwrap().call(()).await, Ok("b"));
+    }
+
+    #[tokio::test]
+    async fn should_discard_error_once_healed_before_called() {
+        let healthy = Arc::new(AtomicBool::new(false));
+        let mut service = FilterLayer::new(TestFilter(false), respond("a"))
+            .defer_ready_errors()
+            .layer(ErrService(healthy.clone()));
+
+        ServiceExt::<()>::ready(&mut service).await.unwrap();
+        healthy.store(true, Ordering::SeqCst);
+
+        assert_eq!(service.ready().await.unwrap().call(()).await, Ok("b"));
+    }
+
+    #[tokio::test]
+    async fn should_keep_failover_of_filter_layer() {
+        let (service, ready) = PendingService::new("a");
+        let service = FilterLayer::new(TestFilter(true), service)
+            .failover_on_pending(true)
+            .defer_ready_errors()
+            .layer(TestService("b"));
+
+        assert_eq!(service.clone().oneshot(()).await, Ok("b"));
+
+        ready.store(true, Ordering::SeqCst);
+        assert_eq!(service.oneshot(()).await, Ok("a"));
+    }
+
+    #[tokio::test]
+    async fn should_keep_caught_panics_of_filter_layer() {
+        let service = FilterLayer::new(PanicFilter { on_poll: false }, TestService("a"))
+            .catch_panics(true)
+            .defer_ready_errors()
+            .layer(TestService("b"));
+
+        assert_eq!(service.oneshot(()).await, Ok("b"));
+    }
+}
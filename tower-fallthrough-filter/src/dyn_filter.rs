@@ -0,0 +1,321 @@
+use std::fmt;
+
+#[cfg(feature = "async")]
+use futures::future::BoxFuture;
+
+use crate::Filter;
+
+#[cfg(feature = "async")]
+use crate::AsyncFilter;
+
+/// An object-safe version of [`Filter`], so filters chosen at runtime
+/// can be stored as trait objects, see [`BoxFilter`].
+///
+/// It is implemented for every `Filter` that is `Send + Sync + 'static`.
+pub trait DynFilter<T> {
+    /// Whether the service should be executed, see [`Filter::matches`].
+    fn dyn_matches(&self, item: &T) -> bool;
+
+    /// Clones the filter into a new box.
+    fn clone_box(&self) -> Box<dyn DynFilter<T> + Send + Sync>;
+}
+
+impl<F, T> DynFilter<T> for F
+where
+    F: Filter<T> + Send + Sync + 'static,
+{
+    fn dyn_matches(&self, item: &T) -> bool {
+        self.matches(item)
+    }
+
+    fn clone_box(&self) -> Box<dyn DynFilter<T> + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// A type-erased [`Filter`], e.g. to store differently typed filters
+/// in a single collection.
+///
+/// # Example
+/// ```rust
+/// use tower_fallthrough_filter::{BoxFilter, Filter, FilterFn};
+///
+/// #[derive(Clone)]
+/// struct IsEven;
+///
+/// impl Filter<u32> for IsEven {
+///     fn matches(&self, item: &u32) -> bool {
+///         item.is_multiple_of(2)
+///     }
+/// }
+///
+/// let filters = vec![
+///     BoxFilter::new(IsEven),
+///     BoxFilter::new(FilterFn::new(|n: &u32| *n > 10)),
+/// ];
+///
+/// assert!(filters.iter().all(|filter| filter.matches(&12)));
+/// assert!(!filters.iter().all(|filter| filter.matches(&4)));
+/// ```
+pub struct BoxFilter<T>(Box<dyn DynFilter<T> + Send + Sync>);
+
+impl<T> BoxFilter<T> {
+    /// Creates a new BoxFilter given a `Filter`.
+    pub fn new<F>(filter: F) -> Self
+    where
+        F: Filter<T> + Send + Sync + 'static,
+    {
+        Self(Box::new(filter))
+    }
+}
+
+impl<T> Clone for BoxFilter<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone_box())
+    }
+}
+
+impl<T> fmt::Debug for BoxFilter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxFilter").finish_non_exhaustive()
+    }
+}
+
+impl<T> Filter<T> for BoxFilter<T> {
+    fn matches(&self, item: &T) -> bool {
+        self.0.dyn_matches(item)
+    }
+}
+
+/// An object-safe version of [`AsyncFilter`], see [`BoxAsyncFilter`].
+///
+/// It is implemented for every `AsyncFilter` that is `Send + Sync + 'static`
+/// and whose future is `'static`.
+#[cfg(feature = "async")]
+pub trait DynAsyncFilter<T> {
+    /// Whether the service should be executed, see [`AsyncFilter::matches`].
+    fn dyn_matches(&self, item: &T) -> BoxFuture<'static, bool>;
+
+    /// Whether the filter matches without awaiting,
+    /// see [`AsyncFilter::matches_now`].
+    fn dyn_matches_now(&self, item: &T) -> Option<bool>;
+
+    /// Clones the filter into a new box.
+    fn clone_box(&self) -> Box<dyn DynAsyncFilter<T> + Send + Sync>;
+}
+
+#[cfg(feature = "async")]
+impl<F, T> DynAsyncFilter<T> for F
+where
+    F: AsyncFilter<T> + Send + Sync + 'static,
+    F::Future: 'static,
+{
+    fn dyn_matches(&self, item: &T) -> BoxFuture<'static, bool> {
+        Box::pin(self.matches(item))
+    }
+
+    fn dyn_matches_now(&self, item: &T) -> Option<bool> {
+        self.matches_now(item)
+    }
+
+    fn clone_box(&self) -> Box<dyn DynAsyncFilter<T> + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// The counterpart of [`BoxFilter`] for an [`AsyncFilter`].
+#[cfg(feature = "async")]
+pub struct BoxAsyncFilter<T>(Box<dyn DynAsyncFilter<T> + Send + Sync>);
+
+#[cfg(feature = "async")]
+impl<T> BoxAsyncFilter<T> {
+    /// Creates a new BoxAsyncFilter given an `AsyncFilter`.
+    pub fn new<F>(filter: F) -> Self
+    where
+        F: AsyncFilter<T> + Send + Sync + 'static,
+        F::Future: 'static,
+    {
+        Self(Box::new(filter))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> Clone for BoxAsyncFilter<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone_box())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> fmt::Debug for BoxAsyncFilter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxAsyncFilter").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> AsyncFilter<T> for BoxAsyncFilter<T> {
+    type Future = BoxFuture<'static, bool>;
+
+    fn matches(&self, item: &T) -> Self::Future {
+        self.0.dyn_matches(item)
+    }
+
+    fn matches_now(&self, item: &T) -> Option<bool> {
+        self.0.dyn_matches_now(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FilterFn;
+
+    struct Request {
+        path: &'static str,
+        admin: bool,
+    }
+
+    #[derive(Clone)]
+    struct IsAdmin;
+
+    impl Filter<Request> for IsAdmin {
+        fn matches(&self, req: &Request) -> bool {
+            req.admin
+        }
+    }
+
+    #[cfg(feature = "async")]
+    impl AsyncFilter<Request> for IsAdmin {
+        type Future = futures::future::Ready<bool>;
+
+        fn matches(&self, req: &Request) -> Self::Future {
+            futures::future::ready(req.admin)
+        }
+    }
+
+    fn filters() -> Vec<BoxFilter<Request>> {
+        vec![
+            BoxFilter::new(IsAdmin),
+            BoxFilter::new(FilterFn::new(|req: &Request| req.path.starts_with("/api"))),
+        ]
+    }
+
+    #[test]
+    fn should_match_like_the_boxed_filter() {
+        let filters = filters();
+        let req = Request {
+            path: "/api/users",
+            admin: false,
+        };
+
+        let matches: Vec<_> = filters.iter().map(|filter| filter.matches(&req)).collect();
+        assert_eq!(matches, [false, true]);
+    }
+
+    #[test]
+    fn should_clone_boxed_filter() {
+        let filter = BoxFilter::new(IsAdmin).clone();
+        let req = Request {
+            path: "/",
+            admin: true,
+        };
+
+        assert!(filter.matches(&req));
+    }
+
+    #[cfg(feature = "chain")]
+    #[tokio::test]
+    async fn should_drive_filter_chain() {
+        use tower::{service_fn, Layer, ServiceExt};
+
+        use crate::FilterChain;
+
+        let chain = filters().into_iter().zip(["admin", "api"]).fold(
+            FilterChain::new(),
+            |chain, (filter, name)| {
+                chain.when(
+                    filter,
+                    service_fn(move |_: Request| async move { Ok::<_, ()>(name) }),
+                )
+            },
+        );
+        let service = chain.layer(service_fn(|_: Request| async { Ok::<_, ()>("page") }));
+
+        let req = |path, admin| Request { path, admin };
+        assert_eq!(
+            service.clone().oneshot(req("/api", true)).await,
+            Ok("admin")
+        );
+        assert_eq!(service.clone().oneshot(req("/api", false)).await, Ok("api"));
+        assert_eq!(service.oneshot(req("/", false)).await, Ok("page"));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn should_match_like_the_boxed_async_filter() {
+        use crate::AsyncFilterFn;
+
+        let filters: Vec<BoxAsyncFilter<Request>> = vec![
+            BoxAsyncFilter::new(AsyncFilterFn::new(|req: &Request| {
+                let admin = req.admin;
+                async move { admin }
+            })),
+            BoxAsyncFilter::new(AsyncFilterFn::new(|_: &Request| async { true })),
+        ];
+        let req = Request {
+            path: "/",
+            admin: false,
+        };
+
+        assert!(!filters[0].clone().matches(&req).await);
+        assert!(filters[1].matches(&req).await);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn should_forward_matches_now() {
+        use crate::test_util::{DecidedFilter, TestFilter};
+
+        let decided = BoxAsyncFilter::new(DecidedFilter(true));
+        assert_eq!(AsyncFilter::matches_now(&decided, &()), Some(true));
+
+        let awaited = BoxAsyncFilter::new(TestFilter(true));
+        assert_eq!(AsyncFilter::matches_now(&awaited, &()), None);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn should_layer_heterogeneous_boxed_async_filters() {
+        use tower::{service_fn, Layer, Service, ServiceExt};
+
+        use crate::{AsyncFilterFn, AsyncFilterLayer};
+
+        let filters: Vec<BoxAsyncFilter<Request>> = vec![
+            BoxAsyncFilter::new(IsAdmin),
+            BoxAsyncFilter::new(AsyncFilterFn::new(|req: &Request| {
+                let path = req.path;
+                async move { path.starts_with("/api") }
+            })),
+        ];
+
+        let respond = |name| service_fn(move |_: Request| async move { Ok::<_, ()>(name) });
+        let mut services: Vec<_> = filters
+            .into_iter()
+            .map(|filter| AsyncFilterLayer::new(filter, respond("matched")).layer(respond("inner")))
+            .collect();
+
+        let req = || Request {
+            path: "/api",
+            admin: false,
+        };
+        assert_eq!(
+            services[0].ready().await.unwrap().call(req()).await,
+            Ok("inner")
+        );
+        assert_eq!(
+            services[1].ready().await.unwrap().call(req()).await,
+            Ok("matched")
+        );
+    }
+}
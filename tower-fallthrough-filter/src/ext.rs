@@ -0,0 +1,210 @@
+use std::{fmt, marker::PhantomData};
+
+use tower::{Layer, Service};
+
+use crate::{Filter, FilterLayer, FilterService};
+
+#[cfg(feature = "async")]
+use crate::{AsyncFilter, AsyncFilterLayer, AsyncFilterService};
+
+/// An extension trait for `Service`s to describe them as
+/// "this service, but only when ..." without naming the layer types.
+///
+/// # Example
+/// ```rust
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{Filter, FilteredServiceExt};
+///
+/// #[derive(Clone)]
+/// struct IsAdmin;
+///
+/// impl Filter<&'static str> for IsAdmin {
+///     fn matches(&self, user: &&'static str) -> bool {
+///         *user == "admin"
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name| service_fn(move |_: &'static str| async move { Ok::<_, ()>(name) });
+///
+/// // Finishing with a fallthrough service creates the service directly.
+/// let service = respond("dashboard").filtered(IsAdmin).or(respond("login"));
+/// assert_eq!(service.clone().oneshot("admin").await, Ok("dashboard"));
+/// assert_eq!(service.oneshot("guest").await, Ok("login"));
+///
+/// // Otherwise it creates a layer, e.g. for `Router::layer`.
+/// let layer = respond("dashboard").filtered(IsAdmin).or_layer();
+/// assert_eq!(layer.layer(respond("login")).oneshot("guest").await, Ok("login"));
+/// # }
+/// ```
+pub trait FilteredServiceExt<T>: Service<T> + Sized {
+    /// Only executes this service if `filter` matches,
+    /// finish with [`Filtered::or`] or [`Filtered::or_layer`].
+    fn filtered<F: Filter<T>>(self, filter: F) -> Filtered<F, Self, T> {
+        Filtered {
+            filter,
+            service: self,
+
+            _marker: PhantomData,
+        }
+    }
+
+    /// Only executes this service if the async `filter` matches,
+    /// finish with [`AsyncFiltered::or`] or [`AsyncFiltered::or_layer`].
+    #[cfg(feature = "async")]
+    fn filtered_async<F: AsyncFilter<T>>(self, filter: F) -> AsyncFiltered<F, Self, T> {
+        AsyncFiltered {
+            filter,
+            service: self,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<S: Service<T>, T> FilteredServiceExt<T> for S {}
+
+/// A service that is only executed if the filter matches,
+/// created by [`FilteredServiceExt::filtered`].
+pub struct Filtered<F, S, T> {
+    filter: F,
+    service: S,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Filter<T>, S: Service<T>, T> Filtered<F, S, T> {
+    /// Falls through to `inner` if the filter doesn't match,
+    /// same as `FilterLayer::new(filter, service).layer(inner)`.
+    pub fn or<I>(self, inner: I) -> FilterService<F, S, I, T>
+    where
+        S: Clone,
+        I: Service<T, Response = S::Response, Error = S::Error> + Clone,
+    {
+        self.or_layer().layer(inner)
+    }
+
+    /// Creates a layer falling through to the service it wraps,
+    /// same as `FilterLayer::new(filter, service)`.
+    pub fn or_layer(self) -> FilterLayer<F, S, T> {
+        FilterLayer::new(self.filter, self.service)
+    }
+}
+
+impl<F: Clone, S: Clone, T> Clone for Filtered<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: fmt::Debug, S: fmt::Debug, T> fmt::Debug for Filtered<F, S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Filtered")
+            .field("filter", &self.filter)
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+/// A service that is only executed if the async filter matches,
+/// created by [`FilteredServiceExt::filtered_async`].
+#[cfg(feature = "async")]
+pub struct AsyncFiltered<F, S, T> {
+    filter: F,
+    service: S,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+#[cfg(feature = "async")]
+impl<F: AsyncFilter<T>, S: Service<T>, T: Send + 'static> AsyncFiltered<F, S, T> {
+    /// Falls through to `inner` if the filter doesn't match,
+    /// same as `AsyncFilterLayer::new(filter, service).layer(inner)`.
+    pub fn or<I>(self, inner: I) -> AsyncFilterService<F, S, I, T>
+    where
+        S: Clone,
+        I: Service<T, Response = S::Response, Error = S::Error> + Clone,
+    {
+        self.or_layer().layer(inner)
+    }
+
+    /// Creates a layer falling through to the service it wraps,
+    /// same as `AsyncFilterLayer::new(filter, service)`.
+    pub fn or_layer(self) -> AsyncFilterLayer<F, S, T> {
+        AsyncFilterLayer::new(self.filter, self.service)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F: Clone, S: Clone, T> Clone for AsyncFiltered<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F: fmt::Debug, S: fmt::Debug, T> fmt::Debug for AsyncFiltered<F, S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncFiltered")
+            .field("filter", &self.filter)
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_util::*;
+
+    type TestFilterService =
+        FilterService<TestFilter, TestService<&'static str>, TestService<&'static str>, ()>;
+
+    #[tokio::test]
+    async fn should_behave_like_filter_layer() {
+        for matches in [true, false] {
+            // NOTE: The annotations prove both create the same type.
+            let extended: TestFilterService = TestService("a")
+                .filtered(TestFilter(matches))
+                .or(TestService("b"));
+            let layered: TestFilterService =
+                FilterLayer::new(TestFilter(matches), TestService("a")).layer(TestService("b"));
+
+            assert_eq!(extended.oneshot(()).await, layered.oneshot(()).await);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_create_layer() {
+        let layer = TestService("a").filtered(TestFilter(false)).or_layer();
+
+        assert_eq!(layer.layer(TestService("b")).oneshot(()).await, Ok("b"));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn should_behave_like_async_filter_layer() {
+        for matches in [true, false] {
+            let extended = TestService("a")
+                .filtered_async(TestFilter(matches))
+                .or(TestService("b"));
+            let layered = AsyncFilterLayer::new(TestFilter(matches), TestService("a"))
+                .layer(TestService("b"));
+
+            assert_eq!(extended.oneshot(()).await, layered.oneshot(()).await);
+        }
+    }
+}
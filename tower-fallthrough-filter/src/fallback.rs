@@ -0,0 +1,260 @@
+use std::{
+    fmt,
+    marker::PhantomData,
+    task::{Context, Poll},
+};
+
+use tower::{Layer, Service};
+
+use crate::futures::FallbackChainFut;
+
+/// A Tower layer that calls the provided service first and falls
+/// through to the inner service only if it fails with an error the
+/// given function accepts, e.g. "try static files, then proxy to
+/// the backend".
+///
+/// Unlike [`FilterLayer`](crate::FilterLayer), the decision is made
+/// after calling the service, so the request has to be cloned.
+/// Responses and errors the function rejects are returned as is.
+///
+/// # Readiness
+/// Only the provided service is polled by `poll_ready`, as most requests
+/// shouldn't fall through. On fallthrough the inner service is cloned
+/// and driven to readiness within the response future, like
+/// `tower::ServiceExt::oneshot`.
+///
+/// # Example
+/// ```rust
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::FallbackChainLayer;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Error {
+///     NotFound,
+///     Forbidden,
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let files = service_fn(|path: &'static str| async move {
+///     match path {
+///         "/index.html" => Ok("file"),
+///         "/.env" => Err(Error::Forbidden),
+///         _ => Err(Error::NotFound),
+///     }
+/// });
+/// let backend = service_fn(|_: &'static str| async { Ok("backend") });
+///
+/// let service = FallbackChainLayer::new(files, |e: &Error| *e == Error::NotFound).layer(backend);
+///
+/// assert_eq!(service.clone().oneshot("/index.html").await, Ok("file"));
+/// assert_eq!(service.clone().oneshot("/users").await, Ok("backend"));
+/// assert_eq!(service.oneshot("/.env").await, Err(Error::Forbidden));
+/// # }
+/// ```
+pub struct FallbackChainLayer<A, P, T> {
+    service: A,
+    should_fallthrough: P,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<A, P, T> FallbackChainLayer<A, P, T>
+where
+    A: Service<T>,
+    P: Fn(&A::Error) -> bool + Clone,
+{
+    /// Creates a new FallbackChainLayer given the `Service` that is
+    /// called first and a function deciding which of its errors
+    /// fall through.
+    pub fn new(service: A, should_fallthrough: P) -> Self {
+        Self {
+            service,
+            should_fallthrough,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<A: Clone, P: Clone, T> Clone for FallbackChainLayer<A, P, T> {
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+            should_fallthrough: self.should_fallthrough.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<A: fmt::Debug, P, T> fmt::Debug for FallbackChainLayer<A, P, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FallbackChainLayer")
+            .field("service", &self.service)
+            .field("should_fallthrough", &std::any::type_name::<P>())
+            .finish()
+    }
+}
+
+impl<A, B, P, T> Layer<B> for FallbackChainLayer<A, P, T>
+where
+    A: Service<T> + Clone,
+    B: Service<T, Response = A::Response, Error = A::Error> + Clone,
+    P: Fn(&A::Error) -> bool + Clone,
+    T: Clone,
+{
+    type Service = FallbackChain<A, B, P, T>;
+
+    fn layer(&self, inner_service: B) -> Self::Service {
+        FallbackChain::new(
+            self.service.clone(),
+            inner_service,
+            self.should_fallthrough.clone(),
+        )
+    }
+}
+
+/// A service calling `A` and falling through to `B` if `A` fails with
+/// an error the given function accepts, see [`FallbackChainLayer`].
+pub struct FallbackChain<A, B, P, T> {
+    service: A,
+    fallback: B,
+    should_fallthrough: P,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<A, B, P, T> FallbackChain<A, B, P, T>
+where
+    A: Service<T>,
+    B: Service<T, Response = A::Response, Error = A::Error> + Clone,
+    P: Fn(&A::Error) -> bool + Clone,
+    T: Clone,
+{
+    /// Creates a new FallbackChain given the `Service` that is called
+    /// first, the `Service` to fall through to and a function deciding
+    /// which errors fall through.
+    pub fn new(service: A, fallback: B, should_fallthrough: P) -> Self {
+        Self {
+            service,
+            fallback,
+            should_fallthrough,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<A: Clone, B: Clone, P: Clone, T> Clone for FallbackChain<A, B, P, T> {
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+            fallback: self.fallback.clone(),
+            should_fallthrough: self.should_fallthrough.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<A: fmt::Debug, B: fmt::Debug, P, T> fmt::Debug for FallbackChain<A, B, P, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FallbackChain")
+            .field("service", &self.service)
+            .field("fallback", &self.fallback)
+            .field("should_fallthrough", &std::any::type_name::<P>())
+            .finish()
+    }
+}
+
+impl<A, B, P, T> Service<T> for FallbackChain<A, B, P, T>
+where
+    A: Service<T>,
+    B: Service<T, Response = A::Response, Error = A::Error> + Clone,
+    P: Fn(&A::Error) -> bool + Clone,
+    T: Clone,
+{
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future = FallbackChainFut<A, B, P, T>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // NOTE: The fallback is only polled once it is needed,
+        //       within the future.
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        let future = self.service.call(req.clone());
+
+        FallbackChainFut::new(
+            future,
+            self.fallback.clone(),
+            req,
+            self.should_fallthrough.clone(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use tower::{service_fn, ServiceExt};
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Error {
+        NotFound,
+        Forbidden,
+    }
+
+    fn chain(
+        primary: Result<&'static str, Error>,
+    ) -> (
+        impl Service<(), Response = &'static str, Error = Error>,
+        Arc<AtomicUsize>,
+    ) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+
+        let primary = service_fn(move |_: ()| async move { primary });
+        let fallback = service_fn(move |_: ()| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { Ok("fallback") }
+        });
+
+        let service =
+            FallbackChainLayer::new(primary, |e: &Error| *e == Error::NotFound).layer(fallback);
+        (service, calls)
+    }
+
+    #[tokio::test]
+    async fn should_return_primary_response() {
+        let (service, calls) = chain(Ok("primary"));
+
+        assert_eq!(service.oneshot(()).await, Ok("primary"));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn should_fall_through_on_accepted_error() {
+        let (service, calls) = chain(Err(Error::NotFound));
+
+        assert_eq!(service.oneshot(()).await, Ok("fallback"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn should_return_rejected_error() {
+        let (service, calls) = chain(Err(Error::Forbidden));
+
+        assert_eq!(service.oneshot(()).await, Err(Error::Forbidden));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,45 @@
+/// Builds nested [`FilterService`](crate::FilterService)s from a list of
+/// `(filter, service)` pairs and the service to fall through to.
+///
+/// The filters are checked in the given order, so
+/// `filter_chain![(f1, s1), (f2, s2); fallthrough]` expands to:
+/// ```text
+/// FilterLayer::new(f1, s1).layer(FilterLayer::new(f2, s2).layer(fallthrough))
+/// ```
+///
+/// # Example
+/// ```rust
+/// use tower::{service_fn, ServiceExt};
+/// use tower_fallthrough_filter::{filter_chain, FilterFn};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name| service_fn(move |_: u32| async move { Ok::<_, ()>(name) });
+/// let below = |max: u32| FilterFn::new(move |n: &u32| *n < max);
+///
+/// let service = filter_chain![
+///     (below(10), respond("small")),
+///     (below(100), respond("medium"));
+///     respond("large")
+/// ];
+///
+/// assert_eq!(service.clone().oneshot(5).await, Ok("small"));
+/// assert_eq!(service.clone().oneshot(50).await, Ok("medium"));
+/// assert_eq!(service.oneshot(500).await, Ok("large"));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! filter_chain {
+    ($(($filter:expr, $service:expr)),+ $(,)?) => {
+        ::core::compile_error!("expected `; fallthrough` after the filters")
+    };
+    (; $fallthrough:expr $(,)?) => {
+        $fallthrough
+    };
+    (($filter:expr, $service:expr) $(, ($filters:expr, $services:expr))* $(,)?; $fallthrough:expr $(,)?) => {
+        $crate::__private::Layer::layer(
+            &$crate::FilterLayer::new($filter, $service),
+            $crate::filter_chain!($(($filters, $services)),*; $fallthrough),
+        )
+    };
+}
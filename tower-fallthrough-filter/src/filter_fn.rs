@@ -0,0 +1,151 @@
+use std::fmt;
+
+use tower::Service;
+
+use crate::{Filter, FilterLayer};
+
+#[cfg(feature = "async")]
+use std::future::Future;
+
+#[cfg(feature = "async")]
+use crate::{AsyncFilter, AsyncFilterLayer};
+
+/// A filter calling the given function, created by [`filter_fn`].
+#[derive(Clone, Copy)]
+pub struct FilterFn<P> {
+    predicate: P,
+}
+
+impl<P> FilterFn<P> {
+    /// Creates a new FilterFn given a function deciding
+    /// whether the request matches.
+    pub fn new<T>(predicate: P) -> Self
+    where
+        P: Fn(&T) -> bool + Clone,
+    {
+        Self { predicate }
+    }
+}
+
+impl<P> fmt::Debug for FilterFn<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilterFn")
+            .field("predicate", &std::any::type_name::<P>())
+            .finish()
+    }
+}
+
+impl<P, T> Filter<T> for FilterFn<P>
+where
+    P: Fn(&T) -> bool + Clone,
+{
+    fn matches(&self, item: &T) -> bool {
+        (self.predicate)(item)
+    }
+}
+
+/// Creates a [`FilterLayer`] executing `service` if `predicate`
+/// returns true, similar to `axum::middleware::from_fn`.
+///
+/// # Example
+/// ```rust
+/// use axum::{extract::Request, routing::get, Router};
+/// use tower_fallthrough_filter::filter_fn;
+///
+/// let beta = Router::new().fallback(get(|| async { "beta" }));
+///
+/// let app: Router = Router::new()
+///     .route("/", get(|| async { "stable" }))
+///     .layer(filter_fn(
+///         |req: &Request| req.headers().contains_key("x-beta"),
+///         beta,
+///     ));
+/// ```
+pub fn filter_fn<P, S, T>(predicate: P, service: S) -> FilterLayer<FilterFn<P>, S, T>
+where
+    P: Fn(&T) -> bool + Clone,
+    S: Service<T>,
+{
+    FilterLayer::new(FilterFn::new(predicate), service)
+}
+
+/// An async filter calling the given function, created
+/// by [`async_filter_fn`].
+///
+/// The returned future can't borrow from the request,
+/// so the required parts have to be cloned into it.
+#[cfg(feature = "async")]
+#[derive(Clone, Copy)]
+pub struct AsyncFilterFn<P> {
+    predicate: P,
+}
+
+#[cfg(feature = "async")]
+impl<P> AsyncFilterFn<P> {
+    /// Creates a new AsyncFilterFn given a function deciding
+    /// whether the request matches.
+    pub fn new<T, Fut>(predicate: P) -> Self
+    where
+        P: Fn(&T) -> Fut + Clone + Send + Sync,
+        Fut: Future<Output = bool> + Send,
+    {
+        Self { predicate }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<P> fmt::Debug for AsyncFilterFn<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncFilterFn")
+            .field("predicate", &std::any::type_name::<P>())
+            .finish()
+    }
+}
+
+#[cfg(feature = "async")]
+impl<P, Fut, T> AsyncFilter<T> for AsyncFilterFn<P>
+where
+    P: Fn(&T) -> Fut + Clone + Send + Sync,
+    Fut: Future<Output = bool> + Send,
+{
+    type Future = Fut;
+
+    fn matches(&self, item: &T) -> Self::Future {
+        (self.predicate)(item)
+    }
+}
+
+/// Creates an [`AsyncFilterLayer`] executing `service` if the future
+/// returned by `predicate` resolves to true, see [`filter_fn`].
+///
+/// # Example
+/// ```rust
+/// use axum::{extract::Request, routing::get, Router};
+/// use tower_fallthrough_filter::async_filter_fn;
+///
+/// let beta = Router::new().fallback(get(|| async { "beta" }));
+///
+/// let app: Router = Router::new()
+///     .route("/", get(|| async { "stable" }))
+///     .layer(async_filter_fn(
+///         |req: &Request| {
+///             let user = req.headers().get("x-user").cloned();
+///             // Imagine looking up the user in a database.
+///             async move { user.is_some_and(|user| user == "tester") }
+///         },
+///         beta,
+///     ));
+/// ```
+#[cfg(feature = "async")]
+pub fn async_filter_fn<P, Fut, S, T>(
+    predicate: P,
+    service: S,
+) -> AsyncFilterLayer<AsyncFilterFn<P>, S, T>
+where
+    P: Fn(&T) -> Fut + Clone + Send + Sync,
+    Fut: Future<Output = bool> + Send,
+    S: Service<T>,
+    T: Send + 'static,
+{
+    AsyncFilterLayer::new(AsyncFilterFn::new(predicate), service)
+}
@@ -0,0 +1,322 @@
+//! Filters based on axum's routing and extractors.
+
+use std::fmt;
+
+use ::axum::extract::{FromRequestParts, MatchedPath};
+use futures::FutureExt;
+use http::Request;
+
+use crate::Filter;
+
+#[cfg(feature = "async")]
+use std::future::Future;
+
+#[cfg(feature = "async")]
+use futures::future::BoxFuture;
+#[cfg(feature = "async")]
+use tower::{Layer, Service};
+
+#[cfg(feature = "async")]
+use crate::{AsyncFilter, AsyncFilterLayer, AsyncFilterService};
+
+/// A filter deciding based on the [`MatchedPath`] axum inserts into
+/// the request extensions, i.e. the route pattern a request matched.
+///
+/// Applied using `Router::layer`, the filter sees whether the request
+/// is about to be handled by a route or would hit the fallback.
+///
+/// # Example
+/// ```rust
+/// use axum::{routing::get, Router};
+/// use tower_fallthrough_filter::{filters::MatchedPathFilter, FilterLayer};
+///
+/// let renderer = Router::new().fallback(get(|| async { "rendered page" }));
+///
+/// // Renders a page for every path that doesn't have a handler.
+/// let app: Router = Router::new()
+///     .route("/api/users", get(|| async { "users" }))
+///     .layer(FilterLayer::new(MatchedPathFilter::unmatched(), renderer));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MatchedPathFilter {
+    rule: Rule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Rule {
+    Matched,
+    Unmatched,
+    Is(&'static str),
+}
+
+impl MatchedPathFilter {
+    /// Matches requests a route matched.
+    pub fn matched() -> Self {
+        Self {
+            rule: Rule::Matched,
+        }
+    }
+
+    /// Matches requests no route matched, which
+    /// would otherwise be handled by the fallback.
+    pub fn unmatched() -> Self {
+        Self {
+            rule: Rule::Unmatched,
+        }
+    }
+
+    /// Matches requests the route with the given pattern matched,
+    /// e.g. `"/users/:id"`.
+    pub fn is(path: &'static str) -> Self {
+        Self {
+            rule: Rule::Is(path),
+        }
+    }
+}
+
+impl<B> Filter<Request<B>> for MatchedPathFilter {
+    fn matches(&self, req: &Request<B>) -> bool {
+        let matched = req.extensions().get::<MatchedPath>();
+
+        match self.rule {
+            Rule::Matched => matched.is_some(),
+            Rule::Unmatched => matched.is_none(),
+            Rule::Is(path) => matched.is_some_and(|matched| matched.as_str() == path),
+        }
+    }
+}
+
+/// A filter deciding based on an axum extractor, e.g. `Path` or
+/// `Query`, like a handler would receive it.
+///
+/// The extractor runs on a copy of the request parts without a state.
+/// A request the extractor rejects doesn't match.
+///
+/// NOTE: Filters decide synchronously, so the extractor has to finish
+/// without waiting, which is the case for the extractors that only read
+/// the request parts. Otherwise the request doesn't match.
+///
+/// # Example
+/// ```rust
+/// use axum::{extract::Path, routing::get, Router};
+/// use tower_fallthrough_filter::{filters::AxumFilter, FilterLayer};
+///
+/// let admin = Router::new().fallback(get(|| async { "admin" }));
+///
+/// // Users with an id below 10 are admins.
+/// let is_admin = AxumFilter::from_extractor::<Path<u32>>(|Path(id)| id < 10);
+///
+/// let app: Router = Router::new()
+///     .route("/users/:id", get(|| async { "user" }))
+///     .layer(FilterLayer::new(is_admin, admin));
+/// ```
+pub struct AxumFilter<E = ()> {
+    predicate: fn(E) -> bool,
+}
+
+impl AxumFilter {
+    /// Creates a new AxumFilter given the extractor and the function
+    /// deciding whether the request matches based on what it extracted.
+    pub fn from_extractor<E>(predicate: fn(E) -> bool) -> AxumFilter<E>
+    where
+        E: FromRequestParts<()>,
+    {
+        AxumFilter { predicate }
+    }
+}
+
+// NOTE: This is required to make the `AxumFilter` clonable
+//       without requiring the extractor to be clonable.
+impl<E> Clone for AxumFilter<E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E> Copy for AxumFilter<E> {}
+
+impl<E> fmt::Debug for AxumFilter<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AxumFilter")
+            .field("extractor", &std::any::type_name::<E>())
+            .finish()
+    }
+}
+
+impl<E, B> Filter<Request<B>> for AxumFilter<E>
+where
+    E: FromRequestParts<()>,
+{
+    fn matches(&self, req: &Request<B>) -> bool {
+        let (mut parts, ()) = copy_parts(req).into_parts();
+
+        match E::from_request_parts(&mut parts, &()).now_or_never() {
+            Some(Ok(extracted)) => (self.predicate)(extracted),
+            Some(Err(_)) | None => false,
+        }
+    }
+}
+
+/// Copies everything but the body of the request.
+fn copy_parts<B>(req: &Request<B>) -> Request<()> {
+    let mut copy = Request::new(());
+    *copy.method_mut() = req.method().clone();
+    *copy.uri_mut() = req.uri().clone();
+    *copy.version_mut() = req.version();
+    *copy.headers_mut() = req.headers().clone();
+    *copy.extensions_mut() = req.extensions().clone();
+    copy
+}
+
+/// A function deciding based on a reference to the state and the
+/// request, e.g. an `async fn(&AppState, &Request<()>) -> bool`,
+/// see [`StatefulAsyncFilter`].
+///
+/// It is implemented for every such function returning a `Send` future.
+#[cfg(feature = "async")]
+pub trait AsyncStateFn<'a, St: 'a> {
+    /// The future returned by the function.
+    type Future: Future<Output = bool> + Send + 'a;
+
+    /// Calls the function.
+    fn call(&self, state: &'a St, req: &'a Request<()>) -> Self::Future;
+}
+
+#[cfg(feature = "async")]
+impl<'a, St: 'a, F, Fut> AsyncStateFn<'a, St> for F
+where
+    F: Fn(&'a St, &'a Request<()>) -> Fut,
+    Fut: Future<Output = bool> + Send + 'a,
+{
+    type Future = Fut;
+
+    fn call(&self, state: &'a St, req: &'a Request<()>) -> Self::Future {
+        self(state, req)
+    }
+}
+
+/// An async filter deciding based on the application state, like an
+/// axum handler using the `State` extractor.
+///
+/// Unlike [`AsyncStatefulFilter`](crate::AsyncStatefulFilter) the
+/// function borrows the state and the request, so it can be an
+/// `async fn`. It receives a copy of the request without the body.
+#[cfg(feature = "async")]
+#[derive(Debug, Clone)]
+pub struct StatefulAsyncFilter<St, F> {
+    state: St,
+    filter: F,
+}
+
+#[cfg(feature = "async")]
+impl<St, F> StatefulAsyncFilter<St, F> {
+    /// Creates a new StatefulAsyncFilter given the state and a
+    /// function deciding based on the state and the request.
+    pub fn new(state: St, filter: F) -> Self {
+        Self { state, filter }
+    }
+
+    /// The state passed to the filter function.
+    pub fn state(&self) -> &St {
+        &self.state
+    }
+}
+
+#[cfg(feature = "async")]
+impl<St, F, B> AsyncFilter<Request<B>> for StatefulAsyncFilter<St, F>
+where
+    St: Clone + Send + Sync + 'static,
+    F: for<'a> AsyncStateFn<'a, St> + Clone + Send + Sync + 'static,
+{
+    type Future = BoxFuture<'static, bool>;
+
+    fn matches(&self, req: &Request<B>) -> Self::Future {
+        let state = self.state.clone();
+        let filter = self.filter.clone();
+        let req = copy_parts(req);
+
+        Box::pin(async move { filter.call(&state, &req).await })
+    }
+}
+
+/// A Tower layer that executes the provided service only if the given
+/// async function returns true for the application state and the
+/// request. Otherwise it falls through to the inner service.
+///
+/// This is a shorthand for an [`AsyncFilterLayer`] with a
+/// [`StatefulAsyncFilter`].
+///
+/// # Example
+/// ```rust
+/// use std::{collections::HashSet, sync::Arc};
+///
+/// use axum::{http::Request, routing::get, Router};
+/// use tower_fallthrough_filter::filters::StatefulAsyncFilterLayer;
+///
+/// #[derive(Clone)]
+/// struct AppState {
+///     beta_users: Arc<HashSet<String>>,
+/// }
+///
+/// async fn is_beta(state: &AppState, req: &Request<()>) -> bool {
+///     let user = req.headers().get("x-user").and_then(|user| user.to_str().ok());
+///     user.is_some_and(|user| state.beta_users.contains(user))
+/// }
+///
+/// let state = AppState {
+///     beta_users: Arc::new(HashSet::from(["ferris".to_string()])),
+/// };
+/// let beta = Router::new().fallback(get(|| async { "beta" }));
+///
+/// let app: Router = Router::new()
+///     .route("/", get(|| async { "stable" }))
+///     .layer(StatefulAsyncFilterLayer::new(state, is_beta, beta));
+/// ```
+#[cfg(feature = "async")]
+pub struct StatefulAsyncFilterLayer<St, F, S, B> {
+    layer: AsyncFilterLayer<StatefulAsyncFilter<St, F>, S, Request<B>>,
+}
+
+#[cfg(feature = "async")]
+impl<St, F, S, B> Clone for StatefulAsyncFilterLayer<St, F, S, B>
+where
+    AsyncFilterLayer<StatefulAsyncFilter<St, F>, S, Request<B>>: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<St, F, S, B> StatefulAsyncFilterLayer<St, F, S, B>
+where
+    St: Clone + Send + Sync + 'static,
+    F: for<'a> AsyncStateFn<'a, St> + Clone + Send + Sync + 'static,
+    S: Service<Request<B>>,
+    B: Send + 'static,
+{
+    /// Creates a new StatefulAsyncFilterLayer given the state, the
+    /// async filter function and the `Service`.
+    pub fn new(state: St, filter: F, service: S) -> Self {
+        Self {
+            layer: AsyncFilterLayer::new(StatefulAsyncFilter::new(state, filter), service),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<St, F, S, I, B> Layer<I> for StatefulAsyncFilterLayer<St, F, S, B>
+where
+    StatefulAsyncFilter<St, F>: AsyncFilter<Request<B>>,
+    S: Service<Request<B>> + Clone,
+    I: Service<Request<B>, Response = S::Response, Error = S::Error>,
+    B: Send + 'static,
+{
+    type Service = AsyncFilterService<StatefulAsyncFilter<St, F>, S, I, Request<B>>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        self.layer.layer(inner_service)
+    }
+}
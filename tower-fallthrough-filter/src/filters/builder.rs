@@ -0,0 +1,474 @@
+use std::{error::Error, fmt};
+
+use http::{HeaderName, HeaderValue, Method, Request};
+
+use crate::{Filter, SharedState};
+
+/// Why an [`HttpFilterBuilder`] couldn't build a filter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HttpFilterError {
+    /// The given header name is not a valid HTTP header name.
+    InvalidHeaderName(String),
+    /// The given header value is not a valid HTTP header value.
+    InvalidHeaderValue(String),
+    /// Two different methods are required within `all`,
+    /// so the filter could never match.
+    ConflictingMethods(Method, Method),
+    /// Two paths are required within `all` that can't both apply to
+    /// the same request, e.g. two different exact paths or a path
+    /// outside of a required prefix, so the filter could never match.
+    ConflictingPaths(String, String),
+}
+
+impl fmt::Display for HttpFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeaderName(name) => write!(f, "invalid header name `{name}`"),
+            Self::InvalidHeaderValue(value) => write!(f, "invalid header value `{value}`"),
+            Self::ConflictingMethods(a, b) => {
+                write!(f, "a request can't have both the method {a} and {b}")
+            }
+            Self::ConflictingPaths(a, b) => {
+                write!(f, "a request can't match both the paths `{a}` and `{b}`")
+            }
+        }
+    }
+}
+
+impl Error for HttpFilterError {}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Rule {
+    Method(Method),
+    Path(String),
+    PathPrefix(String),
+    Header(HeaderName),
+    HeaderValue(HeaderName, HeaderValue),
+    QueryParam(String),
+    All(Vec<Rule>),
+    Any(Vec<Rule>),
+}
+
+impl Rule {
+    fn matches<B>(&self, req: &Request<B>) -> bool {
+        match self {
+            Self::Method(method) => req.method() == method,
+            Self::Path(path) => req.uri().path() == path,
+            Self::PathPrefix(prefix) => req.uri().path().starts_with(prefix.as_str()),
+            Self::Header(name) => req.headers().contains_key(name),
+            Self::HeaderValue(name, value) => {
+                req.headers().get_all(name).iter().any(|v| v == value)
+            }
+            Self::QueryParam(name) => req.uri().query().is_some_and(|query| {
+                query
+                    .split('&')
+                    .any(|pair| pair.split('=').next() == Some(name.as_str()))
+            }),
+            Self::All(rules) => rules.iter().all(|rule| rule.matches(req)),
+            Self::Any(rules) => rules.iter().any(|rule| rule.matches(req)),
+        }
+    }
+
+    fn validate(&self) -> Result<(), HttpFilterError> {
+        let (Self::All(rules) | Self::Any(rules)) = self else {
+            return Ok(());
+        };
+
+        if let Self::All(rules) = self {
+            validate_all(rules)?;
+        }
+
+        rules.iter().try_for_each(Rule::validate)
+    }
+}
+
+// NOTE: Only the rules directly within the group are compared,
+//       which catches the obvious mistakes without having to
+//       reason about nested `any` groups.
+fn validate_all(rules: &[Rule]) -> Result<(), HttpFilterError> {
+    let methods = rules.iter().filter_map(|rule| match rule {
+        Rule::Method(method) => Some(method),
+        _ => None,
+    });
+    if let Some((a, b)) = first_conflict(methods, |a, b| a != b) {
+        return Err(HttpFilterError::ConflictingMethods(a.clone(), b.clone()));
+    }
+
+    let paths = rules.iter().filter_map(|rule| match rule {
+        Rule::Path(path) => Some((path, true)),
+        Rule::PathPrefix(prefix) => Some((prefix, false)),
+        _ => None,
+    });
+    let conflict = first_conflict(paths, |(a, a_exact), (b, b_exact)| {
+        match (a_exact, b_exact) {
+            (true, true) => a != b,
+            (true, false) => !a.starts_with(b.as_str()),
+            (false, true) => !b.starts_with(a.as_str()),
+            (false, false) => !a.starts_with(b.as_str()) && !b.starts_with(a.as_str()),
+        }
+    });
+    if let Some(((a, _), (b, _))) = conflict {
+        return Err(HttpFilterError::ConflictingPaths(a.clone(), b.clone()));
+    }
+
+    Ok(())
+}
+
+fn first_conflict<I>(
+    items: I,
+    conflicts: impl Fn(&I::Item, &I::Item) -> bool,
+) -> Option<(I::Item, I::Item)>
+where
+    I: Iterator,
+    I::Item: Copy,
+{
+    let items: Vec<_> = items.collect();
+
+    items.iter().enumerate().find_map(|(i, a)| {
+        items[i + 1..]
+            .iter()
+            .find(|b| conflicts(a, b))
+            .map(|b| (*a, *b))
+    })
+}
+
+/// A fluent builder composing the common HTTP request checks into
+/// a single [`HttpFilter`], instead of nesting filter types.
+///
+/// All rules added to the builder have to match, use
+/// [`HttpFilterBuilder::any`] for alternatives.
+///
+/// # Example
+/// ```rust
+/// use http::{Method, Request};
+/// use tower_fallthrough_filter::{filters::HttpFilterBuilder, Filter};
+///
+/// let assets = HttpFilterBuilder::new()
+///     .any(|methods| methods.method(Method::GET).method(Method::HEAD))
+///     .any(|paths| paths.path_prefix("/assets/").path_prefix("/favicon"))
+///     .build()
+///     .unwrap();
+///
+/// let req = |method, uri| Request::builder().method(method).uri(uri).body(()).unwrap();
+///
+/// assert!(assets.matches(&req(Method::GET, "/assets/app.css")));
+/// assert!(!assets.matches(&req(Method::POST, "/assets/app.css")));
+/// assert!(!assets.matches(&req(Method::GET, "/api/users")));
+/// ```
+#[derive(Debug)]
+pub struct HttpFilterBuilder {
+    rules: Vec<Rule>,
+    error: Option<HttpFilterError>,
+}
+
+impl HttpFilterBuilder {
+    /// Creates a new HttpFilterBuilder, which matches
+    /// every request as long as no rules are added.
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Requires the request to have the given method.
+    pub fn method(self, method: Method) -> Self {
+        self.rule(Rule::Method(method))
+    }
+
+    /// Requires the path of the request to be exactly `path`.
+    pub fn path(self, path: impl Into<String>) -> Self {
+        self.rule(Rule::Path(path.into()))
+    }
+
+    /// Requires the path of the request to start with `prefix`.
+    ///
+    /// NOTE: The prefix isn't limited to whole segments,
+    /// i.e. `/assets` also matches `/assets-old`.
+    pub fn path_prefix(self, prefix: impl Into<String>) -> Self {
+        self.rule(Rule::PathPrefix(prefix.into()))
+    }
+
+    /// Requires the request to have a header named `name`.
+    pub fn header(mut self, name: &str) -> Self {
+        match HeaderName::try_from(name) {
+            Ok(name) => self.rule(Rule::Header(name)),
+            Err(_) => {
+                self.error
+                    .get_or_insert(HttpFilterError::InvalidHeaderName(name.to_owned()));
+                self
+            }
+        }
+    }
+
+    /// Requires the request to have a header named `name` with the
+    /// value `value`, any of the values if the header is repeated.
+    pub fn header_value(mut self, name: &str, value: &str) -> Self {
+        let Ok(header) = HeaderName::try_from(name) else {
+            self.error
+                .get_or_insert(HttpFilterError::InvalidHeaderName(name.to_owned()));
+            return self;
+        };
+        let Ok(value) = HeaderValue::try_from(value) else {
+            self.error
+                .get_or_insert(HttpFilterError::InvalidHeaderValue(value.to_owned()));
+            return self;
+        };
+
+        self.rule(Rule::HeaderValue(header, value))
+    }
+
+    /// Requires the request to have the already parsed header,
+    /// with the given value if there is one, see `FilterSpec`.
+    pub(crate) fn parsed_header(self, name: HeaderName, value: Option<HeaderValue>) -> Self {
+        match value {
+            Some(value) => self.rule(Rule::HeaderValue(name, value)),
+            None => self.rule(Rule::Header(name)),
+        }
+    }
+
+    /// Requires the query of the request to contain the parameter
+    /// `name`, with or without a value.
+    ///
+    /// NOTE: The query isn't percent-decoded.
+    pub fn query_param(self, name: impl Into<String>) -> Self {
+        self.rule(Rule::QueryParam(name.into()))
+    }
+
+    /// Requires at least one of the rules added by `group` to match.
+    pub fn any(self, group: impl FnOnce(Self) -> Self) -> Self {
+        self.group(group, Rule::Any)
+    }
+
+    /// Requires all of the rules added by `group` to match.
+    pub fn all(self, group: impl FnOnce(Self) -> Self) -> Self {
+        self.group(group, Rule::All)
+    }
+
+    /// Builds the filter.
+    ///
+    /// # Errors
+    /// Fails if a header name is invalid, or if rules required
+    /// together obviously contradict each other, e.g. two different
+    /// exact paths, as the filter could never match.
+    pub fn build(self) -> Result<HttpFilter, HttpFilterError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let rule = Rule::All(self.rules);
+        rule.validate()?;
+
+        Ok(HttpFilter {
+            rule: SharedState::new(rule),
+        })
+    }
+
+    fn rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    fn group(mut self, group: impl FnOnce(Self) -> Self, kind: fn(Vec<Rule>) -> Rule) -> Self {
+        let group = group(Self::new());
+
+        if let Some(error) = group.error {
+            self.error.get_or_insert(error);
+        }
+        self.rule(kind(group.rules))
+    }
+}
+
+impl Default for HttpFilterBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The filter built by an [`HttpFilterBuilder`].
+///
+/// The rules are shared between the clones of the filter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HttpFilter {
+    rule: SharedState<Rule>,
+}
+
+impl<B> Filter<Request<B>> for HttpFilter {
+    fn matches(&self, req: &Request<B>) -> bool {
+        self.rule.matches(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method, uri: &str, header: Option<&str>) -> Request<()> {
+        let mut builder = Request::builder().method(method).uri(uri);
+        if let Some(header) = header {
+            builder = builder.header(header, "1");
+        }
+
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn should_route_assets() {
+        let filter = HttpFilterBuilder::new()
+            .any(|methods| methods.method(Method::GET).method(Method::HEAD))
+            .any(|assets| {
+                assets
+                    .path_prefix("/assets/")
+                    .path("/favicon.ico")
+                    .all(|preview| preview.path_prefix("/drafts/").query_param("preview"))
+                    .header("x-internal")
+            })
+            .build()
+            .unwrap();
+
+        let cases = [
+            (Method::GET, "/assets/app.css", None, true),
+            (Method::HEAD, "/assets/logo.svg", None, true),
+            (Method::GET, "/favicon.ico", None, true),
+            (Method::GET, "/favicon.ico.bak", None, false),
+            (Method::GET, "/drafts/post?preview", None, true),
+            (Method::GET, "/drafts/post?lang=en&preview=1", None, true),
+            (Method::GET, "/drafts/post?previews=1", None, false),
+            (Method::GET, "/drafts/post", None, false),
+            (Method::GET, "/api/users", Some("x-internal"), true),
+            (Method::GET, "/api/users", None, false),
+            (Method::POST, "/assets/app.css", None, false),
+            (Method::DELETE, "/api/users", Some("x-internal"), false),
+        ];
+
+        for (method, uri, header, expected) in cases {
+            let req = request(method.clone(), uri, header);
+            assert_eq!(filter.matches(&req), expected, "{method} {uri} {header:?}");
+        }
+    }
+
+    #[test]
+    fn should_match_everything_without_rules() {
+        let filter = HttpFilterBuilder::new().build().unwrap();
+
+        assert!(filter.matches(&request(Method::PUT, "/", None)));
+    }
+
+    #[test]
+    fn should_reject_contradictions() {
+        let conflicting_paths = HttpFilterBuilder::new().path("/a").path("/b").build();
+        assert_eq!(
+            conflicting_paths.unwrap_err(),
+            HttpFilterError::ConflictingPaths("/a".into(), "/b".into())
+        );
+
+        let outside_prefix = HttpFilterBuilder::new()
+            .all(|group| group.path_prefix("/assets/").path("/index.html"))
+            .build();
+        assert_eq!(
+            outside_prefix.unwrap_err(),
+            HttpFilterError::ConflictingPaths("/assets/".into(), "/index.html".into())
+        );
+
+        let conflicting_methods = HttpFilterBuilder::new()
+            .method(Method::GET)
+            .method(Method::POST)
+            .build();
+        assert_eq!(
+            conflicting_methods.unwrap_err(),
+            HttpFilterError::ConflictingMethods(Method::GET, Method::POST)
+        );
+    }
+
+    #[test]
+    fn should_allow_alternatives() {
+        let filter = HttpFilterBuilder::new()
+            .path_prefix("/assets/")
+            .any(|paths| paths.path("/assets/a.css").path("/assets/b.css"))
+            .build();
+
+        assert!(filter.is_ok());
+    }
+
+    #[test]
+    fn should_compare_by_rules() {
+        let build = |prefix: &str| {
+            HttpFilterBuilder::new()
+                .method(Method::GET)
+                .path_prefix(prefix)
+                .build()
+                .unwrap()
+        };
+
+        assert_eq!(build("/assets/"), build("/assets/"));
+        assert_ne!(build("/assets/"), build("/static/"));
+
+        let filters: std::collections::HashSet<_> =
+            [build("/assets/"), build("/static/"), build("/assets/")].into();
+        assert_eq!(filters.len(), 2);
+    }
+
+    #[test]
+    fn should_match_header_values() {
+        let filter = HttpFilterBuilder::new()
+            .header_value("x-feature", "enabled")
+            .build()
+            .unwrap();
+
+        let req = |values: &[&str]| {
+            let mut builder = Request::builder();
+            for value in values {
+                builder = builder.header("x-feature", *value);
+            }
+            builder.body(()).unwrap()
+        };
+
+        assert!(filter.matches(&req(&["enabled"])));
+        assert!(filter.matches(&req(&["disabled", "enabled"])));
+        assert!(!filter.matches(&req(&["disabled"])));
+        assert!(!filter.matches(&req(&[])));
+
+        let filter = HttpFilterBuilder::new()
+            .header_value("x-feature", "line\nbreak")
+            .build();
+        assert_eq!(
+            filter.unwrap_err(),
+            HttpFilterError::InvalidHeaderValue("line\nbreak".into())
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_header_names() {
+        let filter = HttpFilterBuilder::new()
+            .any(|group| group.header("not a header"))
+            .build();
+
+        assert_eq!(
+            filter.unwrap_err(),
+            HttpFilterError::InvalidHeaderName("not a header".into())
+        );
+    }
+
+    #[test]
+    fn should_share_rules_across_service_clones() {
+        use tower::Layer;
+
+        use crate::{test_util::TestService, FilterLayer, FilterService};
+
+        let filter = HttpFilterBuilder::new()
+            .any(|assets| assets.path_prefix("/assets/").path("/favicon.ico"))
+            .build()
+            .unwrap();
+        let service: FilterService<_, _, _, Request<()>> =
+            FilterLayer::new(filter.clone(), TestService("a")).layer(TestService("b"));
+
+        let before = SharedState::strong_count(&filter.rule);
+        let clones: Vec<_> = (0..100).map(|_| service.clone()).collect();
+
+        // NOTE: Every clone adds a reference to the same rules.
+        assert_eq!(SharedState::strong_count(&filter.rule), before + 100);
+        assert!(clones
+            .iter()
+            .all(|clone| SharedState::ptr_eq(&clone.filter().rule, &filter.rule)));
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use http::{header::HeaderName, Request};
+use rand::RngCore;
+use tower::{Layer, Service};
+
+use crate::{rng::SharedRng, Filter, FilterLayer, FilterService};
+
+/// Which branch a request was routed to by a [`CanaryLayer`].
+///
+/// Inserted into the request extensions before the chosen service
+/// is called, so handlers and logging can tag the request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CanaryDecision {
+    /// The request is handled by the canary service.
+    Canary,
+    /// The request falls through to the stable service.
+    Stable,
+}
+
+/// A handle to change the percentage of traffic a [`CanaryFilter`]
+/// routes to the canary while serving.
+#[derive(Debug, Clone)]
+pub struct CanaryHandle(Arc<AtomicU64>);
+
+impl CanaryHandle {
+    fn new(percent: f64) -> Self {
+        let handle = Self(Arc::new(AtomicU64::new(0)));
+        handle.set_percent(percent);
+        handle
+    }
+
+    /// The percentage of requests routed to the canary.
+    pub fn percent(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    /// Sets the percentage of requests routed to the canary,
+    /// clamped to `0.0..=100.0`.
+    pub fn set_percent(&self, percent: f64) {
+        let percent = percent.clamp(0.0, 100.0);
+        self.0.store(percent.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// A filter that routes a percentage of the requests to a canary.
+///
+/// Optionally an override header can be configured: a value of `1`
+/// always routes the request to the canary and a value of `0` never
+/// does, regardless of the percentage.
+///
+/// # Example
+/// ```rust
+/// use http::Request;
+/// use tower_fallthrough_filter::{filters::CanaryFilter, Filter};
+///
+/// let filter = CanaryFilter::new(0.0).with_override_header("x-canary");
+/// let handle = filter.handle();
+///
+/// let req = Request::builder().header("x-canary", "1").body(()).unwrap();
+/// assert!(filter.matches(&req));
+///
+/// handle.set_percent(100.0);
+/// assert!(filter.matches(&Request::new(())));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CanaryFilter {
+    percent: CanaryHandle,
+    override_header: Option<HeaderName>,
+    rng: SharedRng,
+}
+
+impl CanaryFilter {
+    /// Creates a new CanaryFilter routing `percent` percent
+    /// of the requests to the canary.
+    pub fn new(percent: f64) -> Self {
+        Self {
+            percent: CanaryHandle::new(percent),
+            override_header: None,
+            rng: SharedRng::default(),
+        }
+    }
+
+    /// Lets requests with the given header force the decision.
+    ///
+    /// # Panics
+    /// Panics if `header` isn't a valid header name.
+    pub fn with_override_header(mut self, header: &str) -> Self {
+        let header = HeaderName::try_from(header).expect("Invalid override header name");
+        self.override_header = Some(header);
+        self
+    }
+
+    /// Uses the given random number generator instead of the thread
+    /// local one. It's shared between all clones of this filter.
+    pub fn with_rng<R: RngCore + Send + 'static>(mut self, rng: R) -> Self {
+        self.rng = SharedRng::new(rng);
+        self
+    }
+
+    /// Returns a handle to change the percentage at runtime.
+    pub fn handle(&self) -> CanaryHandle {
+        self.percent.clone()
+    }
+}
+
+impl<B> Filter<Request<B>> for CanaryFilter {
+    fn matches(&self, req: &Request<B>) -> bool {
+        let forced = self
+            .override_header
+            .as_ref()
+            .and_then(|header| req.headers().get(header))
+            .and_then(|value| match value.as_bytes() {
+                b"1" => Some(true),
+                b"0" => Some(false),
+                _ => None,
+            });
+
+        match forced {
+            Some(forced) => forced,
+            None => self.rng.next_f64() * 100.0 < self.percent.percent(),
+        }
+    }
+}
+
+/// A service inserting a fixed [`CanaryDecision`] into the
+/// request extensions before calling the wrapped service.
+#[derive(Debug, Clone)]
+pub struct CanaryDecisionService<S> {
+    service: S,
+    decision: CanaryDecision,
+}
+
+impl<S, B> Service<Request<B>> for CanaryDecisionService<S>
+where
+    S: Service<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<B>) -> Self::Future {
+        req.extensions_mut().insert(self.decision);
+        self.service.call(req)
+    }
+}
+
+/// A [`FilterLayer`] routing requests to a canary service
+/// using a [`CanaryFilter`] and tagging every request with
+/// its [`CanaryDecision`].
+#[derive(Debug)]
+pub struct CanaryLayer<S, B> {
+    layer: FilterLayer<CanaryFilter, CanaryDecisionService<S>, Request<B>>,
+}
+
+impl<S: Clone, B> Clone for CanaryLayer<S, B> {
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<S: Service<Request<B>>, B> CanaryLayer<S, B> {
+    /// Creates a new CanaryLayer given a `CanaryFilter` and the
+    /// canary `Service`.
+    pub fn new(filter: CanaryFilter, canary: S) -> Self {
+        let canary = CanaryDecisionService {
+            service: canary,
+            decision: CanaryDecision::Canary,
+        };
+
+        Self {
+            layer: FilterLayer::new(filter, canary),
+        }
+    }
+}
+
+impl<S, I, B> Layer<I> for CanaryLayer<S, B>
+where
+    S: Service<Request<B>> + Clone,
+    I: Service<Request<B>, Response = S::Response, Error = S::Error>,
+{
+    type Service =
+        FilterService<CanaryFilter, CanaryDecisionService<S>, CanaryDecisionService<I>, Request<B>>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        self.layer.layer(CanaryDecisionService {
+            service: inner_service,
+            decision: CanaryDecision::Stable,
+        })
+    }
+}
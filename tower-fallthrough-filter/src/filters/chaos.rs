@@ -0,0 +1,319 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use rand::RngCore;
+
+use crate::{rng::SharedRng, Filter};
+
+#[cfg(feature = "http")]
+pub use fail::FailService;
+
+/// A handle to change the probability with which a [`ChaosFilter`]
+/// injects faults while serving.
+#[derive(Debug, Clone)]
+pub struct ChaosHandle(Arc<AtomicU64>);
+
+impl ChaosHandle {
+    fn new(probability: f64) -> Self {
+        let handle = Self(Arc::new(AtomicU64::new(0)));
+        handle.set_probability(probability);
+        handle
+    }
+
+    /// The probability with which a request is matched.
+    pub fn probability(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    /// Sets the probability with which a request is matched,
+    /// clamped to `0.0..=1.0`.
+    pub fn set_probability(&self, probability: f64) {
+        let probability = probability.clamp(0.0, 1.0);
+        self.0.store(probability.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Stops injecting faults, the same as setting the probability to zero.
+    pub fn disable(&self) {
+        self.set_probability(0.0);
+    }
+}
+
+/// A filter for [`ChaosFilter`]s that aren't restricted
+/// to a subset of the requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct AnyRequest;
+
+impl<T> Filter<T> for AnyRequest {
+    fn matches(&self, _: &T) -> bool {
+        true
+    }
+}
+
+/// A filter for chaos testing that matches requests with a given
+/// probability, so they can be routed to an error producing service
+/// like [`FailService`], while everything else falls through.
+///
+/// The faults can be restricted to requests matching another filter
+/// using [`ChaosFilter::only_matching`].
+///
+/// As a safety net the filter never matches in release builds,
+/// unless [`ChaosFilter::allow_in_release`] is called explicitly.
+///
+/// # Example
+/// ```rust
+/// use rand::{rngs::StdRng, SeedableRng};
+/// use tower_fallthrough_filter::{filters::ChaosFilter, Filter};
+///
+/// let filter = ChaosFilter::new(0.5).with_rng(StdRng::seed_from_u64(1));
+/// let handle = filter.handle();
+///
+/// let faults = (0..100).filter(|_| filter.matches(&())).count();
+/// assert!((30..=70).contains(&faults));
+///
+/// handle.disable();
+/// assert!((0..100).all(|_| !filter.matches(&())));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChaosFilter<F = AnyRequest> {
+    filter: F,
+    probability: ChaosHandle,
+    rng: SharedRng,
+    allowed: bool,
+}
+
+impl ChaosFilter {
+    /// Creates a new ChaosFilter matching requests with the given
+    /// `probability` in `0.0..=1.0`.
+    pub fn new(probability: f64) -> Self {
+        Self {
+            filter: AnyRequest,
+            probability: ChaosHandle::new(probability),
+            rng: SharedRng::default(),
+            allowed: cfg!(debug_assertions),
+        }
+    }
+}
+
+impl<F> ChaosFilter<F> {
+    /// Only injects faults into requests also matching `filter`.
+    pub fn only_matching<G>(self, filter: G) -> ChaosFilter<G> {
+        ChaosFilter {
+            filter,
+            probability: self.probability,
+            rng: self.rng,
+            allowed: self.allowed,
+        }
+    }
+
+    /// Uses the given random number generator instead of the thread
+    /// local one. It's shared between all clones of this filter.
+    pub fn with_rng<R: RngCore + Send + 'static>(mut self, rng: R) -> Self {
+        self.rng = SharedRng::new(rng);
+        self
+    }
+
+    /// Allows the filter to match in release builds.
+    pub fn allow_in_release(mut self) -> Self {
+        self.allowed = true;
+        self
+    }
+
+    /// Returns a handle to change the probability at runtime.
+    pub fn handle(&self) -> ChaosHandle {
+        self.probability.clone()
+    }
+}
+
+impl<F: Filter<T>, T> Filter<T> for ChaosFilter<F> {
+    fn matches(&self, item: &T) -> bool {
+        let probability = self.probability.probability();
+
+        // NOTE: Checking the probability first doesn't consume random
+        //       numbers while disabled, which keeps seeded runs stable.
+        self.allowed
+            && probability > 0.0
+            && self.filter.matches(item)
+            && self.rng.next_f64() < probability
+    }
+}
+
+#[cfg(feature = "http")]
+mod fail {
+    use std::{
+        fmt,
+        future::{ready, Ready},
+        marker::PhantomData,
+        sync::Arc,
+        task::{Context, Poll},
+    };
+
+    use http::{Response, StatusCode};
+    use tower::Service;
+
+    enum Outcome<E> {
+        Status(StatusCode),
+        Error(E),
+    }
+
+    /// A service that fails every request, either with a response
+    /// with the given status code and an empty body or with an error.
+    ///
+    /// Meant to be paired with a [`ChaosFilter`](super::ChaosFilter).
+    pub struct FailService<B, E> {
+        outcome: Arc<Outcome<E>>,
+
+        _marker: PhantomData<fn() -> B>,
+    }
+
+    impl<B, E> FailService<B, E> {
+        /// Creates a new FailService responding with `status`.
+        pub fn status(status: StatusCode) -> Self {
+            Self::new(Outcome::Status(status))
+        }
+
+        /// Creates a new FailService failing with `error`.
+        pub fn error(error: E) -> Self {
+            Self::new(Outcome::Error(error))
+        }
+
+        fn new(outcome: Outcome<E>) -> Self {
+            Self {
+                outcome: Arc::new(outcome),
+
+                _marker: PhantomData,
+            }
+        }
+    }
+
+    impl<B, E> Clone for FailService<B, E> {
+        fn clone(&self) -> Self {
+            Self {
+                outcome: self.outcome.clone(),
+
+                _marker: PhantomData,
+            }
+        }
+    }
+
+    impl<B, E: fmt::Debug> fmt::Debug for FailService<B, E> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let mut f = f.debug_struct("FailService");
+            match &*self.outcome {
+                Outcome::Status(status) => f.field("status", status),
+                Outcome::Error(error) => f.field("error", error),
+            };
+            f.finish()
+        }
+    }
+
+    impl<T, B: Default, E: Clone> Service<T> for FailService<B, E> {
+        type Response = Response<B>;
+        type Error = E;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: T) -> Self::Future {
+            match &*self.outcome {
+                Outcome::Status(status) => {
+                    let mut res = Response::new(B::default());
+                    *res.status_mut() = *status;
+
+                    ready(Ok(res))
+                }
+                Outcome::Error(error) => ready(Err(error.clone())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::test_util::*;
+
+    fn seeded(probability: f64) -> ChaosFilter {
+        ChaosFilter::new(probability).with_rng(StdRng::seed_from_u64(42))
+    }
+
+    #[test]
+    fn should_match_with_probability() {
+        let filter = seeded(0.2);
+
+        let faults = (0..1000).filter(|_| filter.matches(&())).count();
+        assert!((150..=250).contains(&faults), "{faults} faults");
+    }
+
+    #[test]
+    fn should_be_reproducible() {
+        let decisions =
+            |filter: ChaosFilter| (0..50).map(|_| filter.matches(&())).collect::<Vec<_>>();
+
+        assert_eq!(decisions(seeded(0.5)), decisions(seeded(0.5)));
+    }
+
+    #[test]
+    fn should_only_match_inner_filter() {
+        let filter = seeded(1.0).only_matching(TestFilter(false));
+
+        assert!(!filter.matches(&()));
+    }
+
+    #[test]
+    fn should_disable_instantly() {
+        let filter = seeded(1.0);
+        let handle = filter.handle();
+
+        assert!(filter.matches(&()));
+
+        handle.disable();
+        assert!(!filter.matches(&()));
+        assert_eq!(handle.probability(), 0.0);
+    }
+
+    #[test]
+    fn should_refuse_unless_allowed() {
+        let mut filter = seeded(1.0);
+        filter.allowed = false;
+
+        assert!(!filter.matches(&()));
+        assert!(filter.allow_in_release().matches(&()));
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test]
+    async fn should_fail_requests() {
+        use http::{Request, StatusCode};
+        use tower::{Layer, Service};
+
+        use crate::FilterLayer;
+
+        let inner = || TestService(http::Response::new(()));
+
+        let fail = FailService::<(), _>::status(StatusCode::SERVICE_UNAVAILABLE);
+        let mut middleware = FilterLayer::new(seeded(1.0), fail).layer(inner());
+        let res = middleware.call(Request::new(())).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let fail = FailService::<(), _>::status(StatusCode::IM_A_TEAPOT);
+        let mut middleware = FilterLayer::new(seeded(0.0), fail).layer(inner());
+        let res = middleware.call(Request::new(())).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test]
+    async fn should_fail_with_error() {
+        use tower::Service;
+
+        let mut fail = FailService::<(), _>::error("injected");
+
+        assert_eq!(fail.call(()).await.unwrap_err(), "injected");
+    }
+}
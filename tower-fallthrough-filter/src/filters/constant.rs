@@ -0,0 +1,64 @@
+use crate::Filter;
+
+/// A filter that always returns `B`, to choose the branch at the
+/// type level, e.g. in library crates configuring their routing.
+///
+/// As the decision is a constant, the compiler can remove the
+/// branch that is never taken.
+///
+/// # Example
+/// ```rust
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{filters::ConstFilter, FilterLayer};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name| service_fn(move |_: ()| async move { Ok::<_, ()>(name) });
+///
+/// let service = FilterLayer::new(ConstFilter::<true>, respond("filtered")).layer(respond("inner"));
+/// assert_eq!(service.oneshot(()).await, Ok("filtered"));
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ConstFilter<const B: bool>;
+
+impl<const B: bool> ConstFilter<B> {
+    /// The decision of the filter, usable in const contexts.
+    pub const MATCHES: bool = B;
+}
+
+impl<const B: bool, T> Filter<T> for ConstFilter<B> {
+    fn matches(&self, _: &T) -> bool {
+        B
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of;
+
+    use tower::{Layer, Service};
+
+    use super::*;
+    use crate::{test_util::*, FilterLayer};
+
+    // NOTE: The decision is known at compile time.
+    const _: () = assert!(ConstFilter::<true>::MATCHES && !ConstFilter::<false>::MATCHES);
+
+    #[test]
+    fn should_be_zero_sized() {
+        assert_eq!(size_of::<ConstFilter<true>>(), 0);
+        assert_eq!(size_of::<ConstFilter<false>>(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_take_constant_branch() {
+        let mut always =
+            FilterLayer::new(ConstFilter::<true>, TestService("a")).layer(TestService("b"));
+        let mut never =
+            FilterLayer::new(ConstFilter::<false>, TestService("a")).layer(TestService("b"));
+
+        assert_eq!(always.call(()).await, Ok("a"));
+        assert_eq!(never.call(()).await, Ok("b"));
+    }
+}
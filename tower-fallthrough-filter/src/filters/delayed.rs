@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use rand::Rng;
+
+use crate::AsyncFilter;
+
+/// An async filter delaying the decision of the wrapped filter,
+/// e.g. to test how services behave with a slow filter.
+///
+/// Only meant for tests and benchmarks, so it is only available with
+/// the `testing` feature.
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+///
+/// use tokio::time::Instant;
+/// use tower_fallthrough_filter::{filters::DelayedFilter, AsyncFilter, AsyncFilterFn};
+///
+/// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+/// # async fn main() {
+/// let filter = DelayedFilter::new(AsyncFilterFn::new(|_: &u32| async { true }), Duration::from_secs(1));
+///
+/// let start = Instant::now();
+/// assert!(filter.matches(&42).await);
+/// assert_eq!(start.elapsed(), Duration::from_secs(1));
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DelayedFilter<F> {
+    filter: F,
+    delay: Duration,
+    max_jitter: Duration,
+}
+
+impl<F> DelayedFilter<F> {
+    /// Wraps the `filter`, delaying each decision by `delay`.
+    pub fn new(filter: F, delay: Duration) -> Self {
+        Self {
+            filter,
+            delay,
+            max_jitter: Duration::ZERO,
+        }
+    }
+
+    /// Adds a random delay of up to `max_jitter` to each decision.
+    pub fn with_jitter(mut self, max_jitter: Duration) -> Self {
+        self.max_jitter = max_jitter;
+        self
+    }
+}
+
+impl<F, T> AsyncFilter<T> for DelayedFilter<F>
+where
+    F: AsyncFilter<T>,
+    F::Future: 'static,
+{
+    type Future = BoxFuture<'static, bool>;
+
+    fn matches(&self, item: &T) -> Self::Future {
+        let jitter = rand::thread_rng().gen_range(Duration::ZERO..=self.max_jitter);
+        let delay = self.delay + jitter;
+        let matches = self.filter.matches(item);
+
+        Box::pin(async move {
+            tokio::time::sleep(delay).await;
+            matches.await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::Instant;
+    use tower::{Layer, ServiceExt};
+
+    use super::*;
+    use crate::{test_util::*, AsyncFilterLayer};
+
+    const DELAY: Duration = Duration::from_millis(100);
+
+    #[tokio::test(start_paused = true)]
+    async fn should_delay_decision() {
+        let filter = DelayedFilter::new(TestFilter(true), DELAY);
+        let service = AsyncFilterLayer::new(filter, TestService("a")).layer(TestService("b"));
+
+        let start = Instant::now();
+        assert_eq!(service.oneshot(()).await, Ok("a"));
+        assert_eq!(start.elapsed(), DELAY);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_add_jitter_within_bounds() {
+        let jitter = Duration::from_millis(50);
+        let filter = DelayedFilter::new(TestFilter(false), DELAY).with_jitter(jitter);
+
+        for _ in 0..20 {
+            let start = Instant::now();
+            assert!(!AsyncFilter::<()>::matches(&filter, &()).await);
+
+            let elapsed = start.elapsed();
+            assert!(elapsed >= DELAY, "{elapsed:?} is shorter than the delay");
+            assert!(elapsed <= DELAY + jitter, "{elapsed:?} exceeds the jitter");
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::ops::Range;
+
+use http::Request;
+
+use crate::Filter;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// A filter that shards requests by hashing a key extracted from
+/// the request into a fixed number of buckets and matches if the
+/// bucket falls into the configured range.
+///
+/// The key is hashed using 64 bit [FNV-1a] and the bucket is
+/// `hash % buckets`. The hash doesn't depend on the process,
+/// platform or crate version, so assignments survive restarts
+/// and are identical across instances.
+///
+/// [FNV-1a]: http://www.isthe.com/chongo/tech/comp/fnv/index.html
+///
+/// # Example
+/// ```rust
+/// use http::Request;
+/// use tower_fallthrough_filter::{filters::HashBucketFilter, Filter};
+///
+/// // Tenants landing in the buckets 0..10 (10%) hit the new backend.
+/// let filter = HashBucketFilter::new(
+///     |req: &Request<()>| req.headers().get("x-tenant-id")?.to_str().ok(),
+///     100,
+///     0..10,
+/// );
+///
+/// let req = Request::builder()
+///     .header("x-tenant-id", "tenant-a")
+///     .body(())
+///     .unwrap();
+///
+/// assert_eq!(filter.bucket("tenant-a"), 3);
+/// assert!(filter.matches(&req));
+/// ```
+#[derive(Debug, Clone)]
+pub struct HashBucketFilter<K> {
+    key: K,
+    buckets: u64,
+    range: Range<u64>,
+    on_missing_key: bool,
+}
+
+impl<K> HashBucketFilter<K> {
+    /// Creates a new HashBucketFilter distributing the keys
+    /// returned by `key` over `buckets` buckets and matching
+    /// the buckets in `range`.
+    ///
+    /// Requests without a key don't match by default, see
+    /// [`HashBucketFilter::on_missing_key`].
+    ///
+    /// # Panics
+    /// Panics if `buckets` is zero.
+    pub fn new<B>(key: K, buckets: u64, range: Range<u64>) -> Self
+    where
+        K: Fn(&Request<B>) -> Option<&str>,
+    {
+        assert!(buckets > 0, "HashBucketFilter requires at least one bucket");
+
+        Self {
+            key,
+            buckets,
+            range,
+            on_missing_key: false,
+        }
+    }
+
+    /// Sets the decision for requests the key can't be extracted from.
+    pub fn on_missing_key(mut self, matches: bool) -> Self {
+        self.on_missing_key = matches;
+        self
+    }
+
+    /// Returns the bucket the given key is assigned to.
+    pub fn bucket(&self, key: &str) -> u64 {
+        fnv1a(key.as_bytes()) % self.buckets
+    }
+}
+
+impl<K, B> Filter<Request<B>> for HashBucketFilter<K>
+where
+    K: Fn(&Request<B>) -> Option<&str> + Clone,
+{
+    fn matches(&self, req: &Request<B>) -> bool {
+        match (self.key)(req) {
+            Some(key) => self.range.contains(&self.bucket(key)),
+            None => self.on_missing_key,
+        }
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tenant(req: &Request<()>) -> Option<&str> {
+        req.headers().get("x-tenant-id")?.to_str().ok()
+    }
+
+    fn request(tenant: Option<&str>) -> Request<()> {
+        let mut builder = Request::builder();
+        if let Some(tenant) = tenant {
+            builder = builder.header("x-tenant-id", tenant);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn should_keep_bucket_assignments_stable() {
+        // NOTE: These must never change, otherwise tenants would
+        //       silently move between backends after an upgrade.
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"tenant-a"), 0xc2ef_8128_e3eb_9efb);
+
+        let filter = HashBucketFilter::new(tenant, 100, 0..10);
+        assert_eq!(filter.bucket("tenant-a"), 3);
+        assert_eq!(filter.bucket("tenant-b"), 14);
+        assert_eq!(filter.bucket("tenant-c"), 25);
+        assert_eq!(filter.bucket("acme"), 87);
+        assert_eq!(filter.bucket("globex"), 50);
+
+        let filter = HashBucketFilter::new(tenant, 16, 0..8);
+        assert_eq!(filter.bucket("tenant-a"), 11);
+        assert_eq!(filter.bucket("tenant-c"), 1);
+    }
+
+    #[test]
+    fn should_match_buckets_in_range() {
+        let filter = HashBucketFilter::new(tenant, 100, 0..20);
+
+        assert!(filter.matches(&request(Some("tenant-a"))));
+        assert!(filter.matches(&request(Some("tenant-b"))));
+        assert!(!filter.matches(&request(Some("tenant-c"))));
+        assert!(!filter.matches(&request(Some("acme"))));
+    }
+
+    #[test]
+    fn should_use_default_decision_for_missing_key() {
+        let filter = HashBucketFilter::new(tenant, 100, 0..100);
+        assert!(!filter.matches(&request(None)));
+
+        let filter = filter.on_missing_key(true);
+        assert!(filter.matches(&request(None)));
+    }
+}
@@ -0,0 +1,93 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use crate::Filter;
+
+/// A filter counting the matches and fallthroughs of the wrapped
+/// filter, without pulling in a metrics crate.
+///
+/// The counters are shared between all clones of the filter, so they
+/// can be read from e.g. a `/metrics` handler while serving.
+///
+/// # Example
+/// ```rust
+/// use tower_fallthrough_filter::{filters::MatchCountFilter, Filter, FilterFn};
+///
+/// let filter = MatchCountFilter::new(FilterFn::new(|n: &u32| n.is_multiple_of(2)));
+/// let counted = filter.clone();
+///
+/// for n in 0..5 {
+///     counted.matches(&n);
+/// }
+///
+/// assert_eq!(filter.matched_count(), 3);
+/// assert_eq!(filter.fallthrough_count(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MatchCountFilter<F> {
+    filter: F,
+    // NOTE: The matches and the fallthroughs.
+    counts: Arc<(AtomicU64, AtomicU64)>,
+}
+
+impl<F> MatchCountFilter<F> {
+    /// Wraps the `filter` with both counters set to zero.
+    pub fn new(filter: F) -> Self {
+        Self {
+            filter,
+            counts: Arc::default(),
+        }
+    }
+
+    /// How many requests the wrapped filter matched.
+    pub fn matched_count(&self) -> u64 {
+        self.counts.0.load(Ordering::Relaxed)
+    }
+
+    /// How many requests fell through, as the wrapped filter
+    /// didn't match them.
+    pub fn fallthrough_count(&self) -> u64 {
+        self.counts.1.load(Ordering::Relaxed)
+    }
+}
+
+impl<F: Filter<T>, T> Filter<T> for MatchCountFilter<F> {
+    fn matches(&self, item: &T) -> bool {
+        let matches = self.filter.matches(item);
+
+        let counter = if matches {
+            &self.counts.0
+        } else {
+            &self.counts.1
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::{Layer, ServiceExt};
+
+    use super::*;
+    use crate::{test_util::*, FilterFn, FilterLayer};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn should_count_accurately_under_concurrent_load() {
+        let filter = MatchCountFilter::new(FilterFn::new(|n: &u32| n.is_multiple_of(3)));
+        let service = FilterLayer::new(filter.clone(), TestService("a")).layer(TestService("b"));
+
+        let tasks: Vec<_> = (0..1000u32)
+            .map(|n| tokio::spawn(service.clone().oneshot(n)))
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert_eq!(filter.matched_count(), 334);
+        assert_eq!(filter.fallthrough_count(), 666);
+    }
+}
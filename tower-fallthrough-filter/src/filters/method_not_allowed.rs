@@ -0,0 +1,299 @@
+use std::{
+    fmt,
+    future::{ready, Ready},
+    marker::PhantomData,
+    task::{Context, Poll},
+};
+
+use http::{header::ALLOW, HeaderValue, Method, Request, Response, StatusCode};
+use tower::{Layer, Service};
+
+use crate::{Filter, FilterLayer, FilterService};
+
+/// A service that responds to every request with
+/// `405 Method Not Allowed`, an empty body and an `Allow` header
+/// listing the allowed methods.
+pub struct MethodNotAllowedService<B, E> {
+    allow: HeaderValue,
+
+    _marker: PhantomData<fn() -> (B, E)>,
+}
+
+impl<B, E> MethodNotAllowedService<B, E> {
+    /// Creates a new MethodNotAllowedService given the methods
+    /// allowed for the requested resource.
+    pub fn new(allowed_methods: impl IntoIterator<Item = Method>) -> Self {
+        Self::with_allow(allow_header(allowed_methods))
+    }
+
+    fn with_allow(allow: HeaderValue) -> Self {
+        Self {
+            allow,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<B, E> Clone for MethodNotAllowedService<B, E> {
+    fn clone(&self) -> Self {
+        Self::with_allow(self.allow.clone())
+    }
+}
+
+impl<B, E> fmt::Debug for MethodNotAllowedService<B, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MethodNotAllowedService")
+            .field("allow", &self.allow)
+            .finish()
+    }
+}
+
+/// The value of the `Allow` header, e.g. `GET, HEAD`.
+fn allow_header(methods: impl IntoIterator<Item = Method>) -> HeaderValue {
+    let methods: Vec<_> = methods
+        .into_iter()
+        .map(|method| method.to_string())
+        .collect();
+
+    // NOTE: A method is a token, which is always a valid header value.
+    HeaderValue::try_from(methods.join(", ")).expect("methods are valid header values")
+}
+
+impl<T, B: Default, E> Service<T> for MethodNotAllowedService<B, E> {
+    type Response = Response<B>;
+    type Error = E;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: T) -> Self::Future {
+        let mut res = Response::new(B::default());
+        *res.status_mut() = StatusCode::METHOD_NOT_ALLOWED;
+        res.headers_mut().insert(ALLOW, self.allow.clone());
+
+        ready(Ok(res))
+    }
+}
+
+type MethodFilterService<M, S, B, RB, E> =
+    FilterService<M, S, MethodNotAllowedService<RB, E>, Request<B>>;
+
+/// A Tower layer for method based routing which doesn't fall
+/// through when only the method is wrong.
+///
+/// - If the path and the method filter match, the provided service is called.
+/// - If only the path filter matches, `405 Method Not Allowed` is returned,
+///   with an `Allow` header listing the allowed methods.
+/// - Otherwise the request falls through to the inner service.
+///
+/// # Example
+/// ```rust
+/// use http::{Method, Request, Response, StatusCode};
+/// use tower::{service_fn, Layer, Service, ServiceExt};
+/// use tower_fallthrough_filter::{filters::MethodNotAllowedFilterLayer, Filter};
+///
+/// #[derive(Clone)]
+/// struct IsPost;
+///
+/// impl<B> Filter<Request<B>> for IsPost {
+///     fn matches(&self, req: &Request<B>) -> bool {
+///         req.method() == Method::POST
+///     }
+/// }
+///
+/// #[derive(Clone)]
+/// struct IsUpload;
+///
+/// impl<B> Filter<Request<B>> for IsUpload {
+///     fn matches(&self, req: &Request<B>) -> bool {
+///         req.uri().path() == "/upload"
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let upload = service_fn(|_: Request<()>| async { Ok::<_, ()>(Response::new("uploaded")) });
+/// let router = service_fn(|_: Request<()>| async { Ok::<_, ()>(Response::new("router")) });
+///
+/// let service = MethodNotAllowedFilterLayer::new(IsPost, [Method::POST], IsUpload, upload)
+///     .layer(router);
+///
+/// let req = Request::get("/upload").body(()).unwrap();
+/// let res = service.oneshot(req).await.unwrap();
+/// assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+/// assert_eq!(res.headers()["allow"], "POST");
+/// # }
+/// ```
+pub struct MethodNotAllowedFilterLayer<M, P, S, B> {
+    method_filter: M,
+    allow: HeaderValue,
+    path_filter: P,
+    service: S,
+
+    _marker: PhantomData<fn(B)>,
+}
+
+impl<M: Clone, P: Clone, S: Clone, B> Clone for MethodNotAllowedFilterLayer<M, P, S, B> {
+    fn clone(&self) -> Self {
+        Self {
+            method_filter: self.method_filter.clone(),
+            allow: self.allow.clone(),
+            path_filter: self.path_filter.clone(),
+            service: self.service.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<M, P, S, B> fmt::Debug for MethodNotAllowedFilterLayer<M, P, S, B>
+where
+    M: fmt::Debug,
+    P: fmt::Debug,
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MethodNotAllowedFilterLayer")
+            .field("method_filter", &self.method_filter)
+            .field("allow", &self.allow)
+            .field("path_filter", &self.path_filter)
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+impl<M, P, S, B, RB> MethodNotAllowedFilterLayer<M, P, S, B>
+where
+    M: Filter<Request<B>>,
+    P: Filter<Request<B>>,
+    S: Service<Request<B>, Response = Response<RB>>,
+{
+    /// Creates a new MethodNotAllowedFilterLayer given a `Filter`
+    /// for the method along with the methods it matches, a `Filter`
+    /// for the path and the `Service` handling the requests matching
+    /// both.
+    ///
+    /// The allowed methods are listed in the `Allow` header of the
+    /// `405 Method Not Allowed` responses.
+    pub fn new(
+        method_filter: M,
+        allowed_methods: impl IntoIterator<Item = Method>,
+        path_filter: P,
+        service: S,
+    ) -> Self {
+        Self {
+            method_filter,
+            allow: allow_header(allowed_methods),
+            path_filter,
+            service,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<M, P, S, I, B, RB> Layer<I> for MethodNotAllowedFilterLayer<M, P, S, B>
+where
+    M: Filter<Request<B>>,
+    P: Filter<Request<B>>,
+    S: Service<Request<B>, Response = Response<RB>> + Clone,
+    I: Service<Request<B>, Response = S::Response, Error = S::Error>,
+    RB: Default,
+{
+    type Service = FilterService<P, MethodFilterService<M, S, B, RB, S::Error>, I, Request<B>>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        let method_service = FilterLayer::new(self.method_filter.clone(), self.service.clone())
+            .layer(MethodNotAllowedService::with_allow(self.allow.clone()));
+
+        FilterLayer::new(self.path_filter.clone(), method_service).layer(inner_service)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::Method;
+    use tower::{service_fn, ServiceExt};
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct IsPost;
+
+    impl<B> Filter<Request<B>> for IsPost {
+        fn matches(&self, req: &Request<B>) -> bool {
+            req.method() == Method::POST
+        }
+    }
+
+    #[derive(Clone)]
+    struct IsUpload;
+
+    impl<B> Filter<Request<B>> for IsUpload {
+        fn matches(&self, req: &Request<B>) -> bool {
+            req.uri().path() == "/upload"
+        }
+    }
+
+    async fn request(method: Method, path: &str) -> Response<&'static str> {
+        let upload = service_fn(|_: Request<()>| async { Ok::<_, ()>(Response::new("upload")) });
+        let router = service_fn(|_: Request<()>| async { Ok::<_, ()>(Response::new("router")) });
+
+        let service =
+            MethodNotAllowedFilterLayer::new(IsPost, [Method::POST, Method::PUT], IsUpload, upload)
+                .layer(router);
+
+        let req = Request::builder()
+            .method(method)
+            .uri(path)
+            .body(())
+            .unwrap();
+        service.oneshot(req).await.unwrap()
+    }
+
+    async fn call(method: Method, path: &str) -> (StatusCode, &'static str) {
+        let res = request(method, path).await;
+
+        (res.status(), res.into_body())
+    }
+
+    #[tokio::test]
+    async fn should_call_service_on_matching_method() {
+        assert_eq!(
+            call(Method::POST, "/upload").await,
+            (StatusCode::OK, "upload")
+        );
+    }
+
+    #[tokio::test]
+    async fn should_reject_wrong_method() {
+        assert_eq!(
+            call(Method::GET, "/upload").await,
+            (StatusCode::METHOD_NOT_ALLOWED, "")
+        );
+    }
+
+    #[tokio::test]
+    async fn should_list_allowed_methods() {
+        let res = request(Method::GET, "/upload").await;
+        assert_eq!(res.headers()[ALLOW], "POST, PUT");
+
+        let res = request(Method::POST, "/upload").await;
+        assert!(!res.headers().contains_key(ALLOW));
+    }
+
+    #[tokio::test]
+    async fn should_fall_through_on_other_path() {
+        assert_eq!(
+            call(Method::POST, "/other").await,
+            (StatusCode::OK, "router")
+        );
+        assert_eq!(
+            call(Method::GET, "/other").await,
+            (StatusCode::OK, "router")
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! Ready-made filters for common routing decisions.
+
+#[cfg(all(feature = "axum", feature = "async"))]
+pub use self::axum::{AsyncStateFn, StatefulAsyncFilter, StatefulAsyncFilterLayer};
+#[cfg(feature = "axum")]
+pub use self::axum::{AxumFilter, MatchedPathFilter};
+#[cfg(feature = "http")]
+pub use builder::{HttpFilter, HttpFilterBuilder, HttpFilterError};
+#[cfg(all(feature = "http", feature = "rand"))]
+pub use canary::{CanaryDecision, CanaryDecisionService, CanaryFilter, CanaryHandle, CanaryLayer};
+#[cfg(all(feature = "http", feature = "rand"))]
+pub use chaos::FailService;
+#[cfg(feature = "rand")]
+pub use chaos::{AnyRequest, ChaosFilter, ChaosHandle};
+pub use constant::ConstFilter;
+#[cfg(all(feature = "async", any(test, feature = "testing")))]
+pub use delayed::DelayedFilter;
+#[cfg(feature = "http")]
+pub use hash_bucket::HashBucketFilter;
+pub use match_count::MatchCountFilter;
+#[cfg(feature = "http")]
+pub use method_not_allowed::{MethodNotAllowedFilterLayer, MethodNotAllowedService};
+pub use quorum::QuorumFilter;
+#[cfg(all(feature = "regex", feature = "http"))]
+pub use regex::RegexFilter;
+pub use sample::{SampleFilter, SampleHandle};
+#[cfg(all(feature = "http", feature = "serde"))]
+pub use spec::FilterConfig;
+#[cfg(feature = "http")]
+pub use spec::{FilterSpec, FilterSpecError};
+#[cfg(feature = "http")]
+pub use upgrade::{
+    UpgradeAwareFilterLayer, UpgradeFilter, UpgradeFilterLayer, UpgradeFilterService,
+    UpgradeHeaderFilter, UpgradeRequiredService,
+};
+#[cfg(feature = "wasm-filter")]
+pub use wasm::WasmFilter;
+
+#[cfg(feature = "axum")]
+pub mod axum;
+
+#[cfg(feature = "http")]
+mod builder;
+
+#[cfg(all(feature = "http", feature = "rand"))]
+mod canary;
+
+#[cfg(feature = "rand")]
+mod chaos;
+
+mod constant;
+
+#[cfg(all(feature = "async", any(test, feature = "testing")))]
+mod delayed;
+
+#[cfg(feature = "http")]
+mod hash_bucket;
+
+#[cfg(feature = "http")]
+mod method_not_allowed;
+
+mod match_count;
+
+mod quorum;
+
+#[cfg(feature = "regex")]
+mod regex;
+
+mod sample;
+
+#[cfg(feature = "http")]
+mod spec;
+
+#[cfg(feature = "tower-http")]
+pub mod tower_http;
+
+#[cfg(feature = "http")]
+mod upgrade;
+
+#[cfg(feature = "wasm-filter")]
+mod wasm;
@@ -0,0 +1,248 @@
+use std::sync::Arc;
+
+use crate::{Filter, SharedState};
+
+#[cfg(feature = "async")]
+use crate::{futures::QuorumFut, AsyncFilter};
+
+/// A filter that matches if at least `k` out of its filters match,
+/// e.g. to only roll out to requests where several independent
+/// signals like a percentage, an allowlist and a schedule agree.
+///
+/// The filters have to be of the same type, filters of different
+/// types can be combined into an enum, e.g. using `#[derive(Filter)]`.
+///
+/// As an [`AsyncFilter`] the filters are evaluated one after the
+/// other, stopping as soon as the outcome is certain, i.e. once `k`
+/// filters matched or too few are left for `k` to be reached.
+///
+/// # Example
+/// ```rust
+/// use tower_fallthrough_filter::{filters::QuorumFilter, Filter};
+///
+/// #[derive(Clone)]
+/// struct DivisibleBy(u32);
+///
+/// impl Filter<u32> for DivisibleBy {
+///     fn matches(&self, item: &u32) -> bool {
+///         item % self.0 == 0
+///     }
+/// }
+///
+/// let filter = QuorumFilter::new(vec![DivisibleBy(2), DivisibleBy(3), DivisibleBy(5)], 2);
+///
+/// assert!(filter.matches(&6));
+/// assert!(!filter.matches(&5));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QuorumFilter<F> {
+    // NOTE: Shared, so cloning the filter doesn't clone the filters.
+    filters: SharedState<[F]>,
+    k: usize,
+}
+
+impl<F> QuorumFilter<F> {
+    /// Creates a new QuorumFilter matching if at least
+    /// `k` of the given filters match.
+    ///
+    /// # Panics
+    /// Panics if `k` is greater than the number of filters,
+    /// as the filter could never match.
+    pub fn new(filters: Vec<F>, k: usize) -> Self {
+        assert!(
+            k <= filters.len(),
+            "QuorumFilter requires k to be at most the number of filters"
+        );
+
+        Self {
+            filters: SharedState::from(Arc::from(filters)),
+            k,
+        }
+    }
+}
+
+impl<F, T> Filter<T> for QuorumFilter<F>
+where
+    F: Filter<T>,
+{
+    fn matches(&self, item: &T) -> bool {
+        let matched = self
+            .filters
+            .iter()
+            .filter(|filter| filter.matches(item))
+            .count();
+
+        matched >= self.k
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F, T> AsyncFilter<T> for QuorumFilter<F>
+where
+    F: AsyncFilter<T>,
+{
+    type Future = QuorumFut<F::Future>;
+
+    fn matches(&self, item: &T) -> Self::Future {
+        // NOTE: Futures don't do anything until polled, so creating
+        //       all of them upfront doesn't evaluate the filters.
+        let conditions = self
+            .filters
+            .iter()
+            .map(|filter| filter.matches(item))
+            .collect();
+
+        QuorumFut::new(conditions, self.k)
+    }
+
+    fn matches_now(&self, item: &T) -> Option<bool> {
+        // NOTE: Like the future, the filters are asked in order until
+        //       the outcome is certain, but any undecided filter leaves
+        //       it to the future.
+        let mut matched = 0;
+        for (i, filter) in self.filters.iter().enumerate() {
+            if matched >= self.k {
+                return Some(true);
+            }
+            if matched + self.filters.len() - i < self.k {
+                return Some(false);
+            }
+
+            if filter.matches_now(item)? {
+                matched += 1;
+            }
+        }
+
+        Some(matched >= self.k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct CountingFilter(bool, Arc<AtomicUsize>);
+
+    impl<T> Filter<T> for CountingFilter {
+        fn matches(&self, _: &T) -> bool {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            self.0
+        }
+    }
+
+    #[cfg(feature = "async")]
+    impl<T> AsyncFilter<T> for CountingFilter {
+        type Future = futures::future::BoxFuture<'static, bool>;
+
+        fn matches(&self, _: &T) -> Self::Future {
+            let CountingFilter(matches, counter) = self.clone();
+
+            Box::pin(async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                matches
+            })
+        }
+    }
+
+    fn quorum(decisions: &[bool], k: usize) -> (QuorumFilter<CountingFilter>, Arc<AtomicUsize>) {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let filters = decisions
+            .iter()
+            .map(|matches| CountingFilter(*matches, counter.clone()))
+            .collect();
+
+        (QuorumFilter::new(filters, k), counter)
+    }
+
+    #[test]
+    fn should_match_any_with_threshold_one() {
+        assert!(Filter::matches(&quorum(&[false, false, true], 1).0, &()));
+        assert!(!Filter::matches(&quorum(&[false, false, false], 1).0, &()));
+    }
+
+    #[test]
+    fn should_match_all_with_threshold_n() {
+        assert!(Filter::matches(&quorum(&[true, true, true], 3).0, &()));
+        assert!(!Filter::matches(&quorum(&[true, false, true], 3).0, &()));
+    }
+
+    #[test]
+    fn should_evaluate_all_filters() {
+        let (filter, counter) = quorum(&[true, true, false, true], 2);
+
+        assert!(Filter::matches(&filter, &()));
+        assert_eq!(counter.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn should_compare_filters_and_thresholds() {
+        use crate::filters::ConstFilter;
+
+        let quorum = |k| QuorumFilter::new(vec![ConstFilter::<true>; 3], k);
+
+        assert_eq!(quorum(2), quorum(2));
+        assert_ne!(quorum(2), quorum(3));
+
+        let filters: std::collections::HashSet<_> = [quorum(1), quorum(2), quorum(1)].into();
+        assert_eq!(filters.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "at most the number of filters")]
+    fn should_reject_unreachable_threshold() {
+        quorum(&[true, true], 3);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn should_match_async() {
+        assert!(AsyncFilter::matches(&quorum(&[false, false, true], 1).0, &()).await);
+        assert!(!AsyncFilter::matches(&quorum(&[false, false, false], 1).0, &()).await);
+        assert!(AsyncFilter::matches(&quorum(&[true, true, true], 3).0, &()).await);
+        assert!(!AsyncFilter::matches(&quorum(&[true, false, true], 3).0, &()).await);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn should_exit_early_once_reached() {
+        let (filter, counter) = quorum(&[true, true, false, true], 2);
+
+        assert!(AsyncFilter::matches(&filter, &()).await);
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn should_exit_early_once_unreachable() {
+        let (filter, counter) = quorum(&[false, false, true, true, true], 4);
+
+        assert!(!AsyncFilter::matches(&filter, &()).await);
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn should_decide_now_if_the_filters_do() {
+        use crate::{
+            test_util::{DecidedFilter, TestFilter},
+            BoxAsyncFilter,
+        };
+
+        let decided = |matches| BoxAsyncFilter::new(DecidedFilter(matches));
+        let awaited = || BoxAsyncFilter::new(TestFilter(true));
+        let quorum = |filters, k| AsyncFilter::matches_now(&QuorumFilter::new(filters, k), &());
+
+        assert_eq!(quorum(vec![decided(true), decided(false)], 1), Some(true));
+        assert_eq!(quorum(vec![decided(false), decided(false)], 1), Some(false));
+        // NOTE: The outcome is certain before the awaited filter.
+        assert_eq!(quorum(vec![decided(true), awaited()], 1), Some(true));
+        assert_eq!(quorum(vec![decided(false), awaited()], 2), Some(false));
+        assert_eq!(quorum(vec![decided(true), awaited()], 2), None);
+    }
+}
@@ -0,0 +1,124 @@
+use ::regex::Regex;
+
+#[cfg(feature = "http")]
+use http::Request;
+
+use crate::Filter;
+
+/// Matches strings the regex matches anywhere within,
+/// see `Regex::is_match`.
+impl Filter<str> for Regex {
+    fn matches(&self, item: &str) -> bool {
+        self.is_match(item)
+    }
+}
+
+/// Matches strings the regex matches anywhere within,
+/// see `Regex::is_match`.
+impl Filter<String> for Regex {
+    fn matches(&self, item: &String) -> bool {
+        self.is_match(item)
+    }
+}
+
+/// A filter matching requests if the regex matches the part of the
+/// request returned by the extractor, e.g. the path.
+///
+/// # Example
+/// ```rust
+/// use http::Request;
+/// use regex::Regex;
+/// use tower_fallthrough_filter::{filters::RegexFilter, Filter};
+///
+/// let filter = RegexFilter::new(
+///     |req: &Request<()>| req.uri().path(),
+///     Regex::new(r"^/api/v\d+/").unwrap(),
+/// );
+///
+/// let req = |path| Request::get(path).body(()).unwrap();
+/// assert!(filter.matches(&req("/api/v2/users")));
+/// assert!(!filter.matches(&req("/api/users")));
+/// ```
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
+pub struct RegexFilter<E> {
+    extract: E,
+    regex: Regex,
+}
+
+#[cfg(feature = "http")]
+impl<E> RegexFilter<E> {
+    /// Creates a new RegexFilter matching the part of the request
+    /// returned by `extract` against `regex`.
+    pub fn new<B>(extract: E, regex: Regex) -> Self
+    where
+        E: Fn(&Request<B>) -> &str,
+    {
+        Self { extract, regex }
+    }
+
+    /// The regex the extracted part is matched against.
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+}
+
+#[cfg(feature = "http")]
+impl<E, B> Filter<Request<B>> for RegexFilter<E>
+where
+    E: Fn(&Request<B>) -> &str + Clone,
+{
+    fn matches(&self, req: &Request<B>) -> bool {
+        self.regex.is_match((self.extract)(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_match_strings() {
+        let regex = Regex::new(r"^\d+$").unwrap();
+
+        assert!(Filter::<str>::matches(&regex, "42"));
+        assert!(!Filter::<str>::matches(&regex, "4 2"));
+        assert!(regex.matches(&"42".to_string()));
+        assert!(!regex.matches(&"forty-two".to_string()));
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test]
+    async fn should_route_matching_paths() {
+        use tower::{Layer, ServiceExt};
+
+        use crate::{test_util::*, FilterLayer};
+
+        let filter = RegexFilter::new(
+            |req: &Request<()>| req.uri().path(),
+            Regex::new(r"^/api/v\d+/").unwrap(),
+        );
+        let service = FilterLayer::new(filter, TestService("api")).layer(TestService("page"));
+
+        let req = |path| Request::get(path).body(()).unwrap();
+        assert_eq!(
+            service.clone().oneshot(req("/api/v1/users")).await,
+            Ok("api")
+        );
+        assert_eq!(service.clone().oneshot(req("/api/users")).await, Ok("page"));
+        assert_eq!(service.oneshot(req("/about")).await, Ok("page"));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn should_clone_with_regex() {
+        let filter = RegexFilter::new(
+            |req: &Request<()>| req.uri().path(),
+            Regex::new("^/users").unwrap(),
+        );
+        let clone = filter.clone();
+
+        assert_eq!(clone.regex().as_str(), filter.regex().as_str());
+        assert!(clone.matches(&Request::get("/users/1").body(()).unwrap()));
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use crate::Filter;
+
+/// A handle to change the sampling rate of a [`SampleFilter`]
+/// while serving.
+#[derive(Debug, Clone)]
+pub struct SampleHandle(Arc<AtomicU64>);
+
+impl SampleHandle {
+    /// Every how many requests one is sampled, `0` meaning never.
+    pub fn n(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Samples one in every `n` requests, `0` meaning never.
+    pub fn set_n(&self, n: u64) {
+        self.0.store(n, Ordering::Relaxed);
+    }
+}
+
+/// A filter that deterministically matches exactly one in every
+/// `n` requests, e.g. to route them through a diagnostics service.
+///
+/// The counter is shared between all clones of the filter, so the
+/// rate isn't skewed when the surrounding service gets cloned.
+///
+/// # Example
+/// ```rust
+/// use tower_fallthrough_filter::{filters::SampleFilter, Filter};
+///
+/// let filter = SampleFilter::one_in(3);
+///
+/// let decisions: Vec<_> = (0..6).map(|_| filter.matches(&())).collect();
+/// assert_eq!(decisions, [false, false, true, false, false, true]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SampleFilter {
+    n: SampleHandle,
+    counter: Arc<AtomicU64>,
+}
+
+impl SampleFilter {
+    /// Creates a new SampleFilter matching one in every `n`
+    /// requests, `0` meaning never.
+    pub fn one_in(n: u64) -> Self {
+        Self {
+            n: SampleHandle(Arc::new(AtomicU64::new(n))),
+            counter: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Returns a handle to change the sampling rate at runtime.
+    pub fn handle(&self) -> SampleHandle {
+        self.n.clone()
+    }
+}
+
+impl<T> Filter<T> for SampleFilter {
+    fn matches(&self, _: &T) -> bool {
+        match self.n.n() {
+            0 => false,
+            n => (self.counter.fetch_add(1, Ordering::Relaxed) + 1).is_multiple_of(n),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::{Layer, Service};
+
+    use super::*;
+    use crate::{test_util::*, FilterLayer};
+
+    #[tokio::test]
+    async fn should_sample_exactly_one_in_n_across_clones() {
+        let n = 7;
+        let filter_layer = FilterLayer::new(SampleFilter::one_in(n), TestService("sampled"));
+        let middleware = filter_layer.layer(TestService("normal"));
+
+        let mut clones = [middleware.clone(), middleware.clone(), middleware];
+        let mut sampled = 0;
+
+        for i in 0..10 * n as usize {
+            let clone = &mut clones[i % 3];
+            if clone.call(()).await == Ok("sampled") {
+                sampled += 1;
+            }
+        }
+
+        assert_eq!(sampled, 10);
+    }
+
+    #[test]
+    fn should_adjust_rate_at_runtime() {
+        let filter = SampleFilter::one_in(2);
+        let handle = filter.handle();
+
+        assert_eq!((0..10).filter(|_| filter.matches(&())).count(), 5);
+
+        handle.set_n(0);
+        assert_eq!((0..10).filter(|_| filter.matches(&())).count(), 0);
+
+        handle.set_n(1);
+        assert_eq!((0..10).filter(|_| filter.matches(&())).count(), 10);
+    }
+}
@@ -0,0 +1,443 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use http::{HeaderName, HeaderValue, Method, Request};
+
+#[cfg(feature = "serde")]
+use crate::{filters::ConstFilter, Filter, FilterFn, InvertedFilter};
+use crate::{filters::HttpFilterBuilder, BoxFilter};
+
+/// Why a [`FilterSpec`] couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterSpecError {
+    /// The spec doesn't start with a known kind, e.g. `path_prefix:`.
+    UnknownKind(String),
+    /// The value after the kind is missing or invalid, e.g.
+    /// a path not starting with `/` or an invalid header name.
+    InvalidValue(String),
+}
+
+impl fmt::Display for FilterSpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownKind(spec) => write!(f, "unknown kind of filter spec `{spec}`"),
+            Self::InvalidValue(spec) => write!(f, "invalid value in filter spec `{spec}`"),
+        }
+    }
+}
+
+impl Error for FilterSpecError {}
+
+/// A single rule of an [`HttpFilterBuilder`] parsed from a string,
+/// e.g. to load the routing rules from a config file at startup.
+///
+/// The spec is the kind of the rule and its value, separated by `:`.
+///
+/// | Spec                        | Matches                                  |
+/// |-----------------------------|------------------------------------------|
+/// | `method:GET,POST`           | Any of the methods                       |
+/// | `path:/index.html`          | Exactly the path                         |
+/// | `path_prefix:/api`          | The paths starting with the prefix       |
+/// | `header:x-feature`          | The requests having the header           |
+/// | `header:x-feature=enabled`  | The requests having the header and value |
+///
+/// # Example
+/// ```rust
+/// use http::Request;
+/// use tower_fallthrough_filter::{filters::FilterSpec, Filter};
+///
+/// let spec: FilterSpec = "path_prefix:/api".parse().unwrap();
+/// let filter = spec.into_box_filter();
+///
+/// assert!(filter.matches(&Request::get("/api/users").body(()).unwrap()));
+/// assert!(!filter.matches(&Request::get("/about").body(()).unwrap()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub enum FilterSpec {
+    /// Requires the request to have any of the methods.
+    Method(Vec<Method>),
+    /// Requires the path of the request to be exactly the given one.
+    Path(String),
+    /// Requires the path of the request to start with the prefix.
+    PathPrefix(String),
+    /// Requires the request to have the header, with the given value
+    /// if there is one.
+    Header(HeaderName, Option<HeaderValue>),
+}
+
+impl FilterSpec {
+    /// Builds the filter matching the requests the spec describes.
+    pub fn into_box_filter(self) -> BoxFilter<Request<()>> {
+        let builder = HttpFilterBuilder::new();
+        let builder = match &self {
+            Self::Method(methods) => builder.any(|group| {
+                methods
+                    .iter()
+                    .cloned()
+                    .fold(group, HttpFilterBuilder::method)
+            }),
+            Self::Path(path) => builder.path(path),
+            Self::PathPrefix(prefix) => builder.path_prefix(prefix),
+            Self::Header(name, value) => builder.parsed_header(name.clone(), value.clone()),
+        };
+
+        // NOTE: A single rule parsed into valid names and values
+        //       can't contradict itself, so building never fails.
+        match builder.build() {
+            Ok(filter) => BoxFilter::new(filter),
+            Err(err) => unreachable!("invalid filter spec `{self}`: {err}"),
+        }
+    }
+}
+
+impl FromStr for FilterSpec {
+    type Err = FilterSpecError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = || FilterSpecError::InvalidValue(spec.to_owned());
+
+        let Some((kind, value)) = spec.split_once(':') else {
+            return Err(FilterSpecError::UnknownKind(spec.to_owned()));
+        };
+
+        match kind.trim() {
+            "method" => {
+                let methods = value
+                    .split(',')
+                    .map(|method| Method::from_str(method.trim()).map_err(|_| invalid()))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(Self::Method(methods))
+            }
+            "path" | "path_prefix" => {
+                let path = value.trim();
+                if !path.starts_with('/') {
+                    return Err(invalid());
+                }
+
+                Ok(match kind.trim() {
+                    "path" => Self::Path(path.to_owned()),
+                    _ => Self::PathPrefix(path.to_owned()),
+                })
+            }
+            "header" => {
+                let (name, value) = match value.split_once('=') {
+                    Some((name, value)) => (name, Some(value)),
+                    None => (value, None),
+                };
+
+                let name = HeaderName::from_str(name.trim()).map_err(|_| invalid())?;
+                let value = value
+                    .map(|value| HeaderValue::from_str(value.trim()).map_err(|_| invalid()))
+                    .transpose()?;
+
+                Ok(Self::Header(name, value))
+            }
+            _ => Err(FilterSpecError::UnknownKind(spec.to_owned())),
+        }
+    }
+}
+
+impl TryFrom<String> for FilterSpec {
+    type Error = FilterSpecError;
+
+    fn try_from(spec: String) -> Result<Self, Self::Error> {
+        spec.parse()
+    }
+}
+
+impl From<FilterSpec> for String {
+    fn from(spec: FilterSpec) -> Self {
+        spec.to_string()
+    }
+}
+
+impl fmt::Display for FilterSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Method(methods) => {
+                f.write_str("method:")?;
+                for (i, method) in methods.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    f.write_str(method.as_str())?;
+                }
+                Ok(())
+            }
+            Self::Path(path) => write!(f, "path:{path}"),
+            Self::PathPrefix(prefix) => write!(f, "path_prefix:{prefix}"),
+            Self::Header(name, None) => write!(f, "header:{name}"),
+            // NOTE: The value was parsed from a string, so it is valid UTF-8.
+            Self::Header(name, Some(value)) => {
+                write!(
+                    f,
+                    "header:{name}={}",
+                    String::from_utf8_lossy(value.as_bytes())
+                )
+            }
+        }
+    }
+}
+
+/// A filter composed of [`FilterSpec`]s, e.g. deserialized from the
+/// routing rules in a config file.
+///
+/// # Example
+/// ```rust
+/// use http::{Method, Request};
+/// use tower_fallthrough_filter::{filters::FilterConfig, Filter};
+///
+/// let config: FilterConfig = toml::from_str(
+///     r#"
+///     all = [
+///         { rule = "path_prefix:/api" },
+///         { not = { rule = "method:DELETE" } },
+///     ]
+///     "#,
+/// )
+/// .unwrap();
+/// let filter = config.into_filter();
+///
+/// let req = |method, uri| Request::builder().method(method).uri(uri).body(()).unwrap();
+/// assert!(filter.matches(&req(Method::GET, "/api/users")));
+/// assert!(!filter.matches(&req(Method::DELETE, "/api/users")));
+/// ```
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterConfig {
+    /// Matches every request.
+    Always,
+    /// Matches no request.
+    Never,
+    /// Matches the requests the spec describes.
+    Rule(FilterSpec),
+    /// Matches if all of the filters match, like [`FilterConfig::Always`]
+    /// if there are none.
+    All(Vec<FilterConfig>),
+    /// Matches if any of the filters matches, like [`FilterConfig::Never`]
+    /// if there are none.
+    Any(Vec<FilterConfig>),
+    /// Matches if the filter doesn't.
+    Not(Box<FilterConfig>),
+}
+
+#[cfg(feature = "serde")]
+impl FilterConfig {
+    /// Builds the filter matching the requests the config describes.
+    pub fn into_filter(self) -> BoxFilter<Request<()>> {
+        match self {
+            Self::Always => BoxFilter::new(ConstFilter::<true>),
+            Self::Never => BoxFilter::new(ConstFilter::<false>),
+            Self::Rule(spec) => spec.into_box_filter(),
+            Self::All(configs) => {
+                let filters = into_filters(configs);
+                BoxFilter::new(FilterFn::new(move |req: &Request<()>| {
+                    filters.iter().all(|filter| filter.matches(req))
+                }))
+            }
+            Self::Any(configs) => {
+                let filters = into_filters(configs);
+                BoxFilter::new(FilterFn::new(move |req: &Request<()>| {
+                    filters.iter().any(|filter| filter.matches(req))
+                }))
+            }
+            Self::Not(config) => BoxFilter::new(InvertedFilter(config.into_filter())),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+fn into_filters(configs: Vec<FilterConfig>) -> Vec<BoxFilter<Request<()>>> {
+    configs.into_iter().map(FilterConfig::into_filter).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Filter;
+
+    #[test]
+    fn should_round_trip_specs() {
+        let specs = [
+            "method:GET",
+            "method:GET,POST,DELETE",
+            "path:/index.html",
+            "path_prefix:/api",
+            "header:x-feature",
+            "header:x-feature=enabled",
+        ];
+
+        for spec in specs {
+            let parsed: FilterSpec = spec.parse().unwrap();
+            assert_eq!(parsed.to_string(), spec);
+            assert_eq!(parsed.to_string().parse::<FilterSpec>(), Ok(parsed));
+        }
+    }
+
+    #[test]
+    fn should_normalize_specs() {
+        let parsed: FilterSpec = " header : X-Feature = enabled ".parse().unwrap();
+        assert_eq!(parsed.to_string(), "header:x-feature=enabled");
+
+        let parsed: FilterSpec = "method: GET , POST".parse().unwrap();
+        assert_eq!(parsed, FilterSpec::Method(vec![Method::GET, Method::POST]));
+    }
+
+    #[test]
+    fn should_reject_invalid_specs() {
+        let unknown = ["/api", "query:page", ""];
+        for spec in unknown {
+            assert_eq!(
+                spec.parse::<FilterSpec>(),
+                Err(FilterSpecError::UnknownKind(spec.into()))
+            );
+        }
+
+        let invalid = [
+            "method:",
+            "method:GET,",
+            "path:api",
+            "path_prefix:",
+            "header:not a header",
+            "header:x-feature=line\nbreak",
+        ];
+        for spec in invalid {
+            assert_eq!(
+                spec.parse::<FilterSpec>(),
+                Err(FilterSpecError::InvalidValue(spec.into()))
+            );
+        }
+    }
+
+    #[test]
+    fn should_build_matching_filters() {
+        let req = |method: Method, uri: &str, feature: Option<&str>| {
+            let mut builder = Request::builder().method(method).uri(uri);
+            if let Some(feature) = feature {
+                builder = builder.header("x-feature", feature);
+            }
+            builder.body(()).unwrap()
+        };
+
+        let cases = [
+            ("method:GET,HEAD", req(Method::HEAD, "/", None), true),
+            ("method:GET,HEAD", req(Method::POST, "/", None), false),
+            (
+                "path:/index.html",
+                req(Method::GET, "/index.html", None),
+                true,
+            ),
+            (
+                "path:/index.html",
+                req(Method::GET, "/index.htm", None),
+                false,
+            ),
+            (
+                "path_prefix:/api",
+                req(Method::GET, "/api/users", None),
+                true,
+            ),
+            ("path_prefix:/api", req(Method::GET, "/about", None), false),
+            ("header:x-feature", req(Method::GET, "/", Some("off")), true),
+            ("header:x-feature", req(Method::GET, "/", None), false),
+            (
+                "header:x-feature=on",
+                req(Method::GET, "/", Some("on")),
+                true,
+            ),
+            (
+                "header:x-feature=on",
+                req(Method::GET, "/", Some("off")),
+                false,
+            ),
+        ];
+
+        for (spec, req, expected) in cases {
+            let filter = spec.parse::<FilterSpec>().unwrap().into_box_filter();
+            assert_eq!(filter.matches(&req), expected, "{spec} {req:?}");
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn should_round_trip_configs() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Routing {
+            filter: FilterConfig,
+        }
+
+        let routing: Routing = toml::from_str(
+            r#"
+            [filter]
+            any = [
+                "always",
+                { rule = "path_prefix:/api" },
+                { all = [{ rule = "method:GET,HEAD" }, { not = { rule = "header:x-internal" } }] },
+                { not = "never" },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        let rule = |spec: &str| FilterConfig::Rule(spec.parse().unwrap());
+        let expected = FilterConfig::Any(vec![
+            FilterConfig::Always,
+            rule("path_prefix:/api"),
+            FilterConfig::All(vec![
+                rule("method:GET,HEAD"),
+                FilterConfig::Not(Box::new(rule("header:x-internal"))),
+            ]),
+            FilterConfig::Not(Box::new(FilterConfig::Never)),
+        ]);
+        assert_eq!(routing.filter, expected);
+
+        let serialized = toml::to_string(&routing).unwrap();
+        assert_eq!(toml::from_str::<Routing>(&serialized).unwrap(), routing);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn should_reject_invalid_configs() {
+        let config = toml::from_str::<FilterConfig>(r#"rule = "path_prefix:api""#);
+        assert!(config.is_err());
+
+        let config = toml::from_str::<FilterConfig>(r#"unknown = "path_prefix:/api""#);
+        assert!(config.is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn should_build_composed_filters() {
+        let rule = |spec: &str| FilterConfig::Rule(spec.parse().unwrap());
+        let req = |method: Method, uri: &str| {
+            Request::builder().method(method).uri(uri).body(()).unwrap()
+        };
+
+        let config = FilterConfig::All(vec![
+            rule("path_prefix:/api"),
+            FilterConfig::Not(Box::new(rule("method:DELETE"))),
+        ]);
+        let filter = config.into_filter();
+        assert!(filter.matches(&req(Method::GET, "/api/users")));
+        assert!(!filter.matches(&req(Method::DELETE, "/api/users")));
+        assert!(!filter.matches(&req(Method::GET, "/about")));
+
+        let filter =
+            FilterConfig::Any(vec![FilterConfig::Never, rule("path:/about")]).into_filter();
+        assert!(filter.matches(&req(Method::GET, "/about")));
+        assert!(!filter.matches(&req(Method::GET, "/api")));
+
+        assert!(FilterConfig::All(Vec::new())
+            .into_filter()
+            .matches(&req(Method::GET, "/")));
+        assert!(!FilterConfig::Any(Vec::new())
+            .into_filter()
+            .matches(&req(Method::GET, "/")));
+    }
+}
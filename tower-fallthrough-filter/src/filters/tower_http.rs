@@ -0,0 +1,98 @@
+//! Filters expressing concerns of common `tower_http` middleware,
+//! like timeouts and body size limits, as routing conditions.
+
+use std::time::Duration;
+
+use http::{header::CONTENT_LENGTH, Request};
+use tokio::time::Instant;
+
+use crate::Filter;
+
+/// A filter matching the requests that arrive before the timeout,
+/// which starts once the filter is created and is shared by its clones.
+///
+/// The time is read from `tokio::time::Instant`, so the timeout follows
+/// the paused clock of a test runtime, e.g. with `tokio::time::advance`.
+///
+/// E.g. to route requests to a warm-up or migration service for a
+/// limited time after startup, falling through once it's over.
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+///
+/// use http::Request;
+/// use tower_fallthrough_filter::{filters::tower_http::TimeoutFilter, Filter};
+///
+/// let filter = TimeoutFilter::new(Duration::from_secs(60));
+/// assert!(filter.matches(&Request::new(())));
+///
+/// let filter = TimeoutFilter::new(Duration::ZERO);
+/// assert!(!filter.matches(&Request::new(())));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeoutFilter {
+    deadline: Instant,
+}
+
+impl TimeoutFilter {
+    /// Matches the requests arriving within `duration` from now.
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            deadline: Instant::now() + duration,
+        }
+    }
+
+    /// The time left until the filter stops matching.
+    pub fn remaining(&self) -> Duration {
+        self.deadline.saturating_duration_since(Instant::now())
+    }
+}
+
+impl<B> Filter<Request<B>> for TimeoutFilter {
+    fn matches(&self, _: &Request<B>) -> bool {
+        Instant::now() < self.deadline
+    }
+}
+
+/// A filter matching the requests whose `content-length` header is
+/// within the limit.
+///
+/// Requests without a valid `content-length`, e.g. using a chunked
+/// body, don't match as their size is unknown.
+///
+/// NOTE: Only the header is checked, the body isn't limited. Use
+/// e.g. `tower_http::limit::RequestBodyLimitLayer` to enforce it.
+///
+/// # Example
+/// ```rust
+/// use http::{header::CONTENT_LENGTH, Request};
+/// use tower_fallthrough_filter::{filters::tower_http::SizeLimitFilter, Filter};
+///
+/// let filter = SizeLimitFilter::new(1024);
+///
+/// let req = |length: u64| Request::builder().header(CONTENT_LENGTH, length).body(()).unwrap();
+/// assert!(filter.matches(&req(1024)));
+/// assert!(!filter.matches(&req(1025)));
+/// assert!(!filter.matches(&Request::new(())));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SizeLimitFilter {
+    max_bytes: u64,
+}
+
+impl SizeLimitFilter {
+    /// Matches the requests with a body of at most `max_bytes`.
+    pub fn new(max_bytes: u64) -> Self {
+        Self { max_bytes }
+    }
+}
+
+impl<B> Filter<Request<B>> for SizeLimitFilter {
+    fn matches(&self, req: &Request<B>) -> bool {
+        req.headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok()?.parse::<u64>().ok())
+            .is_some_and(|length| length <= self.max_bytes)
+    }
+}
@@ -0,0 +1,468 @@
+use std::{
+    fmt,
+    future::{ready, Ready},
+    marker::PhantomData,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use http::{header::UPGRADE, Request, Response, StatusCode};
+use tower::{Layer, Service};
+
+use crate::{Filter, FilterLayer, FilterService};
+
+/// A filter matching requests asking for a protocol upgrade,
+/// i.e. requests with an `Upgrade` header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct UpgradeHeaderFilter;
+
+impl<B> Filter<Request<B>> for UpgradeHeaderFilter {
+    fn matches(&self, req: &Request<B>) -> bool {
+        req.headers().contains_key(UPGRADE)
+    }
+}
+
+/// A filter matching requests asking for an upgrade to one of the
+/// given protocols, e.g. `websocket` or `h2c`.
+///
+/// The protocols are compared case-insensitively. A protocol without
+/// a version also matches requests asking for a specific version,
+/// e.g. `HTTP` matches `Upgrade: HTTP/2.0`.
+///
+/// # Example
+/// ```rust
+/// use http::{header::UPGRADE, Request};
+/// use tower_fallthrough_filter::{filters::UpgradeFilter, Filter};
+///
+/// let filter = UpgradeFilter::new(["websocket"]);
+///
+/// let req = Request::get("/").header(UPGRADE, "WebSocket").body(()).unwrap();
+/// assert!(filter.matches(&req));
+///
+/// let req = Request::get("/").header(UPGRADE, "h2c").body(()).unwrap();
+/// assert!(!filter.matches(&req));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UpgradeFilter {
+    protocols: Arc<[String]>,
+}
+
+impl UpgradeFilter {
+    /// Creates a new UpgradeFilter given the protocols to match.
+    pub fn new<P: Into<String>>(protocols: impl IntoIterator<Item = P>) -> Self {
+        Self {
+            protocols: protocols.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    fn is_registered(&self, protocol: &str) -> bool {
+        let name = protocol.split_once('/').map_or(protocol, |(name, _)| name);
+
+        self.protocols.iter().any(|registered| {
+            registered.eq_ignore_ascii_case(protocol) || registered.eq_ignore_ascii_case(name)
+        })
+    }
+}
+
+impl<B> Filter<Request<B>> for UpgradeFilter {
+    fn matches(&self, req: &Request<B>) -> bool {
+        req.headers()
+            .get_all(UPGRADE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|protocol| self.is_registered(protocol.trim()))
+    }
+}
+
+/// A service that responds to every request with
+/// `426 Upgrade Required` and an empty body.
+pub struct UpgradeRequiredService<B, E> {
+    _marker: PhantomData<fn() -> (B, E)>,
+}
+
+impl<B, E> UpgradeRequiredService<B, E> {
+    /// Creates a new UpgradeRequiredService.
+    pub fn new() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<B, E> Default for UpgradeRequiredService<B, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B, E> Clone for UpgradeRequiredService<B, E> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<B, E> fmt::Debug for UpgradeRequiredService<B, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("UpgradeRequiredService")
+    }
+}
+
+impl<T, B: Default, E> Service<T> for UpgradeRequiredService<B, E> {
+    type Response = Response<B>;
+    type Error = E;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: T) -> Self::Future {
+        let mut res = Response::new(B::default());
+        *res.status_mut() = StatusCode::UPGRADE_REQUIRED;
+
+        ready(Ok(res))
+    }
+}
+
+type UpgradeRequiredFilterService<I, B, RB, E> =
+    FilterService<UpgradeHeaderFilter, UpgradeRequiredService<RB, E>, I, Request<B>>;
+
+/// A Tower layer for services handling upgrade requests, e.g. a
+/// WebSocket handshake, which doesn't let upgrade requests fall through.
+///
+/// - If the filter matches, the provided service is called.
+/// - If the filter doesn't match but the request has an `Upgrade`
+///   header, `426 Upgrade Required` is returned, so the upgrade doesn't
+///   end up at a downstream service that can't handle it.
+/// - Otherwise the request falls through to the inner service.
+///
+/// # Example
+/// ```rust
+/// use http::{header::UPGRADE, Request, Response, StatusCode};
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{filters::UpgradeAwareFilterLayer, Filter};
+///
+/// #[derive(Clone)]
+/// struct IsSocket;
+///
+/// impl<B> Filter<Request<B>> for IsSocket {
+///     fn matches(&self, req: &Request<B>) -> bool {
+///         req.uri().path() == "/socket"
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let socket = service_fn(|_: Request<()>| async { Ok::<_, ()>(Response::new("socket")) });
+/// let router = service_fn(|_: Request<()>| async { Ok::<_, ()>(Response::new("router")) });
+///
+/// let service = UpgradeAwareFilterLayer::new(IsSocket, socket).layer(router);
+///
+/// let req = Request::get("/other").header(UPGRADE, "websocket").body(()).unwrap();
+/// let res = service.oneshot(req).await.unwrap();
+/// assert_eq!(res.status(), StatusCode::UPGRADE_REQUIRED);
+/// # }
+/// ```
+pub struct UpgradeAwareFilterLayer<F, S, B> {
+    filter: F,
+    service: S,
+
+    _marker: PhantomData<fn(B)>,
+}
+
+impl<F: Clone, S: Clone, B> Clone for UpgradeAwareFilterLayer<F, S, B> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: fmt::Debug, S: fmt::Debug, B> fmt::Debug for UpgradeAwareFilterLayer<F, S, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UpgradeAwareFilterLayer")
+            .field("filter", &self.filter)
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+impl<F, S, B, RB> UpgradeAwareFilterLayer<F, S, B>
+where
+    F: Filter<Request<B>>,
+    S: Service<Request<B>, Response = Response<RB>>,
+{
+    /// Creates a new UpgradeAwareFilterLayer given a `Filter`
+    /// and the `Service` handling the matching requests.
+    pub fn new(filter: F, service: S) -> Self {
+        Self {
+            filter,
+            service,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, B, RB> Layer<I> for UpgradeAwareFilterLayer<F, S, B>
+where
+    F: Filter<Request<B>>,
+    S: Service<Request<B>, Response = Response<RB>> + Clone,
+    I: Service<Request<B>, Response = S::Response, Error = S::Error>,
+    RB: Default,
+{
+    type Service =
+        FilterService<F, S, UpgradeRequiredFilterService<I, B, RB, S::Error>, Request<B>>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        let upgrade_service = FilterLayer::new(UpgradeHeaderFilter, UpgradeRequiredService::new())
+            .layer(inner_service);
+
+        FilterLayer::new(self.filter.clone(), self.service.clone()).layer(upgrade_service)
+    }
+}
+
+/// The service created by an [`UpgradeFilterLayer`].
+pub type UpgradeFilterService<F, S, I, U, T> =
+    FilterService<F, S, FilterService<UpgradeFilter, U, I, T>, T>;
+
+/// A Tower layer routing upgrade requests, e.g. WebSocket handshakes,
+/// that fall through to a separate upgrade handler.
+///
+/// - If the filter matches, the provided service is called.
+/// - If the filter doesn't match but the request asks for an upgrade to
+///   a protocol the [`UpgradeFilter`] matches, the upgrade handler is called.
+/// - Otherwise the request falls through to the inner service.
+///
+/// # Example
+/// ```rust
+/// use http::{header::UPGRADE, Request, Response};
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{
+///     filters::{UpgradeFilter, UpgradeFilterLayer},
+///     Filter,
+/// };
+///
+/// #[derive(Clone)]
+/// struct IsApi;
+///
+/// impl<B> Filter<Request<B>> for IsApi {
+///     fn matches(&self, req: &Request<B>) -> bool {
+///         req.uri().path().starts_with("/api")
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name| service_fn(move |_: Request<()>| async move { Ok::<_, ()>(Response::new(name)) });
+///
+/// let layer = UpgradeFilterLayer::new(
+///     IsApi,
+///     respond("api"),
+///     UpgradeFilter::new(["websocket"]),
+///     respond("socket"),
+/// );
+/// let service = layer.layer(respond("router"));
+///
+/// let req = Request::get("/live").header(UPGRADE, "websocket").body(()).unwrap();
+/// assert_eq!(*service.clone().oneshot(req).await.unwrap().body(), "socket");
+///
+/// let req = Request::get("/live").body(()).unwrap();
+/// assert_eq!(*service.oneshot(req).await.unwrap().body(), "router");
+/// # }
+/// ```
+pub struct UpgradeFilterLayer<F, S, U, T> {
+    layer: FilterLayer<F, S, T>,
+    upgrade: FilterLayer<UpgradeFilter, U, T>,
+}
+
+impl<F, S, U, T> UpgradeFilterLayer<F, S, U, T>
+where
+    F: Filter<T>,
+    S: Service<T>,
+    U: Service<T, Response = S::Response, Error = S::Error>,
+    UpgradeFilter: Filter<T>,
+{
+    /// Creates a new UpgradeFilterLayer given a `Filter`, the `Service`
+    /// handling the matching requests, the `UpgradeFilter` and the
+    /// `Service` handling the upgrades it matches.
+    pub fn new(filter: F, service: S, upgrade_filter: UpgradeFilter, upgrade_handler: U) -> Self {
+        Self {
+            layer: FilterLayer::new(filter, service),
+            upgrade: FilterLayer::new(upgrade_filter, upgrade_handler),
+        }
+    }
+}
+
+impl<F: Clone, S: Clone, U: Clone, T> Clone for UpgradeFilterLayer<F, S, U, T> {
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer.clone(),
+            upgrade: self.upgrade.clone(),
+        }
+    }
+}
+
+impl<F, S, U, T> fmt::Debug for UpgradeFilterLayer<F, S, U, T>
+where
+    F: fmt::Debug,
+    S: fmt::Debug,
+    U: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UpgradeFilterLayer")
+            .field("filter", self.layer.filter())
+            .field("service", self.layer.matched_service_ref())
+            .field("upgrade_filter", self.upgrade.filter())
+            .field("upgrade_handler", self.upgrade.matched_service_ref())
+            .finish()
+    }
+}
+
+impl<F, S, I, U, T> Layer<I> for UpgradeFilterLayer<F, S, U, T>
+where
+    F: Filter<T>,
+    S: Service<T> + Clone,
+    U: Service<T, Response = S::Response, Error = S::Error> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+    UpgradeFilter: Filter<T>,
+{
+    type Service = UpgradeFilterService<F, S, I, U, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        self.layer.layer(self.upgrade.layer(inner_service))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{extract::ws::WebSocketUpgrade, routing::get, Router};
+    use axum_test::{TestServer, TestServerConfig};
+    use http::header::{CONNECTION, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_VERSION};
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct IsSocket;
+
+    impl<B> Filter<Request<B>> for IsSocket {
+        fn matches(&self, req: &Request<B>) -> bool {
+            req.uri().path() == "/socket"
+        }
+    }
+
+    fn server() -> TestServer {
+        let socket = Router::new().fallback(get(|ws: WebSocketUpgrade| async move {
+            ws.on_upgrade(|_| async {})
+        }));
+
+        let app = Router::new()
+            .route("/page", get(|| async { "page" }))
+            .layer(UpgradeAwareFilterLayer::new(IsSocket, socket));
+
+        // NOTE: The WebSocket upgrade needs a real connection.
+        let config = TestServerConfig::builder().http_transport().build();
+        TestServer::new_with_config(app, config).unwrap()
+    }
+
+    fn handshake(server: &TestServer, path: &str) -> axum_test::TestRequest {
+        server
+            .get(path)
+            .add_header(CONNECTION, "upgrade".parse().unwrap())
+            .add_header(UPGRADE, "websocket".parse().unwrap())
+            .add_header(SEC_WEBSOCKET_VERSION, "13".parse().unwrap())
+            .add_header(
+                SEC_WEBSOCKET_KEY,
+                "dGhlIHNhbXBsZSBub25jZQ==".parse().unwrap(),
+            )
+    }
+
+    #[tokio::test]
+    async fn should_upgrade_matching_requests() {
+        let server = server();
+
+        let res = handshake(&server, "/socket").await;
+        res.assert_status(StatusCode::SWITCHING_PROTOCOLS);
+    }
+
+    #[tokio::test]
+    async fn should_not_fall_through_with_upgrade() {
+        let server = server();
+
+        let res = handshake(&server, "/page").expect_failure().await;
+        res.assert_status(StatusCode::UPGRADE_REQUIRED);
+    }
+
+    #[tokio::test]
+    async fn should_fall_through_without_upgrade() {
+        let server = server();
+
+        server.get("/page").await.assert_text("page");
+    }
+
+    #[test]
+    fn should_match_registered_protocols() {
+        let filter = UpgradeFilter::new(["websocket", "HTTP"]);
+        let upgrade = |protocols: &str| {
+            let req = Request::get("/")
+                .header(UPGRADE, protocols)
+                .body(())
+                .unwrap();
+            filter.matches(&req)
+        };
+
+        assert!(upgrade("websocket"));
+        assert!(upgrade("WebSocket"));
+        assert!(upgrade("h2c, HTTP/2.0"));
+        assert!(!upgrade("h2c"));
+        assert!(!filter.matches(&Request::get("/").body(()).unwrap()));
+    }
+
+    fn handler_server() -> TestServer {
+        let socket = Router::new().fallback(get(|ws: WebSocketUpgrade| async move {
+            ws.on_upgrade(|_| async {})
+        }));
+        let api = Router::new().fallback(get(|| async { "api" }));
+
+        let app = Router::new()
+            .route("/page", get(|| async { "page" }))
+            .layer(UpgradeFilterLayer::new(
+                IsApi,
+                api,
+                UpgradeFilter::new(["websocket"]),
+                socket,
+            ));
+
+        let config = TestServerConfig::builder().http_transport().build();
+        TestServer::new_with_config(app, config).unwrap()
+    }
+
+    #[derive(Clone)]
+    struct IsApi;
+
+    impl<B> Filter<Request<B>> for IsApi {
+        fn matches(&self, req: &Request<B>) -> bool {
+            req.uri().path().starts_with("/api")
+        }
+    }
+
+    #[tokio::test]
+    async fn should_route_upgrades_to_handler() {
+        let server = handler_server();
+
+        let res = handshake(&server, "/page").await;
+        res.assert_status(StatusCode::SWITCHING_PROTOCOLS);
+    }
+
+    #[tokio::test]
+    async fn should_prefer_filtered_service_over_handler() {
+        let server = handler_server();
+
+        handshake(&server, "/api").await.assert_text("api");
+        server.get("/page").await.assert_text("page");
+    }
+}
@@ -0,0 +1,204 @@
+use std::{fmt, path::Path, sync::Arc};
+
+use http::Request;
+use serde::Serialize;
+use wasmtime::{Config, Engine, InstancePre, Linker, Module, Store};
+
+use crate::Filter;
+
+/// The summary of a request handed to the WebAssembly module.
+#[derive(Debug, Serialize)]
+struct RequestSummary<'a> {
+    method: &'a str,
+    path: &'a str,
+    query: Option<&'a str>,
+    headers: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> RequestSummary<'a> {
+    fn new<B>(req: &'a Request<B>) -> Self {
+        Self {
+            method: req.method().as_str(),
+            path: req.uri().path(),
+            query: req.uri().query(),
+            headers: req
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?)))
+                .collect(),
+        }
+    }
+}
+
+struct WasmModule {
+    engine: Engine,
+    instance: InstancePre<()>,
+    fuel: u64,
+}
+
+/// A filter that evaluates a WebAssembly module to decide whether
+/// a request matches, so the routing logic can be changed without
+/// recompiling the server.
+///
+/// The module must export:
+/// - `memory`: the linear memory the request summary is written to.
+/// - `alloc(len: i32) -> i32`: returns a pointer to `len` free bytes.
+/// - `matches(ptr: i32, len: i32) -> i32`: returns non-zero on a match.
+///
+/// The request summary is a JSON object of the form
+/// `{"method":"GET","path":"/","query":null,"headers":[["host","example.com"]]}`
+/// with the fields always in this order. Non UTF-8 header values are skipped.
+///
+/// Every request runs in a fresh instance limited to the configured
+/// amount of fuel, so a misbehaving module can't keep state between
+/// requests or loop forever. Traps, including running out of fuel,
+/// are treated as "no match" and the request falls through.
+#[derive(Clone)]
+pub struct WasmFilter {
+    module: Arc<WasmModule>,
+}
+
+impl WasmFilter {
+    /// Compiles the given module, either in the binary or text
+    /// format, limiting every evaluation to `fuel` units of fuel.
+    pub fn new(module: impl AsRef<[u8]>, fuel: u64) -> wasmtime::Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+
+        let engine = Engine::new(&config)?;
+        let module = Module::new(&engine, module)?;
+        let instance = Linker::new(&engine).instantiate_pre(&module)?;
+
+        Ok(Self {
+            module: Arc::new(WasmModule {
+                engine,
+                instance,
+                fuel,
+            }),
+        })
+    }
+
+    /// Reads and compiles the module at `path`, see [`WasmFilter::new`].
+    pub fn from_file(path: impl AsRef<Path>, fuel: u64) -> wasmtime::Result<Self> {
+        Self::new(std::fs::read(path)?, fuel)
+    }
+
+    fn evaluate(&self, summary: &[u8]) -> wasmtime::Result<bool> {
+        let module = &self.module;
+
+        let mut store = Store::new(&module.engine, ());
+        store.set_fuel(module.fuel)?;
+
+        let instance = module.instance.instantiate(&mut store)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| wasmtime::Error::msg("Module doesn't export `memory`"))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let matches = instance.get_typed_func::<(i32, i32), i32>(&mut store, "matches")?;
+
+        let len = i32::try_from(summary.len())?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory.write(&mut store, usize::try_from(ptr)?, summary)?;
+
+        Ok(matches.call(&mut store, (ptr, len))? != 0)
+    }
+}
+
+impl fmt::Debug for WasmFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasmFilter")
+            .field("fuel", &self.module.fuel)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<B> Filter<Request<B>> for WasmFilter {
+    fn matches(&self, req: &Request<B>) -> bool {
+        let summary = serde_json::to_vec(&RequestSummary::new(req))
+            .expect("Serializing the request summary can't fail");
+
+        self.evaluate(&summary).unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::Method;
+    use tower::{Layer, Service};
+
+    use super::*;
+    use crate::{test_util::*, FilterLayer};
+
+    // Matches if the request summary starts with `{"method":"POST"`.
+    const IS_POST: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (global $next (mut i32) (i32.const 1024))
+
+            (func (export "alloc") (param $len i32) (result i32)
+                (local $ptr i32)
+                (local.set $ptr (global.get $next))
+                (global.set $next (i32.add (global.get $next) (local.get $len)))
+                (local.get $ptr))
+
+            (func (export "matches") (param $ptr i32) (param $len i32) (result i32)
+                (i32.and
+                    ;; "POST" as a little endian i32
+                    (i32.eq
+                        (i32.load (i32.add (local.get $ptr) (i32.const 11)))
+                        (i32.const 0x54534F50))
+                    ;; followed by the closing quote
+                    (i32.eq
+                        (i32.load8_u (i32.add (local.get $ptr) (i32.const 15)))
+                        (i32.const 34)))))
+    "#;
+
+    const LOOPS_FOREVER: &str = r#"
+        (module
+            (memory (export "memory") 1)
+
+            (func (export "alloc") (param $len i32) (result i32)
+                (i32.const 0))
+
+            (func (export "matches") (param $ptr i32) (param $len i32) (result i32)
+                (loop $forever (br $forever))
+                (i32.const 1)))
+    "#;
+
+    fn request(method: Method) -> Request<()> {
+        Request::builder()
+            .method(method)
+            .uri("/upload?name=file")
+            .header("content-type", "text/plain")
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn should_serialize_request_summary() {
+        let req = request(Method::POST);
+        let summary = serde_json::to_string(&RequestSummary::new(&req)).unwrap();
+
+        assert_eq!(
+            summary,
+            r#"{"method":"POST","path":"/upload","query":"name=file","headers":[["content-type","text/plain"]]}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn should_route_using_module() {
+        let filter = WasmFilter::new(IS_POST, 100_000).unwrap();
+        let mut middleware =
+            FilterLayer::new(filter, TestService("wasm")).layer(TestService("inner"));
+
+        assert_eq!(middleware.call(request(Method::POST)).await, Ok("wasm"));
+        assert_eq!(middleware.call(request(Method::GET)).await, Ok("inner"));
+    }
+
+    #[test]
+    fn should_fall_through_when_out_of_fuel() {
+        let filter = WasmFilter::new(LOOPS_FOREVER, 10_000).unwrap();
+
+        assert!(!filter.matches(&request(Method::POST)));
+    }
+}
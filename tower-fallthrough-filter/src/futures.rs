@@ -1,12 +1,156 @@
 use std::{
+    fmt,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 
-use futures::{future::Either, ready, Future};
+use futures::{
+    future::{Either, FusedFuture},
+    ready, Future,
+};
 use tower::Service;
 
-#[pin_project::pin_project]
+use crate::catch_panic::CatchPanics;
+
+#[cfg(all(feature = "async", feature = "chain"))]
+use futures::future::{join_all, BoxFuture, JoinAll};
+
+#[cfg(all(feature = "async", feature = "chain"))]
+use tower::util::BoxCloneService;
+
+#[cfg(all(feature = "async", feature = "chain"))]
+use crate::async_chain::BoxAsyncFilter;
+
+#[cfg(feature = "fallback")]
+use tower::util::Oneshot;
+
+#[cfg(feature = "make")]
+use std::marker::PhantomData;
+
+#[cfg(feature = "make")]
+use futures::{future::TryJoin, TryFuture};
+
+#[cfg(feature = "async")]
+use std::sync::Mutex;
+
+#[cfg(any(feature = "async", feature = "recording"))]
+use std::sync::PoisonError;
+
+#[cfg(feature = "make")]
+use crate::{Filter, FilterService};
+
+#[cfg(feature = "recording")]
+use crate::recording::{RecordedCall, Recording};
+
+#[cfg(feature = "retry")]
+use std::time::Duration;
+
+#[cfg(feature = "circuit-breaker")]
+use crate::circuit_breaker::Circuit;
+
+#[cfg(all(feature = "metrics", feature = "async"))]
+use crate::{metered::report_decision, FilterMetrics};
+
+#[cfg(feature = "retry")]
+use tokio::time::{Instant, Sleep};
+
+#[cfg(feature = "spawn")]
+use tokio::task::JoinHandle;
+
+#[cfg(feature = "retry")]
+use crate::TryFilter;
+
+/// The state a [`SelectServiceAndCallFut`] was dropped in before
+/// producing an output, e.g. because the client disconnected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cancellation {
+    /// The filter was still being awaited, so no service was called.
+    Deciding,
+    /// The filter matched and the filtered service was called.
+    Matched,
+    /// The filter didn't match and the inner service was called.
+    FellThrough,
+}
+
+impl Cancellation {
+    /// Whether the filter had decided before the future was dropped.
+    pub fn is_decided(&self) -> bool {
+        !matches!(self, Self::Deciding)
+    }
+
+    /// Whether the filter matched, or `None` if it hadn't decided yet.
+    pub fn matched(&self) -> Option<bool> {
+        match self {
+            Self::Deciding => None,
+            Self::Matched => Some(true),
+            Self::FellThrough => Some(false),
+        }
+    }
+}
+
+/// The callback invoked when a [`SelectServiceAndCallFut`] is dropped
+/// before producing an output.
+#[derive(Clone)]
+pub(crate) struct OnCancel(pub(crate) Arc<dyn Fn(Cancellation) + Send + Sync>);
+
+impl fmt::Debug for OnCancel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OnCancel")
+    }
+}
+
+/// A slot handing a called service back to the service it was taken
+/// from, so the next request reuses it instead of cloning the service.
+#[cfg(feature = "async")]
+pub(crate) struct Recycled<S>(Arc<Mutex<Option<S>>>);
+
+#[cfg(feature = "async")]
+impl<S> Recycled<S> {
+    /// Takes the service handed back, if any.
+    pub(crate) fn take(&self) -> Option<S> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).take()
+    }
+
+    /// Hands the `service` back, dropping it if the slot is taken,
+    /// e.g. by a concurrent request.
+    pub(crate) fn put(&self, service: S) {
+        let mut slot = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if slot.is_none() {
+            *slot = Some(service);
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<S> Default for Recycled<S> {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(None)))
+    }
+}
+
+// NOTE: This is required to make the `Recycled` slot clonable
+//       as the service might be not clonable.
+#[cfg(feature = "async")]
+impl<S> Clone for Recycled<S> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<S> fmt::Debug for Recycled<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Recycled")
+    }
+}
+
+/// The future of the async filter services, e.g. `AsyncFilterService`.
+///
+/// Awaits the filter and calls the selected service. Once completed
+/// the future is terminated, see `FusedFuture`, and polling it again
+/// returns `Poll::Pending`.
+#[pin_project::pin_project(PinnedDrop)]
 pub struct SelectServiceAndCallFut<C, A, B, T, R, E>
 where
     C: Future<Output = bool>,
@@ -15,18 +159,39 @@ where
     B: Service<T, Response = R, Error = E>,
 {
     #[pin]
-    condition: C,
+    state: SelectState<C, A, B, T>,
 
-    // TODO: I think I can represent this as an enum, so I don't have to
-    //       maintain the invariants myself.
-    // INV: This is Some(...) when future is None
-    value: Option<T>,
+    // NOTE: Invoked when the future is dropped before it completed.
+    on_cancel: Option<OnCancel>,
+    catch_panics: CatchPanics,
 
-    // INV: This is Some(...) when future is None
-    services: Option<(A, B)>,
+    // NOTE: Where the called service is handed back to.
+    #[cfg(feature = "async")]
+    recycle: Option<(Recycled<A>, Recycled<B>)>,
 
-    #[pin]
-    future: Option<Either<A::Future, B::Future>>,
+    // NOTE: The span the future was created in, entered while polling
+    //       so the filter decision is recorded within it.
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+#[pin_project::pin_project(project = SelectStateProj, project_replace = SelectStateProjReplace)]
+enum SelectState<C, A, B, T>
+where
+    A: Service<T>,
+    B: Service<T>,
+{
+    Deciding {
+        #[pin]
+        condition: C,
+        value: T,
+        services: (A, B),
+    },
+    Calling {
+        #[pin]
+        future: Either<A::Future, B::Future>,
+    },
+    Done,
 }
 
 impl<C, A, B, T, R, E> SelectServiceAndCallFut<C, A, B, T, R, E>
@@ -38,12 +203,89 @@ where
 {
     pub fn new(condition: C, value: T, service_a: A, service_b: B) -> Self {
         Self {
-            condition,
-            value: Some(value),
-            future: None,
-            services: Some((service_a, service_b)),
+            state: SelectState::Deciding {
+                condition,
+                value,
+                services: (service_a, service_b),
+            },
+            on_cancel: None,
+            catch_panics: CatchPanics::default(),
+            #[cfg(feature = "async")]
+            recycle: None,
+
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::current(),
         }
     }
+
+    /// Creates the future of a request the filter already decided on,
+    /// awaiting the called service.
+    #[cfg(feature = "async")]
+    pub(crate) fn calling(future: Either<A::Future, B::Future>) -> Self {
+        Self {
+            state: SelectState::Calling { future },
+            on_cancel: None,
+            catch_panics: CatchPanics::default(),
+            #[cfg(feature = "async")]
+            recycle: None,
+
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::current(),
+        }
+    }
+
+    /// Sets whether a panic while polling the filter is caught, so the
+    /// request falls through.
+    #[cfg(feature = "async")]
+    pub(crate) fn catch_panics(mut self, catch_panics: CatchPanics) -> Self {
+        self.catch_panics = catch_panics;
+        self
+    }
+
+    /// Sets the callback invoked if the future is dropped before
+    /// producing an output.
+    #[cfg(feature = "async")]
+    pub(crate) fn on_cancel(mut self, on_cancel: Option<OnCancel>) -> Self {
+        self.on_cancel = on_cancel;
+        self
+    }
+
+    /// Sets the slots the called service is handed back to, once the
+    /// filter decided. The other service is dropped, as it might hold
+    /// resources reserved by `poll_ready`, e.g. a concurrency permit.
+    #[cfg(feature = "async")]
+    pub(crate) fn recycle(mut self, service: Recycled<A>, inner: Recycled<B>) -> Self {
+        self.recycle = Some((service, inner));
+        self
+    }
+}
+
+#[pin_project::pinned_drop]
+impl<C, A, B, T, R, E> PinnedDrop for SelectServiceAndCallFut<C, A, B, T, R, E>
+where
+    C: Future<Output = bool>,
+
+    A: Service<T, Response = R, Error = E>,
+    B: Service<T, Response = R, Error = E>,
+{
+    fn drop(self: Pin<&mut Self>) {
+        let this = self.project();
+        let Some(OnCancel(on_cancel)) = this.on_cancel.take() else {
+            return;
+        };
+
+        let cancellation = match this.state.project() {
+            SelectStateProj::Deciding { .. } => Cancellation::Deciding,
+            SelectStateProj::Calling { future } => match &*future {
+                Either::Left(_) => Cancellation::Matched,
+                Either::Right(_) => Cancellation::FellThrough,
+            },
+            // NOTE: Completed, or the service panicked while being called.
+            SelectStateProj::Done => return,
+        };
+
+        on_cancel(cancellation);
+    }
 }
 
 impl<C, A, B, T, R, E> Future for SelectServiceAndCallFut<C, A, B, T, R, E>
@@ -55,44 +297,924 @@ where
 {
     type Output = Result<R, E>;
 
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut state = this.state;
+
+        loop {
+            match state.as_mut().project() {
+                SelectStateProj::Deciding { condition, .. } => {
+                    #[cfg(feature = "tracing")]
+                    let _entered = this.span.enter();
+
+                    // NOTE: A panicking filter doesn't match, if caught.
+                    let polled = this.catch_panics.catch(|| condition.poll(cx));
+                    let select = ready!(polled.unwrap_or(Poll::Ready(false)));
+
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!(matched = %select);
+
+                    // NOTE: The state is `Done` while calling the service,
+                    //       so a panicking service can't be called twice.
+                    //       The state was `Deciding` until replaced here.
+                    if let SelectStateProjReplace::Deciding {
+                        value,
+                        services: (mut service_a, mut service_b),
+                        ..
+                    } = state.as_mut().project_replace(SelectState::Done)
+                    {
+                        let future = if select {
+                            Either::Left(service_a.call(value))
+                        } else {
+                            Either::Right(service_b.call(value))
+                        };
+                        state.set(SelectState::Calling { future });
+
+                        #[cfg(feature = "async")]
+                        if let Some((recycled_a, recycled_b)) = this.recycle.take() {
+                            if select {
+                                recycled_a.put(service_a);
+                            } else {
+                                recycled_b.put(service_b);
+                            }
+                        }
+                    }
+                }
+                SelectStateProj::Calling { future } => {
+                    let output = ready!(future.poll(cx));
+                    state.set(SelectState::Done);
+
+                    return Poll::Ready(output);
+                }
+                // NOTE: Polled after completion, or after the service
+                //       panicked, so there is nothing left to wait for.
+                SelectStateProj::Done => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<C, A, B, T, R, E> FusedFuture for SelectServiceAndCallFut<C, A, B, T, R, E>
+where
+    C: Future<Output = bool>,
+
+    A: Service<T, Response = R, Error = E>,
+    B: Service<T, Response = R, Error = E>,
+{
+    fn is_terminated(&self) -> bool {
+        matches!(self.state, SelectState::Done)
+    }
+}
+
+#[cfg(all(feature = "async", feature = "chain"))]
+type ChainServices<I, T, R, E> = (Vec<BoxCloneService<T, R, E>>, I);
+
+#[cfg(all(feature = "async", feature = "chain"))]
+type ChainFuture<I, T, R, E> = Either<BoxFuture<'static, Result<R, E>>, <I as Service<T>>::Future>;
+
+/// Calls the service of the `selected` filter, or the inner service if
+/// none matched.
+#[cfg(all(feature = "async", feature = "chain"))]
+fn call_selected<I, T, R, E>(
+    selected: Option<usize>,
+    value: T,
+    (mut services, mut inner): ChainServices<I, T, R, E>,
+) -> ChainFuture<I, T, R, E>
+where
+    I: Service<T, Response = R, Error = E>,
+{
+    match selected {
+        Some(index) => Either::Left(services.swap_remove(index).call(value)),
+        None => Either::Right(inner.call(value)),
+    }
+}
+
+/// The future of an [`AsyncFilterChainService`](crate::AsyncFilterChainService).
+///
+/// Evaluates the filters one after the other and calls the service
+/// of the first matching one, or the inner service if none match.
+#[cfg(all(feature = "async", feature = "chain"))]
+#[pin_project::pin_project]
+pub struct AsyncFilterChainFut<I, T, R, E>
+where
+    I: Service<T, Response = R, Error = E>,
+{
+    filters: Arc<[BoxAsyncFilter<T>]>,
+
+    #[pin]
+    state: ChainState<I, T, R, E>,
+}
+
+#[cfg(all(feature = "async", feature = "chain"))]
+#[pin_project::pin_project(project = ChainStateProj, project_replace = ChainStateProjReplace)]
+enum ChainState<I, T, R, E>
+where
+    I: Service<T, Response = R, Error = E>,
+{
+    Deciding {
+        // The index of the filter currently being evaluated.
+        index: usize,
+        condition: Option<BoxFuture<'static, bool>>,
+        value: T,
+        services: ChainServices<I, T, R, E>,
+    },
+    Calling {
+        #[pin]
+        future: ChainFuture<I, T, R, E>,
+    },
+    Done,
+}
+
+#[cfg(all(feature = "async", feature = "chain"))]
+impl<I, T, R, E> AsyncFilterChainFut<I, T, R, E>
+where
+    I: Service<T, Response = R, Error = E>,
+{
+    pub(crate) fn new(
+        filters: Arc<[BoxAsyncFilter<T>]>,
+        value: T,
+        services: Vec<BoxCloneService<T, R, E>>,
+        inner: I,
+    ) -> Self {
+        Self {
+            filters,
+            state: ChainState::Deciding {
+                index: 0,
+                condition: None,
+                value,
+                services: (services, inner),
+            },
+        }
+    }
+}
+
+#[cfg(all(feature = "async", feature = "chain"))]
+impl<I, T, R, E> Future for AsyncFilterChainFut<I, T, R, E>
+where
+    I: Service<T, Response = R, Error = E>,
+{
+    type Output = Result<R, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut state = this.state;
+
+        loop {
+            match state.as_mut().project() {
+                ChainStateProj::Deciding {
+                    index,
+                    condition,
+                    value,
+                    ..
+                } => {
+                    let selected = loop {
+                        let Some(future) = condition.as_mut() else {
+                            if *index == this.filters.len() {
+                                break None;
+                            }
+
+                            *condition = Some((this.filters[*index])(value));
+                            continue;
+                        };
+
+                        if ready!(future.as_mut().poll(cx)) {
+                            break Some(*index);
+                        }
+
+                        *index += 1;
+                        *condition = None;
+                    };
+
+                    if let ChainStateProjReplace::Deciding {
+                        value, services, ..
+                    } = state.as_mut().project_replace(ChainState::Done)
+                    {
+                        let future = call_selected(selected, value, services);
+                        state.set(ChainState::Calling { future });
+                    }
+                }
+                ChainStateProj::Calling { future } => {
+                    let output = ready!(future.poll(cx));
+                    state.set(ChainState::Done);
+
+                    return Poll::Ready(output);
+                }
+                // NOTE: Polled after completion, or after the service
+                //       panicked, so there is nothing left to wait for.
+                ChainStateProj::Done => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// The future of a [`ConcurrentFilterService`](crate::ConcurrentFilterService).
+///
+/// Evaluates all filters concurrently and calls the service of the
+/// first matching one, or the inner service if none match.
+#[cfg(all(feature = "async", feature = "chain"))]
+#[pin_project::pin_project]
+pub struct ConcurrentFilterFut<I, T, R, E>
+where
+    I: Service<T, Response = R, Error = E>,
+{
+    #[pin]
+    state: ConcurrentState<I, T, R, E>,
+}
+
+#[cfg(all(feature = "async", feature = "chain"))]
+#[pin_project::pin_project(
+    project = ConcurrentStateProj,
+    project_replace = ConcurrentStateProjReplace
+)]
+enum ConcurrentState<I, T, R, E>
+where
+    I: Service<T, Response = R, Error = E>,
+{
+    Deciding {
+        #[pin]
+        conditions: JoinAll<BoxFuture<'static, bool>>,
+        value: T,
+        services: ChainServices<I, T, R, E>,
+    },
+    Calling {
+        #[pin]
+        future: ChainFuture<I, T, R, E>,
+    },
+    Done,
+}
+
+#[cfg(all(feature = "async", feature = "chain"))]
+impl<I, T, R, E> ConcurrentFilterFut<I, T, R, E>
+where
+    I: Service<T, Response = R, Error = E>,
+{
+    pub(crate) fn new(
+        conditions: Vec<BoxFuture<'static, bool>>,
+        value: T,
+        services: Vec<BoxCloneService<T, R, E>>,
+        inner: I,
+    ) -> Self {
+        Self {
+            state: ConcurrentState::Deciding {
+                conditions: join_all(conditions),
+                value,
+                services: (services, inner),
+            },
+        }
+    }
+}
+
+#[cfg(all(feature = "async", feature = "chain"))]
+impl<I, T, R, E> Future for ConcurrentFilterFut<I, T, R, E>
+where
+    I: Service<T, Response = R, Error = E>,
+{
+    type Output = Result<R, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.project().state;
+
+        loop {
+            match state.as_mut().project() {
+                ConcurrentStateProj::Deciding { conditions, .. } => {
+                    let selected = ready!(conditions.poll(cx))
+                        .into_iter()
+                        .position(|matches| matches);
+
+                    if let ConcurrentStateProjReplace::Deciding {
+                        value, services, ..
+                    } = state.as_mut().project_replace(ConcurrentState::Done)
+                    {
+                        let future = call_selected(selected, value, services);
+                        state.set(ConcurrentState::Calling { future });
+                    }
+                }
+                ConcurrentStateProj::Calling { future } => {
+                    let output = ready!(future.poll(cx));
+                    state.set(ConcurrentState::Done);
+
+                    return Poll::Ready(output);
+                }
+                // NOTE: Polled after completion, or after the service
+                //       panicked, so there is nothing left to wait for.
+                ConcurrentStateProj::Done => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// The future of a [`QuorumFilter`](crate::filters::QuorumFilter).
+///
+/// Evaluates the conditions one after the other and resolves as soon
+/// as `k` of them matched, or too few are left for `k` to be reached.
+#[cfg(feature = "async")]
+#[pin_project::pin_project]
+pub struct QuorumFut<C>
+where
+    C: Future<Output = bool>,
+{
+    #[pin]
+    condition: Option<C>,
+    remaining: std::vec::IntoIter<C>,
+
+    matched: usize,
+    k: usize,
+}
+
+#[cfg(feature = "async")]
+impl<C> QuorumFut<C>
+where
+    C: Future<Output = bool>,
+{
+    pub(crate) fn new(conditions: Vec<C>, k: usize) -> Self {
+        Self {
+            condition: None,
+            remaining: conditions.into_iter(),
+            matched: 0,
+            k,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<C> Future for QuorumFut<C>
+where
+    C: Future<Output = bool>,
+{
+    type Output = bool;
+
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let mut this = self.project();
 
-        if let Some(future) = this.future.as_mut().as_pin_mut() {
-            return future.poll(cx);
+        loop {
+            if *this.matched >= *this.k {
+                return Poll::Ready(true);
+            }
+
+            let left = this.remaining.len() + usize::from(this.condition.is_some());
+            if *this.matched + left < *this.k {
+                return Poll::Ready(false);
+            }
+
+            let Some(condition) = this.condition.as_mut().as_pin_mut() else {
+                this.condition.set(this.remaining.next());
+                continue;
+            };
+
+            if ready!(condition.poll(cx)) {
+                *this.matched += 1;
+            }
+            this.condition.set(None);
+        }
+    }
+}
+
+/// The future of a [`ConsumingFilterService`](crate::ConsumingFilterService).
+///
+/// Awaits the filter, which hands the request back, and calls the
+/// selected service with it.
+#[cfg(feature = "async")]
+#[pin_project::pin_project(project = ConsumingSelectFutProj)]
+pub enum ConsumingSelectFut<C, A, B, T>
+where
+    C: Future<Output = (bool, T)>,
+    A: Service<T>,
+    B: Service<T, Response = A::Response, Error = A::Error>,
+{
+    Filtering {
+        #[pin]
+        condition: C,
+        // INV: This is Some(...) until the filter completed.
+        services: Option<(A, B)>,
+    },
+    Calling {
+        #[pin]
+        future: Either<A::Future, B::Future>,
+    },
+}
+
+#[cfg(feature = "async")]
+impl<C, A, B, T> ConsumingSelectFut<C, A, B, T>
+where
+    C: Future<Output = (bool, T)>,
+    A: Service<T>,
+    B: Service<T, Response = A::Response, Error = A::Error>,
+{
+    pub(crate) fn new(condition: C, service_a: A, service_b: B) -> Self {
+        Self::Filtering {
+            condition,
+            services: Some((service_a, service_b)),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<C, A, B, T> Future for ConsumingSelectFut<C, A, B, T>
+where
+    C: Future<Output = (bool, T)>,
+    A: Service<T>,
+    B: Service<T, Response = A::Response, Error = A::Error>,
+{
+    type Output = Result<A::Response, A::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            match self.as_mut().project() {
+                ConsumingSelectFutProj::Filtering {
+                    condition,
+                    services,
+                } => {
+                    let (select, value) = ready!(condition.poll(cx));
+                    let (mut service_a, mut service_b) = services
+                        .take()
+                        .expect("ConsumingSelectFut polled after completion");
+
+                    let future = if select {
+                        Either::Left(service_a.call(value))
+                    } else {
+                        Either::Right(service_b.call(value))
+                    };
+                    self.set(Self::Calling { future });
+                }
+                ConsumingSelectFutProj::Calling { future } => return future.poll(cx),
+            }
+        }
+    }
+}
+
+/// The future of a [`FallbackChain`](crate::FallbackChain).
+///
+/// Awaits the primary service and, if its error should fall through,
+/// drives the fallback service to readiness and calls it.
+#[cfg(feature = "fallback")]
+#[pin_project::pin_project(project = FallbackChainFutProj)]
+pub enum FallbackChainFut<A, B, P, T>
+where
+    A: Service<T>,
+    B: Service<T, Response = A::Response, Error = A::Error>,
+{
+    Primary {
+        #[pin]
+        future: A::Future,
+        // INV: This is Some(...) until the primary service failed.
+        fallback: Option<(B, T, P)>,
+    },
+    Fallback {
+        #[pin]
+        future: Oneshot<B, T>,
+    },
+}
+
+#[cfg(feature = "fallback")]
+impl<A, B, P, T> FallbackChainFut<A, B, P, T>
+where
+    A: Service<T>,
+    B: Service<T, Response = A::Response, Error = A::Error>,
+{
+    pub(crate) fn new(future: A::Future, fallback: B, req: T, should_fallthrough: P) -> Self {
+        Self::Primary {
+            future,
+            fallback: Some((fallback, req, should_fallthrough)),
+        }
+    }
+}
+
+#[cfg(feature = "fallback")]
+impl<A, B, P, T> Future for FallbackChainFut<A, B, P, T>
+where
+    A: Service<T>,
+    B: Service<T, Response = A::Response, Error = A::Error>,
+    P: Fn(&A::Error) -> bool,
+{
+    type Output = Result<A::Response, A::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            match self.as_mut().project() {
+                FallbackChainFutProj::Primary { future, fallback } => {
+                    let error = match ready!(future.poll(cx)) {
+                        Ok(response) => return Poll::Ready(Ok(response)),
+                        Err(error) => error,
+                    };
+
+                    let (service, req, should_fallthrough) = fallback
+                        .take()
+                        .expect("FallbackChainFut polled after completion");
+                    if !should_fallthrough(&error) {
+                        return Poll::Ready(Err(error));
+                    }
+
+                    self.set(Self::Fallback {
+                        future: Oneshot::new(service, req),
+                    });
+                }
+                FallbackChainFutProj::Fallback { future } => return future.poll(cx),
+            }
+        }
+    }
+}
+
+/// The future of a [`MakeFilterService`](crate::MakeFilterService).
+///
+/// Makes both services and creates the [`FilterService`] selecting
+/// between them.
+#[cfg(feature = "make")]
+#[pin_project::pin_project]
+pub struct MakeFilterServiceFut<F, A, B, T>
+where
+    A: TryFuture,
+    B: TryFuture<Error = A::Error>,
+{
+    #[pin]
+    services: TryJoin<A, B>,
+
+    // INV: This is Some(...) until the future completed.
+    filter: Option<F>,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+#[cfg(feature = "make")]
+impl<F, A, B, T> MakeFilterServiceFut<F, A, B, T>
+where
+    A: TryFuture,
+    B: TryFuture<Error = A::Error>,
+{
+    pub(crate) fn new(filter: F, make_service: A, make_inner: B) -> Self {
+        Self {
+            services: futures::future::try_join(make_service, make_inner),
+            filter: Some(filter),
+
+            _marker: PhantomData,
         }
+    }
+}
 
-        let select = ready!(this.condition.poll(cx));
+#[cfg(feature = "make")]
+impl<F, A, B, T> Future for MakeFilterServiceFut<F, A, B, T>
+where
+    F: Filter<T>,
+    A: TryFuture,
+    A::Ok: Service<T>,
+    B: TryFuture<Error = A::Error>,
+    B::Ok: Service<
+        T,
+        Response = <A::Ok as Service<T>>::Response,
+        Error = <A::Ok as Service<T>>::Error,
+    >,
+{
+    type Output = Result<FilterService<F, A::Ok, B::Ok, T>, A::Error>;
 
-        let value = this
-            .value
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let (service, inner) = ready!(this.services.poll(cx))?;
+        let filter = this
+            .filter
             .take()
-            .expect("Invariant violation: value is None when future is None");
+            .expect("MakeFilterServiceFut polled after completion");
+
+        Poll::Ready(Ok(FilterService::new(filter, service, inner)))
+    }
+}
+
+/// The future of a [`RetryFilterService`](crate::RetryFilterService).
+///
+/// Evaluates the filter again after every delay until it succeeds or
+/// no retries are left, and calls the selected service.
+#[cfg(feature = "retry")]
+#[pin_project::pin_project(project = RetryFilterFutProj)]
+pub enum RetryFilterFut<F, S, I, T>
+where
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    Retrying {
+        #[pin]
+        sleep: Sleep,
+        filter: F,
+        retries_left: usize,
+        retry_delay: Duration,
+        // INV: This is Some(...) until the filter succeeded or no
+        //      retries are left.
+        call: Option<(T, S, I)>,
+    },
+    Calling {
+        #[pin]
+        future: Either<S::Future, I::Future>,
+    },
+}
 
-        let (mut service_a, mut service_b) = this
-            .services
+#[cfg(feature = "retry")]
+impl<F, S, I, T> RetryFilterFut<F, S, I, T>
+where
+    F: TryFilter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    pub(crate) fn new(
+        filter: F,
+        req: T,
+        mut service: S,
+        mut inner: I,
+        max_retries: usize,
+        retry_delay: Duration,
+    ) -> Self {
+        match filter.try_matches(&req) {
+            Ok(true) => Self::Calling {
+                future: Either::Left(service.call(req)),
+            },
+            Ok(false) => Self::Calling {
+                future: Either::Right(inner.call(req)),
+            },
+            Err(_) if max_retries == 0 => Self::Calling {
+                future: Either::Right(inner.call(req)),
+            },
+            Err(_) => Self::Retrying {
+                sleep: tokio::time::sleep(retry_delay),
+                filter,
+                retries_left: max_retries,
+                retry_delay,
+                call: Some((req, service, inner)),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "retry")]
+impl<F, S, I, T> Future for RetryFilterFut<F, S, I, T>
+where
+    F: TryFilter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            match self.as_mut().project() {
+                RetryFilterFutProj::Retrying {
+                    mut sleep,
+                    filter,
+                    retries_left,
+                    retry_delay,
+                    call,
+                } => {
+                    ready!(sleep.as_mut().poll(cx));
+                    *retries_left -= 1;
+
+                    let (req, _, _) = call
+                        .as_ref()
+                        .expect("RetryFilterFut polled after completion");
+                    let matched = match filter.try_matches(req) {
+                        Ok(matched) => matched,
+                        Err(_) if *retries_left > 0 => {
+                            sleep.reset(Instant::now() + *retry_delay);
+                            continue;
+                        }
+                        // NOTE: Give up and fall through.
+                        Err(_) => false,
+                    };
+
+                    let (req, mut service, mut inner) =
+                        call.take().expect("RetryFilterFut polled after completion");
+                    let future = if matched {
+                        Either::Left(service.call(req))
+                    } else {
+                        Either::Right(inner.call(req))
+                    };
+                    self.set(Self::Calling { future });
+                }
+                RetryFilterFutProj::Calling { future } => return future.poll(cx),
+            }
+        }
+    }
+}
+
+/// The future of a [`RecordingFilterService`](crate::RecordingFilterService).
+///
+/// Records the request, the selected branch and the response once the
+/// selected service succeeded.
+#[cfg(feature = "recording")]
+#[pin_project::pin_project]
+pub struct RecordingFut<Fut, T, R> {
+    #[pin]
+    future: Fut,
+
+    // INV: This is Some(...) until the future completed.
+    call: Option<(T, bool)>,
+    recording: Recording<T, R>,
+}
+
+#[cfg(feature = "recording")]
+impl<Fut, T, R> RecordingFut<Fut, T, R> {
+    pub(crate) fn new(future: Fut, request: T, branch: bool, recording: Recording<T, R>) -> Self {
+        Self {
+            future,
+            call: Some((request, branch)),
+            recording,
+        }
+    }
+}
+
+#[cfg(feature = "recording")]
+impl<Fut, T, R, E> Future for RecordingFut<Fut, T, R>
+where
+    Fut: Future<Output = Result<R, E>>,
+    R: Clone,
+{
+    type Output = Result<R, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let result = ready!(this.future.poll(cx));
+        let (request, branch) = this
+            .call
             .take()
-            .expect("Invariant violation: services is None when future is None");
+            .expect("RecordingFut polled after completion");
 
-        let fut = if select {
-            Either::Left(service_a.call(value))
-        } else {
-            Either::Right(service_b.call(value))
-        };
+        if let Ok(response) = &result {
+            this.recording
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(RecordedCall {
+                    request,
+                    branch,
+                    response: response.clone(),
+                });
+        }
 
-        this.future.as_mut().set(Some(fut));
+        Poll::Ready(result)
+    }
+}
 
-        this.future
-            .as_mut()
-            .as_pin_mut()
-            .expect("I just set the future :)")
-            .poll(cx)
+/// The future of a [`MeteredAsyncFilter`](crate::MeteredAsyncFilter).
+///
+/// Reports the decision of the wrapped filter and how long it took
+/// once the filter decided.
+#[cfg(all(feature = "metrics", feature = "async"))]
+#[pin_project::pin_project]
+pub struct MeteredAsyncFut<C, M> {
+    #[pin]
+    condition: C,
+
+    name: Arc<str>,
+    metrics: M,
+    start: std::time::Instant,
+}
+
+#[cfg(all(feature = "metrics", feature = "async"))]
+impl<C, M> MeteredAsyncFut<C, M> {
+    pub(crate) fn new(condition: C, name: Arc<str>, metrics: M) -> Self {
+        Self {
+            condition,
+            name,
+            metrics,
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(all(feature = "metrics", feature = "async"))]
+impl<C, M> Future for MeteredAsyncFut<C, M>
+where
+    C: Future<Output = bool>,
+    M: FilterMetrics,
+{
+    type Output = bool;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let matches = ready!(this.condition.poll(cx));
+        this.metrics.on_evaluated(this.name, this.start.elapsed());
+        report_decision(this.metrics, this.name, matches);
+
+        Poll::Ready(matches)
+    }
+}
+
+/// The future of a [`CircuitBreakerService`](crate::CircuitBreakerService).
+///
+/// Records the outcome of the call into the circuit once completed.
+#[cfg(feature = "circuit-breaker")]
+#[pin_project::pin_project(PinnedDrop)]
+pub struct CircuitBreakerFut<Fut> {
+    #[pin]
+    future: Fut,
+
+    // INV: This is Some(...) until the future completed.
+    circuit: Option<Circuit>,
+    // NOTE: Whether this call took the probe slot of a half-open circuit.
+    probe: bool,
+}
+
+#[cfg(feature = "circuit-breaker")]
+impl<Fut> CircuitBreakerFut<Fut> {
+    pub(crate) fn new(future: Fut, circuit: Circuit, probe: bool) -> Self {
+        Self {
+            future,
+            circuit: Some(circuit),
+            probe,
+        }
+    }
+}
+
+#[cfg(feature = "circuit-breaker")]
+impl<Fut, R, E> Future for CircuitBreakerFut<Fut>
+where
+    Fut: Future<Output = Result<R, E>>,
+{
+    type Output = Result<R, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let result = ready!(this.future.poll(cx));
+        if let Some(circuit) = this.circuit.take() {
+            circuit.record(result.is_ok(), *this.probe);
+        }
+
+        Poll::Ready(result)
+    }
+}
+
+#[cfg(feature = "circuit-breaker")]
+#[pin_project::pinned_drop]
+impl<Fut> PinnedDrop for CircuitBreakerFut<Fut> {
+    fn drop(self: Pin<&mut Self>) {
+        // NOTE: Dropped before completing, e.g. as the client disconnected.
+        let this = self.project();
+        if let Some(circuit) = this.circuit.take() {
+            circuit.abandon(*this.probe);
+        }
+    }
+}
+
+/// The filter decision of a [`SpawnedAsyncFilterService`](crate::SpawnedAsyncFilterService),
+/// awaiting the task evaluating the filter.
+///
+/// If the task panicked, the filter doesn't match. Aborts the task
+/// when dropped before it completed, e.g. as the client disconnected.
+#[cfg(feature = "spawn")]
+pub struct SpawnedMatchFut {
+    handle: JoinHandle<bool>,
+}
+
+#[cfg(feature = "spawn")]
+impl SpawnedMatchFut {
+    pub(crate) fn new(handle: JoinHandle<bool>) -> Self {
+        Self { handle }
+    }
+}
+
+#[cfg(feature = "spawn")]
+impl fmt::Debug for SpawnedMatchFut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpawnedMatchFut")
+            .field("handle", &self.handle)
+            .finish()
+    }
+}
+
+#[cfg(feature = "spawn")]
+impl Future for SpawnedMatchFut {
+    type Output = bool;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let matched = ready!(Pin::new(&mut self.handle).poll(cx));
+
+        Poll::Ready(matched.unwrap_or(false))
+    }
+}
+
+#[cfg(feature = "spawn")]
+impl Drop for SpawnedMatchFut {
+    fn drop(&mut self) {
+        // NOTE: Does nothing if the task already completed.
+        self.handle.abort();
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use futures::future::ready;
+    use std::{
+        convert::Infallible,
+        future::poll_fn,
+        panic::{self, AssertUnwindSafe},
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use futures::{
+        future::{pending, ready, Ready},
+        task::noop_waker,
+    };
+    use tower::service_fn;
 
     use super::*;
     use crate::test_util::*;
@@ -120,4 +1242,228 @@ mod tests {
 
         assert_eq!(res, "second");
     }
+
+    #[tokio::test]
+    async fn should_drop_value_when_cancelled() {
+        let first = TestService("first");
+        let second = TestService("second");
+
+        let value = Arc::new(());
+        let fut = SelectServiceAndCallFut::new(pending(), value.clone(), first, second);
+
+        tokio::select! {
+            _ = fut => panic!("The condition never resolves"),
+            _ = tokio::time::sleep(Duration::from_millis(10)) => {}
+        }
+
+        // NOTE: The value is either still owned by this future or already
+        //       moved into the called service, so cancelling can't leak it.
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    /// A future pending until the flag is set.
+    fn gate<T: Copy>(open: &Arc<AtomicBool>, output: T) -> impl Future<Output = T> {
+        let open = open.clone();
+        poll_fn(move |_| match open.load(Ordering::SeqCst) {
+            true => Poll::Ready(output),
+            false => Poll::Pending,
+        })
+    }
+
+    #[test]
+    fn should_transition_through_each_state() {
+        let decided = Arc::new(AtomicBool::new(false));
+        let responded = Arc::new(AtomicBool::new(false));
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let first = {
+            let (responded, calls) = (responded.clone(), calls.clone());
+            service_fn(move |_: ()| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                gate(&responded, Ok::<_, Infallible>("first"))
+            })
+        };
+        let fut =
+            SelectServiceAndCallFut::new(gate(&decided, true), (), first, TestService("second"));
+        let mut fut = Box::pin(fut);
+
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+        assert!(matches!(fut.state, SelectState::Deciding { .. }));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        decided.store(true, Ordering::SeqCst);
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+        assert!(matches!(fut.state, SelectState::Calling { .. }));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        responded.store(true, Ordering::SeqCst);
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(Ok("first")));
+        assert!(matches!(fut.state, SelectState::Done));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn should_not_panic_on_any_poll_sequence() {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        // NOTE: Polled again after completion.
+        let fut =
+            SelectServiceAndCallFut::new(ready(false), (), TestService("a"), TestService("b"));
+        let mut fut = Box::pin(fut);
+        assert!(!fut.is_terminated());
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(Ok("b")));
+        assert!(fut.is_terminated());
+        for _ in 0..3 {
+            assert!(fut.as_mut().poll(&mut cx).is_pending());
+        }
+
+        // NOTE: Polled again after the service panicked, which used to
+        //       violate the invariants of the future.
+        let panicking = service_fn(|_: ()| -> Ready<Result<&str, Infallible>> { panic!("called") });
+        let fut = SelectServiceAndCallFut::new(ready(true), (), panicking, TestService("b"));
+        let mut fut = Box::pin(fut);
+
+        let polled = panic::catch_unwind(AssertUnwindSafe(|| fut.as_mut().poll(&mut cx)));
+        assert!(polled.is_err());
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+    }
+
+    /// Records the cancellations of the futures it is set on.
+    #[cfg(feature = "async")]
+    fn cancellations() -> (Option<OnCancel>, Arc<std::sync::Mutex<Vec<Cancellation>>>) {
+        let cancelled = Arc::<std::sync::Mutex<Vec<_>>>::default();
+        let record = cancelled.clone();
+        let on_cancel = OnCancel(Arc::new(move |cancellation| {
+            record.lock().unwrap().push(cancellation);
+        }));
+
+        (Some(on_cancel), cancelled)
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn should_report_cancellation_while_deciding() {
+        let (on_cancel, cancelled) = cancellations();
+        let fut = SelectServiceAndCallFut::new(pending(), (), TestService("a"), TestService("b"))
+            .on_cancel(on_cancel);
+        let mut fut = Box::pin(fut);
+
+        let waker = noop_waker();
+        assert!(fut
+            .as_mut()
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending());
+        drop(fut);
+
+        let cancelled = cancelled.lock().unwrap();
+        assert_eq!(*cancelled, [Cancellation::Deciding]);
+        assert!(!cancelled[0].is_decided());
+        assert_eq!(cancelled[0].matched(), None);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn should_report_cancellation_while_calling() {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let never = || service_fn(|_: ()| pending::<Result<&str, Infallible>>());
+
+        for (matched, expected) in [
+            (true, Cancellation::Matched),
+            (false, Cancellation::FellThrough),
+        ] {
+            let (on_cancel, cancelled) = cancellations();
+            let fut = SelectServiceAndCallFut::new(ready(matched), (), never(), never())
+                .on_cancel(on_cancel);
+            let mut fut = Box::pin(fut);
+
+            assert!(fut.as_mut().poll(&mut cx).is_pending());
+            drop(fut);
+
+            let cancelled = cancelled.lock().unwrap();
+            assert_eq!(*cancelled, [expected]);
+            assert!(cancelled[0].is_decided());
+            assert_eq!(cancelled[0].matched(), Some(matched));
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn should_not_report_cancellation_once_completed() {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let (on_cancel, cancelled) = cancellations();
+        let fut = SelectServiceAndCallFut::new(ready(true), (), TestService("a"), TestService("b"))
+            .on_cancel(on_cancel);
+        let mut fut = Box::pin(fut);
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(Ok("a")));
+        drop(fut);
+
+        // NOTE: Never polled, so it is dropped while deciding.
+        let (on_cancel, never_polled) = cancellations();
+        drop(
+            SelectServiceAndCallFut::new(ready(true), (), TestService("a"), TestService("b"))
+                .on_cancel(on_cancel),
+        );
+
+        assert!(cancelled.lock().unwrap().is_empty());
+        assert_eq!(*never_polled.lock().unwrap(), [Cancellation::Deciding]);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn should_report_cancellation_of_layered_service() {
+        use std::time::Duration;
+
+        use tower::{Layer, Service, ServiceExt};
+
+        use crate::{AsyncFilterFn, AsyncFilterLayer};
+
+        let cancelled = Arc::<std::sync::Mutex<Vec<_>>>::default();
+        let record = cancelled.clone();
+        let slow = AsyncFilterFn::new(|_: &()| async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            true
+        });
+        let mut service = AsyncFilterLayer::new(slow, TestService("a"))
+            .on_cancel(move |cancellation| record.lock().unwrap().push(cancellation))
+            .layer(TestService("b"));
+
+        let fut = service.ready().await.unwrap().call(());
+        let timeout = tokio::time::timeout(Duration::from_millis(10), fut).await;
+        assert!(timeout.is_err());
+
+        assert_eq!(*cancelled.lock().unwrap(), [Cancellation::Deciding]);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn should_skip_terminated_future_in_select() {
+        use tower::{Layer, Service, ServiceExt};
+
+        use crate::AsyncFilterLayer;
+
+        let mut service =
+            AsyncFilterLayer::new(TestFilter(true), TestService("a")).layer(TestService("b"));
+        let mut fut = service.ready().await.unwrap().call(());
+        let mut never = pending::<()>();
+
+        // NOTE: `select!` requires fused futures, as it skips the
+        //       terminated ones instead of polling them again.
+        futures::select! {
+            res = fut => assert_eq!(res, Ok("a")),
+            _ = never => unreachable!(),
+        }
+        assert!(fut.is_terminated());
+
+        let waker = noop_waker();
+        assert!(Pin::new(&mut fut)
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending());
+    }
 }
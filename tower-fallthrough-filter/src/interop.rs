@@ -0,0 +1,216 @@
+//! Adapters between this crate and `tower::steer`, so pickers written
+//! for a [`Steer`] can be reused with fallthrough semantics and the
+//! other way around.
+//!
+//! # Example
+//! ```rust
+//! use tower::{service_fn, Layer, ServiceExt};
+//! use tower_fallthrough_filter::SelectNLayer;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let version = |name| service_fn(move |_: &'static str| async move { Ok::<_, ()>(name) });
+//!
+//! // NOTE: An existing picker, out of range indices fall through.
+//! let by_version = |path: &&'static str, _: &[_]| match *path {
+//!     "/v1" => 0,
+//!     "/v2" => 1,
+//!     _ => usize::MAX,
+//! };
+//!
+//! let layer = SelectNLayer::from_picker(by_version, vec![version("v1"), version("v2")]);
+//! let service = layer.layer(version("legacy"));
+//!
+//! assert_eq!(service.clone().oneshot("/v2").await, Ok("v2"));
+//! assert_eq!(service.oneshot("/v0").await, Ok("legacy"));
+//! # }
+//! ```
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+use tower::{
+    steer::{Picker, Steer},
+    Service,
+};
+
+use crate::{Filter, FilterLayer, IndexFilter, SelectNLayer};
+
+/// An [`IndexFilter`] driven by a `tower::steer::Picker`.
+///
+/// Picks out of range of the services fall through to the inner
+/// service, as with any other [`IndexFilter`].
+///
+/// NOTE: The picker is shared by all clones of the filter, as
+/// `Picker::pick` takes `&mut self` to allow e.g. round robin.
+#[derive(Debug)]
+pub struct SteerPicker<P, S> {
+    picker: Arc<Mutex<P>>,
+    services: Arc<[S]>,
+}
+
+impl<P, S> SteerPicker<P, S> {
+    /// Creates a new SteerPicker given a `Picker` and the
+    /// `Service`s it is shown when picking.
+    pub fn new(picker: P, services: Vec<S>) -> Self {
+        Self {
+            picker: Arc::new(Mutex::new(picker)),
+            services: services.into(),
+        }
+    }
+}
+
+impl<P, S> Clone for SteerPicker<P, S> {
+    fn clone(&self) -> Self {
+        Self {
+            picker: self.picker.clone(),
+            services: self.services.clone(),
+        }
+    }
+}
+
+impl<P, S, T> IndexFilter<T> for SteerPicker<P, S>
+where
+    P: Picker<S, T>,
+{
+    fn index(&self, item: &T) -> Option<usize> {
+        let index = self
+            .picker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pick(item, &self.services);
+
+        Some(index)
+    }
+}
+
+impl<P, S, T> SelectNLayer<SteerPicker<P, S>, S, T>
+where
+    P: Picker<S, T>,
+    S: Service<T> + Clone,
+{
+    /// Creates a new SelectNLayer given a `tower::steer::Picker`
+    /// and the `Service`s it picks from.
+    pub fn from_picker(picker: P, services: Vec<S>) -> Self {
+        SelectNLayer::new(SteerPicker::new(picker, services.clone()), services)
+    }
+}
+
+/// A `tower::steer::Picker` driven by a [`Filter`], picking the
+/// first service if it matches and the second one otherwise.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FilterPicker<F>(pub F);
+
+impl<F, S, T> Picker<S, T> for FilterPicker<F>
+where
+    F: Filter<T>,
+{
+    fn pick(&mut self, item: &T, _: &[S]) -> usize {
+        if self.0.matches(item) {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+/// Creates a [`Steer`] behaving like a
+/// [`FilterService`](crate::FilterService), calling `service` if the
+/// filter matches and `inner` otherwise.
+///
+/// Unlike with a [`FilterLayer`] both services have to be of the same
+/// type, services of different types can be unified using e.g.
+/// `tower::util::BoxCloneService`.
+pub fn filter_steer<F, S, T>(filter: F, service: S, inner: S) -> Steer<S, FilterPicker<F>, T>
+where
+    F: Filter<T>,
+    S: Service<T>,
+{
+    Steer::new([service, inner], FilterPicker(filter))
+}
+
+/// A [`FilterLayer`] calling a [`Steer`] if the filter matches, so
+/// the matching requests are routed to one of its services while the
+/// others fall through to the inner service without reaching the
+/// picker.
+///
+/// # Example
+/// ```rust
+/// use tower::{service_fn, steer::Steer, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{interop::SteerFilterLayer, FilterFn};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name| service_fn(move |_: u32| async move { Ok::<_, ()>(name) });
+///
+/// let by_parity = |n: &u32, _: &[_]| (n % 2) as usize;
+/// let steer = Steer::new([respond("even"), respond("odd")], by_parity);
+///
+/// let is_api = FilterFn::new(|n: &u32| *n < 100);
+/// let service = SteerFilterLayer::new(is_api, steer).layer(respond("static"));
+///
+/// assert_eq!(service.clone().oneshot(7).await, Ok("odd"));
+/// assert_eq!(service.oneshot(700).await, Ok("static"));
+/// # }
+/// ```
+pub type SteerFilterLayer<F, S, P, T> = FilterLayer<F, Steer<S, P, T>, T>;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tower::{Layer, ServiceExt};
+
+    use super::*;
+    use crate::test_util::*;
+
+    #[tokio::test]
+    async fn should_route_with_steer_picker() {
+        let by_language = |language: &&'static str, services: &[TestService<&'static str>]| {
+            assert_eq!(services.len(), 3);
+
+            ["en", "de", "fr"]
+                .iter()
+                .position(|l| l == language)
+                .unwrap_or(usize::MAX)
+        };
+
+        let services = vec![TestService("en"), TestService("de"), TestService("fr")];
+        let service = SelectNLayer::from_picker(by_language, services).layer(TestService("router"));
+
+        assert_eq!(service.clone().oneshot("de").await, Ok("de"));
+        assert_eq!(service.clone().oneshot("fr").await, Ok("fr"));
+        assert_eq!(service.oneshot("es").await, Ok("router"));
+    }
+
+    #[tokio::test]
+    async fn should_route_filter_with_steer() {
+        let steer = filter_steer(TestFilter(true), TestService("a"), TestService("b"));
+        assert_eq!(steer.oneshot(()).await, Ok("a"));
+
+        let steer = filter_steer(TestFilter(false), TestService("a"), TestService("b"));
+        assert_eq!(steer.oneshot(()).await, Ok("b"));
+    }
+
+    #[tokio::test]
+    async fn should_bypass_steer_on_fallthrough() {
+        let picks = Arc::new(AtomicUsize::new(0));
+        let picker = {
+            let picks = picks.clone();
+            move |n: &usize, _: &[TestService<&'static str>]| {
+                picks.fetch_add(1, Ordering::SeqCst);
+                *n
+            }
+        };
+        let steer = Steer::new([TestService("a"), TestService("b")], picker);
+
+        let is_small = crate::FilterFn::new(|n: &usize| *n < 2);
+        let service = SteerFilterLayer::new(is_small, steer).layer(TestService("inner"));
+
+        assert_eq!(service.clone().oneshot(0).await, Ok("a"));
+        assert_eq!(service.clone().oneshot(1).await, Ok("b"));
+        assert_eq!(picks.load(Ordering::SeqCst), 2);
+
+        assert_eq!(service.oneshot(5).await, Ok("inner"));
+        assert_eq!(picks.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,104 @@
+use tower::{Layer, Service};
+
+use crate::{Filter, FilterLayer, FilterService};
+
+/// A Tower layer negating the filter of a [`FilterLayer`], so its
+/// service is executed for the requests that previously fell through
+/// and the other way around.
+///
+/// Unlike negating the filter before creating the layer, this also
+/// works for layers received from elsewhere.
+///
+/// # Example
+/// ```rust
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{FilterFn, FilterLayer, InvertLayer};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name| service_fn(move |_: u32| async move { Ok::<_, ()>(name) });
+///
+/// let small = FilterLayer::new(FilterFn::new(|n: &u32| *n < 10), respond("large"));
+/// let service = InvertLayer::new(small).layer(respond("small"));
+///
+/// assert_eq!(service.clone().oneshot(5).await, Ok("small"));
+/// assert_eq!(service.oneshot(50).await, Ok("large"));
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct InvertLayer<L> {
+    layer: L,
+}
+
+impl<L> InvertLayer<L> {
+    /// Creates a new InvertLayer given a `FilterLayer`.
+    pub fn new(layer: L) -> Self {
+        Self { layer }
+    }
+
+    /// Returns the wrapped layer.
+    pub fn into_inner(self) -> L {
+        self.layer
+    }
+}
+
+impl<F, S, I, T> Layer<I> for InvertLayer<FilterLayer<F, S, T>>
+where
+    F: Filter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = InvertFilterService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        self.layer
+            .clone()
+            .map_filter(InvertedFilter)
+            .with_fallthrough(inner_service)
+    }
+}
+
+/// The service created by an [`InvertLayer`].
+pub type InvertFilterService<F, S, I, T> = FilterService<InvertedFilter<F>, S, I, T>;
+
+/// A filter matching whenever the wrapped filter doesn't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InvertedFilter<F>(pub F);
+
+impl<F: Filter<T>, T> Filter<T> for InvertedFilter<F> {
+    fn matches(&self, item: &T) -> bool {
+        !self.0.matches(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{filters::ConstFilter, test_util::*};
+
+    #[tokio::test]
+    async fn should_behave_like_negated_filter() {
+        let inverted = InvertLayer::new(FilterLayer::new(ConstFilter::<true>, TestService("a")))
+            .layer(TestService("b"));
+        let negated =
+            FilterLayer::new(ConstFilter::<false>, TestService("a")).layer(TestService("b"));
+        assert_eq!(inverted.oneshot(()).await, negated.oneshot(()).await);
+
+        let inverted = InvertLayer::new(FilterLayer::new(ConstFilter::<false>, TestService("a")))
+            .layer(TestService("b"));
+        let negated =
+            FilterLayer::new(ConstFilter::<true>, TestService("a")).layer(TestService("b"));
+        assert_eq!(inverted.oneshot(()).await, negated.oneshot(()).await);
+    }
+
+    #[tokio::test]
+    async fn should_keep_failover() {
+        let (service_a, _) = PendingService::new("a");
+        let layer = FilterLayer::new(ConstFilter::<false>, service_a).failover_on_pending(true);
+        let service = InvertLayer::new(layer).layer(TestService("b"));
+
+        assert_eq!(service.oneshot(()).await, Ok("b"));
+    }
+}
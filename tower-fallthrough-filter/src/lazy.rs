@@ -0,0 +1,710 @@
+use std::{
+    fmt,
+    marker::PhantomData,
+    task::{Context, Poll},
+};
+
+use futures::{future::Either, task::noop_waker_ref};
+use tower::{util::Oneshot, Layer, Service};
+
+use crate::{catch_panic::CatchPanics, ready::take_ready, Filter, FilterLayer, FilterService};
+
+/// A service like [`FilterService`], but only driving the service the
+/// filter selects to readiness, created by [`FilterService::new_lazy`].
+///
+/// # Readiness
+/// The service is always ready. Once called, the selected service is
+/// cloned and driven to readiness within the response future, like
+/// `tower::ServiceExt::oneshot`. So e.g. wrapping both services in a
+/// `tower::limit::ConcurrencyLimit` only consumes a permit of the
+/// selected one, instead of holding one of each while ready.
+///
+/// With [`FilterLayer::failover_on_pending`] the readiness of the
+/// filtered service is polled once when called instead, falling
+/// through while it isn't ready.
+pub struct LazyFilterService<F, S, I, T> {
+    filter: F,
+    service: S,
+    inner: I,
+    failover_on_pending: bool,
+    catch_panics: CatchPanics,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F, S, I, T> FilterService<F, S, I, T>
+where
+    F: Filter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    /// Creates a new LazyFilterService given a `Filter`, the `Service`
+    /// that is executed if it matches and the `Service` to fall through
+    /// to, only polling the readiness of the selected one.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tower::{limit::ConcurrencyLimit, service_fn, ServiceExt};
+    /// use tower_fallthrough_filter::{Filter, FilterService};
+    ///
+    /// #[derive(Clone)]
+    /// struct IsEven;
+    ///
+    /// impl Filter<u32> for IsEven {
+    ///     fn matches(&self, item: &u32) -> bool {
+    ///         item.is_multiple_of(2)
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let respond = |name| ConcurrencyLimit::new(service_fn(move |_: u32| async move { Ok::<_, ()>(name) }), 1);
+    ///
+    /// let service = FilterService::new_lazy(IsEven, respond("even"), respond("odd"));
+    /// assert_eq!(service.clone().oneshot(2).await, Ok("even"));
+    /// assert_eq!(service.oneshot(3).await, Ok("odd"));
+    /// # }
+    /// ```
+    pub fn new_lazy(filter: F, service: S, inner: I) -> LazyFilterService<F, S, I, T> {
+        LazyFilterService {
+            filter,
+            service,
+            inner,
+            failover_on_pending: false,
+            catch_panics: CatchPanics::default(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: Clone, S: Clone, I: Clone, T> Clone for LazyFilterService<F, S, I, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: self.inner.clone(),
+            failover_on_pending: self.failover_on_pending,
+            catch_panics: self.catch_panics.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> fmt::Debug for LazyFilterService<F, S, I, T>
+where
+    F: fmt::Debug,
+    S: fmt::Debug,
+    I: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyFilterService")
+            .field("filter", &self.filter)
+            .field("service", &self.service)
+            .field("inner", &self.inner)
+            .field("failover_on_pending", &self.failover_on_pending)
+            .field("catch_panics", &self.catch_panics)
+            .finish()
+    }
+}
+
+impl<F, S, I, T> Service<T> for LazyFilterService<F, S, I, T>
+where
+    F: Filter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<Oneshot<S, T>, Oneshot<I, T>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // NOTE: The selected service is only known once called,
+        //       so its readiness is polled within the future.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        if !self.catch_panics.matches(&self.filter, &req) {
+            return Either::Right(Oneshot::new(self.inner.clone(), req));
+        }
+
+        let mut service = self.service.clone();
+        if self.failover_on_pending {
+            // NOTE: Poll once without waiting, the request falls through
+            //       if the filtered service isn't ready.
+            let mut cx = Context::from_waker(noop_waker_ref());
+            if service.poll_ready(&mut cx).is_pending() {
+                return Either::Right(Oneshot::new(self.inner.clone(), req));
+            }
+        }
+
+        Either::Left(Oneshot::new(service, req))
+    }
+}
+
+/// A service like [`FilterService`], but keeping the readiness of each
+/// service apart, created by [`FilterService::new_isolated`].
+///
+/// # Readiness
+/// Both services are driven to readiness, like with a [`FilterService`],
+/// but the service is ready as soon as either of them is. Once called,
+/// the selected service is used if it is ready, otherwise a clone of it
+/// is driven to readiness within the response future. So a saturated
+/// service only stalls the requests selecting it, while the requests
+/// of the other one are still served. Backpressure is only applied
+/// once neither service is ready.
+pub struct IsolatedFilterService<F, S, I, T> {
+    filter: F,
+    service: S,
+    inner: I,
+    failover_on_pending: bool,
+    catch_panics: CatchPanics,
+    // NOTE: Whether the services reported to be ready since last called.
+    service_ready: bool,
+    inner_ready: bool,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F, S, I, T> FilterService<F, S, I, T>
+where
+    F: Filter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    /// Creates a new IsolatedFilterService given a `Filter`, the `Service`
+    /// that is executed if it matches and the `Service` to fall through
+    /// to, keeping the readiness of each service apart.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tower::{limit::ConcurrencyLimit, service_fn, ServiceExt};
+    /// use tower_fallthrough_filter::{Filter, FilterService};
+    ///
+    /// #[derive(Clone)]
+    /// struct IsEven;
+    ///
+    /// impl Filter<u32> for IsEven {
+    ///     fn matches(&self, item: &u32) -> bool {
+    ///         item.is_multiple_of(2)
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let respond = |name| ConcurrencyLimit::new(service_fn(move |_: u32| async move { Ok::<_, ()>(name) }), 1);
+    ///
+    /// let service = FilterService::new_isolated(IsEven, respond("even"), respond("odd"));
+    /// assert_eq!(service.clone().oneshot(2).await, Ok("even"));
+    /// assert_eq!(service.oneshot(3).await, Ok("odd"));
+    /// # }
+    /// ```
+    pub fn new_isolated(filter: F, service: S, inner: I) -> IsolatedFilterService<F, S, I, T> {
+        IsolatedFilterService {
+            filter,
+            service,
+            inner,
+            failover_on_pending: false,
+            catch_panics: CatchPanics::default(),
+            service_ready: false,
+            inner_ready: false,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: Clone, S: Clone, I: Clone, T> Clone for IsolatedFilterService<F, S, I, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: self.inner.clone(),
+            failover_on_pending: self.failover_on_pending,
+            catch_panics: self.catch_panics.clone(),
+            // NOTE: The readiness belongs to the original services.
+            service_ready: false,
+            inner_ready: false,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> fmt::Debug for IsolatedFilterService<F, S, I, T>
+where
+    F: fmt::Debug,
+    S: fmt::Debug,
+    I: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IsolatedFilterService")
+            .field("filter", &self.filter)
+            .field("service", &self.service)
+            .field("inner", &self.inner)
+            .field("failover_on_pending", &self.failover_on_pending)
+            .field("catch_panics", &self.catch_panics)
+            .field("service_ready", &self.service_ready)
+            .field("inner_ready", &self.inner_ready)
+            .finish()
+    }
+}
+
+impl<F, S, I, T> Service<T> for IsolatedFilterService<F, S, I, T>
+where
+    F: Filter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<Oneshot<S, T>, Oneshot<I, T>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if !self.service_ready {
+            if let Poll::Ready(result) = self.service.poll_ready(cx) {
+                result?;
+                self.service_ready = true;
+            }
+        }
+        if !self.inner_ready {
+            if let Poll::Ready(result) = self.inner.poll_ready(cx) {
+                result?;
+                self.inner_ready = true;
+            }
+        }
+
+        if self.service_ready || self.inner_ready {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        let matched = self.catch_panics.matches(&self.filter, &req);
+
+        if matched && (self.service_ready || !self.failover_on_pending) {
+            let service = service_to_call(&mut self.service, &mut self.service_ready);
+            Either::Left(Oneshot::new(service, req))
+        } else {
+            let inner = service_to_call(&mut self.inner, &mut self.inner_ready);
+            Either::Right(Oneshot::new(inner, req))
+        }
+    }
+}
+
+/// Returns the service to call, the ready one if it reported to be
+/// ready, which is replaced by a clone, otherwise a clone to be
+/// driven to readiness within the response future.
+fn service_to_call<S: Clone>(service: &mut S, ready: &mut bool) -> S {
+    if std::mem::take(ready) {
+        take_ready(service)
+    } else {
+        service.clone()
+    }
+}
+
+/// When the services of a [`ReadyPolicyFilterLayer`] are driven to
+/// readiness.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReadyPolicy {
+    /// Both services have to be ready before any request is accepted,
+    /// like with a [`FilterService`].
+    ///
+    /// The backpressure of either service is passed on to the caller,
+    /// but a service that never becomes ready stalls all requests,
+    /// even those that would never reach it.
+    #[default]
+    Both,
+    /// The service is always ready and only the selected service is
+    /// driven to readiness once called, like with a [`LazyFilterService`].
+    ///
+    /// A service that isn't ready only stalls the requests selecting
+    /// it, but as the caller never waits, no backpressure is applied.
+    /// So e.g. a `tower::buffer::Buffer` or a load balancer in front
+    /// of this service keeps accepting requests, which pile up in the
+    /// response futures instead.
+    Lazy,
+    /// The service is ready once either service is and only the
+    /// selected service is driven to readiness once called, like with
+    /// an [`IsolatedFilterService`].
+    ///
+    /// A service that isn't ready only stalls the requests selecting
+    /// it, while backpressure is still applied once neither service
+    /// is ready.
+    Isolated,
+}
+
+impl<F: Filter<T>, S: Service<T>, T> FilterLayer<F, S, T> {
+    /// Sets when the services are driven to readiness,
+    /// see [`ReadyPolicy`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tower::{service_fn, Layer, ServiceExt};
+    /// use tower_fallthrough_filter::{FilterFn, FilterLayer, ReadyPolicy};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let respond = |name| service_fn(move |_: u32| async move { Ok::<_, ()>(name) });
+    ///
+    /// let service = FilterLayer::new(FilterFn::new(|n: &u32| *n < 10), respond("small"))
+    ///     .ready_policy(ReadyPolicy::Lazy)
+    ///     .layer(respond("large"));
+    ///
+    /// assert_eq!(service.clone().oneshot(5).await, Ok("small"));
+    /// assert_eq!(service.oneshot(50).await, Ok("large"));
+    /// # }
+    /// ```
+    pub fn ready_policy(self, policy: ReadyPolicy) -> ReadyPolicyFilterLayer<F, S, T> {
+        ReadyPolicyFilterLayer {
+            layer: self,
+            policy,
+        }
+    }
+}
+
+/// A Tower layer like [`FilterLayer`], but choosing when the services
+/// are driven to readiness at runtime, created by
+/// [`FilterLayer::ready_policy`].
+#[derive(Debug)]
+pub struct ReadyPolicyFilterLayer<F, S, T> {
+    layer: FilterLayer<F, S, T>,
+    policy: ReadyPolicy,
+}
+
+impl<F: Clone, S: Clone, T> Clone for ReadyPolicyFilterLayer<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer.clone(),
+            policy: self.policy,
+        }
+    }
+}
+
+impl<F, S, I, T> Layer<I> for ReadyPolicyFilterLayer<F, S, T>
+where
+    F: Filter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = ReadyPolicyFilterService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        let service = match self.policy {
+            ReadyPolicy::Both => PolicyService::Both(self.layer.layer(inner_service)),
+            ReadyPolicy::Lazy => {
+                let mut service = FilterService::new_lazy(
+                    self.layer.filter.clone(),
+                    self.layer.service.clone(),
+                    inner_service,
+                );
+                service.failover_on_pending = self.layer.failover_on_pending;
+                service.catch_panics = self.layer.catch_panics.clone();
+                PolicyService::Lazy(service)
+            }
+            ReadyPolicy::Isolated => {
+                let mut service = FilterService::new_isolated(
+                    self.layer.filter.clone(),
+                    self.layer.service.clone(),
+                    inner_service,
+                );
+                service.failover_on_pending = self.layer.failover_on_pending;
+                service.catch_panics = self.layer.catch_panics.clone();
+                PolicyService::Isolated(service)
+            }
+        };
+
+        ReadyPolicyFilterService { service }
+    }
+}
+
+/// The service created by a [`ReadyPolicyFilterLayer`].
+#[derive(Debug)]
+pub struct ReadyPolicyFilterService<F, S, I, T> {
+    service: PolicyService<F, S, I, T>,
+}
+
+#[derive(Debug)]
+enum PolicyService<F, S, I, T> {
+    Both(FilterService<F, S, I, T>),
+    Lazy(LazyFilterService<F, S, I, T>),
+    Isolated(IsolatedFilterService<F, S, I, T>),
+}
+
+impl<F, S, I, T> ReadyPolicyFilterService<F, S, I, T> {
+    /// The policy the service was created with.
+    pub fn policy(&self) -> ReadyPolicy {
+        match self.service {
+            PolicyService::Both(_) => ReadyPolicy::Both,
+            PolicyService::Lazy(_) => ReadyPolicy::Lazy,
+            PolicyService::Isolated(_) => ReadyPolicy::Isolated,
+        }
+    }
+}
+
+impl<F: Clone, S: Clone, I: Clone, T> Clone for ReadyPolicyFilterService<F, S, I, T> {
+    fn clone(&self) -> Self {
+        let service = match &self.service {
+            PolicyService::Both(service) => PolicyService::Both(service.clone()),
+            PolicyService::Lazy(service) => PolicyService::Lazy(service.clone()),
+            PolicyService::Isolated(service) => PolicyService::Isolated(service.clone()),
+        };
+
+        Self { service }
+    }
+}
+
+impl<F, S, I, T> Service<T> for ReadyPolicyFilterService<F, S, I, T>
+where
+    F: Filter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // NOTE: The lazy and the isolated service share their future.
+    type Future = Either<
+        <FilterService<F, S, I, T> as Service<T>>::Future,
+        <LazyFilterService<F, S, I, T> as Service<T>>::Future,
+    >;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match &mut self.service {
+            PolicyService::Both(service) => service.poll_ready(cx),
+            PolicyService::Lazy(service) => service.poll_ready(cx),
+            PolicyService::Isolated(service) => service.poll_ready(cx),
+        }
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        match &mut self.service {
+            PolicyService::Both(service) => Either::Left(service.call(req)),
+            PolicyService::Lazy(service) => Either::Right(service.call(req)),
+            PolicyService::Isolated(service) => Either::Right(service.call(req)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        future::{pending, Pending},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    };
+
+    use futures::{poll, FutureExt};
+    use tower::{limit::ConcurrencyLimit, ServiceExt};
+
+    use super::*;
+    use crate::test_util::*;
+
+    #[derive(Clone)]
+    struct NeverResponds;
+
+    impl Service<()> for NeverResponds {
+        type Response = ();
+        type Error = ();
+        type Future = Pending<Result<(), ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            pending()
+        }
+    }
+
+    fn has_permit(service: &mut ConcurrencyLimit<NeverResponds>) -> bool {
+        service.clone().ready_oneshot().now_or_never().is_some()
+    }
+
+    #[tokio::test]
+    async fn should_select_service() {
+        let service = FilterService::new_lazy(TestFilter(true), TestService("a"), TestService("b"));
+        assert_eq!(service.oneshot(()).await, Ok("a"));
+
+        let service =
+            FilterService::new_lazy(TestFilter(false), TestService("a"), TestService("b"));
+        assert_eq!(service.oneshot(()).await, Ok("b"));
+    }
+
+    #[tokio::test]
+    async fn should_only_acquire_selected_permit() {
+        let mut service_a = ConcurrencyLimit::new(NeverResponds, 1);
+        let mut service_b = ConcurrencyLimit::new(NeverResponds, 1);
+
+        // NOTE: Being ready, the eager service holds a permit of each.
+        let mut eager = FilterService::new(TestFilter(true), service_a.clone(), service_b.clone());
+        ServiceExt::<()>::ready(&mut eager).await.unwrap();
+        assert!(!has_permit(&mut service_a));
+        assert!(!has_permit(&mut service_b));
+        drop(eager);
+
+        let mut lazy =
+            FilterService::new_lazy(TestFilter(true), service_a.clone(), service_b.clone());
+        let future = lazy.ready().await.unwrap().call(());
+        tokio::pin!(future);
+
+        assert!(poll!(future.as_mut()).is_pending());
+        assert!(!has_permit(&mut service_a));
+        assert!(has_permit(&mut service_b));
+    }
+
+    #[tokio::test]
+    async fn should_fail_over_when_lazy() {
+        let (service, ready) = PendingService::new("a");
+        let service = FilterLayer::new(TestFilter(true), service)
+            .failover_on_pending(true)
+            .ready_policy(ReadyPolicy::Lazy)
+            .layer(TestService("b"));
+
+        assert_eq!(service.clone().oneshot(()).await, Ok("b"));
+
+        ready.store(true, Ordering::SeqCst);
+        assert_eq!(service.oneshot(()).await, Ok("a"));
+    }
+
+    #[tokio::test]
+    async fn should_catch_panics_when_lazy() {
+        let caught = Arc::new(AtomicBool::new(false));
+        let hook = caught.clone();
+        let service = FilterLayer::new(PanicFilter { on_poll: false }, TestService("a"))
+            .catch_panics(true)
+            .on_panic(move |_| hook.store(true, Ordering::SeqCst))
+            .ready_policy(ReadyPolicy::Lazy)
+            .layer(TestService("b"));
+
+        assert_eq!(service.oneshot(()).await, Ok("b"));
+        assert!(caught.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn should_not_wait_for_pending_fallthrough_when_lazy() {
+        let (inner, _) = PendingService::new("b");
+        let service = FilterLayer::new(TestFilter(true), TestService("a"))
+            .ready_policy(ReadyPolicy::Lazy)
+            .layer(inner);
+
+        assert_eq!(service.policy(), ReadyPolicy::Lazy);
+        assert_eq!(service.oneshot(()).await, Ok("a"));
+    }
+
+    #[tokio::test]
+    async fn should_wait_for_pending_fallthrough_when_both() {
+        let (inner, ready) = PendingService::new("b");
+        let mut service = FilterLayer::new(TestFilter(true), TestService("a"))
+            .ready_policy(ReadyPolicy::Both)
+            .layer(inner);
+
+        assert!(ServiceExt::<()>::ready(&mut service)
+            .now_or_never()
+            .is_none());
+
+        ready.store(true, Ordering::SeqCst);
+        assert_eq!(service.oneshot(()).await, Ok("a"));
+    }
+
+    #[tokio::test]
+    async fn should_isolate_backpressure_of_saturated_service_when_lazy() {
+        let render = ConcurrencyLimit::new(
+            tower::service_fn(|_: bool| pending::<Result<&str, Infallible>>()),
+            1,
+        );
+        let api = ConcurrencyLimit::new(TestService("api"), 1);
+        let is_page = crate::FilterFn::new(|is_page: &bool| *is_page);
+
+        // NOTE: The first page holds the only permit of the renderer.
+        let eager = FilterLayer::new(is_page, render.clone()).layer(api.clone());
+        let _rendering = tokio::spawn(eager.clone().oneshot(true));
+        tokio::task::yield_now().await;
+
+        // NOTE: Waiting for both services, the API requests are stalled.
+        assert!(eager.oneshot(false).now_or_never().is_none());
+
+        let lazy = FilterLayer::new(is_page, render)
+            .ready_policy(ReadyPolicy::Lazy)
+            .layer(api);
+
+        let page = lazy.clone().oneshot(true);
+        tokio::pin!(page);
+        assert!(poll!(page.as_mut()).is_pending());
+
+        let requests: Vec<_> = (0..10)
+            .map(|_| tokio::spawn(lazy.clone().oneshot(false)))
+            .collect();
+        for request in requests {
+            assert_eq!(request.await.unwrap(), Ok("api"));
+        }
+        assert!(poll!(page.as_mut()).is_pending());
+    }
+
+    #[tokio::test]
+    async fn should_select_service_when_isolated() {
+        let service =
+            FilterService::new_isolated(TestFilter(true), TestService("a"), TestService("b"));
+        assert_eq!(service.oneshot(()).await, Ok("a"));
+
+        let service =
+            FilterService::new_isolated(TestFilter(false), TestService("a"), TestService("b"));
+        assert_eq!(service.oneshot(()).await, Ok("b"));
+    }
+
+    #[tokio::test]
+    async fn should_isolate_backpressure_of_saturated_service_when_isolated() {
+        let render = ConcurrencyLimit::new(
+            tower::service_fn(|_: bool| pending::<Result<&str, Infallible>>()),
+            1,
+        );
+        let api = ConcurrencyLimit::new(TestService("api"), 1);
+        let is_page = crate::FilterFn::new(|is_page: &bool| *is_page);
+
+        let service = FilterLayer::new(is_page, render)
+            .ready_policy(ReadyPolicy::Isolated)
+            .layer(api);
+        assert_eq!(service.policy(), ReadyPolicy::Isolated);
+
+        // NOTE: The first page holds the only permit of the renderer.
+        let _rendering = tokio::spawn(service.clone().oneshot(true));
+        tokio::task::yield_now().await;
+
+        let page = service.clone().oneshot(true);
+        tokio::pin!(page);
+        assert!(poll!(page.as_mut()).is_pending());
+
+        let requests: Vec<_> = (0..10)
+            .map(|_| tokio::spawn(service.clone().oneshot(false)))
+            .collect();
+        for request in requests {
+            assert_eq!(request.await.unwrap(), Ok("api"));
+        }
+        assert!(poll!(page.as_mut()).is_pending());
+    }
+
+    #[tokio::test]
+    async fn should_apply_backpressure_once_neither_is_ready_when_isolated() {
+        let (service, service_ready) = PendingService::new("a");
+        let (inner, inner_ready) = PendingService::new("b");
+        let mut service = FilterService::new_isolated(TestFilter(false), service, inner);
+
+        assert!(ServiceExt::<()>::ready(&mut service)
+            .now_or_never()
+            .is_none());
+
+        service_ready.store(true, Ordering::SeqCst);
+        let future = service.ready().await.unwrap().call(());
+        tokio::pin!(future);
+        assert!(poll!(future.as_mut()).is_pending());
+
+        inner_ready.store(true, Ordering::SeqCst);
+        assert_eq!(future.await, Ok("b"));
+    }
+}
@@ -1,23 +1,288 @@
 use std::{
+    any::Any,
+    error::Error,
+    fmt,
+    future::Future,
     marker::PhantomData,
+    sync::Arc,
     task::{Context, Poll},
 };
 
-use ::futures::{future::Either, ready};
+use ::futures::{
+    future::{self, Either},
+    ready, TryFutureExt,
+};
 use tower::{Layer, Service};
 
+use crate::catch_panic::CatchPanics;
+
 #[cfg(test)]
 pub mod test_util;
 
 #[cfg(feature = "futures")]
 pub mod futures;
 
+pub mod filters;
+
+pub mod compat;
+
+#[cfg(feature = "steer")]
+pub mod interop;
+
+#[cfg(feature = "macros")]
+mod macros;
+
+mod filter_chain;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use tower::Layer;
+
+    #[cfg(feature = "macros")]
+    pub use crate::macros::glob_matches;
+    #[cfg(feature = "macros")]
+    pub use http;
+}
+
+#[cfg(feature = "derive")]
+pub use tower_fallthrough_filter_derive::Filter;
+
+pub use after_call::AfterCallFilterService;
+
+mod after_call;
+
+pub use before_call::BeforeCallFilterService;
+
+mod before_call;
+
+#[cfg(feature = "async")]
+pub use borrowing::{BorrowingAsyncFilter, BorrowingAsyncFilterLayer, BorrowingAsyncFilterService};
+
+#[cfg(feature = "async")]
+mod borrowing;
+
+#[cfg(feature = "boxed")]
+pub use boxed::{BoxCloneLayer, DynFilterLayer, DynFilterService};
+
+#[cfg(feature = "boxed")]
+mod boxed;
+
+#[cfg(feature = "buffer")]
+pub use buffered::BufferedFilterLayer;
+
+#[cfg(feature = "buffer")]
+mod buffered;
+
+mod catch_panic;
+
+#[cfg(any(feature = "async", feature = "lazy"))]
+mod ready;
+
+#[cfg(feature = "chain")]
+pub use chain::{ChainDecision, FilterChain, FilterChainHandle, FilterChainService};
+
+#[cfg(feature = "chain")]
+mod chain;
+
+#[cfg(feature = "circuit-breaker")]
+pub use circuit_breaker::{
+    CircuitBreakerFilter, CircuitBreakerFilterLayer, CircuitBreakerFilterService,
+    CircuitBreakerService, CircuitState,
+};
+
+#[cfg(feature = "circuit-breaker")]
+mod circuit_breaker;
+
+pub use conditional::ConditionalFilterLayer;
+
+#[doc(hidden)]
+pub use conditional::{Enabled, Toggle};
+
+mod conditional;
+
+#[cfg(feature = "async")]
+pub use consuming::{ConsumingFilter, ConsumingFilterLayer, ConsumingFilterService};
+
+#[cfg(feature = "async")]
+mod consuming;
+
+pub use deferred::{DeferredErrorFilterLayer, DeferredErrorFilterService};
+
+mod deferred;
+
+pub use dyn_filter::{BoxFilter, DynFilter};
+
+#[cfg(feature = "async")]
+pub use dyn_filter::{BoxAsyncFilter, DynAsyncFilter};
+
+mod dyn_filter;
+
+pub use ext::{Filtered, FilteredServiceExt};
+
+#[cfg(feature = "async")]
+pub use ext::AsyncFiltered;
+
+mod ext;
+
+#[cfg(feature = "fallback")]
+pub use fallback::{FallbackChain, FallbackChainLayer};
+
+#[cfg(feature = "fallback")]
+mod fallback;
+
+pub use filter_fn::{filter_fn, FilterFn};
+
+#[cfg(feature = "async")]
+pub use filter_fn::{async_filter_fn, AsyncFilterFn};
+
+mod filter_fn;
+
+pub use invert::{InvertFilterService, InvertLayer, InvertedFilter};
+
+mod invert;
+
+#[cfg(feature = "lazy")]
+pub use lazy::{
+    IsolatedFilterService, LazyFilterService, ReadyPolicy, ReadyPolicyFilterLayer,
+    ReadyPolicyFilterService,
+};
+
+#[cfg(feature = "lazy")]
+mod lazy;
+
+#[cfg(feature = "make")]
+pub use make::MakeFilterService;
+
+#[cfg(feature = "make")]
+mod make;
+
+pub use peek::{PeekFilter, PeekFilterLayer, PeekFilterService};
+
+mod peek;
+
+#[cfg(feature = "recording")]
+pub use recording::{RecordedCall, Recording, RecordingFilterLayer, RecordingFilterService};
+
+#[cfg(feature = "recording")]
+mod recording;
+
+#[cfg(feature = "retry")]
+pub use retry::{RetryFilterLayer, RetryFilterService};
+
+#[cfg(feature = "retry")]
+mod retry;
+
+#[cfg(all(feature = "async", feature = "chain"))]
+pub use concurrent::{ConcurrentFilterLayer, ConcurrentFilterService};
+
+#[cfg(all(feature = "async", feature = "chain"))]
+mod concurrent;
+
+#[cfg(feature = "http")]
+pub use annotate::{
+    AnnotateFuture, AnnotateService, AnnotatedFilterLayer, AnnotatedLayer, ExperimentAssignment,
+    X_EXPERIMENT,
+};
+
+#[cfg(all(feature = "http", feature = "async"))]
+pub use annotate::AnnotatedAsyncFilterLayer;
+
+#[cfg(feature = "http")]
+mod annotate;
+
 #[cfg(feature = "async")]
 pub use async_feature::{AsyncFilter, AsyncFilterLayer, AsyncFilterService};
 
+#[cfg(feature = "async")]
+pub use futures::Cancellation;
+
+#[cfg(feature = "async")]
+pub use local::{LocalAsyncFilter, LocalAsyncFilterLayer, LocalAsyncFilterService};
+
+#[cfg(all(feature = "async", feature = "http"))]
+pub use async_feature::HttpAsyncFilterService;
+
 #[cfg(feature = "async")]
 mod async_feature;
 
+#[cfg(feature = "async")]
+mod local;
+
+#[cfg(all(feature = "async", feature = "chain"))]
+pub use async_chain::{AsyncFilterChain, AsyncFilterChainService};
+
+#[cfg(all(feature = "async", feature = "chain"))]
+mod async_chain;
+
+#[cfg(feature = "metrics")]
+pub use metered::{
+    AtomicMetrics, FilterMetrics, MeteredFilter, MeteredFilterLayer, MetricsFilterLayer,
+    MetricsFilterLayerBuilder, MetricsFilterMetrics,
+};
+
+#[cfg(all(feature = "metrics", feature = "async"))]
+pub use metered::{MeteredAsyncFilter, MetricsAsyncFilterLayer};
+
+#[cfg(feature = "metrics")]
+mod metered;
+
+#[cfg(feature = "rand")]
+pub use weighted::{WeightedSelectLayer, WeightedSelectService, WeightsHandle};
+
+#[cfg(feature = "rand")]
+mod rng;
+
+pub use select_n::{IndexFilter, SelectNLayer, SelectNService};
+
+mod select_n;
+
+#[cfg(feature = "service-map")]
+pub use service_map::{KeyFilter, ServiceMapHandle, ServiceMapLayer, ServiceMapService};
+
+#[cfg(feature = "service-map")]
+mod service_map;
+
+pub use shared::{ShareableFilter, SharedFilter, SharedState};
+
+#[cfg(feature = "async")]
+pub use shared::ShareableAsyncFilter;
+
+mod shared;
+
+pub use stateful::{StatefulFilter, StatefulFilterLayer};
+
+#[cfg(feature = "async")]
+pub use stateful::AsyncStatefulFilter;
+
+mod stateful;
+
+#[cfg(feature = "spawn")]
+pub use spawned::{SpawnedAsyncFilterLayer, SpawnedAsyncFilterService};
+
+#[cfg(feature = "spawn")]
+mod spawned;
+
+#[cfg(feature = "stream")]
+pub use stream::FilterStream;
+
+#[cfg(feature = "stream")]
+mod stream;
+
+#[cfg(feature = "retry")]
+pub use try_async_filter::{
+    AsyncTryFilterLayer, AsyncTryFilterService, TryAsyncFilter, TryDecision,
+};
+
+#[cfg(feature = "retry")]
+mod try_async_filter;
+
+pub use try_filter::{TryFilter, TryFilterError, TryFilterLayer, TryFilterService};
+
+mod try_filter;
+
+#[cfg(feature = "rand")]
+mod weighted;
+
 /// A filter that allows a service to be executed based on a condition
 ///
 /// # Example
@@ -36,7 +301,41 @@ mod async_feature;
 /// let filter = MyFilter;
 /// assert_eq!(filter.matches(&()), true);
 /// ```
-pub trait Filter<T>: Clone {
+///
+/// # Composing filters
+/// Filters are combined in a [`FilterFn`], so the composition reads
+/// like the condition it checks. [`InvertedFilter`] negates a filter.
+/// ```rust
+/// use tower_fallthrough_filter::{Filter, FilterFn, InvertedFilter};
+///
+/// #[derive(Clone)]
+/// struct IsApi;
+///
+/// impl Filter<(&str, &str)> for IsApi {
+///     fn matches(&self, (_, path): &(&str, &str)) -> bool {
+///         path.starts_with("/api")
+///     }
+/// }
+///
+/// #[derive(Clone)]
+/// struct IsAdmin;
+///
+/// impl Filter<(&str, &str)> for IsAdmin {
+///     fn matches(&self, (user, _): &(&str, &str)) -> bool {
+///         *user == "admin"
+///     }
+/// }
+///
+/// // NOTE: API requests by anyone but the admin.
+/// let restricted = FilterFn::new(|req: &(&str, &str)| {
+///     IsApi.matches(req) && InvertedFilter(IsAdmin).matches(req)
+/// });
+///
+/// assert!(restricted.matches(&("guest", "/api/users")));
+/// assert!(!restricted.matches(&("admin", "/api/users")));
+/// assert!(!restricted.matches(&("guest", "/about")));
+/// ```
+pub trait Filter<T: ?Sized>: Clone {
     /// Whether the service should be executed
     ///
     /// If `true`, the service will be executed,  otherwise it will
@@ -44,6 +343,29 @@ pub trait Filter<T>: Clone {
     fn matches(&self, item: &T) -> bool;
 }
 
+/// Always or never matches, depending on the value.
+///
+/// See [`ConstFilter`](filters::ConstFilter) to decide at compile time.
+impl<T> Filter<T> for bool {
+    fn matches(&self, _: &T) -> bool {
+        *self
+    }
+}
+
+/// Matches strings containing the given one.
+impl Filter<String> for String {
+    fn matches(&self, item: &String) -> bool {
+        item.contains(self.as_str())
+    }
+}
+
+/// Calls the function, e.g. to choose the filter at runtime.
+impl<T> Filter<T> for Arc<dyn Fn(&T) -> bool + Send + Sync> {
+    fn matches(&self, item: &T) -> bool {
+        self(item)
+    }
+}
+
 /// A Tower layer that executes the provided service only
 /// if the given filter returns true.
 /// Otherwise it falls through to the inner server.
@@ -93,37 +415,110 @@ pub trait Filter<T>: Clone {
 ///     assert_eq!(middleware.call(true).await, Ok("A".to_string()));
 ///     assert_eq!(middleware.call(false).await, Ok("B".to_string()));
 /// }
+/// ```
+///
+/// # Rejecting instead of falling through
+/// To either handle or reject a request, e.g. when there is no
+/// service to fall through to, use an inner service returning
+/// an error:
+/// ```rust
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{Filter, FilterLayer};
+///
+/// #[derive(Clone)]
+/// struct IsAdmin;
+///
+/// impl Filter<&'static str> for IsAdmin {
+///     fn matches(&self, user: &&'static str) -> bool {
+///         *user == "admin"
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let dashboard = service_fn(|_: &'static str| async { Ok("dashboard") });
+/// let reject = service_fn(|_: &'static str| async { Err("forbidden") });
+///
+/// let service = FilterLayer::new(IsAdmin, dashboard).layer(reject);
+/// assert_eq!(service.clone().oneshot("admin").await, Ok("dashboard"));
+/// assert_eq!(service.oneshot("guest").await, Err("forbidden"));
+/// # }
+/// ```
+///
+/// # Reconnecting backends
+/// The inner service doesn't have to be clonable, so a remote backend
+/// can be wrapped in a `tower::reconnect::Reconnect`, which connects
+/// again using the given `MakeService` once the connection failed.
+///
+/// `Reconnect` reports connection errors as `tower::BoxError`, so the
+/// filtered service has to use it as well, e.g. using
+/// `ServiceExt::map_err`. A failed connection attempt is only returned
+/// by the next request falling through, requests matching the filter
+/// are served in the meantime.
+/// ```rust
+/// use tower::{
+///     reconnect::Reconnect, service_fn, util::BoxCloneService, BoxError, Layer, Service, ServiceExt,
+/// };
+/// use tower_fallthrough_filter::{Filter, FilterLayer};
+///
+/// #[derive(Clone)]
+/// struct IsCached;
+///
+/// impl Filter<&'static str> for IsCached {
+///     fn matches(&self, path: &&'static str) -> bool {
+///         path.starts_with("/static")
+///     }
+/// }
+///
+/// type Backend = BoxCloneService<&'static str, &'static str, BoxError>;
 ///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let cache = service_fn(|_: &'static str| async { Ok::<_, BoxError>("cache") });
+/// // NOTE: Connects to the backend, e.g. using a TCP connection.
+/// let connect = service_fn(|addr: &'static str| {
+///     let backend: Backend =
+///         BoxCloneService::new(service_fn(move |_: &'static str| async move { Ok(addr) }));
+///     // NOTE: `Reconnect` requires the connection future to be `Unpin`.
+///     std::future::ready(Ok::<_, BoxError>(backend))
+/// });
+///
+/// let backend = Reconnect::new::<Backend, &'static str>(connect, "backend:8080");
+/// let mut service = FilterLayer::new(IsCached, cache).layer(backend);
+///
+/// assert_eq!(service.ready().await.unwrap().call("/static/app.js").await.unwrap(), "cache");
+/// assert_eq!(service.ready().await.unwrap().call("/users").await.unwrap(), "backend:8080");
+/// # }
+/// ```
 #[derive(Debug)]
-pub struct FilterLayer<F, S, T, R, E>
-where
-    F: Filter<T>,
-    S: Service<T, Response = R, Error = E>,
-{
+pub struct FilterLayer<F, S, T> {
     filter: F,
     service: S,
+    failover_on_pending: bool,
+    catch_panics: CatchPanics,
 
-    _marker: PhantomData<(T, R, E)>,
+    // NOTE: A function pointer is used, here and in the other layers and
+    //       services, so they stay `Send + Sync` even if the request type,
+    //       e.g. a streaming body, isn't `Sync`.
+    _marker: PhantomData<fn(T)>,
 }
 
 // NOTE: This is required to make the `FilterLayer` clonable
 //       as the `PhantomData` might be not clonable.
-impl<F, S, R, E, T> Clone for FilterLayer<F, S, T, R, E>
-where
-    F: Filter<T> + Clone,
-    S: Service<T, Response = R, Error = E> + Clone,
-{
+impl<F: Clone, S: Clone, T> Clone for FilterLayer<F, S, T> {
     fn clone(&self) -> Self {
         Self {
             filter: self.filter.clone(),
             service: self.service.clone(),
+            failover_on_pending: self.failover_on_pending,
+            catch_panics: self.catch_panics.clone(),
 
             _marker: PhantomData,
         }
     }
 }
 
-impl<F: Filter<T>, S: Service<T>, T> FilterLayer<F, S, T, S::Response, S::Error> {
+impl<F: Filter<T>, S: Service<T>, T> FilterLayer<F, S, T> {
     /// Creates a new FilterLayer given a `Service` and a `Filter`.
     ///
     /// NOTE: The Service and the Filter have to operate on the same
@@ -132,19 +527,235 @@ impl<F: Filter<T>, S: Service<T>, T> FilterLayer<F, S, T, S::Response, S::Error>
         Self {
             filter,
             service,
+            failover_on_pending: false,
+            catch_panics: CatchPanics::default(),
 
             _marker: PhantomData,
         }
     }
+
+    /// Whether requests should fall through to the inner service
+    /// while the filtered service isn't ready, even if they match.
+    ///
+    /// By default the created service is only ready once both services
+    /// are. With failover enabled only the inner service has to be
+    /// ready, the filtered service is polled once without waiting for
+    /// it, e.g. to use a cache only while it keeps up with the load.
+    ///
+    /// NOTE: A readiness of the filtered service that wasn't used,
+    /// as the request didn't match, is kept for the next request.
+    pub fn failover_on_pending(mut self, enabled: bool) -> Self {
+        self.failover_on_pending = enabled;
+        self
+    }
+
+    /// Whether a panicking filter should be treated as not matching,
+    /// so the request falls through instead of the panic unwinding
+    /// through the service, e.g. taking down the connection.
+    ///
+    /// Disabled by default, so buggy filters stay loud in development.
+    /// The panic is still reported by the panic hook of the process,
+    /// see [`FilterLayer::on_panic`] to handle the payload as well.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tower::{service_fn, Layer, ServiceExt};
+    /// use tower_fallthrough_filter::{FilterFn, FilterLayer};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let respond = |name| service_fn(move |_: &'static str| async move { Ok::<_, ()>(name) });
+    ///
+    /// let page = FilterFn::new(|query: &&'static str| query.parse::<u32>().expect("a page") > 0);
+    /// let service = FilterLayer::new(page, respond("page"))
+    ///     .catch_panics(true)
+    ///     .layer(respond("index"));
+    ///
+    /// assert_eq!(service.clone().oneshot("2").await, Ok("page"));
+    /// assert_eq!(service.oneshot("two").await, Ok("index"));
+    /// # }
+    /// ```
+    pub fn catch_panics(mut self, enabled: bool) -> Self {
+        self.catch_panics.enable(enabled);
+        self
+    }
+
+    /// Sets a hook called with the payload of a panicking filter,
+    /// e.g. to log it, if [`FilterLayer::catch_panics`] is enabled.
+    pub fn on_panic(mut self, hook: impl Fn(&(dyn Any + Send)) + Send + Sync + 'static) -> Self {
+        self.catch_panics.set_hook(hook);
+        self
+    }
+
+    /// Creates the service falling through to `inner`, like
+    /// `Layer::layer` but consuming the layer instead of cloning
+    /// the filter and the service.
+    pub fn with_fallthrough<I>(self, inner: I) -> FilterService<F, S, I, T>
+    where
+        I: Service<T, Response = S::Response, Error = S::Error>,
+    {
+        let mut service = FilterService::new(self.filter, self.service, inner);
+        service.failover_on_pending = self.failover_on_pending;
+        service.catch_panics = self.catch_panics;
+        service
+    }
 }
 
-impl<F, S, I, T, R, E> Layer<I> for FilterLayer<F, S, T, R, E>
+impl<F, S, T> FilterLayer<F, S, T> {
+    /// The filter deciding whether the service is executed.
+    pub fn filter(&self) -> &F {
+        &self.filter
+    }
+
+    /// A mutable reference to the filter.
+    pub fn filter_mut(&mut self) -> &mut F {
+        &mut self.filter
+    }
+
+    /// The service executed if the filter matches.
+    pub fn matched_service_ref(&self) -> &S {
+        &self.service
+    }
+
+    /// A mutable reference to the service executed if the filter matches.
+    pub fn matched_service_mut(&mut self) -> &mut S {
+        &mut self.service
+    }
+
+    /// Returns the filter and the service.
+    pub fn into_parts(self) -> (F, S) {
+        (self.filter, self.service)
+    }
+
+    /// Replaces the filter with the result of `f`,
+    /// e.g. to wrap it in another filter.
+    pub fn map_filter<F2: Filter<T>>(self, f: impl FnOnce(F) -> F2) -> FilterLayer<F2, S, T> {
+        FilterLayer {
+            filter: f(self.filter),
+            service: self.service,
+            failover_on_pending: self.failover_on_pending,
+            catch_panics: self.catch_panics,
+
+            _marker: PhantomData,
+        }
+    }
+
+    /// Replaces the service executed if the filter matches with the
+    /// result of `f`, e.g. to apply middleware only to that service.
+    ///
+    /// As there is no inner service yet, it can be mapped using
+    /// [`FilterService::map_inner`] once the layer is applied,
+    /// or before passing it to [`Layer::layer`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tower::{Layer, ServiceBuilder, ServiceExt};
+    /// # use tower::service_fn;
+    /// # use tower_fallthrough_filter::{Filter, FilterLayer};
+    /// #
+    /// # #[derive(Clone)]
+    /// # struct IsEven;
+    /// #
+    /// # impl Filter<u32> for IsEven {
+    /// #     fn matches(&self, item: &u32) -> bool {
+    /// #         item.is_multiple_of(2)
+    /// #     }
+    /// # }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let echo = service_fn(|n: u32| async move { Ok::<_, ()>(n) });
+    ///
+    /// let service = FilterLayer::new(IsEven, echo)
+    ///     .map_service(|service| ServiceBuilder::new().map_response(|n: u32| n / 2).service(service))
+    ///     .layer(echo);
+    ///
+    /// assert_eq!(service.clone().oneshot(4).await, Ok(2));
+    /// assert_eq!(service.oneshot(3).await, Ok(3));
+    /// # }
+    /// ```
+    pub fn map_service<S2: Service<T>>(self, f: impl FnOnce(S) -> S2) -> FilterLayer<F, S2, T> {
+        FilterLayer {
+            filter: self.filter,
+            service: f(self.service),
+            failover_on_pending: self.failover_on_pending,
+            catch_panics: self.catch_panics,
+
+            _marker: PhantomData,
+        }
+    }
+
+    /// Applies `middleware`, e.g. one created by axum's
+    /// `middleware::from_fn`, above the filter, so it sees every
+    /// request before the filter does, no matter which service handles
+    /// it, and may change what the filter decides on.
+    ///
+    /// Use [`FilterLayer::below`] to only apply it to the matching
+    /// requests instead.
+    ///
+    /// # Example
+    /// ```rust
+    /// use axum::{extract::Request, middleware::{self, Next}, response::Response, routing::get, Router};
+    /// use tower_fallthrough_filter::{FilterFn, FilterLayer};
+    ///
+    /// async fn log(req: Request, next: Next) -> Response {
+    ///     println!("{} {}", req.method(), req.uri());
+    ///     next.run(req).await
+    /// }
+    ///
+    /// let is_page = FilterFn::new(|req: &Request| !req.uri().path().starts_with("/api"));
+    /// let pages = Router::new().fallback(get(|| async { "page" }));
+    ///
+    /// // NOTE: Logs the requests of both the pages and the API.
+    /// let app: Router = Router::new()
+    ///     .route("/api/users", get(|| async { "users" }))
+    ///     .layer(FilterLayer::new(is_page, pages).above(middleware::from_fn(log)));
+    /// ```
+    pub fn above<L>(self, middleware: L) -> tower::layer::util::Stack<Self, L> {
+        tower::layer::util::Stack::new(self, middleware)
+    }
+
+    /// Applies `middleware`, e.g. one created by axum's
+    /// `middleware::from_fn`, below the filter, wrapping only the
+    /// service executed if the filter matches. So it neither sees the
+    /// requests falling through nor influences the filter.
+    ///
+    /// Use [`FilterLayer::above`] to apply it to every request instead.
+    ///
+    /// # Example
+    /// ```rust
+    /// use axum::{extract::Request, middleware::{self, Next}, response::Response, routing::get, Router};
+    /// use tower_fallthrough_filter::{FilterFn, FilterLayer};
+    ///
+    /// async fn log(req: Request, next: Next) -> Response {
+    ///     println!("{} {}", req.method(), req.uri());
+    ///     next.run(req).await
+    /// }
+    ///
+    /// let is_page = FilterFn::new(|req: &Request| !req.uri().path().starts_with("/api"));
+    /// let pages = Router::new().fallback(get(|| async { "page" }));
+    ///
+    /// // NOTE: Only logs the requests of the pages.
+    /// let app: Router = Router::new()
+    ///     .route("/api/users", get(|| async { "users" }))
+    ///     .layer(FilterLayer::new(is_page, pages).below(middleware::from_fn(log)));
+    /// ```
+    pub fn below<L>(self, middleware: L) -> FilterLayer<F, L::Service, T>
+    where
+        L: Layer<S>,
+        L::Service: Service<T>,
+    {
+        self.map_service(|service| middleware.layer(service))
+    }
+}
+
+impl<F, S, I, T> Layer<I> for FilterLayer<F, S, T>
 where
-    F: Filter<T> + Clone,
-    S: Service<T, Response = R, Error = E> + Clone,
-    I: Service<T, Response = R, Error = E> + Clone,
+    F: Filter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
 {
-    type Service = FilterService<F, S, I, T, R, E>;
+    type Service = FilterService<F, S, I, T>;
 
     fn layer(&self, inner_service: I) -> Self::Service {
         let filter = self.filter.clone();
@@ -154,6 +765,9 @@ where
             filter,
             service: filtered_service,
             inner: inner_service,
+            failover_on_pending: self.failover_on_pending,
+            catch_panics: self.catch_panics.clone(),
+            service_ready: false,
 
             _marker: PhantomData,
         }
@@ -161,52 +775,286 @@ where
 }
 
 #[derive(Debug)]
-pub struct FilterService<F, S, I, T, R, E>
-where
-    F: Filter<T>,
-    S: Service<T, Response = R, Error = E>,
-    I: Service<T, Response = R, Error = E>,
-{
+pub struct FilterService<F, S, I, T> {
     filter: F,
     service: S,
     inner: I,
+    failover_on_pending: bool,
+    catch_panics: CatchPanics,
+    // NOTE: Whether the filtered service reported to be ready, only
+    //       tracked when failing over, as it isn't required then.
+    service_ready: bool,
 
-    _marker: PhantomData<(T, R, E)>,
+    _marker: PhantomData<fn(T)>,
 }
 
+/// A [`FilterService`] handling `http::Request`s with a body of type `B`,
+/// e.g. to spell out the type of a struct field.
+#[cfg(feature = "http")]
+pub type HttpFilterService<F, S, I, B> = FilterService<F, S, I, http::Request<B>>;
+
 // NOTE: This is required to make the `FilterService` clonable
 //       as the `PhantomData` might be not clonable.
-impl<F, S, I, T, R, E> Clone for FilterService<F, S, I, T, R, E>
-where
-    F: Filter<T>,
-    S: Service<T, Response = R, Error = E> + Clone,
-    I: Service<T, Response = R, Error = E> + Clone,
-{
+impl<F: Clone, S: Clone, I: Clone, T> Clone for FilterService<F, S, I, T> {
     fn clone(&self) -> Self {
         Self {
             filter: self.filter.clone(),
             service: self.service.clone(),
             inner: self.inner.clone(),
+            failover_on_pending: self.failover_on_pending,
+            catch_panics: self.catch_panics.clone(),
+            // NOTE: The readiness belongs to the original service.
+            service_ready: false,
 
             _marker: PhantomData,
         }
     }
 }
 
-impl<F, S, I, T, R, E> Service<T> for FilterService<F, S, I, T, R, E>
+impl<F, S, I, T> FilterService<F, S, I, T>
 where
     F: Filter<T>,
-    S: Service<T, Response = R, Error = E>,
-    S::Future: Send + 'static,
-    I: Service<T, Response = R, Error = E>,
-    I::Future: Send + 'static,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    /// Creates a new FilterService given a `Filter`, the `Service` that
+    /// is executed if it matches and the `Service` to fall through to.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tower::{service_fn, ServiceExt};
+    /// use tower_fallthrough_filter::{Filter, FilterService};
+    ///
+    /// #[derive(Clone)]
+    /// struct IsEven;
+    ///
+    /// impl Filter<u32> for IsEven {
+    ///     fn matches(&self, item: &u32) -> bool {
+    ///         item.is_multiple_of(2)
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let respond = |name| service_fn(move |_: u32| async move { Ok::<_, ()>(name) });
+    ///
+    /// let service = FilterService::new(IsEven, respond("even"), respond("odd"));
+    /// assert_eq!(service.clone().oneshot(2).await, Ok("even"));
+    /// assert_eq!(service.oneshot(3).await, Ok("odd"));
+    /// # }
+    /// ```
+    pub fn new(filter: F, service: S, inner: I) -> Self {
+        Self {
+            filter,
+            service,
+            inner,
+            failover_on_pending: false,
+            catch_panics: CatchPanics::default(),
+            service_ready: false,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> FilterService<F, S, I, T> {
+    /// The filter deciding which service is executed.
+    pub fn filter(&self) -> &F {
+        &self.filter
+    }
+
+    /// A mutable reference to the filter.
+    pub fn filter_mut(&mut self) -> &mut F {
+        &mut self.filter
+    }
+
+    /// The service executed if the filter matches.
+    pub fn matched_service_ref(&self) -> &S {
+        &self.service
+    }
+
+    /// A mutable reference to the service executed if the filter matches.
+    ///
+    /// NOTE: As the service might be changed, e.g. replaced by one that
+    /// isn't ready, `poll_ready` has to be called again before the next
+    /// request, see [`FilterService::replace_service`].
+    pub fn matched_service_mut(&mut self) -> &mut S {
+        self.service_ready = false;
+        &mut self.service
+    }
+
+    /// The inner service requests fall through to.
+    pub fn fallthrough_ref(&self) -> &I {
+        &self.inner
+    }
+
+    /// A mutable reference to the inner service requests fall through to,
+    /// see [`FilterService::matched_service_mut`].
+    pub fn fallthrough_mut(&mut self) -> &mut I {
+        &mut self.inner
+    }
+
+    /// Returns the filter, the filtered and the inner service.
+    pub fn into_parts(self) -> (F, S, I) {
+        (self.filter, self.service, self.inner)
+    }
+
+    /// Replaces the filter with the result of `f`,
+    /// e.g. to wrap it in another filter.
+    pub fn map_filter<F2: Filter<T>>(self, f: impl FnOnce(F) -> F2) -> FilterService<F2, S, I, T> {
+        FilterService {
+            filter: f(self.filter),
+            service: self.service,
+            inner: self.inner,
+            failover_on_pending: self.failover_on_pending,
+            catch_panics: self.catch_panics,
+            service_ready: self.service_ready,
+
+            _marker: PhantomData,
+        }
+    }
+
+    /// Replaces the service executed if the filter matches with
+    /// the result of `f`, see [`FilterLayer::map_service`].
+    ///
+    /// NOTE: As with any service, `poll_ready` has to be called again
+    /// before the next request, so the new service gets ready.
+    pub fn map_service<S2: Service<T>>(
+        self,
+        f: impl FnOnce(S) -> S2,
+    ) -> FilterService<F, S2, I, T> {
+        FilterService {
+            filter: self.filter,
+            service: f(self.service),
+            inner: self.inner,
+            failover_on_pending: self.failover_on_pending,
+            catch_panics: self.catch_panics,
+            service_ready: false,
+
+            _marker: PhantomData,
+        }
+    }
+
+    /// Replaces the inner service requests fall through to with
+    /// the result of `f`, see [`FilterService::map_service`].
+    pub fn map_inner<I2: Service<T>>(self, f: impl FnOnce(I) -> I2) -> FilterService<F, S, I2, T> {
+        FilterService {
+            filter: self.filter,
+            service: self.service,
+            inner: f(self.inner),
+            failover_on_pending: self.failover_on_pending,
+            catch_panics: self.catch_panics,
+            service_ready: self.service_ready,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> FilterService<F, S, I, T>
+where
+    F: Filter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    /// Replaces the filtered service, e.g. to roll out a new version,
+    /// and returns the old one.
+    ///
+    /// Requests already dispatched are unaffected, so the old service
+    /// can be kept around to drain them before shutting it down.
+    ///
+    /// NOTE: As with any service, `poll_ready` has to be called again
+    /// before the next request, so the new service gets ready.
+    pub fn replace_service(&mut self, new_service: S) -> S {
+        self.service_ready = false;
+        std::mem::replace(&mut self.service, new_service)
+    }
+
+    /// Replaces the inner service requests fall through to and
+    /// returns the old one, see [`FilterService::replace_service`].
+    pub fn replace_inner(&mut self, new_inner: I) -> I {
+        std::mem::replace(&mut self.inner, new_inner)
+    }
+
+    /// Calls the filtered service if the filter matches, otherwise
+    /// fails with [`FilterOrError::FilterMiss`] instead of falling
+    /// through to the inner service.
+    ///
+    /// NOTE: With [`FilterLayer::failover_on_pending`] a matching request
+    /// also fails with `FilterMiss` while the filtered service isn't ready.
+    pub fn call_or_error(
+        &mut self,
+        req: T,
+    ) -> impl Future<Output = Result<S::Response, FilterOrError<S::Error>>> {
+        let matched = self.catch_panics.matches(&self.filter, &req);
+
+        if matched && self.service_available() {
+            self.service_ready = false;
+            Either::Left(self.service.call(req).map_err(FilterOrError::ServiceError))
+        } else {
+            Either::Right(future::ready(Err(FilterOrError::FilterMiss)))
+        }
+    }
+
+    // NOTE: The filtered service can always be called, unless it is
+    //       failing over and wasn't ready when last polled.
+    fn service_available(&self) -> bool {
+        !self.failover_on_pending || self.service_ready
+    }
+}
+
+/// The error returned by [`FilterService::call_or_error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOrError<E> {
+    /// The filter didn't match the request.
+    FilterMiss,
+    /// The filtered service failed.
+    ServiceError(E),
+}
+
+impl<E: fmt::Display> fmt::Display for FilterOrError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FilterMiss => f.write_str("the filter didn't match the request"),
+            Self::ServiceError(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for FilterOrError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::FilterMiss => None,
+            Self::ServiceError(err) => Some(err),
+        }
+    }
+}
+
+impl<F, S, I, T> Service<T> for FilterService<F, S, I, T>
+where
+    F: Filter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
 {
     type Response = S::Response;
     type Error = S::Error;
     type Future = Either<S::Future, I::Future>;
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "filter_service_poll_ready", level = "trace", skip_all)
+    )]
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        ready!(self.service.poll_ready(cx))?;
+        if !self.failover_on_pending {
+            ready!(self.service.poll_ready(cx))?;
+        } else if !self.service_ready {
+            // NOTE: Poll once without waiting, the requests fall through
+            //       as long as the filtered service isn't ready.
+            if let Poll::Ready(result) = self.service.poll_ready(cx) {
+                result?;
+                self.service_ready = true;
+            }
+        }
         // NOTE: It is probably best to poll the `inner_service` here as well
         //       as otherwise it might be called when it isn't ready yet.
         ready!(self.inner.poll_ready(cx))?;
@@ -215,7 +1063,25 @@ where
     }
 
     fn call(&mut self, req: T) -> Self::Future {
-        if self.filter.matches(&req) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "filter_service",
+            filter = %std::any::type_name::<F>()
+        )
+        .entered();
+
+        let matched = self.catch_panics.matches(&self.filter, &req);
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(matched = %matched);
+
+        if matched && !self.service_available() {
+            #[cfg(feature = "tracing")]
+            tracing::trace!("filtered service not ready, falling through");
+
+            Either::Right(self.inner.call(req))
+        } else if matched {
+            self.service_ready = false;
             Either::Left(self.service.call(req))
         } else {
             Either::Right(self.inner.call(req))
@@ -225,9 +1091,63 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::Ordering;
+
+    use ::futures::FutureExt;
+    use tower::{ServiceBuilder, ServiceExt};
+
     use super::*;
     use crate::test_util::*;
 
+    #[tokio::test]
+    async fn should_allow_unsend_futures() {
+        // NOTE: The futures hold an `Rc`, so they are neither `Send` nor `Sync`.
+        let respond = |n: u32| {
+            tower::service_fn(move |_: ()| {
+                let n = std::rc::Rc::new(n);
+                async move { Ok::<_, std::convert::Infallible>(*n) }
+            })
+        };
+
+        for (matches, expected) in [(true, 1), (false, 2)] {
+            let service = FilterLayer::new(TestFilter(matches), respond(1)).layer(respond(2));
+            assert_eq!(service.oneshot(()).await, Ok(expected));
+        }
+    }
+
+    #[test]
+    fn should_match_bool() {
+        assert!(true.matches(&()));
+        assert!(!false.matches(&"anything"));
+    }
+
+    #[test]
+    fn should_match_substring() {
+        let filter = String::from("/api");
+
+        assert!(filter.matches(&"/api/users".to_string()));
+        assert!(filter.matches(&"/v1/api".to_string()));
+        assert!(!filter.matches(&"/users".to_string()));
+    }
+
+    #[tokio::test]
+    async fn should_match_dyn_fn() {
+        type DynFilter = Arc<dyn Fn(&u32) -> bool + Send + Sync>;
+
+        let filters: Vec<DynFilter> = vec![
+            Arc::new(|n: &u32| *n > 10),
+            Arc::new(|n: &u32| n.is_multiple_of(2)),
+        ];
+
+        assert!(filters[0].matches(&11));
+        assert!(!filters[1].matches(&11));
+
+        let service =
+            FilterLayer::new(filters[1].clone(), TestService("even")).layer(TestService("odd"));
+        assert_eq!(service.clone().oneshot(2).await, Ok("even"));
+        assert_eq!(service.oneshot(3).await, Ok("odd"));
+    }
+
     #[tokio::test]
     async fn should_allow() {
         let service_a = TestService("a");
@@ -253,4 +1173,261 @@ mod tests {
 
         assert_eq!(middleware.call(()).await, Ok("b"));
     }
+
+    #[tokio::test]
+    async fn should_construct_without_layer() {
+        for matches in [true, false] {
+            let layered = FilterLayer::new(TestFilter(matches), TestService("a"))
+                .layer(TestService("b"))
+                .oneshot(())
+                .await;
+            let consumed = FilterLayer::new(TestFilter(matches), TestService("a"))
+                .with_fallthrough(TestService("b"))
+                .oneshot(())
+                .await;
+            let constructed =
+                FilterService::new(TestFilter(matches), TestService("a"), TestService("b"))
+                    .oneshot(())
+                    .await;
+
+            assert_eq!(layered, consumed);
+            assert_eq!(layered, constructed);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_keep_failover_with_fallthrough() {
+        let (service_a, _) = PendingService::new("a");
+        let mut middleware = FilterLayer::new(TestFilter(true), service_a)
+            .failover_on_pending(true)
+            .with_fallthrough(TestService("b"));
+
+        assert_eq!(middleware.ready().await.unwrap().call(()).await, Ok("b"));
+    }
+
+    #[tokio::test]
+    async fn should_mutate_parts() {
+        let mut middleware = FilterLayer::new(TestFilter(true), TestService("a"))
+            .map_filter(|TestFilter(matches)| TestFilter(!matches))
+            .layer(TestService("b"));
+
+        assert!(!middleware.filter().0);
+        assert_eq!(middleware.ready().await.unwrap().call(()).await, Ok("b"));
+
+        middleware.fallthrough_mut().0 = "c";
+        assert_eq!(middleware.ready().await.unwrap().call(()).await, Ok("c"));
+
+        middleware.filter_mut().0 = true;
+        middleware.matched_service_mut().0 = "d";
+        assert_eq!(middleware.ready().await.unwrap().call(()).await, Ok("d"));
+
+        let mut middleware = middleware.map_filter(|_| TestFilter(false));
+        assert_eq!(middleware.ready().await.unwrap().call(()).await, Ok("c"));
+
+        let (filter, service, inner) = middleware.into_parts();
+        assert!(!filter.0);
+        assert_eq!((service.0, inner.0), ("d", "c"));
+    }
+
+    #[tokio::test]
+    async fn should_map_services() {
+        let ten_times = || ServiceBuilder::new().map_response(|n: u32| n * 10);
+
+        let layer = FilterLayer::new(TestFilter(true), TestService(1))
+            .map_service(|service| ten_times().service(service));
+        assert_eq!(
+            layer.clone().layer(TestService(2)).oneshot(()).await,
+            Ok(10)
+        );
+
+        let middleware = layer
+            .map_filter(|_| TestFilter(false))
+            .layer(TestService(2))
+            .map_inner(|inner| ten_times().service(inner));
+        assert_eq!(middleware.clone().oneshot(()).await, Ok(20));
+
+        let middleware = middleware
+            .map_service(|_| TestService(3))
+            .map_filter(|_| TestFilter(true));
+        assert_eq!(middleware.oneshot(()).await, Ok(3));
+    }
+
+    #[tokio::test]
+    async fn should_layer_by_reference() {
+        // NOTE: `Layer` is implemented for references by `tower` itself.
+        fn apply<L: Layer<TestService<&'static str>>>(layer: L, name: &'static str) -> L::Service {
+            layer.layer(TestService(name))
+        }
+
+        let layer = FilterLayer::new(TestFilter(false), TestService("a"));
+        let first = apply(&layer, "b");
+        let second = apply(&layer, "c");
+
+        assert_eq!(first.oneshot(()).await, Ok("b"));
+        assert_eq!(second.oneshot(()).await, Ok("c"));
+    }
+
+    #[tokio::test]
+    async fn should_accept_service_fn() {
+        // NOTE: `service_fn` is `Clone` as long as the closure is,
+        //       so no extra wrapping is required to use it here.
+        let service_a = tower::service_fn(|req: bool| async move { Ok::<_, ()>(req) });
+        let service_b = tower::service_fn(|req: bool| async move { Ok::<_, ()>(!req) });
+
+        let filter = TestFilter(true);
+        let filter_layer = FilterLayer::new(filter, service_a);
+
+        let mut middleware = filter_layer.layer(service_b);
+
+        assert_eq!(middleware.call(true).await, Ok(true));
+    }
+
+    #[tokio::test]
+    async fn should_replace_services() {
+        let filter_layer = FilterLayer::new(TestFilter(true), TestService("v1"));
+        let mut middleware = filter_layer.layer(TestService("inner v1"));
+
+        let in_flight = middleware.call(());
+
+        let mut old = middleware.replace_service(TestService("v2"));
+        let mut old_inner = middleware.replace_inner(TestService("inner v2"));
+
+        assert_eq!(middleware.call(()).await, Ok("v2"));
+        assert_eq!(in_flight.await, Ok("v1"));
+
+        // NOTE: The old services are still usable, e.g. to drain them.
+        assert_eq!(old.call(()).await, Ok("v1"));
+        assert_eq!(old_inner.call(()).await, Ok("inner v1"));
+
+        middleware.filter = TestFilter(false);
+        assert_eq!(middleware.call(()).await, Ok("inner v2"));
+    }
+
+    #[tokio::test]
+    async fn should_call_or_error() {
+        let filter_layer = FilterLayer::new(TestFilter(true), TestService("a"));
+        let mut middleware = filter_layer.layer(TestService("b"));
+
+        assert_eq!(middleware.call_or_error(()).await, Ok("a"));
+
+        middleware.filter = TestFilter(false);
+        assert_eq!(
+            middleware.call_or_error(()).await,
+            Err(FilterOrError::FilterMiss)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_wrap_service_errors() {
+        let failing = tower::service_fn(|_: ()| async { Err::<&str, _>("unavailable") });
+        let mut middleware = FilterLayer::new(TestFilter(true), failing)
+            .layer(tower::service_fn(|_: ()| async { Ok::<_, &str>("inner") }));
+
+        assert_eq!(
+            middleware.call_or_error(()).await,
+            Err(FilterOrError::ServiceError("unavailable"))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_wait_for_both_services_by_default() {
+        let (service_a, _) = PendingService::new("a");
+        let mut middleware = FilterLayer::new(TestFilter(true), service_a).layer(TestService("b"));
+
+        assert!(ServiceExt::<()>::ready(&mut middleware)
+            .now_or_never()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn should_fail_over_while_pending() {
+        let (service_a, ready) = PendingService::new("a");
+        let filter_layer = FilterLayer::new(TestFilter(true), service_a).failover_on_pending(true);
+        let mut middleware = filter_layer.layer(TestService("b"));
+
+        assert_eq!(middleware.ready().await.unwrap().call(()).await, Ok("b"));
+        assert_eq!(middleware.ready().await.unwrap().call(()).await, Ok("b"));
+
+        ready.store(true, Ordering::SeqCst);
+        assert_eq!(middleware.ready().await.unwrap().call(()).await, Ok("a"));
+    }
+
+    #[tokio::test]
+    async fn should_keep_unused_readiness() {
+        let (service_a, ready) = PendingService::new("a");
+        let filter_layer = FilterLayer::new(TestFilter(false), service_a).failover_on_pending(true);
+        let mut middleware = filter_layer.layer(TestService("b"));
+
+        ready.store(true, Ordering::SeqCst);
+        assert_eq!(middleware.ready().await.unwrap().call(()).await, Ok("b"));
+
+        // NOTE: The service got ready while the request didn't match,
+        //       so it is used even though it isn't ready anymore.
+        ready.store(false, Ordering::SeqCst);
+        middleware.filter = TestFilter(true);
+        assert_eq!(middleware.ready().await.unwrap().call(()).await, Ok("a"));
+        assert_eq!(middleware.ready().await.unwrap().call(()).await, Ok("b"));
+    }
+
+    #[tokio::test]
+    async fn should_shed_load_to_fallthrough() {
+        use tower::limit::ConcurrencyLimit;
+
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let released = released.shared();
+        let slow = tower::service_fn(move |_: ()| {
+            let released = released.clone();
+            async move {
+                let _ = released.await;
+                Ok::<_, std::convert::Infallible>("a")
+            }
+        });
+
+        let service = FilterLayer::new(TestFilter(true), ConcurrencyLimit::new(slow, 1))
+            .failover_on_pending(true)
+            .layer(TestService("b"));
+
+        // NOTE: The first request holds the only permit until released,
+        //       so the second one falls through instead of queueing.
+        let first = tokio::spawn(service.clone().oneshot(()));
+        tokio::task::yield_now().await;
+        assert_eq!(service.clone().oneshot(()).await, Ok("b"));
+
+        release.send(()).unwrap();
+        assert_eq!(first.await.unwrap(), Ok("a"));
+        assert_eq!(service.oneshot(()).await, Ok("a"));
+    }
+
+    #[tokio::test]
+    async fn should_fall_through_when_filter_panics() {
+        let panics = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let hook_panics = panics.clone();
+        let layer = FilterLayer::new(PanicFilter { on_poll: false }, TestService("a"))
+            .catch_panics(true)
+            .on_panic(move |payload| {
+                let message = payload.downcast_ref::<&str>().copied();
+                hook_panics.lock().unwrap().push(message);
+            });
+
+        let mut service = layer.layer(TestService("b"));
+        assert_eq!(service.ready().await.unwrap().call(()).await, Ok("b"));
+        assert_eq!(
+            service.call_or_error(()).await,
+            Err(FilterOrError::FilterMiss)
+        );
+
+        assert_eq!(
+            *panics.lock().unwrap(),
+            [Some("PanicFilter evaluated"), Some("PanicFilter evaluated")]
+        );
+    }
+
+    #[test]
+    fn should_not_catch_panics_by_default() {
+        let mut service = FilterLayer::new(PanicFilter { on_poll: false }, TestService("a"))
+            .layer(TestService("b"));
+
+        let called = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| service.call(())));
+        assert!(called.is_err());
+    }
 }
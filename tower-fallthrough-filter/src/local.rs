@@ -0,0 +1,217 @@
+use std::{
+    future::Future,
+    marker::PhantomData,
+    task::{Context, Poll},
+};
+
+use futures::ready;
+use tower::{Layer, Service};
+
+use crate::{futures::SelectServiceAndCallFut, ready::take_ready};
+
+/// The counterpart of [`AsyncFilter`](crate::AsyncFilter) for futures that aren't `Send`,
+/// e.g. on wasm32 or within a `tokio::task::LocalSet`.
+///
+/// # Example
+/// ```rust
+/// use std::{future::Future, rc::Rc};
+///
+/// use tower_fallthrough_filter::LocalAsyncFilter;
+///
+/// #[derive(Clone)]
+/// struct IsAdmin;
+///
+/// impl LocalAsyncFilter<Rc<str>> for IsAdmin {
+///     type Future = std::pin::Pin<Box<dyn Future<Output = bool>>>;
+///
+///     fn matches(&self, user: &Rc<str>) -> Self::Future {
+///         // NOTE: The `Rc` makes the future `!Send`.
+///         let user = user.clone();
+///         Box::pin(async move { &*user == "admin" })
+///     }
+/// }
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// assert!(IsAdmin.matches(&Rc::from("admin")).await);
+/// # }
+/// ```
+pub trait LocalAsyncFilter<T>: Clone {
+    type Future: Future<Output = bool>;
+
+    fn matches(&self, item: &T) -> Self::Future;
+}
+
+/// The counterpart of [`AsyncFilterLayer`](crate::AsyncFilterLayer)
+/// for a [`LocalAsyncFilter`], without requiring anything to be `Send`.
+///
+/// The fallthrough and readiness behave just like the ones of the
+/// `AsyncFilterLayer`.
+#[derive(Debug)]
+pub struct LocalAsyncFilterLayer<F, S, T> {
+    filter: F,
+    service: S,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, T> Clone for LocalAsyncFilterLayer<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: LocalAsyncFilter<T>, S: Service<T>, T> LocalAsyncFilterLayer<F, S, T> {
+    /// Creates a new LocalAsyncFilterLayer given a `LocalAsyncFilter`
+    /// and a `Service`.
+    pub fn new(filter: F, service: S) -> Self {
+        Self {
+            filter,
+            service,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> Layer<I> for LocalAsyncFilterLayer<F, S, T>
+where
+    F: LocalAsyncFilter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = LocalAsyncFilterService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        LocalAsyncFilterService::new(self.filter.clone(), self.service.clone(), inner_service)
+    }
+}
+
+#[derive(Debug)]
+pub struct LocalAsyncFilterService<F, S, I, T> {
+    filter: F,
+    service: S,
+    inner: I,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F, S, I, T> LocalAsyncFilterService<F, S, I, T>
+where
+    F: LocalAsyncFilter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    /// Creates a new LocalAsyncFilterService given a `LocalAsyncFilter`,
+    /// the `Service` that is executed if it matches and the `Service`
+    /// to fall through to.
+    pub fn new(filter: F, service: S, inner: I) -> Self {
+        Self {
+            filter,
+            service,
+            inner,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: Clone, S: Clone, I: Clone, T> Clone for LocalAsyncFilterService<F, S, I, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: self.inner.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> Service<T> for LocalAsyncFilterService<F, S, I, T>
+where
+    F: LocalAsyncFilter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = SelectServiceAndCallFut<F::Future, S, I, T, S::Response, S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.service.poll_ready(cx))?;
+        ready!(self.inner.poll_ready(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        let matches = self.filter.matches(&req);
+        let service = take_ready(&mut self.service);
+        let inner = take_ready(&mut self.inner);
+
+        SelectServiceAndCallFut::new(matches, req, service, inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, sync::atomic::Ordering};
+
+    use futures::{future::poll_fn, poll};
+    use tokio::task::{yield_now, LocalSet};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_util::*;
+
+    /// A filter whose future holds an `Rc`, so it isn't `Send`.
+    #[derive(Clone)]
+    struct RcFilter(Rc<bool>);
+
+    impl<T> LocalAsyncFilter<T> for RcFilter {
+        type Future = std::pin::Pin<Box<dyn Future<Output = bool>>>;
+
+        fn matches(&self, _: &T) -> Self::Future {
+            let matches = self.0.clone();
+            Box::pin(async move {
+                yield_now().await;
+                *matches
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn should_run_unsend_filters_locally() {
+        LocalSet::new()
+            .run_until(async {
+                for (matches, expected) in [(true, "a"), (false, "b")] {
+                    let service =
+                        LocalAsyncFilterLayer::new(RcFilter(Rc::new(matches)), TestService("a"))
+                            .layer(TestService("b"));
+
+                    let task = tokio::task::spawn_local(service.oneshot(()));
+                    assert_eq!(task.await.unwrap(), Ok(expected));
+                }
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn should_wait_for_both_services() {
+        let (service, ready) = PendingService::new("a");
+        let mut service =
+            LocalAsyncFilterLayer::new(RcFilter(Rc::new(false)), service).layer(TestService("b"));
+
+        assert!(poll!(poll_fn(|cx| service.poll_ready(cx))).is_pending());
+
+        ready.store(true, Ordering::SeqCst);
+        service.ready().await.unwrap();
+        assert_eq!(service.call(()).await, Ok("b"));
+    }
+}
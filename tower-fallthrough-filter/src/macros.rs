@@ -0,0 +1,207 @@
+/// Declares an HTTP filter from a list of rules, all of which have to
+/// match.
+///
+/// The filter is a zero-sized `Clone` type implementing
+/// [`Filter`](crate::Filter) for `http::Request<B>`, the rules are
+/// checked inline without any boxing.
+///
+/// # Rules
+/// - `GET | HEAD`: The request has one of the given methods.
+/// - `path: "/static/**"`: The path matches the glob, where `*` matches
+///   within a single segment and `**` any number of segments.
+/// - `prefix: "/api"`: The path starts with the given prefix.
+/// - `header: "x-internal"`: The request has the given header.
+/// - `!rule`: The rule doesn't match.
+///
+/// # Example
+/// ```rust
+/// use http::{Method, Request};
+/// use tower_fallthrough_filter::{filter, Filter};
+///
+/// let assets = filter!(GET | HEAD, path: "/static/**/*.css", !header: "x-no-cache");
+///
+/// let req = |method, uri| Request::builder().method(method).uri(uri).body(()).unwrap();
+///
+/// assert!(assets.matches(&req(Method::GET, "/static/themes/dark.css")));
+/// assert!(!assets.matches(&req(Method::GET, "/static/app.js")));
+/// assert!(!assets.matches(&req(Method::POST, "/static/app.css")));
+/// ```
+#[macro_export]
+macro_rules! filter {
+    (@cond $req:ident; ! $($rule:tt)+) => {
+        !$crate::filter!(@cond $req; $($rule)+)
+    };
+    (@cond $req:ident; path: $glob:literal) => {
+        $crate::__private::glob_matches($glob, $req.uri().path())
+    };
+    (@cond $req:ident; prefix: $prefix:literal) => {
+        $req.uri().path().starts_with($prefix)
+    };
+    (@cond $req:ident; header: $name:literal) => {
+        $req.headers().contains_key($name)
+    };
+    (@cond $req:ident; $($method:ident)|+) => {
+        false $(|| $req.method() == $crate::__private::http::Method::$method)+
+    };
+    (@cond $req:ident; $($rule:tt)*) => {
+        ::core::compile_error!(::core::concat!(
+            "unknown filter rule `",
+            ::core::stringify!($($rule)*),
+            "`, expected methods, `path:`, `prefix:` or `header:`"
+        ))
+    };
+
+    // NOTE: Splits the rules at the commas, as a rule can
+    //       consist of any number of tokens.
+    (@split [$($rules:tt)*] [$($rule:tt)+] , $($rest:tt)*) => {
+        $crate::filter!(@split [$($rules)* [$($rule)+]] [] $($rest)*)
+    };
+    (@split [$($rules:tt)*] [] , $($rest:tt)*) => {
+        ::core::compile_error!("expected a filter rule before `,`")
+    };
+    (@split [$($rules:tt)*] [$($rule:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::filter!(@split [$($rules)*] [$($rule)* $next] $($rest)*)
+    };
+    (@split [$($rules:tt)*] [$($rule:tt)+]) => {
+        $crate::filter!(@build $($rules)* [$($rule)+])
+    };
+    (@split [$($rules:tt)*] []) => {
+        $crate::filter!(@build $($rules)*)
+    };
+
+    (@build $([$($rule:tt)+])*) => {{
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        struct MacroFilter;
+
+        impl<B> $crate::Filter<$crate::__private::http::Request<B>> for MacroFilter {
+            #[allow(unused_variables)]
+            fn matches(&self, req: &$crate::__private::http::Request<B>) -> bool {
+                true $(&& $crate::filter!(@cond req; $($rule)+))*
+            }
+        }
+
+        MacroFilter
+    }};
+
+    ($($rules:tt)*) => {
+        $crate::filter!(@split [] [] $($rules)*)
+    };
+}
+
+/// Matches `path` against `glob`, see [`filter!`].
+pub fn glob_matches(glob: &str, path: &str) -> bool {
+    let glob: Vec<_> = glob.split('/').collect();
+    let path: Vec<_> = path.split('/').collect();
+
+    segments_match(&glob, &path)
+}
+
+fn segments_match(glob: &[&str], path: &[&str]) -> bool {
+    match (glob.split_first(), path.split_first()) {
+        (None, None) => true,
+        (Some((&"**", rest)), _) => {
+            (0..=path.len()).any(|skip| segments_match(rest, &path[skip..]))
+        }
+        (Some((pattern, glob)), Some((segment, path))) => {
+            segment_matches(pattern.as_bytes(), segment.as_bytes()) && segments_match(glob, path)
+        }
+        _ => false,
+    }
+}
+
+fn segment_matches(pattern: &[u8], segment: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => segment.is_empty(),
+        Some((b'*', rest)) => {
+            (0..=segment.len()).any(|skip| segment_matches(rest, &segment[skip..]))
+        }
+        Some((byte, rest)) => segment
+            .split_first()
+            .is_some_and(|(first, segment)| first == byte && segment_matches(rest, segment)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{Method, Request};
+
+    use super::*;
+    use crate::{filters::HttpFilterBuilder, Filter};
+
+    fn request(method: Method, uri: &str, header: Option<&str>) -> Request<()> {
+        let mut builder = Request::builder().method(method).uri(uri);
+        if let Some(header) = header {
+            builder = builder.header(header, "1");
+        }
+
+        builder.body(()).unwrap()
+    }
+
+    fn requests() -> Vec<Request<()>> {
+        let mut requests = Vec::new();
+        for method in [Method::GET, Method::HEAD, Method::POST] {
+            for uri in [
+                "/",
+                "/static",
+                "/static/app.css",
+                "/static/img/logo.svg",
+                "/api/users",
+            ] {
+                for header in [None, Some("x-internal")] {
+                    requests.push(request(method.clone(), uri, header));
+                }
+            }
+        }
+
+        requests
+    }
+
+    #[test]
+    fn should_match_glob() {
+        assert!(glob_matches("/static/**", "/static/img/logo.svg"));
+        assert!(glob_matches("/static/**", "/static/"));
+        assert!(glob_matches("/static/*.css", "/static/app.css"));
+        assert!(glob_matches("/**/*.css", "/a/b/c.css"));
+        assert!(glob_matches("/users/*/posts", "/users/42/posts"));
+
+        assert!(!glob_matches("/static/*.css", "/static/app.js"));
+        assert!(!glob_matches("/static/*", "/static/img/logo.svg"));
+        assert!(!glob_matches("/users/*/posts", "/users/posts"));
+    }
+
+    #[test]
+    fn should_match_hand_composed_filter() {
+        let expanded = filter!(GET | HEAD, prefix: "/static", header: "x-internal");
+        let composed = HttpFilterBuilder::new()
+            .any(|methods| methods.method(Method::GET).method(Method::HEAD))
+            .path_prefix("/static")
+            .header("x-internal")
+            .build()
+            .unwrap();
+
+        for req in requests() {
+            assert_eq!(expanded.matches(&req), composed.matches(&req), "{req:?}");
+        }
+    }
+
+    #[test]
+    fn should_negate_rules() {
+        let expanded = filter!(!POST, path: "/static/**", !header: "x-internal",);
+        let composed = |req: &Request<()>| {
+            req.method() != Method::POST
+                && glob_matches("/static/**", req.uri().path())
+                && !req.headers().contains_key("x-internal")
+        };
+
+        for req in requests() {
+            assert_eq!(expanded.matches(&req), composed(&req), "{req:?}");
+        }
+    }
+
+    #[test]
+    fn should_match_everything_without_rules() {
+        let filter = filter!();
+
+        assert!(filter.matches(&request(Method::DELETE, "/", None)));
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    fmt,
+    marker::PhantomData,
+    task::{Context, Poll},
+};
+
+use futures::ready;
+use tower::Service;
+
+use crate::{futures::MakeFilterServiceFut, Filter, FilterService};
+
+/// A `MakeService` creating a [`FilterService`] per target, e.g. per
+/// connection of a Hyper server, by making both the filtered and the
+/// inner service for it.
+///
+/// `tower::MakeService` is implemented for every service returning
+/// services, so this is a `Service` of the target, which is cloned to
+/// make both services.
+///
+/// # Example
+/// ```rust
+/// use std::net::SocketAddr;
+///
+/// use tower::{make::MakeService, service_fn, ServiceExt};
+/// use tower_fallthrough_filter::{FilterFn, MakeFilterService};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let make = |name: &'static str| {
+///     service_fn(move |addr: SocketAddr| async move {
+///         Ok::<_, ()>(service_fn(move |_: u32| async move { Ok::<_, ()>(format!("{name} {addr}")) }))
+///     })
+/// };
+///
+/// let mut make_service = MakeFilterService::new(FilterFn::new(|n: &u32| *n < 10), make("small"), make("large"));
+///
+/// let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
+/// let service = make_service.make_service(addr).await.unwrap();
+/// assert_eq!(service.oneshot(5).await.unwrap(), "small 127.0.0.1:8080");
+/// # }
+/// ```
+pub struct MakeFilterService<F, M, N, T> {
+    filter: F,
+    make_service: M,
+    make_inner: N,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Filter<T>, M, N, T> MakeFilterService<F, M, N, T> {
+    /// Creates a new MakeFilterService given a `Filter`, the
+    /// `MakeService` of the service that is executed if it matches and
+    /// the `MakeService` of the service to fall through to.
+    pub fn new(filter: F, make_service: M, make_inner: N) -> Self {
+        Self {
+            filter,
+            make_service,
+            make_inner,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: Clone, M: Clone, N: Clone, T> Clone for MakeFilterService<F, M, N, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            make_service: self.make_service.clone(),
+            make_inner: self.make_inner.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, M, N, T> fmt::Debug for MakeFilterService<F, M, N, T>
+where
+    F: fmt::Debug,
+    M: fmt::Debug,
+    N: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MakeFilterService")
+            .field("filter", &self.filter)
+            .field("make_service", &self.make_service)
+            .field("make_inner", &self.make_inner)
+            .finish()
+    }
+}
+
+impl<F, M, N, C, T> Service<C> for MakeFilterService<F, M, N, T>
+where
+    F: Filter<T>,
+    M: Service<C>,
+    M::Response: Service<T>,
+    N: Service<C, Error = M::Error>,
+    N::Response: Service<
+        T,
+        Response = <M::Response as Service<T>>::Response,
+        Error = <M::Response as Service<T>>::Error,
+    >,
+    C: Clone,
+{
+    type Response = FilterService<F, M::Response, N::Response, T>;
+    type Error = M::Error;
+    type Future = MakeFilterServiceFut<F, M::Future, N::Future, T>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.make_service.poll_ready(cx))?;
+        ready!(self.make_inner.poll_ready(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, target: C) -> Self::Future {
+        MakeFilterServiceFut::new(
+            self.filter.clone(),
+            self.make_service.call(target.clone()),
+            self.make_inner.call(target),
+        )
+    }
+}
@@ -0,0 +1,409 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tower::{Layer, Service};
+
+use crate::{Filter, FilterLayer, FilterService};
+
+#[cfg(feature = "async")]
+use std::time::Instant;
+
+#[cfg(feature = "async")]
+use crate::{futures::MeteredAsyncFut, AsyncFilter, AsyncFilterLayer};
+
+/// An observer that gets notified about every routing decision
+/// made by a [`MeteredFilterLayer`].
+///
+/// # Example
+/// ```rust
+/// # use tower_fallthrough_filter::FilterMetrics;
+///
+/// #[derive(Debug, Clone)]
+/// struct PrintMetrics;
+///
+/// impl FilterMetrics for PrintMetrics {
+///     fn on_match(&self, filter_name: &str) {
+///         println!("{filter_name} matched");
+///     }
+///
+///     fn on_fallthrough(&self, filter_name: &str) {
+///         println!("{filter_name} fell through");
+///     }
+/// }
+/// ```
+pub trait FilterMetrics: Clone {
+    /// Called when the filter matched and the filtered service
+    /// will handle the request.
+    fn on_match(&self, filter_name: &str);
+
+    /// Called when the filter didn't match and the request
+    /// falls through to the inner service.
+    fn on_fallthrough(&self, filter_name: &str);
+
+    /// Called with the time it took to evaluate an async filter,
+    /// see [`MeteredAsyncFilter`].
+    fn on_evaluated(&self, filter_name: &str, duration: Duration) {
+        let _ = (filter_name, duration);
+    }
+}
+
+/// A [`FilterMetrics`] implementation which simply counts the
+/// decisions using atomics. Mostly useful for testing.
+///
+/// Clones share the same counters.
+#[derive(Debug, Clone, Default)]
+pub struct AtomicMetrics {
+    matched: Arc<AtomicU64>,
+    fallthrough: Arc<AtomicU64>,
+}
+
+impl AtomicMetrics {
+    /// Creates new metrics with both counters set to zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many requests were handled by the filtered service.
+    pub fn matched(&self) -> u64 {
+        self.matched.load(Ordering::Relaxed)
+    }
+
+    /// How many requests fell through to the inner service.
+    pub fn fallthrough(&self) -> u64 {
+        self.fallthrough.load(Ordering::Relaxed)
+    }
+}
+
+impl FilterMetrics for AtomicMetrics {
+    fn on_match(&self, _: &str) {
+        self.matched.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_fallthrough(&self, _: &str) {
+        self.fallthrough.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A [`FilterMetrics`] implementation reporting to the global
+/// recorder of the [`metrics`] crate, e.g. a Prometheus exporter.
+///
+/// Records the counters `filter_matched_total` and
+/// `filter_fallthrough_total` and the histogram
+/// `filter_evaluation_duration_seconds` for async filters by default,
+/// labeled with `filter = <filter_name>`. The names can be configured
+/// with a [`MetricsFilterLayerBuilder`].
+#[derive(Debug, Clone)]
+pub struct MetricsFilterMetrics {
+    matched: Arc<str>,
+    fallthrough: Arc<str>,
+    evaluation_duration: Arc<str>,
+}
+
+impl MetricsFilterMetrics {
+    /// Creates new metrics using the default metric names.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for MetricsFilterMetrics {
+    fn default() -> Self {
+        Self {
+            matched: "filter_matched_total".into(),
+            fallthrough: "filter_fallthrough_total".into(),
+            evaluation_duration: "filter_evaluation_duration_seconds".into(),
+        }
+    }
+}
+
+impl FilterMetrics for MetricsFilterMetrics {
+    fn on_match(&self, filter_name: &str) {
+        metrics::counter!(self.matched.to_string(), "filter" => filter_name.to_owned())
+            .increment(1);
+    }
+
+    fn on_fallthrough(&self, filter_name: &str) {
+        metrics::counter!(self.fallthrough.to_string(), "filter" => filter_name.to_owned())
+            .increment(1);
+    }
+
+    fn on_evaluated(&self, filter_name: &str, duration: Duration) {
+        metrics::histogram!(self.evaluation_duration.to_string(), "filter" => filter_name.to_owned())
+            .record(duration.as_secs_f64());
+    }
+}
+
+/// A [`FilterLayer`] reporting every decision to the [`metrics`] crate,
+/// created by a [`MetricsFilterLayerBuilder`].
+pub type MetricsFilterLayer<F, S, T> = FilterLayer<MeteredFilter<F, MetricsFilterMetrics>, S, T>;
+
+/// An [`AsyncFilterLayer`] reporting every decision and the evaluation
+/// duration to the [`metrics`] crate, created by a
+/// [`MetricsFilterLayerBuilder`].
+#[cfg(feature = "async")]
+pub type MetricsAsyncFilterLayer<F, S, T> =
+    AsyncFilterLayer<MeteredAsyncFilter<F, MetricsFilterMetrics>, S, T>;
+
+/// Builds a [`MetricsFilterLayer`] or a [`MetricsAsyncFilterLayer`],
+/// labeling the metrics with the type name of the filter.
+///
+/// # Example
+/// ```rust
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{Filter, MetricsFilterLayerBuilder};
+///
+/// #[derive(Clone)]
+/// struct IsEven;
+///
+/// impl Filter<u32> for IsEven {
+///     fn matches(&self, item: &u32) -> bool {
+///         item.is_multiple_of(2)
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name| service_fn(move |_: u32| async move { Ok::<_, ()>(name) });
+///
+/// let service = MetricsFilterLayerBuilder::new()
+///     .matched_name("router.even")
+///     .fallthrough_name("router.odd")
+///     .build(IsEven, respond("even"))
+///     .layer(respond("odd"));
+///
+/// assert_eq!(service.oneshot(2).await, Ok("even"));
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MetricsFilterLayerBuilder {
+    metrics: MetricsFilterMetrics,
+}
+
+impl MetricsFilterLayerBuilder {
+    /// Creates a new MetricsFilterLayerBuilder using the default
+    /// metric names, see [`MetricsFilterMetrics`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the counter of matched requests.
+    pub fn matched_name(mut self, name: impl Into<Arc<str>>) -> Self {
+        self.metrics.matched = name.into();
+        self
+    }
+
+    /// Sets the name of the counter of requests falling through.
+    pub fn fallthrough_name(mut self, name: impl Into<Arc<str>>) -> Self {
+        self.metrics.fallthrough = name.into();
+        self
+    }
+
+    /// Sets the name of the histogram of the evaluation durations of
+    /// async filters, in seconds.
+    pub fn evaluation_duration_name(mut self, name: impl Into<Arc<str>>) -> Self {
+        self.metrics.evaluation_duration = name.into();
+        self
+    }
+
+    /// Creates the MetricsFilterLayer given a `Filter` and a `Service`.
+    pub fn build<F, S, T>(self, filter: F, service: S) -> MetricsFilterLayer<F, S, T>
+    where
+        F: Filter<T>,
+        S: Service<T>,
+    {
+        let name = std::any::type_name::<F>();
+        FilterLayer::new(MeteredFilter::new(name, filter, self.metrics), service)
+    }
+
+    /// Creates the MetricsAsyncFilterLayer given an `AsyncFilter` and
+    /// a `Service`.
+    #[cfg(feature = "async")]
+    pub fn build_async<F, S, T>(self, filter: F, service: S) -> MetricsAsyncFilterLayer<F, S, T>
+    where
+        F: AsyncFilter<T>,
+        S: Service<T>,
+        T: Send + 'static,
+    {
+        let name = std::any::type_name::<F>();
+        AsyncFilterLayer::new(MeteredAsyncFilter::new(name, filter, self.metrics), service)
+    }
+}
+
+/// The async counterpart of [`MeteredFilter`], additionally reporting
+/// how long the wrapped filter took to evaluate.
+#[cfg(feature = "async")]
+#[derive(Debug, Clone)]
+pub struct MeteredAsyncFilter<F, M> {
+    name: Arc<str>,
+    filter: F,
+    metrics: M,
+}
+
+#[cfg(feature = "async")]
+impl<F, M> MeteredAsyncFilter<F, M> {
+    /// Wraps the `filter` reporting its decisions to `metrics`
+    /// under the given `name`.
+    pub fn new(name: impl Into<Arc<str>>, filter: F, metrics: M) -> Self {
+        Self {
+            name: name.into(),
+            filter,
+            metrics,
+        }
+    }
+
+    /// The name the decisions are reported under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F, M, T> AsyncFilter<T> for MeteredAsyncFilter<F, M>
+where
+    F: AsyncFilter<T>,
+    M: FilterMetrics + Send,
+{
+    type Future = MeteredAsyncFut<F::Future, M>;
+
+    fn matches(&self, item: &T) -> Self::Future {
+        MeteredAsyncFut::new(
+            self.filter.matches(item),
+            self.name.clone(),
+            self.metrics.clone(),
+        )
+    }
+
+    fn matches_now(&self, item: &T) -> Option<bool> {
+        let start = Instant::now();
+        let matches = self.filter.matches_now(item)?;
+        self.metrics.on_evaluated(&self.name, start.elapsed());
+        report_decision(&self.metrics, &self.name, matches);
+
+        Some(matches)
+    }
+}
+
+/// A [`Filter`] which reports every decision of the wrapped
+/// filter to a [`FilterMetrics`] observer.
+#[derive(Debug, Clone)]
+pub struct MeteredFilter<F, M> {
+    name: Arc<str>,
+    filter: F,
+    metrics: M,
+}
+
+impl<F, M> MeteredFilter<F, M> {
+    /// Wraps the `filter` reporting its decisions to `metrics`
+    /// under the given `name`.
+    pub fn new(name: impl Into<Arc<str>>, filter: F, metrics: M) -> Self {
+        Self {
+            name: name.into(),
+            filter,
+            metrics,
+        }
+    }
+
+    /// The name the decisions are reported under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<F, M, T> Filter<T> for MeteredFilter<F, M>
+where
+    F: Filter<T>,
+    M: FilterMetrics,
+{
+    fn matches(&self, item: &T) -> bool {
+        let matches = self.filter.matches(item);
+        report_decision(&self.metrics, &self.name, matches);
+
+        matches
+    }
+}
+
+/// Reports a decision of the filter named `name` to `metrics`.
+pub(crate) fn report_decision<M: FilterMetrics>(metrics: &M, name: &str, matches: bool) {
+    if matches {
+        metrics.on_match(name);
+    } else {
+        metrics.on_fallthrough(name);
+    }
+}
+
+/// A [`FilterLayer`] which records every routing decision
+/// through a user-supplied [`FilterMetrics`].
+///
+/// # Example
+/// ```rust
+/// use tower_fallthrough_filter::{AtomicMetrics, Filter, MeteredFilterLayer};
+/// use tower::{service_fn, Layer, Service};
+///
+/// #[derive(Debug, Clone)]
+/// struct IsEven;
+///
+/// impl Filter<u32> for IsEven {
+///     fn matches(&self, item: &u32) -> bool {
+///         item.is_multiple_of(2)
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let even = service_fn(|_: u32| async { Ok::<_, ()>("even") });
+///     let odd = service_fn(|_: u32| async { Ok::<_, ()>("odd") });
+///     let metrics = AtomicMetrics::new();
+///
+///     let mut service = MeteredFilterLayer::new("is_even", IsEven, even, metrics.clone())
+///         .layer(odd);
+///
+///     assert_eq!(service.call(2).await, Ok("even"));
+///     assert_eq!(service.call(3).await, Ok("odd"));
+///     assert_eq!(metrics.matched(), 1);
+///     assert_eq!(metrics.fallthrough(), 1);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct MeteredFilterLayer<F, S, M, T> {
+    layer: FilterLayer<MeteredFilter<F, M>, S, T>,
+}
+
+impl<F, S, M, T> Clone for MeteredFilterLayer<F, S, M, T>
+where
+    FilterLayer<MeteredFilter<F, M>, S, T>: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<F: Filter<T>, S: Service<T>, M: FilterMetrics, T> MeteredFilterLayer<F, S, M, T> {
+    /// Creates a new MeteredFilterLayer given a name for the
+    /// filter, the `Filter`, the `Service` and the `FilterMetrics`.
+    pub fn new(name: impl Into<Arc<str>>, filter: F, service: S, metrics: M) -> Self {
+        Self {
+            layer: FilterLayer::new(MeteredFilter::new(name, filter, metrics), service),
+        }
+    }
+}
+
+impl<F, S, M, I, T> Layer<I> for MeteredFilterLayer<F, S, M, T>
+where
+    F: Filter<T>,
+    S: Service<T> + Clone,
+    M: FilterMetrics,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = FilterService<MeteredFilter<F, M>, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        self.layer.layer(inner_service)
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    marker::PhantomData,
+    task::{Context, Poll},
+};
+
+use futures::{future::Either, ready};
+use tower::{Layer, Service};
+
+/// A filter with mutable access to the request, so it can annotate
+/// the request for the selected service while deciding, e.g. insert
+/// an extension with the outcome.
+///
+/// # Example
+/// ```rust
+/// use axum::http::Request;
+/// use tower_fallthrough_filter::PeekFilter;
+///
+/// #[derive(Clone)]
+/// struct IsBeta;
+///
+/// #[derive(Clone)]
+/// struct Beta(bool);
+///
+/// impl<B> PeekFilter<Request<B>> for IsBeta {
+///     fn peek(&self, req: &mut Request<B>) -> bool {
+///         let beta = req.headers().contains_key("x-beta");
+///         req.extensions_mut().insert(Beta(beta));
+///         beta
+///     }
+/// }
+///
+/// let mut req = Request::new(());
+/// assert!(!IsBeta.peek(&mut req));
+/// assert!(req.extensions().get::<Beta>().is_some());
+/// ```
+pub trait PeekFilter<T>: Clone {
+    /// Whether the service should be executed, see
+    /// [`Filter::matches`](crate::Filter::matches).
+    ///
+    /// Changes to the `item` are seen by whichever service is called.
+    fn peek(&self, item: &mut T) -> bool;
+}
+
+/// A Tower layer like [`FilterLayer`](crate::FilterLayer) for a
+/// [`PeekFilter`].
+#[derive(Debug)]
+pub struct PeekFilterLayer<F, S, T> {
+    filter: F,
+    service: S,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, T> Clone for PeekFilterLayer<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: PeekFilter<T>, S: Service<T>, T> PeekFilterLayer<F, S, T> {
+    /// Creates a new PeekFilterLayer given a `PeekFilter` and a `Service`.
+    pub fn new(filter: F, service: S) -> Self {
+        Self {
+            filter,
+            service,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> Layer<I> for PeekFilterLayer<F, S, T>
+where
+    F: PeekFilter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = PeekFilterService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        PeekFilterService {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: inner_service,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PeekFilterService<F, S, I, T> {
+    filter: F,
+    service: S,
+    inner: I,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, I: Clone, T> Clone for PeekFilterService<F, S, I, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: self.inner.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> Service<T> for PeekFilterService<F, S, I, T>
+where
+    F: PeekFilter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<S::Future, I::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.service.poll_ready(cx))?;
+        ready!(self.inner.poll_ready(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: T) -> Self::Future {
+        if self.filter.peek(&mut req) {
+            Either::Left(self.service.call(req))
+        } else {
+            Either::Right(self.inner.call(req))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request, routing::get, Extension, Router};
+    use tower::{service_fn, ServiceExt};
+
+    use super::*;
+    use crate::test_util::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct FilterResult(bool);
+
+    #[derive(Clone)]
+    struct HasHeader;
+
+    impl<B> PeekFilter<Request<B>> for HasHeader {
+        fn peek(&self, req: &mut Request<B>) -> bool {
+            let matched = req.headers().contains_key("x-peek");
+            req.extensions_mut().insert(FilterResult(matched));
+            matched
+        }
+    }
+
+    #[derive(Clone)]
+    struct Peeking(bool);
+
+    impl<T> PeekFilter<T> for Peeking {
+        fn peek(&self, _: &mut T) -> bool {
+            self.0
+        }
+    }
+
+    fn request(peek: bool) -> Request<Body> {
+        let mut req = Request::builder();
+        if peek {
+            req = req.header("x-peek", "1");
+        }
+        req.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_behave_like_filter_layer() {
+        assert_behaves_like_filter_layer(|matches| {
+            PeekFilterLayer::new(Peeking(matches), TestService("a"))
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn should_pass_extension_to_selected_service() {
+        let extension = |name: &'static str| {
+            service_fn(move |req: Request<Body>| async move {
+                Ok::<_, ()>((name, req.extensions().get::<FilterResult>().copied()))
+            })
+        };
+        let service = PeekFilterLayer::new(HasHeader, extension("a")).layer(extension("b"));
+
+        assert_eq!(
+            service.clone().oneshot(request(true)).await,
+            Ok(("a", Some(FilterResult(true))))
+        );
+        assert_eq!(
+            service.oneshot(request(false)).await,
+            Ok(("b", Some(FilterResult(false))))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_pass_extension_to_axum_handler() {
+        let handler = |name: &'static str| {
+            get(
+                move |Extension(result): Extension<FilterResult>| async move {
+                    format!("{name}: {}", result.0)
+                },
+            )
+        };
+        let app = Router::new()
+            .fallback(handler("b"))
+            .layer(PeekFilterLayer::new(
+                HasHeader,
+                Router::new().fallback(handler("a")),
+            ));
+
+        for (peek, expected) in [(true, "a: true"), (false, "b: false")] {
+            let res = app.clone().oneshot(request(peek)).await.unwrap();
+            let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(body, expected);
+        }
+    }
+}
@@ -0,0 +1,18 @@
+/// Takes the `service` to call it, leaving a clone in its place.
+///
+/// The service is ready, as `poll_ready` returned `Poll::Ready(Ok(()))`,
+/// but its clone might not be, see
+/// <https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services>.
+/// So the ready service is the one called, e.g. once the response
+/// future selected it, and the clone is driven to readiness by the next
+/// call to `poll_ready`.
+pub(crate) fn take_ready<S: Clone>(service: &mut S) -> S {
+    take_ready_with(service, S::clone)
+}
+
+/// Like [`take_ready`], but leaving the service created by `replacement`
+/// in its place, e.g. one handed back by an earlier request.
+pub(crate) fn take_ready_with<S>(service: &mut S, replacement: impl FnOnce(&S) -> S) -> S {
+    let replacement = replacement(service);
+    std::mem::replace(service, replacement)
+}
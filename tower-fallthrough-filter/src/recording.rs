@@ -0,0 +1,263 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures::{future::Either, ready};
+use serde::Serialize;
+use tower::{Layer, Service};
+
+use crate::{futures::RecordingFut, Filter};
+
+/// The calls recorded by a [`RecordingFilterService`], shared by all
+/// clones of it and of the [`RecordingFilterLayer`] creating it.
+pub type Recording<T, R> = Arc<Mutex<Vec<RecordedCall<T, R>>>>;
+
+/// A call recorded by a [`RecordingFilterService`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct RecordedCall<T, R> {
+    /// The request passed to the service.
+    pub request: T,
+    /// Whether the filter matched, so the filtered service was called
+    /// instead of the inner service.
+    pub branch: bool,
+    /// The response of the called service.
+    pub response: R,
+}
+
+/// Creates a [`RecordingFilterLayer`] given a `Filter` and a `Service`,
+/// like [`FilterLayer::new`](crate::FilterLayer::new).
+///
+/// # Example
+/// ```rust
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{recording_filter_layer, FilterFn};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name| service_fn(move |_: u32| async move { Ok::<_, ()>(name) });
+///
+/// let layer = recording_filter_layer!(FilterFn::new(|n: &u32| *n < 10), respond("small"));
+/// let recording = layer.recording();
+///
+/// let service = layer.layer(respond("large"));
+/// service.clone().oneshot(5).await.unwrap();
+/// service.oneshot(50).await.unwrap();
+///
+/// let calls = recording.lock().unwrap();
+/// assert_eq!((calls[0].request, calls[0].branch, calls[0].response), (5, true, "small"));
+/// assert_eq!((calls[1].request, calls[1].branch, calls[1].response), (50, false, "large"));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! recording_filter_layer {
+    ($filter:expr, $service:expr $(,)?) => {
+        $crate::RecordingFilterLayer::new($filter, $service)
+    };
+}
+
+/// A Tower layer like [`FilterLayer`](crate::FilterLayer), but recording
+/// every successful call into a shared [`Recording`], e.g. to assert what
+/// the filter decided in an integration test and to replay the requests.
+///
+/// Failed calls aren't recorded, as there is no response.
+pub struct RecordingFilterLayer<F, S: Service<T>, T> {
+    filter: F,
+    service: S,
+    recording: Recording<T, S::Response>,
+}
+
+impl<F, S, T> RecordingFilterLayer<F, S, T>
+where
+    F: Filter<T>,
+    S: Service<T>,
+{
+    /// Creates a new RecordingFilterLayer given a `Filter` and a
+    /// `Service`, starting with an empty recording.
+    pub fn new(filter: F, service: S) -> Self {
+        Self {
+            filter,
+            service,
+            recording: Arc::default(),
+        }
+    }
+}
+
+impl<F, S: Service<T>, T> RecordingFilterLayer<F, S, T> {
+    /// The calls recorded by the services created by this layer.
+    pub fn recording(&self) -> Recording<T, S::Response> {
+        self.recording.clone()
+    }
+}
+
+impl<F: Clone, S: Service<T> + Clone, T> Clone for RecordingFilterLayer<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            recording: self.recording.clone(),
+        }
+    }
+}
+
+impl<F, S, T> fmt::Debug for RecordingFilterLayer<F, S, T>
+where
+    F: fmt::Debug,
+    S: Service<T> + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordingFilterLayer")
+            .field("filter", &self.filter)
+            .field("service", &self.service)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, S, I, T> Layer<I> for RecordingFilterLayer<F, S, T>
+where
+    F: Filter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = RecordingFilterService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        RecordingFilterService {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: inner_service,
+            recording: self.recording.clone(),
+        }
+    }
+}
+
+/// The service created by a [`RecordingFilterLayer`].
+pub struct RecordingFilterService<F, S: Service<T>, I, T> {
+    filter: F,
+    service: S,
+    inner: I,
+    recording: Recording<T, S::Response>,
+}
+
+impl<F, S: Service<T>, I, T> RecordingFilterService<F, S, I, T> {
+    /// The calls recorded by this service and its clones.
+    pub fn recording(&self) -> Recording<T, S::Response> {
+        self.recording.clone()
+    }
+}
+
+impl<F, S, I, T> Clone for RecordingFilterService<F, S, I, T>
+where
+    F: Clone,
+    S: Service<T> + Clone,
+    I: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: self.inner.clone(),
+            recording: self.recording.clone(),
+        }
+    }
+}
+
+impl<F, S, I, T> fmt::Debug for RecordingFilterService<F, S, I, T>
+where
+    F: fmt::Debug,
+    S: Service<T> + fmt::Debug,
+    I: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordingFilterService")
+            .field("filter", &self.filter)
+            .field("service", &self.service)
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, S, I, T> Service<T> for RecordingFilterService<F, S, I, T>
+where
+    F: Filter<T>,
+    S: Service<T>,
+    S::Response: Clone + Serialize,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+    T: Clone + Serialize,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = RecordingFut<Either<S::Future, I::Future>, T, S::Response>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.service.poll_ready(cx))?;
+        ready!(self.inner.poll_ready(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        let matched = self.filter.matches(&req);
+        let request = req.clone();
+
+        let future = if matched {
+            Either::Left(self.service.call(req))
+        } else {
+            Either::Right(self.inner.call(req))
+        };
+
+        RecordingFut::new(future, request, matched, self.recording.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use tower::{service_fn, ServiceExt};
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct IsApi;
+
+    impl Filter<String> for IsApi {
+        fn matches(&self, path: &String) -> bool {
+            path.starts_with("/api")
+        }
+    }
+
+    fn echo(
+        name: &'static str,
+    ) -> impl Service<String, Response = String, Error = Infallible> + Clone {
+        service_fn(move |path: String| async move { Ok(format!("{name} {path}")) })
+    }
+
+    #[tokio::test]
+    async fn should_replay_recorded_calls() {
+        let layer = recording_filter_layer!(IsApi, echo("api"));
+        let service = layer.layer(echo("page"));
+
+        for path in ["/api/users", "/about", "/api/posts"] {
+            service.clone().oneshot(path.to_string()).await.unwrap();
+        }
+
+        let recorded = layer.recording().lock().unwrap().clone();
+        assert_eq!(
+            recorded.iter().map(|call| call.branch).collect::<Vec<_>>(),
+            [true, false, true]
+        );
+
+        // NOTE: A fresh service has to make the same decisions and
+        //       return the same responses.
+        let fresh = recording_filter_layer!(IsApi, echo("api"));
+        let service = fresh.layer(echo("page"));
+        for call in &recorded {
+            let response = service.clone().oneshot(call.request.clone()).await;
+            assert_eq!(response.unwrap(), call.response);
+        }
+
+        assert_eq!(*fresh.recording().lock().unwrap(), recorded);
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    marker::PhantomData,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::ready;
+use tower::{Layer, Service};
+
+use crate::{futures::RetryFilterFut, ready::take_ready, TryFilter};
+
+/// A Tower layer like [`TryFilterLayer`](crate::TryFilterLayer), but
+/// evaluating the filter again after `retry_delay` if it fails, e.g. as
+/// a feature flag isn't loaded from the remote store yet.
+///
+/// If the filter still fails after `max_retries` retries, the request
+/// falls through to the inner service.
+///
+/// # Example
+/// ```rust
+/// use std::{
+///     sync::{
+///         atomic::{AtomicBool, Ordering},
+///         Arc,
+///     },
+///     time::Duration,
+/// };
+///
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{RetryFilterLayer, TryFilter};
+///
+/// #[derive(Clone)]
+/// struct FeatureFlag(Arc<AtomicBool>);
+///
+/// impl TryFilter<u32> for FeatureFlag {
+///     type Error = &'static str;
+///
+///     fn try_matches(&self, _: &u32) -> Result<bool, Self::Error> {
+///         // NOTE: Loaded lazily, so the first evaluation fails.
+///         match self.0.swap(true, Ordering::SeqCst) {
+///             true => Ok(true),
+///             false => Err("not loaded yet"),
+///         }
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name| service_fn(move |_: u32| async move { Ok::<_, ()>(name) });
+///
+/// let flag = FeatureFlag(Arc::new(AtomicBool::new(false)));
+/// let service = RetryFilterLayer::new(flag, respond("beta"), 3, Duration::from_millis(1))
+///     .layer(respond("stable"));
+///
+/// assert_eq!(service.oneshot(42).await, Ok("beta"));
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RetryFilterLayer<F, S, T> {
+    filter: F,
+    service: S,
+    max_retries: usize,
+    retry_delay: Duration,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, T> Clone for RetryFilterLayer<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            max_retries: self.max_retries,
+            retry_delay: self.retry_delay,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: TryFilter<T>, S: Service<T>, T> RetryFilterLayer<F, S, T> {
+    /// Creates a new RetryFilterLayer given a `TryFilter`, a `Service`,
+    /// how often a failed filter is retried and the delay before each
+    /// retry.
+    pub fn new(filter: F, service: S, max_retries: usize, retry_delay: Duration) -> Self {
+        Self {
+            filter,
+            service,
+            max_retries,
+            retry_delay,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> Layer<I> for RetryFilterLayer<F, S, T>
+where
+    F: TryFilter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = RetryFilterService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        RetryFilterService {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: inner_service,
+            max_retries: self.max_retries,
+            retry_delay: self.retry_delay,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RetryFilterService<F, S, I, T> {
+    filter: F,
+    service: S,
+    inner: I,
+    max_retries: usize,
+    retry_delay: Duration,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, I: Clone, T> Clone for RetryFilterService<F, S, I, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: self.inner.clone(),
+            max_retries: self.max_retries,
+            retry_delay: self.retry_delay,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> Service<T> for RetryFilterService<F, S, I, T>
+where
+    F: TryFilter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = RetryFilterFut<F, S, I, T>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.service.poll_ready(cx))?;
+        ready!(self.inner.poll_ready(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        let service = take_ready(&mut self.service);
+        let inner = take_ready(&mut self.inner);
+
+        RetryFilterFut::new(
+            self.filter.clone(),
+            req,
+            service,
+            inner,
+            self.max_retries,
+            self.retry_delay,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use tokio::time::Instant;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_util::*;
+
+    const DELAY: Duration = Duration::from_millis(100);
+
+    #[tokio::test(start_paused = true)]
+    async fn should_match_on_second_attempt() {
+        let filter = Flaky::new(1);
+        let service = RetryFilterLayer::new(filter.clone(), TestService("a"), 3, DELAY)
+            .layer(TestService("b"));
+
+        let start = Instant::now();
+        assert_eq!(service.oneshot(()).await, Ok("a"));
+        assert_eq!(filter.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(start.elapsed(), DELAY);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_fall_through_once_retries_are_exhausted() {
+        let filter = Flaky::new(usize::MAX);
+        let service = RetryFilterLayer::new(filter.clone(), TestService("a"), 3, DELAY)
+            .layer(TestService("b"));
+
+        let start = Instant::now();
+        assert_eq!(service.oneshot(()).await, Ok("b"));
+        assert_eq!(filter.calls.load(Ordering::SeqCst), 4);
+        assert_eq!(start.elapsed(), DELAY * 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_not_wait_without_retries() {
+        let filter = Flaky::new(usize::MAX);
+        let service = RetryFilterLayer::new(filter.clone(), TestService("a"), 0, DELAY)
+            .layer(TestService("b"));
+
+        let start = Instant::now();
+        assert_eq!(service.oneshot(()).await, Ok("b"));
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+}
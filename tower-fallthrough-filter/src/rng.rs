@@ -0,0 +1,50 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use rand::{Rng, RngCore};
+
+/// A random number generator shared between all clones of a filter.
+///
+/// Uses the thread local generator unless a custom one was injected,
+/// which is mostly useful to get reproducible decisions in tests.
+#[derive(Clone, Default)]
+pub(crate) struct SharedRng(Option<Arc<Mutex<dyn RngCore + Send>>>);
+
+impl SharedRng {
+    pub(crate) fn new<R: RngCore + Send + 'static>(rng: R) -> Self {
+        Self(Some(Arc::new(Mutex::new(rng))))
+    }
+
+    /// Returns a random number in `0.0..1.0`.
+    pub(crate) fn next_f64(&self) -> f64 {
+        match &self.0 {
+            Some(rng) => rng.lock().unwrap_or_else(PoisonError::into_inner).gen(),
+            None => rand::random(),
+        }
+    }
+
+    /// Returns a random number in `0..upper`.
+    ///
+    /// # Panics
+    /// Panics if `upper` is zero.
+    pub(crate) fn below(&self, upper: u64) -> u64 {
+        match &self.0 {
+            Some(rng) => rng
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .gen_range(0..upper),
+            None => rand::thread_rng().gen_range(0..upper),
+        }
+    }
+}
+
+impl fmt::Debug for SharedRng {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("SharedRng(Custom)"),
+            None => f.write_str("SharedRng(ThreadRng)"),
+        }
+    }
+}
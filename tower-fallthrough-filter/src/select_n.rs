@@ -0,0 +1,227 @@
+use std::{
+    marker::PhantomData,
+    task::{Context, Poll},
+};
+
+use ::futures::{future::Either, ready};
+use tower::{Layer, Service};
+
+/// A filter choosing one out of several services by index.
+///
+/// # Example
+/// ```rust
+/// # use tower_fallthrough_filter::IndexFilter;
+///
+/// #[derive(Debug, Clone)]
+/// struct ByLanguage;
+///
+/// impl IndexFilter<&str> for ByLanguage {
+///     fn index(&self, language: &&str) -> Option<usize> {
+///         ["en", "de", "fr"].iter().position(|l| l == language)
+///     }
+/// }
+///
+/// assert_eq!(ByLanguage.index(&"de"), Some(1));
+/// assert_eq!(ByLanguage.index(&"es"), None);
+/// ```
+pub trait IndexFilter<T>: Clone {
+    /// The index of the service that should be executed
+    ///
+    /// If `None` or out of range, it will fall through
+    /// to the next service.
+    fn index(&self, item: &T) -> Option<usize>;
+}
+
+/// A Tower layer that executes the service at the index returned
+/// by the given [`IndexFilter`]. Otherwise, or if the index is
+/// out of range, it falls through to the inner service.
+///
+/// The services have to be of the same type, services of different
+/// types can be unified using e.g. `tower::util::BoxCloneService`.
+///
+/// # Example
+/// ```rust
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{IndexFilter, SelectNLayer};
+///
+/// #[derive(Clone)]
+/// struct ByLanguage;
+///
+/// impl IndexFilter<&'static str> for ByLanguage {
+///     fn index(&self, language: &&'static str) -> Option<usize> {
+///         ["en", "de", "fr", "it"].iter().position(|l| l == language)
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let renderer = |name| service_fn(move |_: &'static str| async move { Ok::<_, ()>(name) });
+///
+/// let renderers = vec![
+///     renderer("english"),
+///     renderer("german"),
+///     renderer("french"),
+///     renderer("italian"),
+/// ];
+/// let service = SelectNLayer::new(ByLanguage, renderers).layer(renderer("router"));
+///
+/// assert_eq!(service.clone().oneshot("fr").await, Ok("french"));
+/// assert_eq!(service.oneshot("es").await, Ok("router"));
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SelectNLayer<F, S, T> {
+    filter: F,
+    services: Vec<S>,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, T> Clone for SelectNLayer<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            services: self.services.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: IndexFilter<T>, S: Service<T>, T> SelectNLayer<F, S, T> {
+    /// Creates a new SelectNLayer given an `IndexFilter` and the
+    /// `Service`s it chooses from.
+    pub fn new(filter: F, services: Vec<S>) -> Self {
+        Self {
+            filter,
+            services,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> Layer<I> for SelectNLayer<F, S, T>
+where
+    F: IndexFilter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = SelectNService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        SelectNService {
+            filter: self.filter.clone(),
+            services: self.services.clone(),
+            inner: inner_service,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// The service created by a [`SelectNLayer`].
+///
+/// It is only ready once all services and the inner service are
+/// ready, as the [`IndexFilter`] might choose any of them for the next
+/// request.
+#[derive(Debug)]
+pub struct SelectNService<F, S, I, T> {
+    filter: F,
+    services: Vec<S>,
+    inner: I,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, I: Clone, T> Clone for SelectNService<F, S, I, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            services: self.services.clone(),
+            inner: self.inner.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> Service<T> for SelectNService<F, S, I, T>
+where
+    F: IndexFilter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<S::Future, I::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // NOTE: Every service might get chosen, so all of them
+        //       have to be ready before accepting a request.
+        for service in &mut self.services {
+            ready!(service.poll_ready(cx))?;
+        }
+        ready!(self.inner.poll_ready(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        let chosen = self
+            .filter
+            .index(&req)
+            .and_then(|index| self.services.get_mut(index));
+
+        match chosen {
+            Some(service) => Either::Left(service.call(req)),
+            None => Either::Right(self.inner.call(req)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    #[derive(Clone)]
+    struct FixedIndex(Option<usize>);
+
+    impl<T> IndexFilter<T> for FixedIndex {
+        fn index(&self, _: &T) -> Option<usize> {
+            self.0
+        }
+    }
+
+    type TestSelectNService =
+        SelectNService<FixedIndex, TestService<&'static str>, TestService<&'static str>, ()>;
+
+    fn middleware(index: Option<usize>) -> TestSelectNService {
+        let services = vec![
+            TestService("en"),
+            TestService("de"),
+            TestService("fr"),
+            TestService("it"),
+        ];
+
+        SelectNLayer::new(FixedIndex(index), services).layer(TestService("router"))
+    }
+
+    #[tokio::test]
+    async fn should_call_chosen_service() {
+        for (index, expected) in ["en", "de", "fr", "it"].into_iter().enumerate() {
+            assert_eq!(middleware(Some(index)).call(()).await, Ok(expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn should_fall_through_on_none() {
+        assert_eq!(middleware(None).call(()).await, Ok("router"));
+    }
+
+    #[tokio::test]
+    async fn should_fall_through_when_out_of_range() {
+        assert_eq!(middleware(Some(4)).call(()).await, Ok("router"));
+    }
+}
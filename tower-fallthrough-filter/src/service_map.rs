@@ -0,0 +1,318 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    marker::PhantomData,
+    sync::{Arc, PoisonError, RwLock},
+    task::{Context, Poll},
+};
+
+use ::futures::future::Either;
+use tower::{util::Oneshot, Layer, Service};
+
+/// A filter extracting the key a request is dispatched by,
+/// e.g. the tenant or the host.
+///
+/// # Example
+/// ```rust
+/// # use tower_fallthrough_filter::KeyFilter;
+///
+/// #[derive(Debug, Clone)]
+/// struct Tenant;
+///
+/// impl KeyFilter<String, String> for Tenant {
+///     fn key(&self, path: &String) -> Option<String> {
+///         path.strip_prefix("/t/")?.split('/').next().map(str::to_owned)
+///     }
+/// }
+///
+/// assert_eq!(Tenant.key(&"/t/acme/users".to_string()), Some("acme".to_string()));
+/// assert_eq!(Tenant.key(&"/health".to_string()), None);
+/// ```
+pub trait KeyFilter<T, K>: Clone {
+    /// The key of the service that should be executed
+    ///
+    /// If `None` or there is no service for the key,
+    /// it will fall through to the next service.
+    fn key(&self, item: &T) -> Option<K>;
+}
+
+/// A handle to insert and remove the services of a
+/// [`ServiceMapLayer`] while serving.
+pub struct ServiceMapHandle<K, S>(Arc<RwLock<HashMap<K, S>>>);
+
+impl<K: Eq + Hash, S> ServiceMapHandle<K, S> {
+    /// Adds a service for `key`, returning the one it replaced.
+    pub fn insert(&self, key: K, service: S) -> Option<S> {
+        self.0
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key, service)
+    }
+
+    /// Removes the service for `key`, so its requests fall through.
+    pub fn remove(&self, key: &K) -> Option<S> {
+        self.0
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(key)
+    }
+
+    /// Whether there is a service for `key`.
+    pub fn contains(&self, key: &K) -> bool {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(key)
+    }
+
+    fn get(&self, key: &K) -> Option<S>
+    where
+        S: Clone,
+    {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(key)
+            .cloned()
+    }
+}
+
+impl<K, S> Clone for ServiceMapHandle<K, S> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<K, S> fmt::Debug for ServiceMapHandle<K, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ServiceMapHandle")
+    }
+}
+
+/// A Tower layer dispatching requests to a service looked up by the
+/// key the given [`KeyFilter`] extracts. If there is no key, or no
+/// service for it, the request falls through to the inner service.
+///
+/// The services can be changed while serving using the handle
+/// returned by [`ServiceMapLayer::handle`], which is shared by all
+/// services created by this layer.
+///
+/// # Readiness
+/// Only the inner service is polled by `poll_ready`. Waiting for every
+/// mapped service would let a single overloaded backend stall all keys,
+/// so instead the chosen service is cloned and driven to readiness
+/// within the response future, like `tower::ServiceExt::oneshot`.
+///
+/// # Example
+/// ```rust
+/// use std::collections::HashMap;
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{KeyFilter, ServiceMapLayer};
+///
+/// #[derive(Clone)]
+/// struct Host;
+///
+/// impl KeyFilter<&'static str, &'static str> for Host {
+///     fn key(&self, host: &&'static str) -> Option<&'static str> {
+///         Some(*host)
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let site = |name| service_fn(move |_: &'static str| async move { Ok::<_, ()>(name) });
+///
+/// let layer = ServiceMapLayer::new(Host, HashMap::from([("a.example", site("a"))]));
+/// let handle = layer.handle();
+/// let service = layer.layer(site("default"));
+///
+/// assert_eq!(service.clone().oneshot("b.example").await, Ok("default"));
+///
+/// handle.insert("b.example", site("b"));
+/// assert_eq!(service.oneshot("b.example").await, Ok("b"));
+/// # }
+/// ```
+pub struct ServiceMapLayer<F, K, S, T> {
+    filter: F,
+    services: ServiceMapHandle<K, S>,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, K, S, T> Clone for ServiceMapLayer<F, K, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            services: self.services.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: fmt::Debug, K, S, T> fmt::Debug for ServiceMapLayer<F, K, S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServiceMapLayer")
+            .field("filter", &self.filter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, K, S, T> ServiceMapLayer<F, K, S, T>
+where
+    F: KeyFilter<T, K>,
+    K: Eq + Hash,
+    S: Service<T>,
+{
+    /// Creates a new ServiceMapLayer given a `KeyFilter` and
+    /// the initial `Service`s by their key.
+    pub fn new(filter: F, services: HashMap<K, S>) -> Self {
+        Self {
+            filter,
+            services: ServiceMapHandle(Arc::new(RwLock::new(services))),
+
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns a handle to change the services at runtime.
+    pub fn handle(&self) -> ServiceMapHandle<K, S> {
+        self.services.clone()
+    }
+}
+
+impl<F, K, S, I, T> Layer<I> for ServiceMapLayer<F, K, S, T>
+where
+    F: KeyFilter<T, K>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = ServiceMapService<F, K, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        ServiceMapService {
+            filter: self.filter.clone(),
+            services: self.services.clone(),
+            inner: inner_service,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+pub struct ServiceMapService<F, K, S, I, T> {
+    filter: F,
+    services: ServiceMapHandle<K, S>,
+    inner: I,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, K, S, I: Clone, T> Clone for ServiceMapService<F, K, S, I, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            services: self.services.clone(),
+            inner: self.inner.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: fmt::Debug, K, S, I: fmt::Debug, T> fmt::Debug for ServiceMapService<F, K, S, I, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServiceMapService")
+            .field("filter", &self.filter)
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, K, S, I, T> Service<T> for ServiceMapService<F, K, S, I, T>
+where
+    F: KeyFilter<T, K>,
+    K: Eq + Hash,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<Oneshot<S, T>, I::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        let service = self
+            .filter
+            .key(&req)
+            .and_then(|key| self.services.get(&key));
+
+        match service {
+            Some(service) => Either::Left(Oneshot::new(service, req)),
+            None => Either::Right(self.inner.call(req)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_util::*;
+
+    #[derive(Clone)]
+    struct Tenant;
+
+    impl KeyFilter<&'static str, &'static str> for Tenant {
+        fn key(&self, path: &&'static str) -> Option<&'static str> {
+            path.strip_prefix("/t/")
+        }
+    }
+
+    type TestServiceMapLayer =
+        ServiceMapLayer<Tenant, &'static str, TestService<&'static str>, &'static str>;
+
+    fn layer() -> TestServiceMapLayer {
+        let services = HashMap::from([
+            ("acme", TestService("acme")),
+            ("globex", TestService("globex")),
+        ]);
+
+        ServiceMapLayer::new(Tenant, services)
+    }
+
+    #[tokio::test]
+    async fn should_dispatch_by_key() {
+        let service = layer().layer(TestService("inner"));
+
+        assert_eq!(service.clone().oneshot("/t/acme").await, Ok("acme"));
+        assert_eq!(service.oneshot("/t/globex").await, Ok("globex"));
+    }
+
+    #[tokio::test]
+    async fn should_fall_through_on_miss() {
+        let service = layer().layer(TestService("inner"));
+
+        assert_eq!(service.clone().oneshot("/t/initech").await, Ok("inner"));
+        assert_eq!(service.oneshot("/health").await, Ok("inner"));
+    }
+
+    #[tokio::test]
+    async fn should_apply_runtime_changes() {
+        let layer = layer();
+        let handle = layer.handle();
+        let service = layer.layer(TestService("inner"));
+
+        handle.insert("initech", TestService("initech"));
+        assert_eq!(service.clone().oneshot("/t/initech").await, Ok("initech"));
+
+        handle.remove(&"acme");
+        assert!(!handle.contains(&"acme"));
+        assert_eq!(service.oneshot("/t/acme").await, Ok("inner"));
+    }
+}
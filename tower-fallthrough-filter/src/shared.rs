@@ -0,0 +1,321 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::Arc,
+};
+
+use tower::Service;
+
+use crate::{Filter, FilterLayer};
+
+#[cfg(feature = "async")]
+use std::future::Future;
+
+#[cfg(feature = "async")]
+use crate::{AsyncFilter, AsyncFilterLayer};
+
+/// A filter like [`Filter`], but without requiring it to be clonable,
+/// e.g. as it holds a memory mapped routing table or a client handle.
+///
+/// It is shared between the created services, see
+/// [`FilterLayer::new_shared`].
+pub trait ShareableFilter<T>: Send + Sync {
+    /// Whether the service should be executed, see [`Filter::matches`].
+    fn matches(&self, item: &T) -> bool;
+}
+
+/// The async counterpart of [`ShareableFilter`], see
+/// [`AsyncFilterLayer::new_shared`].
+#[cfg(feature = "async")]
+pub trait ShareableAsyncFilter<T>: Send + Sync {
+    type Future: Future<Output = bool> + Send;
+
+    /// Whether the service should be executed,
+    /// see [`AsyncFilter::matches`].
+    fn matches(&self, item: &T) -> Self::Future;
+
+    /// Whether the filter matches without awaiting,
+    /// see [`AsyncFilter::matches_now`].
+    fn matches_now(&self, item: &T) -> Option<bool> {
+        let _ = item;
+        None
+    }
+}
+
+/// A filter sharing a [`ShareableFilter`] or a [`ShareableAsyncFilter`]
+/// using an `Arc`, created by the `new_shared` constructors.
+pub struct SharedFilter<F>(Arc<F>);
+
+impl<F> Clone for SharedFilter<F> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<F: fmt::Debug> fmt::Debug for SharedFilter<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedFilter").field(&self.0).finish()
+    }
+}
+
+impl<F: ShareableFilter<T>, T> Filter<T> for SharedFilter<F> {
+    fn matches(&self, item: &T) -> bool {
+        self.0.matches(item)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F: ShareableAsyncFilter<T>, T> AsyncFilter<T> for SharedFilter<F> {
+    type Future = F::Future;
+
+    fn matches(&self, item: &T) -> Self::Future {
+        self.0.matches(item)
+    }
+
+    fn matches_now(&self, item: &T) -> Option<bool> {
+        self.0.matches_now(item)
+    }
+}
+
+/// Immutable state of a filter shared between its clones, so cloning
+/// the filter is a pointer bump instead of a deep copy.
+///
+/// Filters are cloned into every service created by a layer, and axum
+/// clones the whole middleware stack per connection. A filter holding
+/// a large routing table, a rule set or a compiled pattern set should
+/// therefore keep it in a `SharedState`, or an `Arc`, as the built-in
+/// filters do, e.g. [`HttpFilter`](crate::filters::HttpFilter).
+///
+/// State changing at runtime is shared the same way, but needs
+/// interior mutability, e.g. an `RwLock` or an atomic.
+///
+/// # Example
+/// ```rust
+/// use std::collections::HashSet;
+///
+/// use tower_fallthrough_filter::{Filter, SharedState};
+///
+/// #[derive(Clone)]
+/// struct Allowlist {
+///     users: SharedState<HashSet<String>>,
+/// }
+///
+/// impl Filter<String> for Allowlist {
+///     fn matches(&self, user: &String) -> bool {
+///         self.users.contains(user)
+///     }
+/// }
+///
+/// let filter = Allowlist {
+///     users: SharedState::new(HashSet::from(["ferris".to_string()])),
+/// };
+/// let clone = filter.clone();
+///
+/// assert!(clone.matches(&"ferris".to_string()));
+/// assert_eq!(SharedState::strong_count(&filter.users), 2);
+/// ```
+pub struct SharedState<T: ?Sized>(Arc<T>);
+
+impl<T> SharedState<T> {
+    /// Creates a new SharedState given the state.
+    pub fn new(state: T) -> Self {
+        Self(Arc::new(state))
+    }
+}
+
+impl<T: ?Sized> SharedState<T> {
+    /// The number of clones sharing the state, see `Arc::strong_count`.
+    pub fn strong_count(this: &Self) -> usize {
+        Arc::strong_count(&this.0)
+    }
+
+    /// Whether both share the same state, see `Arc::ptr_eq`.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.0, &other.0)
+    }
+}
+
+impl<T: ?Sized> Clone for SharedState<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: ?Sized> Deref for SharedState<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> From<T> for SharedState<T> {
+    fn from(state: T) -> Self {
+        Self::new(state)
+    }
+}
+
+impl<T: ?Sized> From<Arc<T>> for SharedState<T> {
+    fn from(state: Arc<T>) -> Self {
+        Self(state)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for SharedState<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedState").field(&&*self.0).finish()
+    }
+}
+
+impl<T: ?Sized + PartialEq> PartialEq for SharedState<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: ?Sized + Eq> Eq for SharedState<T> {}
+
+impl<T: ?Sized + Hash> Hash for SharedState<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl<F, S, T> FilterLayer<SharedFilter<F>, S, T>
+where
+    F: ShareableFilter<T>,
+    S: Service<T>,
+{
+    /// Creates a new FilterLayer given a [`ShareableFilter`] and a
+    /// `Service`, sharing the filter instead of cloning it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::collections::HashSet;
+    ///
+    /// use tower::{service_fn, Layer, ServiceExt};
+    /// use tower_fallthrough_filter::{FilterLayer, ShareableFilter};
+    ///
+    /// // NOTE: Not clonable, e.g. as it is expensive to copy.
+    /// struct Blocklist(HashSet<&'static str>);
+    ///
+    /// impl ShareableFilter<&'static str> for Blocklist {
+    ///     fn matches(&self, user: &&'static str) -> bool {
+    ///         self.0.contains(user)
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let respond = |name| service_fn(move |_: &'static str| async move { Ok::<_, ()>(name) });
+    ///
+    /// let blocklist = Blocklist(HashSet::from(["mallory"]));
+    /// let service = FilterLayer::new_shared(blocklist, respond("blocked")).layer(respond("welcome"));
+    ///
+    /// assert_eq!(service.clone().oneshot("mallory").await, Ok("blocked"));
+    /// assert_eq!(service.oneshot("alice").await, Ok("welcome"));
+    /// # }
+    /// ```
+    pub fn new_shared(filter: F, service: S) -> Self {
+        Self::new(SharedFilter(Arc::new(filter)), service)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F, S, T> AsyncFilterLayer<SharedFilter<F>, S, T>
+where
+    F: ShareableAsyncFilter<T>,
+    S: Service<T>,
+    T: Send + 'static,
+{
+    /// Creates a new AsyncFilterLayer given a [`ShareableAsyncFilter`]
+    /// and a `Service`, see [`FilterLayer::new_shared`].
+    pub fn new_shared(filter: F, service: S) -> Self {
+        Self::new(SharedFilter(Arc::new(filter)), service)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::{Layer, ServiceExt};
+
+    use super::*;
+    use crate::test_util::*;
+
+    // NOTE: Intentionally not clonable.
+    struct Handle {
+        matches: bool,
+    }
+
+    struct NotClone(Handle);
+
+    impl<T> ShareableFilter<T> for NotClone {
+        fn matches(&self, _: &T) -> bool {
+            self.0.matches
+        }
+    }
+
+    #[cfg(feature = "async")]
+    impl<T> ShareableAsyncFilter<T> for NotClone {
+        type Future = futures::future::Ready<bool>;
+
+        fn matches(&self, _: &T) -> Self::Future {
+            futures::future::ready(self.0.matches)
+        }
+    }
+
+    #[tokio::test]
+    async fn should_share_filter() {
+        for (matches, expected) in [(true, "a"), (false, "b")] {
+            let layer = FilterLayer::new_shared(NotClone(Handle { matches }), TestService("a"));
+
+            let service = layer.clone().layer(TestService("b"));
+            assert_eq!(service.clone().oneshot(()).await, Ok(expected));
+            assert_eq!(
+                layer.layer(TestService("b")).oneshot(()).await,
+                Ok(expected)
+            );
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn should_share_async_filter() {
+        for (matches, expected) in [(true, "a"), (false, "b")] {
+            let layer =
+                AsyncFilterLayer::new_shared(NotClone(Handle { matches }), TestService("a"));
+
+            let service = layer.clone().layer(TestService("b"));
+            assert_eq!(service.clone().oneshot(()).await, Ok(expected));
+            assert_eq!(
+                layer.layer(TestService("b")).oneshot(()).await,
+                Ok(expected)
+            );
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn should_forward_matches_now() {
+        struct Cached(Handle);
+
+        impl<T> ShareableAsyncFilter<T> for Cached {
+            type Future = futures::future::Ready<bool>;
+
+            fn matches(&self, _: &T) -> Self::Future {
+                unreachable!("decided without the future")
+            }
+
+            fn matches_now(&self, _: &T) -> Option<bool> {
+                Some(self.0.matches)
+            }
+        }
+
+        let filter = SharedFilter(Arc::new(Cached(Handle { matches: true })));
+        assert_eq!(AsyncFilter::matches_now(&filter, &()), Some(true));
+
+        let filter = SharedFilter(Arc::new(NotClone(Handle { matches: true })));
+        assert_eq!(AsyncFilter::matches_now(&filter, &()), None);
+    }
+}
@@ -0,0 +1,271 @@
+use std::marker::PhantomData;
+use std::task::{Context, Poll};
+
+use futures::ready;
+use tower::{Layer, Service};
+
+use crate::{
+    futures::{SelectServiceAndCallFut, SpawnedMatchFut},
+    ready::take_ready,
+    AsyncFilter,
+};
+
+/// A Tower layer like [`AsyncFilterLayer`](crate::AsyncFilterLayer), but
+/// evaluating the filter in a separate task using `tokio::spawn`.
+///
+/// This is intended for expensive filters, as the filter makes progress
+/// on its own once the request is received, without blocking the task
+/// polling the response future. If the filter panics, the request
+/// falls through to the inner service. If the response future is
+/// dropped while the filter is evaluated, the task is aborted.
+///
+/// # Panics
+/// The created service has to be called within a tokio runtime.
+///
+/// # Example
+/// ```rust
+/// use futures::future::BoxFuture;
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{AsyncFilter, SpawnedAsyncFilterLayer};
+///
+/// #[derive(Clone)]
+/// struct IsPrime;
+///
+/// impl AsyncFilter<u64> for IsPrime {
+///     type Future = BoxFuture<'static, bool>;
+///
+///     fn matches(&self, item: &u64) -> Self::Future {
+///         let n = *item;
+///         Box::pin(async move { n > 1 && (2..n).all(|d| !n.is_multiple_of(d)) })
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name| service_fn(move |_: u64| async move { Ok::<_, ()>(name) });
+///
+/// let service = SpawnedAsyncFilterLayer::new(IsPrime, respond("prime")).layer(respond("other"));
+/// assert_eq!(service.clone().oneshot(7919).await, Ok("prime"));
+/// assert_eq!(service.oneshot(7917).await, Ok("other"));
+/// # }
+/// ```
+pub struct SpawnedAsyncFilterLayer<F, S, T> {
+    filter: F,
+    service: S,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, T> Clone for SpawnedAsyncFilterLayer<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: AsyncFilter<T>, S: Service<T>, T: Send + 'static> SpawnedAsyncFilterLayer<F, S, T> {
+    /// Creates a new SpawnedAsyncFilterLayer given an `AsyncFilter`
+    /// and a `Service`.
+    pub fn new(filter: F, service: S) -> Self {
+        Self {
+            filter,
+            service,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> Layer<I> for SpawnedAsyncFilterLayer<F, S, T>
+where
+    F: AsyncFilter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+    T: Send + 'static,
+{
+    type Service = SpawnedAsyncFilterService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        SpawnedAsyncFilterService {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: inner_service,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// The service created by a [`SpawnedAsyncFilterLayer`], spawning a
+/// task evaluating the filter for every request.
+#[derive(Debug)]
+pub struct SpawnedAsyncFilterService<F, S, I, T> {
+    filter: F,
+    service: S,
+    inner: I,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, I: Clone, T> Clone for SpawnedAsyncFilterService<F, S, I, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: self.inner.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> Service<T> for SpawnedAsyncFilterService<F, S, I, T>
+where
+    F: AsyncFilter<T>,
+    F::Future: Send + 'static,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = SelectServiceAndCallFut<SpawnedMatchFut, S, I, T, S::Response, S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.service.poll_ready(cx))?;
+        ready!(self.inner.poll_ready(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        let matched = SpawnedMatchFut::new(tokio::spawn(self.filter.matches(&req)));
+
+        let service = take_ready(&mut self.service);
+        let inner = take_ready(&mut self.inner);
+
+        SelectServiceAndCallFut::new(matched, req, service, inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use futures::{
+        future::{pending, BoxFuture},
+        poll,
+    };
+    use tokio::sync::oneshot;
+
+    use super::*;
+    use crate::test_util::*;
+
+    const FILTER_DURATION: Duration = Duration::from_millis(200);
+
+    #[derive(Clone)]
+    struct SlowFilter(bool);
+
+    impl<T> AsyncFilter<T> for SlowFilter {
+        type Future = BoxFuture<'static, bool>;
+
+        fn matches(&self, _: &T) -> Self::Future {
+            let matches = self.0;
+            Box::pin(async move {
+                // NOTE: Blocking on purpose, like an expensive computation.
+                std::thread::sleep(FILTER_DURATION);
+                matches
+            })
+        }
+    }
+
+    /// Never decides, holding the sender until it is dropped.
+    #[derive(Clone)]
+    struct PendingFilter(Arc<Mutex<Option<oneshot::Sender<()>>>>);
+
+    impl PendingFilter {
+        fn new(sender: oneshot::Sender<()>) -> Self {
+            Self(Arc::new(Mutex::new(Some(sender))))
+        }
+    }
+
+    impl<T> AsyncFilter<T> for PendingFilter {
+        type Future = BoxFuture<'static, bool>;
+
+        fn matches(&self, _: &T) -> Self::Future {
+            let sender = self.0.lock().unwrap().take();
+            Box::pin(async move {
+                let _sender = sender;
+                pending().await
+            })
+        }
+    }
+
+    #[derive(Clone)]
+    struct PanickingFilter;
+
+    impl<T> AsyncFilter<T> for PanickingFilter {
+        type Future = BoxFuture<'static, bool>;
+
+        fn matches(&self, _: &T) -> Self::Future {
+            Box::pin(async move { panic!("The filter failed") })
+        }
+    }
+
+    #[tokio::test]
+    async fn should_allow() {
+        let layer = SpawnedAsyncFilterLayer::new(TestFilter(true), TestService("a"));
+
+        assert_eq!(layer.layer(TestService("b")).call(()).await, Ok("a"));
+    }
+
+    #[tokio::test]
+    async fn should_fall_through() {
+        let layer = SpawnedAsyncFilterLayer::new(TestFilter(false), TestService("a"));
+
+        assert_eq!(layer.layer(TestService("b")).call(()).await, Ok("b"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_not_block_while_filtering() {
+        let layer = SpawnedAsyncFilterLayer::new(SlowFilter(true), TestService("a"));
+        let mut middleware = layer.layer(TestService("b"));
+
+        let start = Instant::now();
+        let future = middleware.call(());
+        tokio::pin!(future);
+
+        assert!(poll!(future.as_mut()).is_pending());
+        assert!(start.elapsed() < FILTER_DURATION);
+
+        assert_eq!(future.await, Ok("a"));
+        assert!(start.elapsed() >= FILTER_DURATION);
+    }
+
+    #[tokio::test]
+    async fn should_abort_filter_when_dropped() {
+        let (sender, receiver) = oneshot::channel::<()>();
+        let layer = SpawnedAsyncFilterLayer::new(PendingFilter::new(sender), TestService("a"));
+
+        let future = layer.layer(TestService("b")).call(());
+        tokio::task::yield_now().await;
+        drop(future);
+
+        // NOTE: The sender is dropped along with the aborted task.
+        let aborted = tokio::time::timeout(Duration::from_secs(1), receiver).await;
+        assert!(matches!(aborted, Ok(Err(_))));
+    }
+
+    #[tokio::test]
+    async fn should_fall_through_if_filter_panics() {
+        let layer = SpawnedAsyncFilterLayer::new(PanickingFilter, TestService("a"));
+
+        assert_eq!(layer.layer(TestService("b")).call(()).await, Ok("b"));
+    }
+}
@@ -0,0 +1,149 @@
+use tower::{Layer, Service};
+
+use crate::{Filter, FilterLayer, FilterService};
+
+#[cfg(feature = "async")]
+use std::future::Future;
+
+#[cfg(feature = "async")]
+use crate::AsyncFilter;
+
+/// A filter deciding based on shared application state, similar to
+/// axum's `State` extractor, e.g. a feature flag store or a pool.
+///
+/// The state is cloned along with the filter, so it should be cheap
+/// to clone, e.g. by wrapping it in an `Arc`.
+///
+/// # Example
+/// ```rust
+/// use std::sync::{
+///     atomic::{AtomicBool, Ordering},
+///     Arc,
+/// };
+/// use tower_fallthrough_filter::{Filter, StatefulFilter};
+///
+/// let enabled = Arc::new(AtomicBool::new(false));
+/// let filter = StatefulFilter::new(enabled.clone(), |enabled: &Arc<AtomicBool>, _: &()| {
+///     enabled.load(Ordering::Relaxed)
+/// });
+///
+/// assert!(!filter.matches(&()));
+/// enabled.store(true, Ordering::Relaxed);
+/// assert!(filter.matches(&()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct StatefulFilter<S, F> {
+    state: S,
+    filter: F,
+}
+
+impl<S, F> StatefulFilter<S, F> {
+    /// Creates a new StatefulFilter given the state and a
+    /// function deciding based on the state and the request.
+    pub fn new(state: S, filter: F) -> Self {
+        Self { state, filter }
+    }
+
+    /// The state passed to the filter function.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+}
+
+impl<S, F, T> Filter<T> for StatefulFilter<S, F>
+where
+    S: Clone + Send + Sync + 'static,
+    F: Fn(&S, &T) -> bool + Clone,
+{
+    fn matches(&self, item: &T) -> bool {
+        (self.filter)(&self.state, item)
+    }
+}
+
+/// The async counterpart of [`StatefulFilter`], for state that has
+/// to be queried asynchronously, e.g. a database connection pool.
+///
+/// The returned future can't borrow from the state or the request,
+/// so the required parts have to be cloned into it.
+#[cfg(feature = "async")]
+#[derive(Debug, Clone)]
+pub struct AsyncStatefulFilter<S, F> {
+    state: S,
+    filter: F,
+}
+
+#[cfg(feature = "async")]
+impl<S, F> AsyncStatefulFilter<S, F> {
+    /// Creates a new AsyncStatefulFilter given the state and a
+    /// function deciding based on the state and the request.
+    pub fn new(state: S, filter: F) -> Self {
+        Self { state, filter }
+    }
+
+    /// The state passed to the filter function.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+}
+
+#[cfg(feature = "async")]
+impl<S, F, Fut, T> AsyncFilter<T> for AsyncStatefulFilter<S, F>
+where
+    S: Clone + Send + Sync + 'static,
+    F: Fn(&S, &T) -> Fut + Clone + Send + Sync,
+    Fut: Future<Output = bool> + Send,
+{
+    type Future = Fut;
+
+    fn matches(&self, item: &T) -> Self::Future {
+        (self.filter)(&self.state, item)
+    }
+}
+
+/// A Tower layer that executes the provided service only if the
+/// given function returns true for the shared state and the request.
+/// Otherwise it falls through to the inner service.
+///
+/// This is a shorthand for a [`FilterLayer`] with a [`StatefulFilter`].
+#[derive(Debug)]
+pub struct StatefulFilterLayer<St, F, S, T> {
+    layer: FilterLayer<StatefulFilter<St, F>, S, T>,
+}
+
+impl<St, F, S, T> Clone for StatefulFilterLayer<St, F, S, T>
+where
+    FilterLayer<StatefulFilter<St, F>, S, T>: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<St, F, S: Service<T>, T> StatefulFilterLayer<St, F, S, T>
+where
+    St: Clone + Send + Sync + 'static,
+    F: Fn(&St, &T) -> bool + Clone,
+{
+    /// Creates a new StatefulFilterLayer given the state, the
+    /// filter function and the `Service`.
+    pub fn new(state: St, filter: F, service: S) -> Self {
+        Self {
+            layer: FilterLayer::new(StatefulFilter::new(state, filter), service),
+        }
+    }
+}
+
+impl<St, F, S, I, T> Layer<I> for StatefulFilterLayer<St, F, S, T>
+where
+    StatefulFilter<St, F>: Filter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = FilterService<StatefulFilter<St, F>, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        self.layer.layer(inner_service)
+    }
+}
@@ -0,0 +1,151 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+use tower::{util::CallAll, BoxError, Service};
+
+use crate::{Filter, FilterService};
+
+/// A stream calling a [`FilterService`] for every item of the given
+/// stream, e.g. in a batch pipeline where the filter decides whether
+/// an item is processed by the filtered or the inner service.
+///
+/// The responses are yielded in the order of the items, no matter
+/// which of the services handled them or how long it took.
+///
+/// # Example
+/// ```rust
+/// use futures::{stream, StreamExt};
+/// use tower::{service_fn, Layer};
+/// use tower_fallthrough_filter::{Filter, FilterLayer, FilterStream};
+///
+/// #[derive(Clone)]
+/// struct IsEven;
+///
+/// impl Filter<u32> for IsEven {
+///     fn matches(&self, item: &u32) -> bool {
+///         item % 2 == 0
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let halve = service_fn(|n: u32| async move { Ok::<_, std::convert::Infallible>(n / 2) });
+/// let triple = service_fn(|n: u32| async move { Ok(n * 3 + 1) });
+///
+/// let service = FilterLayer::new(IsEven, halve).layer(triple);
+/// let results: Vec<_> = FilterStream::new(service, stream::iter([6, 3, 10]))
+///     .map(Result::unwrap)
+///     .collect()
+///     .await;
+///
+/// assert_eq!(results, [3, 10, 5]);
+/// # }
+/// ```
+#[pin_project::pin_project]
+pub struct FilterStream<F, S, I, T, St>
+where
+    F: Filter<T>,
+    S: Service<T>,
+    S::Error: Into<BoxError>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+    St: Stream<Item = T>,
+{
+    #[pin]
+    inner: CallAll<FilterService<F, S, I, T>, St>,
+}
+
+impl<F, S, I, T, St> FilterStream<F, S, I, T, St>
+where
+    F: Filter<T>,
+    S: Service<T>,
+    S::Error: Into<BoxError>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+    St: Stream<Item = T>,
+{
+    /// Creates a new FilterStream given a `FilterService`
+    /// and the `Stream` of items to call it with.
+    pub fn new(service: FilterService<F, S, I, T>, items: St) -> Self {
+        Self {
+            inner: CallAll::new(service, items),
+        }
+    }
+
+    /// Returns the `FilterService`, dropping the remaining items.
+    pub fn into_inner(self) -> FilterService<F, S, I, T> {
+        self.inner.into_inner()
+    }
+}
+
+impl<F, S, I, T, St> Stream for FilterStream<F, S, I, T, St>
+where
+    F: Filter<T>,
+    S: Service<T>,
+    S::Error: Into<BoxError>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+    St: Stream<Item = T>,
+{
+    type Item = Result<S::Response, BoxError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.project().inner.poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, time::Duration};
+
+    use futures::{stream, StreamExt};
+    use tower::{service_fn, Layer};
+
+    use super::*;
+    use crate::FilterLayer;
+
+    #[derive(Clone)]
+    struct IsEven;
+
+    impl Filter<u64> for IsEven {
+        fn matches(&self, item: &u64) -> bool {
+            item.is_multiple_of(2)
+        }
+    }
+
+    #[tokio::test]
+    async fn should_keep_item_order() {
+        // NOTE: The filtered service is slower, so its responses
+        //       would come last if the order wasn't kept.
+        let slow = service_fn(|n: u64| async move {
+            tokio::time::sleep(Duration::from_millis(20 - n)).await;
+            Ok::<_, Infallible>(format!("even {n}"))
+        });
+        let fast = service_fn(|n: u64| async move { Ok(format!("odd {n}")) });
+
+        let service = FilterLayer::new(IsEven, slow).layer(fast);
+        let results: Vec<_> = FilterStream::new(service, stream::iter(0..6))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(
+            results,
+            ["even 0", "odd 1", "even 2", "odd 3", "even 4", "odd 5"]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_yield_errors_per_item() {
+        let fail = service_fn(|n: u64| async move { Err::<u64, _>(format!("{n} rejected")) });
+        let pass = service_fn(|n: u64| async move { Ok(n) });
+
+        let service = FilterLayer::new(IsEven, fail).layer(pass);
+        let results: Vec<_> = FilterStream::new(service, stream::iter([1, 2, 3]))
+            .map(|result| result.map_err(|err| err.to_string()))
+            .collect()
+            .await;
+
+        assert_eq!(results, [Ok(1), Err("2 rejected".to_owned()), Ok(3)]);
+    }
+}
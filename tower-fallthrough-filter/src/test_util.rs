@@ -1,16 +1,23 @@
 use std::{
     convert::Infallible,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
 };
 
 use futures::future::{ready, Ready};
-use tower::Service;
+use tower::{Layer, Service, ServiceExt};
 
-use crate::Filter;
+use crate::{Filter, FilterLayer, TryFilter};
 
 #[cfg(feature = "async")]
 use crate::AsyncFilter;
 
+#[cfg(feature = "retry")]
+use crate::TryAsyncFilter;
+
 #[derive(Debug)]
 pub struct TestService<T>(pub T);
 
@@ -34,6 +41,129 @@ impl<T: Clone, R> Service<R> for TestService<T> {
     }
 }
 
+/// A service that is only ready while the returned flag is set,
+/// panicking if it is called without being ready.
+#[derive(Debug)]
+pub struct PendingService<T> {
+    value: T,
+    ready: Arc<AtomicBool>,
+    polled_ready: bool,
+}
+
+impl<T> PendingService<T> {
+    pub fn new(value: T) -> (Self, Arc<AtomicBool>) {
+        let ready = Arc::new(AtomicBool::new(false));
+        let service = Self {
+            value,
+            ready: ready.clone(),
+            polled_ready: false,
+        };
+
+        (service, ready)
+    }
+}
+
+impl<T: Clone> Clone for PendingService<T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            ready: self.ready.clone(),
+            polled_ready: false,
+        }
+    }
+}
+
+impl<T: Clone, R> Service<R> for PendingService<T> {
+    type Response = T;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.ready.load(Ordering::SeqCst) {
+            self.polled_ready = true;
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&mut self, _: R) -> Self::Future {
+        assert!(
+            self.polled_ready,
+            "PendingService called without being ready"
+        );
+        self.polled_ready = false;
+
+        ready(Ok(self.value.clone()))
+    }
+}
+
+/// A service that isn't clonable, responding with the number
+/// of requests it handled so far.
+#[derive(Debug, Default)]
+pub struct NotClone {
+    calls: usize,
+}
+
+impl<R> Service<R> for NotClone {
+    type Response = usize;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: R) -> Self::Future {
+        self.calls += 1;
+        ready(Ok(self.calls))
+    }
+}
+
+/// A service counting how often it, or any of its clones, was cloned.
+#[derive(Debug)]
+pub struct CloneCountingService<T> {
+    value: T,
+    clones: Arc<AtomicUsize>,
+}
+
+impl<T> CloneCountingService<T> {
+    pub fn new(value: T) -> (Self, Arc<AtomicUsize>) {
+        let clones = Arc::new(AtomicUsize::new(0));
+        let service = Self {
+            value,
+            clones: clones.clone(),
+        };
+
+        (service, clones)
+    }
+}
+
+impl<T: Clone> Clone for CloneCountingService<T> {
+    fn clone(&self) -> Self {
+        self.clones.fetch_add(1, Ordering::SeqCst);
+
+        Self {
+            value: self.value.clone(),
+            clones: self.clones.clone(),
+        }
+    }
+}
+
+impl<T: Clone, R> Service<R> for CloneCountingService<T> {
+    type Response = T;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: R) -> Self::Future {
+        ready(Ok(self.value.clone()))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TestFilter(pub bool);
 
@@ -51,3 +181,109 @@ impl<T> AsyncFilter<T> for TestFilter {
         ready(self.0)
     }
 }
+
+/// An `AsyncFilter` deciding without awaiting, like a cache hit,
+/// panicking if its future is created.
+#[cfg(feature = "async")]
+#[derive(Debug, Clone)]
+pub struct DecidedFilter(pub bool);
+
+#[cfg(feature = "async")]
+impl<T> AsyncFilter<T> for DecidedFilter {
+    type Future = Ready<bool>;
+
+    fn matches(&self, _: &T) -> Self::Future {
+        unreachable!("decided without the future")
+    }
+
+    fn matches_now(&self, _: &T) -> Option<bool> {
+        Some(self.0)
+    }
+}
+
+/// A filter panicking when evaluated, or with the `AsyncFilter` either
+/// when creating its future or while polling it, see `PanicFilter::on_poll`.
+#[derive(Debug, Clone, Copy)]
+pub struct PanicFilter {
+    pub on_poll: bool,
+}
+
+impl<T> Filter<T> for PanicFilter {
+    fn matches(&self, _: &T) -> bool {
+        panic!("PanicFilter evaluated")
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> AsyncFilter<T> for PanicFilter {
+    type Future = futures::future::Lazy<fn(&mut Context<'_>) -> bool>;
+
+    fn matches(&self, _: &T) -> Self::Future {
+        assert!(self.on_poll, "PanicFilter evaluated");
+        futures::future::lazy(|_| panic!("PanicFilter polled"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Unavailable;
+
+/// A filter failing the given number of times before it matches.
+#[derive(Debug, Clone)]
+pub struct Flaky {
+    failures: usize,
+    pub calls: Arc<AtomicUsize>,
+}
+
+impl Flaky {
+    pub fn new(failures: usize) -> Self {
+        Self {
+            failures,
+            calls: Arc::default(),
+        }
+    }
+
+    fn attempt(&self) -> Result<bool, Unavailable> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+        if call < self.failures {
+            Err(Unavailable)
+        } else {
+            Ok(true)
+        }
+    }
+}
+
+impl<T> TryFilter<T> for Flaky {
+    type Error = Unavailable;
+
+    fn try_matches(&self, _: &T) -> Result<bool, Self::Error> {
+        self.attempt()
+    }
+}
+
+#[cfg(feature = "retry")]
+impl<T> TryAsyncFilter<T> for Flaky {
+    type Error = Unavailable;
+    type Future = Ready<Result<bool, Self::Error>>;
+
+    fn try_matches(&self, _: &T) -> Self::Future {
+        ready(self.attempt())
+    }
+}
+
+/// Asserts that the layers built by `layer` select the same service
+/// as a `FilterLayer` whose filter returns the given `bool`.
+pub async fn assert_behaves_like_filter_layer<L>(layer: impl Fn(bool) -> L)
+where
+    L: Layer<TestService<&'static str>>,
+    L::Service: Service<(), Response = &'static str>,
+{
+    for matches in [true, false] {
+        let response = layer(matches).layer(TestService("b")).oneshot(()).await;
+        let expected = FilterLayer::new(TestFilter(matches), TestService("a"))
+            .layer(TestService("b"))
+            .oneshot(())
+            .await;
+
+        assert_eq!(response.ok(), expected.ok(), "matches: {matches}");
+    }
+}
@@ -0,0 +1,440 @@
+use std::{
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{future::BoxFuture, ready};
+use tokio::time::Instant;
+use tower::{Layer, Service};
+
+use crate::{ready::take_ready, TryFilterError};
+
+type DecisionHook<T> = Arc<dyn Fn(&mut T, TryDecision) + Send + Sync>;
+
+/// The async counterpart of [`TryFilter`](crate::TryFilter), e.g. for a
+/// filter asking a feature flag service over the network.
+///
+/// # Example
+/// ```rust
+/// use futures::future::BoxFuture;
+/// use tower_fallthrough_filter::TryAsyncFilter;
+///
+/// #[derive(Clone)]
+/// struct FeatureFlag(&'static str);
+///
+/// impl TryAsyncFilter<u32> for FeatureFlag {
+///     type Error = String;
+///     type Future = BoxFuture<'static, Result<bool, Self::Error>>;
+///
+///     fn try_matches(&self, user: &u32) -> Self::Future {
+///         let (flag, user) = (self.0, *user);
+///         Box::pin(async move {
+///             // NOTE: E.g. a request to the feature flag service.
+///             match flag {
+///                 "beta" => Ok(user < 100),
+///                 _ => Err(format!("unknown flag {flag}")),
+///             }
+///         })
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// assert_eq!(FeatureFlag("beta").try_matches(&42).await, Ok(true));
+/// assert!(FeatureFlag("gamma").try_matches(&42).await.is_err());
+/// # }
+/// ```
+pub trait TryAsyncFilter<T>: Clone {
+    type Error;
+    type Future: Future<Output = Result<bool, Self::Error>> + Send;
+
+    /// Whether the service should be executed, see
+    /// [`AsyncFilter::matches`](crate::AsyncFilter::matches).
+    fn try_matches(&self, item: &T) -> Self::Future;
+}
+
+/// How a request was dispatched by an [`AsyncTryFilterService`],
+/// passed to the hook set by [`AsyncTryFilterLayer::on_decision`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TryDecision {
+    /// Whether the filter matched, so the filtered service is called.
+    pub matched: bool,
+    /// Whether the filter failed on every attempt, so the request
+    /// falls through or fails with [`TryFilterError::Filter`], see
+    /// [`AsyncTryFilterLayer::fallthrough_on_error`].
+    pub filter_failed: bool,
+    /// How often the filter was evaluated, including retries.
+    pub attempts: u32,
+    /// The time spent evaluating the filter, including the backoff
+    /// between the retries.
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct RetryPolicy {
+    retries: u32,
+    backoff: Duration,
+}
+
+/// A Tower layer like [`AsyncFilterLayer`](crate::AsyncFilterLayer)
+/// for a [`TryAsyncFilter`], retrying the filter on failure.
+///
+/// If the filter still fails after the configured retries, the request
+/// fails with [`TryFilterError::Filter`], unless the layer was created
+/// using [`AsyncTryFilterLayer::fallthrough_on_error`].
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+///
+/// use futures::future::{ready, Ready};
+/// use tower::{service_fn, Layer, ServiceExt};
+/// use tower_fallthrough_filter::{AsyncTryFilterLayer, TryAsyncFilter};
+///
+/// #[derive(Clone)]
+/// struct Unreachable;
+///
+/// impl TryAsyncFilter<u32> for Unreachable {
+///     type Error = &'static str;
+///     type Future = Ready<Result<bool, Self::Error>>;
+///
+///     fn try_matches(&self, _: &u32) -> Self::Future {
+///         ready(Err("connection refused"))
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let respond = |name| service_fn(move |_: u32| async move { Ok::<_, ()>(name) });
+///
+/// let service = AsyncTryFilterLayer::fallthrough_on_error(Unreachable, respond("beta"))
+///     .retries(2)
+///     .backoff(Duration::from_millis(1))
+///     .layer(respond("stable"));
+///
+/// assert_eq!(service.oneshot(42).await, Ok("stable"));
+/// # }
+/// ```
+pub struct AsyncTryFilterLayer<F, S, T> {
+    filter: F,
+    service: S,
+    fallthrough_on_error: bool,
+    policy: RetryPolicy,
+    hook: Option<DecisionHook<T>>,
+}
+
+impl<F: Clone, S: Clone, T> Clone for AsyncTryFilterLayer<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            fallthrough_on_error: self.fallthrough_on_error,
+            policy: self.policy,
+            hook: self.hook.clone(),
+        }
+    }
+}
+
+impl<F: fmt::Debug, S: fmt::Debug, T> fmt::Debug for AsyncTryFilterLayer<F, S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncTryFilterLayer")
+            .field("filter", &self.filter)
+            .field("service", &self.service)
+            .field("fallthrough_on_error", &self.fallthrough_on_error)
+            .field("retries", &self.policy.retries)
+            .field("backoff", &self.policy.backoff)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F: TryAsyncFilter<T>, S: Service<T>, T> AsyncTryFilterLayer<F, S, T> {
+    /// Creates a new AsyncTryFilterLayer given a `TryAsyncFilter` and a
+    /// `Service`, failing requests the filter fails on.
+    pub fn new(filter: F, service: S) -> Self {
+        Self {
+            filter,
+            service,
+            fallthrough_on_error: false,
+            policy: RetryPolicy::default(),
+            hook: None,
+        }
+    }
+
+    /// Creates a new AsyncTryFilterLayer given a `TryAsyncFilter` and a
+    /// `Service`, falling through to the inner service if the filter
+    /// fails, as if it didn't match.
+    pub fn fallthrough_on_error(filter: F, service: S) -> Self {
+        Self {
+            fallthrough_on_error: true,
+            ..Self::new(filter, service)
+        }
+    }
+}
+
+impl<F, S, T> AsyncTryFilterLayer<F, S, T> {
+    /// Evaluates the filter again up to `retries` times if it fails,
+    /// as a new future is created by every call of `try_matches`.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.policy.retries = retries;
+        self
+    }
+
+    /// Waits for `backoff` before every retry.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.policy.backoff = backoff;
+        self
+    }
+
+    /// Sets a hook called with the decision for every request before
+    /// it is dispatched or fails as the filter failed, e.g. to record
+    /// the latency added by the filter.
+    pub fn on_decision<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(&mut T, TryDecision) + Send + Sync + 'static,
+    {
+        self.hook = Some(Arc::new(hook));
+        self
+    }
+}
+
+impl<F, S, I, T> Layer<I> for AsyncTryFilterLayer<F, S, T>
+where
+    F: TryAsyncFilter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = AsyncTryFilterService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        AsyncTryFilterService {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: inner_service,
+            fallthrough_on_error: self.fallthrough_on_error,
+            policy: self.policy,
+            hook: self.hook.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+pub struct AsyncTryFilterService<F, S, I, T> {
+    filter: F,
+    service: S,
+    inner: I,
+    fallthrough_on_error: bool,
+    policy: RetryPolicy,
+    hook: Option<DecisionHook<T>>,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, I: Clone, T> Clone for AsyncTryFilterService<F, S, I, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: self.inner.clone(),
+            fallthrough_on_error: self.fallthrough_on_error,
+            policy: self.policy,
+            hook: self.hook.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> fmt::Debug for AsyncTryFilterService<F, S, I, T>
+where
+    F: fmt::Debug,
+    S: fmt::Debug,
+    I: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncTryFilterService")
+            .field("filter", &self.filter)
+            .field("service", &self.service)
+            .field("inner", &self.inner)
+            .field("fallthrough_on_error", &self.fallthrough_on_error)
+            .field("retries", &self.policy.retries)
+            .field("backoff", &self.policy.backoff)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, S, I, T> Service<T> for AsyncTryFilterService<F, S, I, T>
+where
+    F: TryAsyncFilter<T> + Send + 'static,
+    F::Error: Send + 'static,
+    S: Service<T> + Clone + Send + 'static,
+    S::Future: Send,
+    I: Service<T, Response = S::Response, Error = S::Error> + Clone + Send + 'static,
+    I::Future: Send,
+    T: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = TryFilterError<F::Error, S::Error>;
+    type Future = BoxFuture<'static, Result<S::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.service.poll_ready(cx)).map_err(TryFilterError::Service)?;
+        ready!(self.inner.poll_ready(cx)).map_err(TryFilterError::Service)?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: T) -> Self::Future {
+        let filter = self.filter.clone();
+        let mut service = take_ready(&mut self.service);
+        let mut inner = take_ready(&mut self.inner);
+
+        let fallthrough_on_error = self.fallthrough_on_error;
+        let policy = self.policy;
+        let hook = self.hook.clone();
+
+        Box::pin(async move {
+            let start = Instant::now();
+            let mut attempts = 0;
+
+            let result = loop {
+                attempts += 1;
+                match filter.try_matches(&req).await {
+                    Err(_) if attempts <= policy.retries => {
+                        tokio::time::sleep(policy.backoff).await;
+                    }
+                    result => break result,
+                }
+            };
+
+            let (matched, filter_failed) = match result {
+                Ok(matched) => (matched, false),
+                Err(_) => (false, true),
+            };
+
+            if let Some(hook) = &hook {
+                let decision = TryDecision {
+                    matched,
+                    filter_failed,
+                    attempts,
+                    elapsed: start.elapsed(),
+                };
+                hook(&mut req, decision);
+            }
+
+            if let Err(err) = result {
+                if !fallthrough_on_error {
+                    return Err(TryFilterError::Filter(err));
+                }
+            }
+
+            let res = if matched {
+                service.call(req).await
+            } else {
+                inner.call(req).await
+            };
+            res.map_err(TryFilterError::Service)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::Ordering, Mutex};
+
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_util::*;
+
+    type Decisions = Arc<Mutex<Vec<TryDecision>>>;
+
+    fn recorder() -> (
+        Decisions,
+        impl Fn(&mut (), TryDecision) + Send + Sync + 'static,
+    ) {
+        let decisions = Arc::new(Mutex::new(Vec::new()));
+        let recorded = decisions.clone();
+
+        (decisions, move |_: &mut (), decision| {
+            recorded.lock().unwrap().push(decision)
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_retry_failing_filter() {
+        let filter = Flaky::new(2);
+        let (decisions, hook) = recorder();
+
+        let service = AsyncTryFilterLayer::new(filter.clone(), TestService("a"))
+            .retries(2)
+            .backoff(Duration::from_millis(100))
+            .on_decision(hook)
+            .layer(TestService("b"));
+
+        assert_eq!(service.oneshot(()).await, Ok("a"));
+        assert_eq!(filter.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            *decisions.lock().unwrap(),
+            [TryDecision {
+                matched: true,
+                filter_failed: false,
+                attempts: 3,
+                elapsed: Duration::from_millis(200),
+            }]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_return_error_after_retries() {
+        let filter = Flaky::new(3);
+        let (decisions, hook) = recorder();
+
+        let service = AsyncTryFilterLayer::new(filter.clone(), TestService("a"))
+            .retries(2)
+            .backoff(Duration::from_millis(100))
+            .on_decision(hook)
+            .layer(TestService("b"));
+
+        assert_eq!(
+            service.oneshot(()).await,
+            Err(TryFilterError::Filter(Unavailable))
+        );
+        assert_eq!(filter.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            *decisions.lock().unwrap(),
+            [TryDecision {
+                matched: false,
+                filter_failed: true,
+                attempts: 3,
+                elapsed: Duration::from_millis(200),
+            }]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_fall_through_after_retries() {
+        let (decisions, hook) = recorder();
+
+        let service = AsyncTryFilterLayer::fallthrough_on_error(Flaky::new(3), TestService("a"))
+            .retries(1)
+            .backoff(Duration::from_millis(100))
+            .on_decision(hook)
+            .layer(TestService("b"));
+
+        assert_eq!(service.oneshot(()).await, Ok("b"));
+        assert_eq!(
+            *decisions.lock().unwrap(),
+            [TryDecision {
+                matched: false,
+                filter_failed: true,
+                attempts: 2,
+                elapsed: Duration::from_millis(100),
+            }]
+        );
+    }
+}
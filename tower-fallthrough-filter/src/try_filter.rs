@@ -0,0 +1,267 @@
+use std::{
+    error::Error,
+    fmt,
+    marker::PhantomData,
+    task::{Context, Poll},
+};
+
+use futures::{
+    future::{self, Either, MapErr, Ready},
+    ready, TryFutureExt,
+};
+use tower::{Layer, Service};
+
+/// A filter that can fail, e.g. as it consults a database.
+///
+/// # Example
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// use tower_fallthrough_filter::TryFilter;
+///
+/// #[derive(Clone)]
+/// struct IsBanned(HashMap<&'static str, bool>);
+///
+/// impl TryFilter<&'static str> for IsBanned {
+///     type Error = String;
+///
+///     fn try_matches(&self, user: &&'static str) -> Result<bool, Self::Error> {
+///         self.0.get(user).copied().ok_or_else(|| format!("unknown user {user}"))
+///     }
+/// }
+///
+/// let filter = IsBanned(HashMap::from([("mallory", true)]));
+/// assert_eq!(filter.try_matches(&"mallory"), Ok(true));
+/// assert!(filter.try_matches(&"alice").is_err());
+/// ```
+pub trait TryFilter<T>: Clone {
+    type Error;
+
+    /// Whether the service should be executed, see
+    /// [`Filter::matches`](crate::Filter::matches).
+    fn try_matches(&self, item: &T) -> Result<bool, Self::Error>;
+}
+
+/// The error returned by a [`TryFilterService`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryFilterError<F, E> {
+    /// The filter failed.
+    Filter(F),
+    /// The called service failed.
+    Service(E),
+}
+
+impl<F: fmt::Display, E: fmt::Display> fmt::Display for TryFilterError<F, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Filter(err) => write!(f, "the filter failed: {err}"),
+            Self::Service(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<F: Error + 'static, E: Error + 'static> Error for TryFilterError<F, E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Filter(err) => Some(err),
+            Self::Service(err) => Some(err),
+        }
+    }
+}
+
+/// A Tower layer like [`FilterLayer`](crate::FilterLayer) for a
+/// [`TryFilter`].
+///
+/// If the filter fails, the request fails with
+/// [`TryFilterError::Filter`], unless the layer was created using
+/// [`TryFilterLayer::fallthrough_on_error`].
+#[derive(Debug)]
+pub struct TryFilterLayer<F, S, T> {
+    filter: F,
+    service: S,
+    fallthrough_on_error: bool,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, T> Clone for TryFilterLayer<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            fallthrough_on_error: self.fallthrough_on_error,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: TryFilter<T>, S: Service<T>, T> TryFilterLayer<F, S, T> {
+    /// Creates a new TryFilterLayer given a `TryFilter` and a `Service`,
+    /// failing requests the filter fails on.
+    pub fn new(filter: F, service: S) -> Self {
+        Self {
+            filter,
+            service,
+            fallthrough_on_error: false,
+
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a new TryFilterLayer given a `TryFilter` and a `Service`,
+    /// falling through to the inner service if the filter fails, as if
+    /// it didn't match.
+    pub fn fallthrough_on_error(filter: F, service: S) -> Self {
+        Self {
+            fallthrough_on_error: true,
+            ..Self::new(filter, service)
+        }
+    }
+}
+
+impl<F, S, I, T> Layer<I> for TryFilterLayer<F, S, T>
+where
+    F: TryFilter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = TryFilterService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        TryFilterService {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: inner_service,
+            fallthrough_on_error: self.fallthrough_on_error,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TryFilterService<F, S, I, T> {
+    filter: F,
+    service: S,
+    inner: I,
+    fallthrough_on_error: bool,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, I: Clone, T> Clone for TryFilterService<F, S, I, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            service: self.service.clone(),
+            inner: self.inner.clone(),
+            fallthrough_on_error: self.fallthrough_on_error,
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+type ServiceError<F, E> = fn(E) -> TryFilterError<F, E>;
+
+type TryFilterFuture<A, B, F, R, E> =
+    Either<MapErr<Either<A, B>, ServiceError<F, E>>, Ready<Result<R, TryFilterError<F, E>>>>;
+
+impl<F, S, I, T> Service<T> for TryFilterService<F, S, I, T>
+where
+    F: TryFilter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Response = S::Response;
+    type Error = TryFilterError<F::Error, S::Error>;
+    type Future = TryFilterFuture<S::Future, I::Future, F::Error, S::Response, S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.service.poll_ready(cx)).map_err(TryFilterError::Service)?;
+        ready!(self.inner.poll_ready(cx)).map_err(TryFilterError::Service)?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        let matched = match self.filter.try_matches(&req) {
+            Ok(matched) => matched,
+            Err(_) if self.fallthrough_on_error => false,
+            Err(err) => return Either::Right(future::ready(Err(TryFilterError::Filter(err)))),
+        };
+
+        let future = if matched {
+            Either::Left(self.service.call(req))
+        } else {
+            Either::Right(self.inner.call(req))
+        };
+
+        Either::Left(future.map_err(TryFilterError::Service as ServiceError<_, _>))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_util::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct LookupFailed;
+
+    #[derive(Clone)]
+    struct Lookup(Result<bool, LookupFailed>);
+
+    impl<T> TryFilter<T> for Lookup {
+        type Error = LookupFailed;
+
+        fn try_matches(&self, _: &T) -> Result<bool, Self::Error> {
+            self.0.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn should_behave_like_filter_layer() {
+        assert_behaves_like_filter_layer(|matches| {
+            TryFilterLayer::new(Lookup(Ok(matches)), TestService("a"))
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn should_return_filter_error() {
+        let service = TryFilterLayer::new(Lookup(Err(LookupFailed)), TestService("a"))
+            .layer(TestService("b"));
+
+        assert_eq!(
+            service.oneshot(()).await,
+            Err(TryFilterError::Filter(LookupFailed))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_return_service_error() {
+        let respond = |result: Result<&'static str, &'static str>| {
+            tower::service_fn(move |_: ()| async move { result })
+        };
+        let service = TryFilterLayer::new(Lookup(Ok(false)), respond(Ok("a")))
+            .layer(respond(Err("unavailable")));
+
+        assert_eq!(
+            service.oneshot(()).await,
+            Err(TryFilterError::Service("unavailable"))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_fall_through_on_filter_error() {
+        let service =
+            TryFilterLayer::fallthrough_on_error(Lookup(Err(LookupFailed)), TestService("a"))
+                .layer(TestService("b"));
+
+        assert_eq!(service.oneshot(()).await, Ok("b"));
+    }
+}
@@ -0,0 +1,291 @@
+use std::{
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use ::futures::{future::Either, ready};
+use rand::RngCore;
+use tower::{Layer, Service};
+
+use crate::{rng::SharedRng, Filter};
+
+/// A handle to change the weights of a [`WeightedSelectLayer`]
+/// while serving.
+#[derive(Debug, Clone)]
+pub struct WeightsHandle(Arc<[AtomicU32]>);
+
+impl WeightsHandle {
+    /// The current weight of every candidate.
+    pub fn weights(&self) -> Vec<u32> {
+        self.0
+            .iter()
+            .map(|weight| weight.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// Sets the weight of the candidate at `index`.
+    ///
+    /// # Panics
+    /// Panics if there is no candidate at `index`.
+    pub fn set(&self, index: usize, weight: u32) {
+        self.0[index].store(weight, Ordering::Relaxed);
+    }
+
+    fn pick(&self, rng: &SharedRng) -> Option<usize> {
+        let weights = self.weights();
+        let total = weights.iter().map(|weight| u64::from(*weight)).sum::<u64>();
+
+        if total == 0 {
+            return None;
+        }
+
+        let mut point = rng.below(total);
+        weights.iter().position(|weight| {
+            let weight = u64::from(*weight);
+            if point < weight {
+                true
+            } else {
+                point -= weight;
+                false
+            }
+        })
+    }
+}
+
+/// A Tower layer that, if the given filter matches, calls one of
+/// several candidate services picked randomly according to their
+/// weights. Otherwise it falls through to the inner service.
+///
+/// The candidates have to be of the same type, services of different
+/// types can be unified using e.g. `tower::util::BoxCloneService`.
+/// If all weights are zero, the request falls through.
+///
+/// # Example
+/// ```rust
+/// use tower_fallthrough_filter::{Filter, WeightedSelectLayer};
+/// use tower::{service_fn, Layer, Service};
+///
+/// #[derive(Debug, Clone)]
+/// struct Always;
+///
+/// impl<T> Filter<T> for Always {
+///     fn matches(&self, _: &T) -> bool {
+///         true
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let renderer = |name| service_fn(move |_: ()| async move { Ok::<_, ()>(name) });
+///
+/// let candidates = vec![(90, renderer("stable")), (10, renderer("experimental"))];
+/// let layer = WeightedSelectLayer::new(Always, candidates);
+/// let handle = layer.handle();
+/// let mut service = layer.layer(renderer("inner"));
+///
+/// handle.set(0, 0);
+/// assert_eq!(service.call(()).await, Ok("experimental"));
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct WeightedSelectLayer<F, S, T> {
+    filter: F,
+    services: Vec<S>,
+    weights: WeightsHandle,
+    rng: SharedRng,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, T> Clone for WeightedSelectLayer<F, S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            services: self.services.clone(),
+            weights: self.weights.clone(),
+            rng: self.rng.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: Filter<T>, S: Service<T>, T> WeightedSelectLayer<F, S, T> {
+    /// Creates a new WeightedSelectLayer given a `Filter` and the
+    /// candidate `Service`s paired with their weights.
+    pub fn new(filter: F, candidates: Vec<(u32, S)>) -> Self {
+        let (weights, services): (Vec<_>, Vec<_>) = candidates
+            .into_iter()
+            .map(|(weight, service)| (AtomicU32::new(weight), service))
+            .unzip();
+
+        Self {
+            filter,
+            services,
+            weights: WeightsHandle(weights.into()),
+            rng: SharedRng::default(),
+
+            _marker: PhantomData,
+        }
+    }
+
+    /// Uses the given random number generator instead of the thread
+    /// local one. It's shared between all services created by this layer.
+    pub fn with_rng<G: RngCore + Send + 'static>(mut self, rng: G) -> Self {
+        self.rng = SharedRng::new(rng);
+        self
+    }
+
+    /// Returns a handle to change the weights at runtime.
+    pub fn handle(&self) -> WeightsHandle {
+        self.weights.clone()
+    }
+}
+
+impl<F, S, I, T> Layer<I> for WeightedSelectLayer<F, S, T>
+where
+    F: Filter<T>,
+    S: Service<T> + Clone,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Service = WeightedSelectService<F, S, I, T>;
+
+    fn layer(&self, inner_service: I) -> Self::Service {
+        WeightedSelectService {
+            filter: self.filter.clone(),
+            services: self.services.clone(),
+            inner: inner_service,
+            weights: self.weights.clone(),
+            rng: self.rng.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// The service created by a [`WeightedSelectLayer`].
+///
+/// It is only ready once all candidates and the inner service are
+/// ready, as any of them might be picked for the next request. The
+/// weights are shared with the layer, so changes made through its
+/// [`WeightsHandle`] apply to the next request.
+#[derive(Debug)]
+pub struct WeightedSelectService<F, S, I, T> {
+    filter: F,
+    services: Vec<S>,
+    inner: I,
+    weights: WeightsHandle,
+    rng: SharedRng,
+
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, S: Clone, I: Clone, T> Clone for WeightedSelectService<F, S, I, T> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            services: self.services.clone(),
+            inner: self.inner.clone(),
+            weights: self.weights.clone(),
+            rng: self.rng.clone(),
+
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, I, T> Service<T> for WeightedSelectService<F, S, I, T>
+where
+    F: Filter<T>,
+    S: Service<T>,
+    I: Service<T, Response = S::Response, Error = S::Error>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<S::Future, I::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // NOTE: Every candidate might get picked, so all of them
+        //       have to be ready before accepting a request.
+        for service in &mut self.services {
+            ready!(service.poll_ready(cx))?;
+        }
+        ready!(self.inner.poll_ready(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        let picked = if self.filter.matches(&req) {
+            self.weights.pick(&self.rng)
+        } else {
+            None
+        };
+
+        match picked {
+            Some(index) => Either::Left(self.services[index].call(req)),
+            None => Either::Right(self.inner.call(req)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::test_util::*;
+
+    #[tokio::test]
+    async fn should_pick_according_to_weights() {
+        let candidates = vec![
+            (90, TestService("stable")),
+            (10, TestService("experimental")),
+        ];
+        let layer = WeightedSelectLayer::new(TestFilter(true), candidates)
+            .with_rng(StdRng::seed_from_u64(7));
+
+        let mut middleware = layer.layer(TestService("inner"));
+
+        let mut experimental = 0;
+        for _ in 0..1000 {
+            if middleware.call(()).await == Ok("experimental") {
+                experimental += 1;
+            }
+        }
+
+        assert!(
+            (70..=130).contains(&experimental),
+            "{experimental} were experimental"
+        );
+    }
+
+    #[tokio::test]
+    async fn should_fall_through() {
+        let candidates = vec![(1, TestService("a")), (1, TestService("b"))];
+        let layer = WeightedSelectLayer::new(TestFilter(false), candidates);
+
+        let mut middleware = layer.layer(TestService("inner"));
+
+        assert_eq!(middleware.call(()).await, Ok("inner"));
+    }
+
+    #[tokio::test]
+    async fn should_apply_runtime_weights() {
+        let candidates = vec![(1, TestService("a")), (1, TestService("b"))];
+        let layer = WeightedSelectLayer::new(TestFilter(true), candidates);
+        let handle = layer.handle();
+
+        let mut middleware = layer.layer(TestService("inner"));
+
+        handle.set(0, 0);
+        assert_eq!(middleware.call(()).await, Ok("b"));
+
+        handle.set(1, 0);
+        assert_eq!(middleware.call(()).await, Ok("inner"));
+    }
+}
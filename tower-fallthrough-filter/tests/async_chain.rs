@@ -0,0 +1,57 @@
+use axum::{extract::Request, routing::get, Router};
+use axum_test::TestServer;
+use futures::future::BoxFuture;
+use tower_fallthrough_filter::{AsyncFilter, AsyncFilterChain};
+
+/// Pretends to look up whether the path belongs to the given section.
+#[derive(Clone)]
+struct Section(&'static str);
+
+impl AsyncFilter<Request> for Section {
+    type Future = BoxFuture<'static, bool>;
+
+    fn matches(&self, req: &Request) -> Self::Future {
+        let matches = req.uri().path().starts_with(self.0);
+
+        Box::pin(async move {
+            tokio::task::yield_now().await;
+            matches
+        })
+    }
+}
+
+fn respond(name: &'static str) -> Router {
+    Router::new().fallback(get(move || async move { name }))
+}
+
+#[tokio::test]
+async fn should_route_to_first_matching_section() {
+    let chain = AsyncFilterChain::new()
+        .when(Section("/admin"), respond("admin"))
+        .when(Section("/api"), respond("api"))
+        .when(Section("/"), respond("pages"));
+
+    let app = Router::new()
+        .route("/health", get(|| async { "inner" }))
+        .layer(chain);
+    let server = TestServer::new(app).unwrap();
+
+    server.get("/admin/users").await.assert_text("admin");
+    server.get("/api/users").await.assert_text("api");
+    server.get("/about").await.assert_text("pages");
+}
+
+#[tokio::test]
+async fn should_fall_through_without_match() {
+    let chain = AsyncFilterChain::new()
+        .when(Section("/admin"), respond("admin"))
+        .when(Section("/api"), respond("api"))
+        .when(Section("/static"), respond("static"));
+
+    let app = Router::new()
+        .route("/health", get(|| async { "inner" }))
+        .layer(chain);
+    let server = TestServer::new(app).unwrap();
+
+    server.get("/health").await.assert_text("inner");
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, Request},
+    routing::get,
+    Router,
+};
+use axum_test::TestServer;
+use tower_fallthrough_filter::{
+    filters::{AxumFilter, MatchedPathFilter},
+    Filter, FilterLayer,
+};
+
+fn server<F: Filter<Request> + Send + Sync + 'static>(filter: F) -> TestServer {
+    let filtered = Router::new().fallback(get(|| async { "filtered" }));
+
+    let app = Router::new()
+        .route("/users", get(|| async { "users" }))
+        .route("/users/:id", get(|| async { "user" }))
+        .fallback(get(|| async { "fallback" }))
+        .layer(FilterLayer::new(filter, filtered));
+
+    TestServer::new(app).unwrap()
+}
+
+#[tokio::test]
+async fn should_filter_unmatched() {
+    let server = server(MatchedPathFilter::unmatched());
+
+    server.get("/users").await.assert_text("users");
+    server.get("/users/1").await.assert_text("user");
+    server.get("/about").await.assert_text("filtered");
+}
+
+#[tokio::test]
+async fn should_filter_matched() {
+    let server = server(MatchedPathFilter::matched());
+
+    server.get("/users").await.assert_text("filtered");
+    server.get("/users/1").await.assert_text("filtered");
+    server.get("/about").await.assert_text("fallback");
+}
+
+#[tokio::test]
+async fn should_filter_by_pattern() {
+    let server = server(MatchedPathFilter::is("/users/:id"));
+
+    server.get("/users").await.assert_text("users");
+    server.get("/users/1").await.assert_text("filtered");
+    server.get("/about").await.assert_text("fallback");
+}
+
+#[tokio::test]
+async fn should_filter_by_path_extractor() {
+    let server = server(AxumFilter::from_extractor::<Path<u32>>(|Path(id)| id < 10));
+
+    server.get("/users/1").await.assert_text("filtered");
+    server.get("/users/42").await.assert_text("user");
+    // NOTE: The extractor rejects ids that aren't numbers.
+    server.get("/users/me").await.assert_text("user");
+    server.get("/users").await.assert_text("users");
+}
+
+#[tokio::test]
+async fn should_filter_by_query_extractor() {
+    let server = server(
+        AxumFilter::from_extractor::<Query<HashMap<String, String>>>(|Query(query)| {
+            query
+                .get("preview")
+                .is_some_and(|preview| preview == "true")
+        }),
+    );
+    let get = |path, preview| server.get(path).add_query_param("preview", preview);
+
+    get("/users", "true").await.assert_text("filtered");
+    get("/users", "false").await.assert_text("users");
+    get("/about", "true").await.assert_text("filtered");
+    server.get("/users").await.assert_text("users");
+}
@@ -0,0 +1,68 @@
+use std::{collections::HashSet, sync::Arc};
+
+use axum::{http::Request, routing::get, Router};
+use axum_test::TestServer;
+use tokio::sync::RwLock;
+use tower_fallthrough_filter::filters::StatefulAsyncFilterLayer;
+
+#[derive(Clone, Default)]
+struct AppState {
+    beta_users: Arc<RwLock<HashSet<String>>>,
+}
+
+async fn is_beta(state: &AppState, req: &Request<()>) -> bool {
+    let Some(user) = req
+        .headers()
+        .get("x-user")
+        .and_then(|user| user.to_str().ok())
+    else {
+        return false;
+    };
+
+    state.beta_users.read().await.contains(user)
+}
+
+fn server(state: AppState) -> TestServer {
+    let beta = Router::new().fallback(get(|| async { "beta" }));
+
+    let app = Router::new()
+        .route("/", get(|| async { "stable" }))
+        .layer(StatefulAsyncFilterLayer::new(state, is_beta, beta));
+
+    TestServer::new(app).unwrap()
+}
+
+#[tokio::test]
+async fn should_filter_by_state() {
+    let state = AppState::default();
+    state.beta_users.write().await.insert("ferris".to_string());
+    let server = server(state);
+
+    server.get("/").await.assert_text("stable");
+    server
+        .get("/")
+        .add_header("x-user".parse().unwrap(), "ferris".parse().unwrap())
+        .await
+        .assert_text("beta");
+    server
+        .get("/")
+        .add_header("x-user".parse().unwrap(), "corro".parse().unwrap())
+        .await
+        .assert_text("stable");
+}
+
+#[tokio::test]
+async fn should_see_state_changes() {
+    let state = AppState::default();
+    let server = server(state.clone());
+    let request = || {
+        server
+            .get("/")
+            .add_header("x-user".parse().unwrap(), "ferris".parse().unwrap())
+    };
+
+    request().await.assert_text("stable");
+
+    state.beta_users.write().await.insert("ferris".to_string());
+    request().await.assert_text("beta");
+}
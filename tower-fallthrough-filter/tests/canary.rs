@@ -0,0 +1,72 @@
+use axum::{extract::Request, routing::get, Extension, Router};
+use axum_test::TestServer;
+use rand::{rngs::StdRng, SeedableRng};
+use tower_fallthrough_filter::filters::{CanaryDecision, CanaryFilter, CanaryLayer};
+
+fn server(filter: CanaryFilter) -> TestServer {
+    let canary =
+        Router::new().fallback(get(
+            |Extension(decision): Extension<CanaryDecision>| async move {
+                format!("canary {decision:?}")
+            },
+        ));
+
+    let app = Router::new()
+        .route(
+            "/",
+            get(|req: Request| async move {
+                let decision = req.extensions().get::<CanaryDecision>().copied();
+                format!("stable {decision:?}")
+            }),
+        )
+        .layer(CanaryLayer::new(filter, canary));
+
+    TestServer::new(app).unwrap()
+}
+
+#[tokio::test]
+async fn should_force_canary_with_override_header() {
+    let server = server(CanaryFilter::new(0.0).with_override_header("x-canary"));
+
+    server.get("/").await.assert_text("stable Some(Stable)");
+    server
+        .get("/")
+        .add_header("x-canary".parse().unwrap(), "1".parse().unwrap())
+        .await
+        .assert_text("canary Canary");
+}
+
+#[tokio::test]
+async fn should_force_stable_with_override_header() {
+    let server = server(CanaryFilter::new(100.0).with_override_header("x-canary"));
+
+    server.get("/").await.assert_text("canary Canary");
+    server
+        .get("/")
+        .add_header("x-canary".parse().unwrap(), "0".parse().unwrap())
+        .await
+        .assert_text("stable Some(Stable)");
+}
+
+#[tokio::test]
+async fn should_route_percentage_to_canary() {
+    let filter = CanaryFilter::new(10.0).with_rng(StdRng::seed_from_u64(42));
+    let handle = filter.handle();
+    let server = server(filter);
+
+    let mut canary = 0;
+    for _ in 0..1000 {
+        if server.get("/").await.text().starts_with("canary") {
+            canary += 1;
+        }
+    }
+    assert!(
+        (70..=130).contains(&canary),
+        "{canary} requests hit the canary"
+    );
+
+    handle.set_percent(0.0);
+    for _ in 0..100 {
+        server.get("/").await.assert_text("stable Some(Stable)");
+    }
+}
@@ -0,0 +1,6 @@
+#[test]
+fn derive() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/derive/pass_*.rs");
+    t.compile_fail("tests/ui/derive/fail_*.rs");
+}
@@ -0,0 +1,58 @@
+use std::convert::Infallible;
+
+use tower::{service_fn, util::BoxCloneService, ServiceExt};
+use tower_fallthrough_filter::{filter_chain, FilterFn};
+
+type Respond = BoxCloneService<u32, &'static str, Infallible>;
+
+fn respond(name: &'static str) -> Respond {
+    BoxCloneService::new(service_fn(move |_: u32| async move { Ok(name) }))
+}
+
+fn divisible_by(n: u32) -> FilterFn<impl Fn(&u32) -> bool + Clone> {
+    FilterFn::new(move |item: &u32| item.is_multiple_of(n))
+}
+
+#[tokio::test]
+async fn should_check_filters_in_order() {
+    let service = filter_chain![
+        (divisible_by(7), respond("seven")),
+        (divisible_by(5), respond("five")),
+        (divisible_by(3), respond("three")),
+        (divisible_by(2), respond("two"));
+        respond("fallthrough")
+    ];
+
+    for (item, expected) in [
+        (14, "seven"),
+        (35, "seven"),
+        (10, "five"),
+        (15, "five"),
+        (9, "three"),
+        (6, "three"),
+        (4, "two"),
+        (11, "fallthrough"),
+    ] {
+        assert_eq!(service.clone().oneshot(item).await, Ok(expected));
+    }
+}
+
+#[tokio::test]
+async fn should_support_sixteen_pairs() {
+    let equals = |n: u32| FilterFn::new(move |item: &u32| *item == n);
+
+    let service = filter_chain![
+        (equals(0), respond("0")), (equals(1), respond("1")),
+        (equals(2), respond("2")), (equals(3), respond("3")),
+        (equals(4), respond("4")), (equals(5), respond("5")),
+        (equals(6), respond("6")), (equals(7), respond("7")),
+        (equals(8), respond("8")), (equals(9), respond("9")),
+        (equals(10), respond("10")), (equals(11), respond("11")),
+        (equals(12), respond("12")), (equals(13), respond("13")),
+        (equals(14), respond("14")), (equals(15), respond("15"));
+        respond("fallthrough")
+    ];
+
+    assert_eq!(service.clone().oneshot(15).await, Ok("15"));
+    assert_eq!(service.oneshot(16).await, Ok("fallthrough"));
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{MatchedPath, Query, Request},
+    response::Html,
+    routing::get,
+    Router,
+};
+use axum_test::TestServer;
+use tower_fallthrough_filter::filter_fn;
+
+fn render() -> Router {
+    Router::new().fallback(get(
+        |Query(query): Query<HashMap<String, String>>| async move {
+            let name = query.get("name").map_or("Unknown", String::as_str);
+            Html(format!("Hello {name}!"))
+        },
+    ))
+}
+
+fn is_unmatched(req: &Request) -> bool {
+    req.extensions().get::<MatchedPath>().is_none()
+}
+
+#[tokio::test]
+async fn should_render_unmatched_routes() {
+    let app = Router::new()
+        .nest(
+            "/api",
+            Router::new().route("/hello", get(|| async { "Hello, World!" })),
+        )
+        .layer(filter_fn(is_unmatched, render()));
+    let server = TestServer::new(app).unwrap();
+
+    server.get("/api/hello").await.assert_text("Hello, World!");
+    server
+        .get("/page")
+        .add_query_param("name", "Ferris")
+        .await
+        .assert_text("Hello Ferris!");
+    server.get("/").await.assert_text("Hello Unknown!");
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn should_render_unmatched_routes_async() {
+    use tower_fallthrough_filter::async_filter_fn;
+
+    let app = Router::new()
+        .nest(
+            "/api",
+            Router::new().route("/hello", get(|| async { "Hello, World!" })),
+        )
+        .layer(async_filter_fn(
+            |req: &Request| {
+                let unmatched = is_unmatched(req);
+                async move { unmatched }
+            },
+            render(),
+        ));
+    let server = TestServer::new(app).unwrap();
+
+    server.get("/api/hello").await.assert_text("Hello, World!");
+    server
+        .get("/page")
+        .add_query_param("name", "Ferris")
+        .await
+        .assert_text("Hello Ferris!");
+}
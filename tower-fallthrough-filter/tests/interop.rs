@@ -0,0 +1,71 @@
+use std::convert::Infallible;
+
+use axum::http::{
+    header::{ACCEPT_ENCODING, ACCESS_CONTROL_ALLOW_ORIGIN, CONTENT_ENCODING, ORIGIN},
+    Request, Response,
+};
+use tower::{service_fn, util::BoxCloneService, ServiceBuilder, ServiceExt};
+use tower_fallthrough_filter::{Filter, FilterLayer};
+use tower_http::{compression::CompressionLayer, cors::CorsLayer};
+
+type Respond = BoxCloneService<Request<String>, Response<String>, Infallible>;
+
+fn respond(name: &'static str) -> Respond {
+    // NOTE: Long enough to be compressed by the default predicate.
+    let body = name.repeat(64);
+
+    BoxCloneService::new(service_fn(move |_: Request<String>| {
+        let body = body.clone();
+        async move { Ok(Response::new(body)) }
+    }))
+}
+
+#[derive(Clone)]
+struct IsApi;
+
+impl<B> Filter<Request<B>> for IsApi {
+    fn matches(&self, req: &Request<B>) -> bool {
+        req.uri().path().starts_with("/api")
+    }
+}
+
+fn filter_layer() -> FilterLayer<IsApi, Respond, Request<String>> {
+    FilterLayer::new(IsApi, respond("api"))
+}
+
+#[tokio::test]
+async fn should_compress_both_branches() {
+    let service = ServiceBuilder::new()
+        .layer(CompressionLayer::new())
+        .layer(filter_layer())
+        .service(respond("page"));
+
+    for path in ["/api/users", "/about"] {
+        let req = Request::get(path)
+            .header(ACCEPT_ENCODING, "gzip")
+            .body(String::new())
+            .unwrap();
+        let res = service.clone().oneshot(req).await.unwrap();
+
+        assert_eq!(res.headers()[CONTENT_ENCODING], "gzip", "{path}");
+    }
+}
+
+#[tokio::test]
+async fn should_add_cors_headers_to_both_branches() {
+    let service = ServiceBuilder::new()
+        .layer(CorsLayer::permissive())
+        .layer(filter_layer())
+        .service(respond("page"));
+
+    for (path, branch) in [("/api/users", "api"), ("/about", "page")] {
+        let req = Request::get(path)
+            .header(ORIGIN, "https://example.com")
+            .body(String::new())
+            .unwrap();
+        let res = service.clone().oneshot(req).await.unwrap();
+
+        assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "*", "{path}");
+        assert!(res.body().starts_with(branch));
+    }
+}
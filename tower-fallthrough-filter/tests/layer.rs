@@ -0,0 +1,7 @@
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/layer/pass_*.rs");
+    #[cfg(feature = "http")]
+    t.pass("tests/ui/layer/http/pass_*.rs");
+}
@@ -0,0 +1,5 @@
+#[test]
+fn macros() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/macros/fail_*.rs");
+}
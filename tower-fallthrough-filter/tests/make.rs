@@ -0,0 +1,65 @@
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use tower::{make::MakeService, service_fn, Service, ServiceExt};
+use tower_fallthrough_filter::{FilterFn, MakeFilterService};
+
+/// Makes a service per connection counting the requests it handled,
+/// while `made` counts the created services.
+fn per_connection(
+    name: &'static str,
+    made: Arc<AtomicUsize>,
+) -> impl Service<
+    SocketAddr,
+    Response = impl Service<&'static str, Response = String, Error = Infallible> + Clone,
+    Error = Infallible,
+> + Clone {
+    service_fn(move |peer: SocketAddr| {
+        made.fetch_add(1, Ordering::SeqCst);
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        async move {
+            Ok(service_fn(move |path: &'static str| {
+                let count = requests.fetch_add(1, Ordering::SeqCst) + 1;
+                async move { Ok(format!("{name} {path} from {peer} #{count}")) }
+            }))
+        }
+    })
+}
+
+#[tokio::test]
+async fn should_make_services_per_connection() {
+    let made_api = Arc::new(AtomicUsize::new(0));
+    let made_pages = Arc::new(AtomicUsize::new(0));
+
+    let mut make_service = MakeFilterService::new(
+        FilterFn::new(|path: &&'static str| path.starts_with("/api")),
+        per_connection("api", made_api.clone()),
+        per_connection("page", made_pages.clone()),
+    );
+
+    let alice = SocketAddr::from(([10, 0, 0, 1], 4000));
+    let bob = SocketAddr::from(([10, 0, 0, 2], 4000));
+
+    let conn_alice = make_service.make_service(alice).await.unwrap();
+    let conn_bob = make_service.make_service(bob).await.unwrap();
+    assert_eq!(made_api.load(Ordering::SeqCst), 2);
+    assert_eq!(made_pages.load(Ordering::SeqCst), 2);
+
+    let res = conn_alice.clone().oneshot("/api/users").await.unwrap();
+    assert_eq!(res, "api /api/users from 10.0.0.1:4000 #1");
+    let res = conn_alice.oneshot("/api/posts").await.unwrap();
+    assert_eq!(res, "api /api/posts from 10.0.0.1:4000 #2");
+
+    // NOTE: The services of other connections don't share any state.
+    let res = conn_bob.clone().oneshot("/api/users").await.unwrap();
+    assert_eq!(res, "api /api/users from 10.0.0.2:4000 #1");
+    let res = conn_bob.oneshot("/about").await.unwrap();
+    assert_eq!(res, "page /about from 10.0.0.2:4000 #1");
+}
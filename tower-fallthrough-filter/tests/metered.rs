@@ -0,0 +1,230 @@
+use axum::{extract::Request, routing::get, Router};
+use axum_test::TestServer;
+use metrics_exporter_prometheus::PrometheusBuilder;
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+use tower::{service_fn, Layer, ServiceExt};
+use tower_fallthrough_filter::{
+    AtomicMetrics, Filter, MeteredFilterLayer, MetricsFilterLayerBuilder, MetricsFilterMetrics,
+};
+
+#[derive(Clone)]
+struct StartsWithStatic;
+
+impl Filter<Request> for StartsWithStatic {
+    fn matches(&self, req: &Request) -> bool {
+        req.uri().path().starts_with("/static")
+    }
+}
+
+#[derive(Clone)]
+struct IsEven;
+
+impl Filter<u32> for IsEven {
+    fn matches(&self, item: &u32) -> bool {
+        item.is_multiple_of(2)
+    }
+}
+
+#[tokio::test]
+async fn should_count_routing_decisions() {
+    let metrics = AtomicMetrics::new();
+    let layer = MeteredFilterLayer::new(
+        "static",
+        StartsWithStatic,
+        Router::new().fallback(get(|| async { "static" })),
+        metrics.clone(),
+    );
+
+    let app = Router::new()
+        .route("/api", get(|| async { "api" }))
+        .layer(layer);
+    let server = TestServer::new(app).unwrap();
+
+    server.get("/static/a.css").await.assert_text("static");
+    server.get("/static/b.css").await.assert_text("static");
+    server.get("/api").await.assert_text("api");
+
+    assert_eq!(metrics.matched(), 2);
+    assert_eq!(metrics.fallthrough(), 1);
+}
+
+#[test]
+fn should_report_to_metrics_recorder() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+
+    let matched = service_fn(|_: u32| async { Ok::<_, ()>("matched") });
+    let inner = service_fn(|_: u32| async { Ok::<_, ()>("inner") });
+    let metrics = MetricsFilterMetrics::new();
+    let service = MeteredFilterLayer::new("is_even", IsEven, matched, metrics).layer(inner);
+
+    metrics::with_local_recorder(&recorder, || {
+        for i in 0..5 {
+            let expected = if i % 2 == 0 { "matched" } else { "inner" };
+            assert_eq!(
+                futures::executor::block_on(service.clone().oneshot(i)),
+                Ok(expected)
+            );
+        }
+    });
+
+    let counters = snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .map(|(key, _, _, value)| {
+            let key = key.key();
+            let label = key.labels().next().unwrap();
+            assert_eq!((label.key(), label.value()), ("filter", "is_even"));
+
+            match value {
+                DebugValue::Counter(count) => (key.name().to_owned(), count),
+                other => panic!("unexpected metric value {other:?}"),
+            }
+        })
+        .collect::<std::collections::HashMap<_, _>>();
+
+    assert_eq!(counters["filter_matched_total"], 3);
+    assert_eq!(counters["filter_fallthrough_total"], 2);
+}
+
+#[test]
+fn should_render_prometheus_metrics() {
+    let recorder = PrometheusBuilder::new().build_recorder();
+    let handle = recorder.handle();
+
+    let matched = service_fn(|_: u32| async { Ok::<_, ()>("matched") });
+    let inner = service_fn(|_: u32| async { Ok::<_, ()>("inner") });
+    let metrics = MetricsFilterMetrics::new();
+    let service = MeteredFilterLayer::new("is_even", IsEven, matched, metrics).layer(inner);
+
+    metrics::with_local_recorder(&recorder, || {
+        for i in 0..5 {
+            futures::executor::block_on(service.clone().oneshot(i)).unwrap();
+        }
+    });
+
+    let rendered = handle.render();
+    assert!(rendered.contains(r#"filter_matched_total{filter="is_even"} 3"#));
+    assert!(rendered.contains(r#"filter_fallthrough_total{filter="is_even"} 2"#));
+}
+
+#[test]
+fn should_report_to_configured_metric_names() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+
+    let matched = service_fn(|_: u32| async { Ok::<_, ()>("matched") });
+    let inner = service_fn(|_: u32| async { Ok::<_, ()>("inner") });
+    let service = MetricsFilterLayerBuilder::new()
+        .matched_name("router.even")
+        .fallthrough_name("router.odd")
+        .build(IsEven, matched)
+        .layer(inner);
+
+    metrics::with_local_recorder(&recorder, || {
+        for i in 0..5 {
+            futures::executor::block_on(service.clone().oneshot(i)).unwrap();
+        }
+    });
+
+    let counters = snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .map(|(key, _, _, value)| {
+            let key = key.key();
+            let label = key.labels().next().unwrap();
+            assert_eq!(
+                (label.key(), label.value()),
+                ("filter", std::any::type_name::<IsEven>())
+            );
+
+            match value {
+                DebugValue::Counter(count) => (key.name().to_owned(), count),
+                other => panic!("unexpected metric value {other:?}"),
+            }
+        })
+        .collect::<std::collections::HashMap<_, _>>();
+
+    assert_eq!(counters["router.even"], 3);
+    assert_eq!(counters["router.odd"], 2);
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn should_record_async_evaluation_duration() {
+    use futures::future::{ready, Ready};
+    use tower_fallthrough_filter::AsyncFilter;
+
+    #[derive(Clone)]
+    struct IsOdd;
+
+    impl AsyncFilter<u32> for IsOdd {
+        type Future = Ready<bool>;
+
+        fn matches(&self, item: &u32) -> Self::Future {
+            ready(!item.is_multiple_of(2))
+        }
+    }
+
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+
+    let matched = service_fn(|_: u32| async { Ok::<_, ()>("matched") });
+    let inner = service_fn(|_: u32| async { Ok::<_, ()>("inner") });
+    let service = MetricsFilterLayerBuilder::new()
+        .build_async(IsOdd, matched)
+        .layer(inner);
+
+    metrics::with_local_recorder(&recorder, || {
+        for i in 0..3 {
+            futures::executor::block_on(service.clone().oneshot(i)).unwrap();
+        }
+    });
+
+    let metrics = snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .map(|(key, _, _, value)| (key.key().name().to_owned(), value))
+        .collect::<std::collections::HashMap<_, _>>();
+
+    assert_eq!(metrics["filter_matched_total"], DebugValue::Counter(1));
+    assert_eq!(metrics["filter_fallthrough_total"], DebugValue::Counter(2));
+    match &metrics["filter_evaluation_duration_seconds"] {
+        DebugValue::Histogram(durations) => assert_eq!(durations.len(), 3),
+        other => panic!("unexpected metric value {other:?}"),
+    }
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn should_report_decisions_made_now() {
+    use futures::future::Ready;
+    use tower_fallthrough_filter::{AsyncFilter, MeteredAsyncFilter};
+
+    /// Decides without awaiting, like a cache hit.
+    #[derive(Clone)]
+    struct IsEvenNow;
+
+    impl AsyncFilter<u32> for IsEvenNow {
+        type Future = Ready<bool>;
+
+        fn matches(&self, _: &u32) -> Self::Future {
+            unreachable!("decided without the future")
+        }
+
+        fn matches_now(&self, item: &u32) -> Option<bool> {
+            Some(item.is_multiple_of(2))
+        }
+    }
+
+    let metrics = AtomicMetrics::new();
+    let filter = MeteredAsyncFilter::new("is_even", IsEvenNow, metrics.clone());
+
+    assert_eq!(filter.matches_now(&2), Some(true));
+    assert_eq!(filter.matches_now(&3), Some(false));
+    assert_eq!(metrics.matched(), 1);
+    assert_eq!(metrics.fallthrough(), 1);
+}
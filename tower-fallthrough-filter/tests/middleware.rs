@@ -0,0 +1,61 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::Request,
+    middleware::{self, Next},
+    routing::get,
+    Router,
+};
+use axum_test::TestServer;
+use tower_fallthrough_filter::{FilterFn, FilterLayer};
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+fn app(layer: impl FnOnce(FilterLayer<IsPage, Router, Request>) -> Router) -> TestServer {
+    let pages = Router::new().fallback(get(|| async { "page" }));
+    let filter_layer = FilterLayer::new(FilterFn::new(is_page as fn(&Request) -> bool), pages);
+
+    TestServer::new(layer(filter_layer)).unwrap()
+}
+
+type IsPage = FilterFn<fn(&Request) -> bool>;
+
+fn is_page(req: &Request) -> bool {
+    !req.uri().path().starts_with("/api")
+}
+
+fn api() -> Router {
+    Router::new().route("/api/users", get(|| async { "users" }))
+}
+
+#[tokio::test]
+async fn should_log_every_request_above() {
+    let log = Log::default();
+    let logged = log.clone();
+    let logging = middleware::from_fn(move |req: Request, next: Next| {
+        logged.lock().unwrap().push(req.uri().path().to_string());
+        next.run(req)
+    });
+    let server = app(|layer| api().layer(layer.above(logging)));
+
+    server.get("/api/users").await.assert_text("users");
+    server.get("/about").await.assert_text("page");
+
+    assert_eq!(*log.lock().unwrap(), ["/api/users", "/about"]);
+}
+
+#[tokio::test]
+async fn should_log_matching_requests_below() {
+    let log = Log::default();
+    let logged = log.clone();
+    let logging = middleware::from_fn(move |req: Request, next: Next| {
+        logged.lock().unwrap().push(req.uri().path().to_string());
+        next.run(req)
+    });
+    let server = app(|layer| api().layer(layer.below(logging)));
+
+    server.get("/api/users").await.assert_text("users");
+    server.get("/about").await.assert_text("page");
+
+    assert_eq!(*log.lock().unwrap(), ["/about"]);
+}
@@ -0,0 +1,79 @@
+use std::{
+    future::{ready, Ready},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use tower::{
+    reconnect::Reconnect, service_fn, util::BoxCloneService, BoxError, Layer, Service, ServiceExt,
+};
+use tower_fallthrough_filter::{Filter, FilterLayer};
+
+#[derive(Clone)]
+struct IsLocal;
+
+impl Filter<&'static str> for IsLocal {
+    fn matches(&self, path: &&'static str) -> bool {
+        path.starts_with("/local")
+    }
+}
+
+type Connection = BoxCloneService<&'static str, String, BoxError>;
+
+/// Connects to the backend, failing the first `failures` attempts.
+fn connector(
+    failures: usize,
+    attempts: Arc<AtomicUsize>,
+) -> impl Service<
+    &'static str,
+    Response = Connection,
+    Error = BoxError,
+    Future = Ready<Result<Connection, BoxError>>,
+> {
+    service_fn(move |addr: &'static str| {
+        let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+        if attempt < failures {
+            return ready(Err(BoxError::from("connection refused")));
+        }
+
+        ready(Ok(BoxCloneService::new(service_fn(
+            move |path: &'static str| async move { Ok(format!("{addr}{path} #{attempt}")) },
+        ))))
+    })
+}
+
+fn local() -> impl Service<&'static str, Response = String, Error = BoxError> + Clone {
+    service_fn(|path: &'static str| async move { Ok::<_, BoxError>(format!("local{path}")) })
+}
+
+#[tokio::test]
+async fn should_fall_through_to_reconnecting_backend() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let backend =
+        Reconnect::new::<Connection, &'static str>(connector(0, attempts.clone()), "backend");
+    let mut service = FilterLayer::new(IsLocal, local()).layer(backend);
+
+    let res = service.ready().await.unwrap().call("/local/a").await;
+    assert_eq!(res.unwrap(), "local/local/a");
+
+    let res = service.ready().await.unwrap().call("/remote").await;
+    assert_eq!(res.unwrap(), "backend/remote #0");
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn should_reconnect_after_failed_attempt() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let backend =
+        Reconnect::new::<Connection, &'static str>(connector(1, attempts.clone()), "backend");
+    let mut service = FilterLayer::new(IsLocal, local()).layer(backend);
+
+    let res = service.ready().await.unwrap().call("/remote").await;
+    assert_eq!(res.unwrap_err().to_string(), "connection refused");
+
+    let res = service.ready().await.unwrap().call("/remote").await;
+    assert_eq!(res.unwrap(), "backend/remote #1");
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,95 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use axum::{extract::Request, routing::get, Router};
+use axum_test::TestServer;
+use tower_fallthrough_filter::StatefulFilterLayer;
+
+type Flags = Arc<RwLock<HashMap<String, bool>>>;
+
+fn server(flags: Flags) -> TestServer {
+    let new_ui = Router::new().fallback(get(|| async { "new ui" }));
+
+    let layer = StatefulFilterLayer::new(
+        flags,
+        |flags: &Flags, req: &Request| {
+            let flag = format!("new-ui:{}", req.uri().path());
+            flags.read().unwrap().get(&flag).copied().unwrap_or(false)
+        },
+        new_ui,
+    );
+
+    let app = Router::new()
+        .route("/", get(|| async { "old ui" }))
+        .route("/settings", get(|| async { "old ui" }))
+        .layer(layer);
+
+    TestServer::new(app).unwrap()
+}
+
+#[tokio::test]
+async fn should_route_by_feature_flag() {
+    let flags = Flags::default();
+    flags.write().unwrap().insert("new-ui:/".to_owned(), true);
+
+    let server = server(flags);
+
+    server.get("/").await.assert_text("new ui");
+    server.get("/settings").await.assert_text("old ui");
+}
+
+#[tokio::test]
+async fn should_follow_flag_changes() {
+    let flags = Flags::default();
+    let server = server(flags.clone());
+
+    server.get("/settings").await.assert_text("old ui");
+
+    flags
+        .write()
+        .unwrap()
+        .insert("new-ui:/settings".to_owned(), true);
+    server.get("/settings").await.assert_text("new ui");
+
+    flags
+        .write()
+        .unwrap()
+        .insert("new-ui:/settings".to_owned(), false);
+    server.get("/settings").await.assert_text("old ui");
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn should_route_by_async_feature_flag() {
+    use tower_fallthrough_filter::{AsyncFilterLayer, AsyncStatefulFilter};
+
+    let flags = Flags::default();
+    flags.write().unwrap().insert("new-ui".to_owned(), true);
+
+    let filter = AsyncStatefulFilter::new(flags.clone(), |flags: &Flags, _: &Request| {
+        let flags = flags.clone();
+        async move {
+            // Pretend the flags are stored remotely.
+            tokio::task::yield_now().await;
+            flags
+                .read()
+                .unwrap()
+                .get("new-ui")
+                .copied()
+                .unwrap_or(false)
+        }
+    });
+    let new_ui = Router::new().fallback(get(|| async { "new ui" }));
+
+    let app = Router::new()
+        .route("/", get(|| async { "old ui" }))
+        .layer(AsyncFilterLayer::new(filter, new_ui));
+    let server = TestServer::new(app).unwrap();
+
+    server.get("/").await.assert_text("new ui");
+
+    flags.write().unwrap().insert("new-ui".to_owned(), false);
+    server.get("/").await.assert_text("old ui");
+}
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use axum::{
+    http::{header::CONTENT_LENGTH, HeaderValue},
+    routing::{get, post},
+    Router,
+};
+use axum_test::TestServer;
+use tower_fallthrough_filter::{
+    filters::tower_http::{SizeLimitFilter, TimeoutFilter},
+    Filter, FilterLayer,
+};
+
+fn server<F>(filter: F) -> TestServer
+where
+    F: Filter<axum::extract::Request> + Send + Sync + 'static,
+{
+    let filtered = Router::new().fallback(|| async { "filtered" });
+
+    let app = Router::new()
+        .route("/", get(|| async { "fallback" }))
+        .route("/upload", post(|| async { "fallback" }))
+        .layer(FilterLayer::new(filter, filtered));
+
+    TestServer::new(app).unwrap()
+}
+
+#[tokio::test(start_paused = true)]
+async fn should_match_before_timeout() {
+    let server = server(TimeoutFilter::new(Duration::from_millis(200)));
+    server.get("/").await.assert_text("filtered");
+
+    tokio::time::advance(Duration::from_millis(200)).await;
+    server.get("/").await.assert_text("fallback");
+}
+
+#[tokio::test]
+async fn should_match_within_size_limit() {
+    let server = server(SizeLimitFilter::new(8));
+    let upload = |body: &'static str| {
+        server
+            .post("/upload")
+            .add_header(CONTENT_LENGTH, HeaderValue::from(body.len()))
+            .text(body)
+    };
+
+    upload("12345678").await.assert_text("filtered");
+    upload("123456789").await.assert_text("fallback");
+
+    // NOTE: Without a `content-length` the size is unknown.
+    server
+        .post("/upload")
+        .text("1")
+        .await
+        .assert_text("fallback");
+}
@@ -0,0 +1,113 @@
+use std::{
+    convert::Infallible,
+    io,
+    sync::{Arc, Mutex},
+};
+
+use tower::{service_fn, Layer, Service, ServiceExt};
+use tower_fallthrough_filter::{AsyncFilter, AsyncFilterLayer, Filter, FilterLayer};
+use tracing::Level;
+use tracing_subscriber::fmt::MakeWriter;
+
+#[derive(Clone)]
+struct IsEven;
+
+impl Filter<u32> for IsEven {
+    fn matches(&self, item: &u32) -> bool {
+        item.is_multiple_of(2)
+    }
+}
+
+impl AsyncFilter<u32> for IsEven {
+    type Future = futures::future::Ready<bool>;
+
+    fn matches(&self, item: &u32) -> Self::Future {
+        futures::future::ready(item.is_multiple_of(2))
+    }
+}
+
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Capture {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(str::to_owned)
+            .collect()
+    }
+}
+
+impl io::Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Capture {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+fn subscriber(capture: &Capture) -> impl tracing::Subscriber {
+    tracing_subscriber::fmt()
+        .with_max_level(Level::TRACE)
+        .with_ansi(false)
+        .without_time()
+        .with_writer(capture.clone())
+        .finish()
+}
+
+fn respond(
+    name: &'static str,
+) -> impl Service<u32, Response = &'static str, Error = Infallible, Future = impl Send> + Clone + Send
+{
+    service_fn(move |_: u32| async move { Ok(name) })
+}
+
+#[tokio::test]
+async fn should_trace_sync_decisions() {
+    let capture = Capture::default();
+    let _guard = tracing::subscriber::set_default(subscriber(&capture));
+
+    let service = FilterLayer::new(IsEven, respond("even")).layer(respond("odd"));
+    assert_eq!(service.clone().oneshot(2).await, Ok("even"));
+    assert_eq!(service.oneshot(3).await, Ok("odd"));
+
+    let lines = capture.lines();
+    let decisions: Vec<_> = lines
+        .iter()
+        .filter(|line| line.contains("filter_service{filter=tracing::IsEven}"))
+        .collect();
+
+    assert_eq!(decisions.len(), 2, "{lines:#?}");
+    assert!(decisions[0].ends_with("matched=true"), "{lines:#?}");
+    assert!(decisions[1].ends_with("matched=false"), "{lines:#?}");
+}
+
+#[tokio::test]
+async fn should_trace_async_decisions() {
+    let capture = Capture::default();
+    let _guard = tracing::subscriber::set_default(subscriber(&capture));
+
+    let service = AsyncFilterLayer::new(IsEven, respond("even")).layer(respond("odd"));
+    assert_eq!(service.oneshot(4).await, Ok("even"));
+
+    let lines = capture.lines();
+    assert!(
+        lines.iter().any(
+            |line| line.contains("filter_service{filter=tracing::IsEven}")
+                && line.ends_with("matched=true")
+        ),
+        "{lines:#?}"
+    );
+}
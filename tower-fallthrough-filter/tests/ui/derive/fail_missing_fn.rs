@@ -0,0 +1,14 @@
+use tower_fallthrough_filter::Filter;
+
+fn is_api(path: &String) -> bool {
+    path.starts_with("/api")
+}
+
+#[derive(Clone, Filter)]
+enum Route {
+    #[filter(fn = "is_api")]
+    Api,
+    Asset,
+}
+
+fn main() {}
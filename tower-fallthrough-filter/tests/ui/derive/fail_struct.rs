@@ -0,0 +1,6 @@
+use tower_fallthrough_filter::Filter;
+
+#[derive(Clone, Filter)]
+struct Route;
+
+fn main() {}
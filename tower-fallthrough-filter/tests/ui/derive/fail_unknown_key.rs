@@ -0,0 +1,9 @@
+use tower_fallthrough_filter::Filter;
+
+#[derive(Clone, Filter)]
+enum Route {
+    #[filter(function = "is_api")]
+    Api,
+}
+
+fn main() {}
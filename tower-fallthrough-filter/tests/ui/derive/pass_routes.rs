@@ -0,0 +1,41 @@
+use tower_fallthrough_filter::Filter;
+
+fn is_api(path: &String) -> bool {
+    path.starts_with("/api")
+}
+
+fn is_asset(path: &String) -> bool {
+    path.starts_with("/assets")
+}
+
+fn never<T>(_: &T) -> bool {
+    false
+}
+
+#[derive(Clone, Filter)]
+#[filter(item = "String")]
+enum Route {
+    #[filter(fn = "is_api")]
+    Api,
+    #[filter(fn = "is_asset")]
+    Asset { _cache: bool },
+    #[filter(fn = "never")]
+    Disabled(()),
+}
+
+#[derive(Clone, Filter)]
+enum Generic {
+    #[filter(fn = "never")]
+    Never,
+}
+
+fn main() {
+    let path = "/api/users".to_string();
+
+    assert!(Route::Api.matches(&path));
+    assert!(!Route::Asset { _cache: true }.matches(&path));
+    assert!(!Route::Disabled(()).matches(&path));
+
+    assert!(!Generic::Never.matches(&42));
+    assert!(!Generic::Never.matches(&path));
+}
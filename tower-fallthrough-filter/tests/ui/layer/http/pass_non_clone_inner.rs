@@ -0,0 +1,41 @@
+use std::{
+    convert::Infallible,
+    future::{ready, Ready},
+    task::{Context, Poll},
+};
+
+use http::{Method, Request, Response};
+use tower::{Layer, Service};
+use tower_fallthrough_filter::{
+    filters::{MethodNotAllowedFilterLayer, UpgradeAwareFilterLayer},
+    FilterFn,
+};
+
+// NOTE: Intentionally not clonable, e.g. as it owns a connection.
+struct Connection;
+
+impl Service<Request<()>> for Connection {
+    type Response = Response<&'static str>;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: Request<()>) -> Self::Future {
+        ready(Ok(Response::new("connection")))
+    }
+}
+
+fn main() {
+    let is_post = FilterFn::new(|req: &Request<()>| req.method() == Method::POST);
+    let is_upload = FilterFn::new(|req: &Request<()>| req.uri().path() == "/upload");
+    let respond = tower::service_fn(|_: Request<()>| ready(Ok::<_, Infallible>(Response::new("upload"))));
+
+    let mut service = MethodNotAllowedFilterLayer::new(is_post, [Method::POST], is_upload, respond).layer(Connection);
+    let _ = service.call(Request::new(()));
+
+    let mut service = UpgradeAwareFilterLayer::new(is_upload, respond).layer(Connection);
+    let _ = service.call(Request::new(()));
+}
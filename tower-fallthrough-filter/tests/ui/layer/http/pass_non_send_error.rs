@@ -0,0 +1,27 @@
+use std::{future::ready, rc::Rc};
+
+use http::{Method, Request, Response};
+use tower::{Layer, Service};
+use tower_fallthrough_filter::{
+    filters::{MethodNotAllowedFilterLayer, UpgradeAwareFilterLayer},
+    FilterFn,
+};
+
+// NOTE: Neither the error nor the body are `Send`, e.g. on a local executor.
+type Error = Rc<str>;
+type Body = Rc<str>;
+
+fn main() {
+    let is_post = FilterFn::new(|req: &Request<()>| req.method() == Method::POST);
+    let is_upload = FilterFn::new(|req: &Request<()>| req.uri().path() == "/upload");
+    let respond = |name: &'static str| {
+        tower::service_fn(move |_: Request<()>| ready(Ok::<_, Error>(Response::new(Body::from(name)))))
+    };
+
+    let mut service = MethodNotAllowedFilterLayer::new(is_post, [Method::POST], is_upload, respond("upload"))
+        .layer(respond("router"));
+    let _ = service.call(Request::new(()));
+
+    let mut service = UpgradeAwareFilterLayer::new(is_upload, respond("upload")).layer(respond("router"));
+    let _ = service.call(Request::new(()));
+}
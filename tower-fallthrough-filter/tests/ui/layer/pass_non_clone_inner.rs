@@ -0,0 +1,33 @@
+use std::{
+    convert::Infallible,
+    future::{ready, Ready},
+    task::{Context, Poll},
+};
+
+use tower::{Layer, Service};
+use tower_fallthrough_filter::{FilterFn, FilterLayer};
+
+// NOTE: Intentionally not clonable, e.g. as it owns a connection.
+struct Connection;
+
+impl Service<u32> for Connection {
+    type Response = &'static str;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: u32) -> Self::Future {
+        ready(Ok("connection"))
+    }
+}
+
+fn main() {
+    let even = FilterFn::new(|n: &u32| n % 2 == 0);
+    let respond = tower::service_fn(|_: u32| ready(Ok::<_, Infallible>("even")));
+
+    let mut service = FilterLayer::new(even, respond).layer(Connection);
+    let _ = service.call(3);
+}
@@ -0,0 +1,5 @@
+use tower_fallthrough_filter::filter;
+
+fn main() {
+    let _ = filter!(GET | FETCH);
+}